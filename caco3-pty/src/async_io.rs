@@ -0,0 +1,169 @@
+//! Pseudo terminal for `async-io` based runtimes such as smol.
+//!
+//! ```ignore
+//! use futures_lite::{AsyncReadExt, AsyncWriteExt};
+//!
+//! smol::block_on(async {
+//!     let command = PtyCommandBuilder::new("bash").build_std();
+//!     let (mut pty, mut child) = PseudoTerminal::allocate()?.spawn_async_io(command)?;
+//!     pty.write_all(b"exit 3\n").await?;
+//!     let status = child.status().await?;
+//! })
+//! ```
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use async_io::Async;
+use async_process::Child;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::pair::into_owned_fd;
+use crate::{sys, AllocateError, FromFdError, PtyPair, ResizeError, SizeError, SpawnError};
+
+/// Parent end of a pseudo terminal, readable and writable with `futures-io` traits.
+///
+/// Reading fails with `EIO` once every child end is closed, which should be treated as the end of output.
+pub struct PseudoTerminal {
+    inner: Async<File>,
+}
+
+impl PseudoTerminal {
+    /// Allocate a new pseudo terminal.
+    pub fn allocate() -> Result<PtyPair, AllocateError> {
+        PtyPair::new()
+    }
+
+    /// Use the parent end of a pseudo terminal opened by other means, e.g. handed over by a
+    /// container runtime.
+    ///
+    /// The file descriptor is switched to non-blocking mode.
+    pub fn from_fd(fd: OwnedFd) -> Result<Self, FromFdError> {
+        if !fd.is_terminal() {
+            return Err(FromFdError::NotTerminal);
+        }
+        let inner = Async::new(File::from(fd)).map_err(FromFdError::WrapAsyncFd)?;
+        Ok(Self { inner })
+    }
+
+    /// Resize the pseudo-terminal.
+    ///
+    /// Should be called when the terminal emulator changes size.
+    pub fn resize(&self, width: u32, height: u32) -> Result<(), ResizeError> {
+        sys::resize_pty(self.inner.as_fd(), width, height).map_err(ResizeError)
+    }
+
+    /// Current size as `(width, height)`.
+    pub fn size(&self) -> Result<(u32, u32), SizeError> {
+        sys::get_pty_size(self.inner.as_fd()).map_err(SizeError)
+    }
+}
+
+impl PtyPair {
+    /// Same as [`spawn`](PtyPair::spawn) for `async-io` based runtimes.
+    pub fn spawn_async_io(
+        self,
+        mut command: std::process::Command,
+    ) -> Result<(PseudoTerminal, Child), SpawnError> {
+        let (pty_master, stdio) = self.prepare(&mut command)?;
+        let child = async_process::Command::from(command)
+            .stdin(stdio.stdin)
+            .stdout(stdio.stdout)
+            .stderr(stdio.stderr)
+            .spawn()
+            .map_err(SpawnError::Spawn)?;
+        let pty = PseudoTerminal {
+            inner: Async::new(File::from(into_owned_fd(pty_master)))
+                .map_err(SpawnError::WrapAsyncFd)?,
+        };
+        Ok((pty, child))
+    }
+}
+
+impl AsFd for PseudoTerminal {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
+}
+
+impl AsyncRead for PseudoTerminal {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_read_impl(&self.inner, cx, buf)
+    }
+}
+
+impl AsyncRead for &PseudoTerminal {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_read_impl(&self.inner, cx, buf)
+    }
+}
+
+fn poll_read_impl(
+    fd: &Async<File>,
+    cx: &mut Context<'_>,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    loop {
+        match fd.get_ref().read(buf) {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            result => return Poll::Ready(result),
+        }
+        ready!(fd.poll_readable(cx))?;
+    }
+}
+
+impl AsyncWrite for PseudoTerminal {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_write_impl(&self.inner, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for &PseudoTerminal {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_write_impl(&self.inner, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn poll_write_impl(fd: &Async<File>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+    loop {
+        match fd.get_ref().write(buf) {
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+            result => return Poll::Ready(result),
+        }
+        ready!(fd.poll_writable(cx))?;
+    }
+}
@@ -0,0 +1,41 @@
+use std::os::fd::BorrowedFd;
+
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::watch;
+
+use crate::{sys, AutoResizeError, PseudoTerminal, ResizeError};
+
+/// Keep size of `pty` in sync with the terminal `terminal_fd`, usually stdin of the parent.
+///
+/// `pty` is resized immediately, then again whenever `SIGWINCH` is received.
+/// Never returns unless an error occurs, drop the future to stop.
+pub async fn autoresize(
+    pty: &PseudoTerminal,
+    terminal_fd: BorrowedFd<'_>,
+) -> Result<(), AutoResizeError> {
+    let mut window_changed =
+        signal(SignalKind::window_change()).map_err(AutoResizeError::Signal)?;
+    loop {
+        let (width, height) = sys::get_pty_size(terminal_fd).map_err(AutoResizeError::GetSize)?;
+        pty.resize(width, height).map_err(AutoResizeError::Resize)?;
+        if window_changed.recv().await.is_none() {
+            return Ok(());
+        }
+    }
+}
+
+/// Resize `pty` to every `(width, height)` sent to `sizes`, for terminals not attached to this process.
+///
+/// `pty` is resized to the current value immediately. Returns when the sender is dropped.
+pub async fn autoresize_from_watch(
+    pty: &PseudoTerminal,
+    mut sizes: watch::Receiver<(u32, u32)>,
+) -> Result<(), ResizeError> {
+    loop {
+        let (width, height) = *sizes.borrow_and_update();
+        pty.resize(width, height)?;
+        if sizes.changed().await.is_err() {
+            return Ok(());
+        }
+    }
+}
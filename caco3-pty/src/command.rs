@@ -0,0 +1,270 @@
+use std::ffi::{OsStr, OsString};
+use std::io;
+use std::os::fd::RawFd;
+use std::os::unix::process::CommandExt;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::sys;
+
+/// Which file descriptors of the parent a child may inherit.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum FdPolicy {
+    /// Only standard I/O and file descriptors given to [`PtyCommandBuilder::inherit_fd`].
+    #[default]
+    Explicit,
+    /// Leave file descriptors as they are, those without close-on-exec flag are inherited.
+    Unchanged,
+}
+
+/// Build a command to be spawned in a pseudo terminal.
+///
+/// By default, `TERM` is `xterm-256color`, `COLORTERM` is `truecolor` and only standard I/O
+/// is inherited.
+///
+/// ```ignore
+/// let (pty, child) = PtyCommandBuilder::new("bash")
+///     .clean_env(true)
+///     .keep_env("PATH")
+///     .env("HOME", "/home/guest")
+///     .current_dir("/home/guest")
+///     .uid(1000)
+///     .gid(1000)
+///     .spawn(PseudoTerminal::allocate()?)
+///     .await?;
+/// ```
+#[derive(Debug)]
+pub struct PtyCommandBuilder {
+    program: OsString,
+    args: Vec<OsString>,
+    clean_env: bool,
+    keep_env: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    term: Option<OsString>,
+    colorterm: Option<OsString>,
+    current_dir: Option<PathBuf>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    fd_policy: FdPolicy,
+    inherit_fds: Vec<RawFd>,
+}
+
+impl PtyCommandBuilder {
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_owned(),
+            args: Vec::new(),
+            clean_env: false,
+            keep_env: Vec::new(),
+            envs: Vec::new(),
+            term: Some("xterm-256color".into()),
+            colorterm: Some("truecolor".into()),
+            current_dir: None,
+            uid: None,
+            gid: None,
+            fd_policy: FdPolicy::default(),
+            inherit_fds: Vec::new(),
+        }
+    }
+
+    pub fn get_program(&self) -> &OsStr {
+        &self.program
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args
+            .extend(args.into_iter().map(|arg| arg.as_ref().to_owned()));
+        self
+    }
+
+    /// Don't inherit environment of the parent, except variables given to [`keep_env`](Self::keep_env).
+    pub fn clean_env(mut self, clean: bool) -> Self {
+        self.clean_env = clean;
+        self
+    }
+
+    /// Inherit a variable from the parent in clean environment mode.
+    pub fn keep_env(mut self, key: impl AsRef<OsStr>) -> Self {
+        self.keep_env.push(key.as_ref().to_owned());
+        self
+    }
+
+    pub fn env(mut self, key: impl AsRef<OsStr>, val: impl AsRef<OsStr>) -> Self {
+        self.envs
+            .push((key.as_ref().to_owned(), val.as_ref().to_owned()));
+        self
+    }
+
+    /// Value of `TERM`, overriding other sources; `None` leaves it as is.
+    pub fn term(mut self, term: Option<impl AsRef<OsStr>>) -> Self {
+        self.term = term.map(|term| term.as_ref().to_owned());
+        self
+    }
+
+    /// Value of `COLORTERM`, overriding other sources; `None` leaves it as is.
+    pub fn colorterm(mut self, colorterm: Option<impl AsRef<OsStr>>) -> Self {
+        self.colorterm = colorterm.map(|colorterm| colorterm.as_ref().to_owned());
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Switch the child to this user before exec.
+    ///
+    /// If the parent is root, group defaults to primary group of the user.
+    pub fn uid(mut self, uid: u32) -> Self {
+        self.uid = Some(uid);
+        self
+    }
+
+    /// Switch the child to this group before exec, supplementary groups are dropped if the parent is root.
+    pub fn gid(mut self, gid: u32) -> Self {
+        self.gid = Some(gid);
+        self
+    }
+
+    pub fn fd_policy(mut self, policy: FdPolicy) -> Self {
+        self.fd_policy = policy;
+        self
+    }
+
+    /// Let the child inherit `fd` with the same number, even if it has close-on-exec flag.
+    ///
+    /// `fd` must stay open until the child is spawned.
+    pub fn inherit_fd(mut self, fd: RawFd) -> Self {
+        self.inherit_fds.push(fd);
+        self
+    }
+
+    /// Build the command for any runtime, see [`build`](Self::build) for tokio.
+    ///
+    /// Failures in the child before exec, e.g. switching user, are reported by spawning
+    /// the command with the OS error.
+    pub fn build_std(self) -> Command {
+        // the child can't look up the user database, it is not async-signal-safe
+        let gid = self
+            .resolve_gid()
+            .map_err(|err| err.raw_os_error().unwrap_or(libc::ENOENT));
+        self.build_with_gid(gid)
+    }
+
+    /// Group of the child, if only `uid` is given by root it is primary group of the user,
+    /// otherwise the child would keep groups of root.
+    fn resolve_gid(&self) -> io::Result<Option<u32>> {
+        match (self.uid, self.gid) {
+            (Some(uid), None) if nix::unistd::geteuid().is_root() => {
+                sys::primary_gid(uid).map(Some)
+            }
+            (_, gid) => Ok(gid),
+        }
+    }
+
+    /// `gid` is a group or an errno to fail the child with.
+    fn build_with_gid(self, gid: Result<Option<u32>, i32>) -> Command {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args);
+        if self.clean_env {
+            command.env_clear();
+            for key in &self.keep_env {
+                if let Some(val) = std::env::var_os(key) {
+                    command.env(key, val);
+                }
+            }
+        }
+        command.envs(self.envs);
+        if let Some(term) = self.term {
+            command.env("TERM", term);
+        }
+        if let Some(colorterm) = self.colorterm {
+            command.env("COLORTERM", colorterm);
+        }
+        if let Some(dir) = self.current_dir {
+            command.current_dir(dir);
+        }
+
+        let Self {
+            uid,
+            fd_policy,
+            mut inherit_fds,
+            ..
+        } = self;
+        inherit_fds.sort_unstable();
+        inherit_fds.dedup();
+        unsafe {
+            // errors are returned as is, allocating after fork is not safe in a multithreaded process
+            command.pre_exec(move || {
+                let gid = gid.map_err(io::Error::from_raw_os_error)?;
+                if fd_policy == FdPolicy::Explicit {
+                    sys::set_cloexec_except(&inherit_fds)?;
+                }
+                for &fd in &inherit_fds {
+                    sys::set_cloexec(fd, false)?;
+                }
+                sys::drop_privileges(uid, gid)
+            });
+        }
+        command
+    }
+
+    #[cfg(feature = "tokio")]
+    pub fn build(self) -> tokio::process::Command {
+        self.build_std().into()
+    }
+
+    /// Build the command and spawn it in `pty`.
+    #[cfg(feature = "tokio")]
+    pub async fn spawn(
+        self,
+        pty: crate::PtyPair,
+    ) -> Result<(crate::PseudoTerminal, tokio::process::Child), crate::SpawnError> {
+        let gid = self
+            .resolve_gid()
+            .map_err(crate::SpawnError::DropPrivileges)?;
+        pty.spawn(self.build_with_gid(Ok(gid)).into()).await
+    }
+
+    /// Build the command and spawn it in `pty` for `async-io` based runtimes.
+    #[cfg(feature = "async-io")]
+    pub fn spawn_async_io(
+        self,
+        pty: crate::PtyPair,
+    ) -> Result<(crate::async_io::PseudoTerminal, async_process::Child), crate::SpawnError> {
+        let gid = self
+            .resolve_gid()
+            .map_err(crate::SpawnError::DropPrivileges)?;
+        pty.spawn_async_io(self.build_with_gid(Ok(gid)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_gid() {
+        let uid = nix::unistd::getuid().as_raw();
+        let builder = PtyCommandBuilder::new("true").uid(uid);
+        let expect = nix::unistd::geteuid()
+            .is_root()
+            .then(|| sys::primary_gid(uid).unwrap());
+        assert_eq!(builder.resolve_gid().unwrap(), expect);
+        let builder = builder.gid(12345);
+        assert_eq!(builder.resolve_gid().unwrap(), Some(12345));
+        assert_eq!(PtyCommandBuilder::new("true").resolve_gid().unwrap(), None);
+
+        let err = sys::primary_gid(u32::MAX - 1).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,233 @@
+//! Decode terminal output as UTF-8 without splitting multibyte characters across reads.
+#[cfg(feature = "tokio")]
+use std::io;
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+#[cfg(feature = "tokio")]
+use crate::sys;
+
+/// Incremental UTF-8 decoder for output arriving in chunks.
+///
+/// An incomplete sequence at the end of a chunk is kept until the next chunk, invalid bytes
+/// are replaced with `U+FFFD`.
+///
+/// ```
+/// use caco3_pty::decode::Utf8Decoder;
+///
+/// let bytes = "สวัสดี".as_bytes();
+/// let mut decoder = Utf8Decoder::new();
+/// let mut text = String::new();
+/// decoder.decode(&bytes[..4], &mut text);
+/// assert_eq!(text, "ส");
+/// decoder.decode(&bytes[4..], &mut text);
+/// decoder.finish(&mut text);
+/// assert_eq!(text, "สวัสดี");
+/// ```
+#[derive(Debug, Default)]
+pub struct Utf8Decoder {
+    /// Incomplete sequence from the end of the previous chunk.
+    pending: Vec<u8>,
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append decoded `bytes` to `out`.
+    pub fn decode(&mut self, mut bytes: &[u8], out: &mut String) {
+        while !self.pending.is_empty() && !bytes.is_empty() {
+            self.pending.push(bytes[0]);
+            match std::str::from_utf8(&self.pending) {
+                Ok(s) => {
+                    out.push_str(s);
+                    self.pending.clear();
+                    bytes = &bytes[1..];
+                }
+                Err(err) if err.error_len().is_none() => bytes = &bytes[1..],
+                Err(_) => {
+                    // the pending prefix is a maximal subpart of an invalid sequence, it is
+                    // replaced by one U+FFFD and the byte which doesn't continue it is decoded again
+                    out.push(char::REPLACEMENT_CHARACTER);
+                    self.pending.clear();
+                }
+            }
+        }
+        if !bytes.is_empty() {
+            self.decode_complete(bytes, out);
+        }
+    }
+
+    /// Flush an incomplete sequence at the end of output as `U+FFFD`.
+    pub fn finish(&mut self, out: &mut String) {
+        if !self.pending.is_empty() {
+            self.pending.clear();
+            out.push(char::REPLACEMENT_CHARACTER);
+        }
+    }
+
+    /// Decode `bytes` while nothing is pending, keeping an incomplete sequence at the end.
+    fn decode_complete(&mut self, mut bytes: &[u8], out: &mut String) {
+        debug_assert!(self.pending.is_empty());
+        loop {
+            match std::str::from_utf8(bytes) {
+                Ok(s) => {
+                    out.push_str(s);
+                    return;
+                }
+                Err(err) => {
+                    let (valid, rest) = bytes.split_at(err.valid_up_to());
+                    out.push_str(std::str::from_utf8(valid).expect("checked by from_utf8"));
+                    match err.error_len() {
+                        Some(len) => {
+                            out.push(char::REPLACEMENT_CHARACTER);
+                            bytes = &rest[len..];
+                        }
+                        None => {
+                            self.pending.extend_from_slice(rest);
+                            return;
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Read text from a terminal program, e.g. a [`PseudoTerminal`](crate::PseudoTerminal).
+///
+/// ```ignore
+/// let mut stream = Utf8Stream::new(session.pty());
+/// while let Some(text) = stream.read_str().await? {
+///     print!("{text}");
+/// }
+/// ```
+#[cfg(feature = "tokio")]
+pub struct Utf8Stream<R> {
+    reader: R,
+    decoder: Utf8Decoder,
+    buf: Box<[u8]>,
+    text: String,
+    done: bool,
+}
+
+#[cfg(feature = "tokio")]
+impl<R> Utf8Stream<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            decoder: Utf8Decoder::new(),
+            buf: vec![0; 4096].into_boxed_slice(),
+            text: String::new(),
+            done: false,
+        }
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl<R: AsyncRead + Unpin> Utf8Stream<R> {
+    /// Read the next non-empty chunk of text, `None` at the end of output.
+    pub async fn read_str(&mut self) -> io::Result<Option<&str>> {
+        self.text.clear();
+        while self.text.is_empty() {
+            if self.done {
+                return Ok(None);
+            }
+            let len = sys::read_len(self.reader.read(&mut self.buf).await)?;
+            if len == 0 {
+                self.done = true;
+                self.decoder.finish(&mut self.text);
+            } else {
+                self.decoder.decode(&self.buf[..len], &mut self.text);
+            }
+        }
+        Ok(Some(&self.text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_chunks(chunks: &[&[u8]]) -> String {
+        let mut decoder = Utf8Decoder::new();
+        let mut text = String::new();
+        for chunk in chunks {
+            decoder.decode(chunk, &mut text);
+        }
+        decoder.finish(&mut text);
+        text
+    }
+
+    #[test]
+    fn test_split_multibyte() {
+        let bytes = "aก€😀b".as_bytes();
+        let chunks: Vec<_> = bytes.chunks(1).collect();
+        assert_eq!(decode_chunks(&chunks), "aก€😀b");
+
+        let mut decoder = Utf8Decoder::new();
+        let mut text = String::new();
+        decoder.decode(&bytes[..3], &mut text);
+        assert_eq!(text, "a", "incomplete character is kept");
+        decoder.decode(&bytes[3..], &mut text);
+        assert_eq!(text, "aก€😀b");
+    }
+
+    #[test]
+    fn test_invalid_bytes() {
+        assert_eq!(decode_chunks(&[b"a\xffb"]), "a\u{FFFD}b");
+        assert_eq!(decode_chunks(&[b"a\xff", b"\xfeb"]), "a\u{FFFD}\u{FFFD}b");
+        // a lone continuation byte
+        assert_eq!(decode_chunks(&[b"a", b"\x80", b"b"]), "a\u{FFFD}b");
+    }
+
+    #[test]
+    fn test_truncated_at_end() {
+        assert_eq!(decode_chunks(&[b"a\xe0\xb8"]), "a\u{FFFD}");
+        assert_eq!(decode_chunks(&[b"a\xf0", b"\x9f\x98"]), "a\u{FFFD}");
+
+        let mut decoder = Utf8Decoder::new();
+        let mut text = String::new();
+        decoder.finish(&mut text);
+        assert_eq!(text, "", "nothing is pending");
+    }
+
+    #[test]
+    fn test_invalid_after_prefix() {
+        // maximal subpart of an invalid sequence is replaced by one U+FFFD
+        assert_eq!(decode_chunks(&[b"\xe0\xb8", b"A"]), "\u{FFFD}A");
+        assert_eq!(decode_chunks(&[b"\xe0", b"\xb8A"]), "\u{FFFD}A");
+        assert_eq!(decode_chunks(&[b"\xf0\x9f", b"\xe0\xb8\x81"]), "\u{FFFD}ก");
+        // E0 must be followed by A0..BF, so E0 80 is two maximal subparts
+        assert_eq!(decode_chunks(&[b"\xe0", b"\x80"]), "\u{FFFD}\u{FFFD}");
+    }
+
+    #[test]
+    fn test_same_as_lossy() {
+        let inputs: &[&[u8]] = &[
+            "สวัสดี".as_bytes(),
+            b"\xe0\xb8A\xf0\x9f\x98\x80\xed\xa0\x80z",
+            b"\xc3\x28\xa0\xa1\xe2\x28\xa1\xf0\x28\x8c\xbc\xf8\xa1\xa1\xa1\xa1",
+            b"\xf4\x90\x80\x80\xe1\x80\xc2ok\xe0",
+        ];
+        for bytes in inputs {
+            let expected = String::from_utf8_lossy(bytes);
+            for i in 0..=bytes.len() {
+                for j in i..=bytes.len() {
+                    let chunks = [&bytes[..i], &bytes[i..j], &bytes[j..]];
+                    assert_eq!(
+                        decode_chunks(&chunks),
+                        expected,
+                        "{bytes:x?} split at {i}, {j}"
+                    );
+                }
+            }
+        }
+    }
+}
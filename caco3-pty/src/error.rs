@@ -17,14 +17,61 @@ pub enum SpawnError {
     SetControllingTerminal(std::io::Error),
     Spawn(std::io::Error),
     WrapAsyncFd(std::io::Error),
+    DropPrivileges(std::io::Error),
+}
+
+#[derive(Debug)]
+pub enum FromFdError {
+    NotTerminal,
+    SetNonBlocking(std::io::Error),
+    WrapAsyncFd(std::io::Error),
 }
 
 #[derive(Debug)]
 pub struct ResizeError(pub std::io::Error);
 
+#[derive(Debug)]
+pub struct SizeError(pub std::io::Error);
+
+#[derive(Debug)]
+pub enum CursorPositionError {
+    Write(std::io::Error),
+    Read(std::io::Error),
+    /// Output ended before the terminal replied.
+    Closed,
+    TimedOut,
+}
+
+#[derive(Debug)]
+pub enum AutoResizeError {
+    Signal(std::io::Error),
+    GetSize(std::io::Error),
+    Resize(ResizeError),
+}
+
+#[derive(Debug)]
+pub struct RawModeError(pub std::io::Error);
+
+#[derive(Debug)]
+pub enum WaitError {
+    Read(std::io::Error),
+    Wait(std::io::Error),
+    Kill(KillError),
+}
+
+#[derive(Debug)]
+pub struct KillError(pub std::io::Error);
+
 impl Error for AllocateError {}
 impl Error for SpawnError {}
+impl Error for FromFdError {}
 impl Error for ResizeError {}
+impl Error for SizeError {}
+impl Error for CursorPositionError {}
+impl Error for AutoResizeError {}
+impl Error for RawModeError {}
+impl Error for WaitError {}
+impl Error for KillError {}
 
 impl Display for AllocateError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -64,7 +111,26 @@ impl Display for SpawnError {
             SpawnError::Spawn(err) => write!(f, "failed to spawn child process: {err}"),
             SpawnError::WrapAsyncFd(err) => write!(
                 f,
-                "failed to wrap pseudo terminal file descriptor for use with tokio: {err}"
+                "failed to register pseudo terminal file descriptor with async runtime: {err}"
+            ),
+            SpawnError::DropPrivileges(err) => {
+                write!(f, "failed to switch user or group of child process: {err}")
+            }
+        }
+    }
+}
+
+impl Display for FromFdError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FromFdError::NotTerminal => f.write_str("file descriptor is not a terminal device"),
+            FromFdError::SetNonBlocking(err) => write!(
+                f,
+                "failed to set non-blocking mode on pseudo terminal file descriptor: {err}"
+            ),
+            FromFdError::WrapAsyncFd(err) => write!(
+                f,
+                "failed to register pseudo terminal file descriptor with async runtime: {err}"
             ),
         }
     }
@@ -76,3 +142,67 @@ impl Display for ResizeError {
         write!(f, "failed to resize terminal device: {e}")
     }
 }
+
+impl Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(e) = self;
+        write!(f, "failed to get size of terminal device: {e}")
+    }
+}
+
+impl Display for CursorPositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CursorPositionError::Write(err) => {
+                write!(f, "failed to send cursor position query: {err}")
+            }
+            CursorPositionError::Read(err) => {
+                write!(f, "failed to read cursor position report: {err}")
+            }
+            CursorPositionError::Closed => {
+                f.write_str("terminal closed before reporting cursor position")
+            }
+            CursorPositionError::TimedOut => {
+                f.write_str("terminal did not report cursor position in time")
+            }
+        }
+    }
+}
+
+impl Display for AutoResizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoResizeError::Signal(err) => {
+                write!(f, "failed to listen for window size changes: {err}")
+            }
+            AutoResizeError::GetSize(err) => {
+                write!(f, "failed to get size of controlling terminal: {err}")
+            }
+            AutoResizeError::Resize(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Display for RawModeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(e) = self;
+        write!(f, "failed to switch terminal to raw mode: {e}")
+    }
+}
+
+impl Display for WaitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WaitError::Read(err) => write!(f, "failed to read from pseudo terminal: {err}"),
+            WaitError::Wait(err) => write!(f, "failed to wait for child process: {err}"),
+            WaitError::Kill(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl Display for KillError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(e) = self;
+        write!(f, "failed to signal child process group: {e}")
+    }
+}
@@ -1,7 +1,39 @@
+// without a runtime feature, pseudo terminals can't be spawned and only helpers are usable
+#![cfg_attr(not(any(feature = "tokio", feature = "async-io")), allow(dead_code))]
+
 mod error;
 pub use error::*;
 
+#[cfg(feature = "async-io")]
+pub mod async_io;
+#[cfg(feature = "tokio")]
+mod autoresize;
+mod command;
+pub mod decode;
+#[cfg(feature = "tokio")]
+pub mod manager;
+#[cfg(feature = "tokio")]
 mod nixpty;
-mod sys;
+mod pair;
+#[cfg(feature = "tokio")]
+pub mod proxy;
+pub mod rawmode;
+pub mod scrollback;
+#[cfg(feature = "tokio")]
+mod session;
+#[cfg(feature = "tokio")]
+pub mod stream;
+pub mod sys;
+#[cfg(feature = "tokio")]
+mod watchdog;
 
-pub use nixpty::*;
\ No newline at end of file
+#[cfg(feature = "tokio")]
+pub use autoresize::*;
+pub use command::*;
+#[cfg(feature = "tokio")]
+pub use nixpty::*;
+pub use pair::*;
+#[cfg(feature = "tokio")]
+pub use session::*;
+#[cfg(feature = "tokio")]
+pub use watchdog::*;
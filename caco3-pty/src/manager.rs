@@ -0,0 +1,454 @@
+//! Registry of terminal sessions shared by multiple viewers.
+//!
+//! Every session runs in a background task reading output of the child, which is broadcast
+//! to all attachments of the session and kept in a [`Scrollback`] for viewers attaching later.
+//! Input of every attachment is written to the same pseudo terminal.
+//!
+//! ```ignore
+//! let manager = SessionManager::new();
+//! let id = manager
+//!     .spawn(PtyCommandBuilder::new("bash"), PseudoTerminal::allocate()?)
+//!     .await?;
+//!
+//! let mut viewer = manager.attach(id).unwrap();
+//! viewer.write(b"echo hello\n").await?;
+//! while let Some(event) = viewer.recv().await {
+//!     match event {
+//!         SessionEvent::Output(bytes) => stdout.write_all(&bytes).await?,
+//!         SessionEvent::Resized { width, height } => redraw(width, height),
+//!         SessionEvent::Lagged(_) => redraw_all(),
+//!         SessionEvent::Exited(reason) => println!("{reason}"),
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::ffi::OsString;
+use std::fmt::{self, Display};
+use std::io;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::SystemTime;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::sync::{broadcast, mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+use crate::scrollback::Scrollback;
+use crate::session::DRAIN_IDLE;
+use crate::{
+    sys, ExitReason, KillError, PseudoTerminal, PtyCommandBuilder, PtyPair, PtySession,
+    ResizeError, Signal, SpawnError,
+};
+
+/// Events buffered for each attachment before it starts lagging.
+const EVENT_CAPACITY: usize = 1024;
+/// Input chunks buffered before writers wait for the child to read.
+const INPUT_CAPACITY: usize = 64;
+/// Scrollback of each session unless configured by [`SessionManager::with_scrollback`].
+const DEFAULT_SCROLLBACK_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct SessionId(u64);
+
+impl SessionId {
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for SessionId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// What attachments of a session receive.
+#[derive(Debug, Clone)]
+pub enum SessionEvent {
+    /// Output of the child.
+    Output(Arc<[u8]>),
+    /// The pseudo terminal was resized by one of the attachments or the manager.
+    Resized { width: u32, height: u32 },
+    /// The attachment was too slow and this many events were dropped, the screen should be
+    /// redrawn from scratch.
+    Lagged(u64),
+    /// The child has exited, no more events follow.
+    Exited(ExitReason),
+}
+
+/// Snapshot of a session, as returned by [`SessionManager::list`].
+#[derive(Debug, Clone)]
+pub struct SessionInfo {
+    pub id: SessionId,
+    pub pid: Option<u32>,
+    pub program: OsString,
+    pub created: SystemTime,
+    /// Number of live attachments.
+    pub attached: usize,
+    /// Last size set through the manager, `None` if never resized.
+    pub size: Option<(u32, u32)>,
+}
+
+/// Registry of running sessions keyed by [`SessionId`].
+///
+/// Cloning gives another handle to the same registry. Once every handle is dropped, remaining
+/// sessions are hung up and killed.
+#[derive(Clone)]
+pub struct SessionManager {
+    inner: Arc<Inner>,
+}
+
+struct Inner {
+    sessions: Mutex<HashMap<SessionId, Entry>>,
+    next_id: AtomicU64,
+    /// Empty scrollback cloned for every new session.
+    scrollback: Scrollback,
+}
+
+/// Output of a session, the scrollback is updated together with broadcasting, so attaching
+/// neither misses nor repeats output.
+struct Output {
+    scrollback: Mutex<Scrollback>,
+    events: broadcast::Sender<SessionEvent>,
+}
+
+struct Entry {
+    pid: Option<u32>,
+    program: OsString,
+    created: SystemTime,
+    size: Option<(u32, u32)>,
+    attached: Arc<AtomicUsize>,
+    output: Arc<Output>,
+    requests: mpsc::Sender<Request>,
+    task: JoinHandle<()>,
+}
+
+enum Request {
+    Input(Vec<u8>),
+    Resize {
+        width: u32,
+        height: u32,
+        reply: oneshot::Sender<Result<(), ResizeError>>,
+    },
+}
+
+impl SessionManager {
+    pub fn new() -> Self {
+        Self::with_scrollback(Scrollback::new(DEFAULT_SCROLLBACK_BYTES))
+    }
+
+    /// Keep recent output of every session within limits of `scrollback`, which should be empty.
+    pub fn with_scrollback(scrollback: Scrollback) -> Self {
+        let inner = Inner {
+            sessions: Mutex::default(),
+            next_id: AtomicU64::default(),
+            scrollback,
+        };
+        Self {
+            inner: Arc::new(inner),
+        }
+    }
+
+    /// Spawn `command` in `pty` and register it as a new session.
+    ///
+    /// The session is removed once the child has exited and its output is drained.
+    pub async fn spawn(
+        &self,
+        command: PtyCommandBuilder,
+        pty: PtyPair,
+    ) -> Result<SessionId, SpawnError> {
+        let program = command.get_program().to_owned();
+        let (pty, child) = command.spawn(pty).await?;
+        let session = PtySession::new(pty, child);
+        let pid = session.id();
+        let id = SessionId(self.inner.next_id.fetch_add(1, Ordering::Relaxed));
+        let (events, _) = broadcast::channel(EVENT_CAPACITY);
+        let output = Arc::new(Output {
+            scrollback: Mutex::new(self.inner.scrollback.clone()),
+            events,
+        });
+        let (requests, requests_rx) = mpsc::channel(INPUT_CAPACITY);
+
+        // the lock is held until the entry is inserted, so the task can't remove it before
+        let mut sessions = self.inner.sessions.lock().unwrap();
+        let task = tokio::spawn(run(
+            id,
+            session,
+            output.clone(),
+            requests_rx,
+            Arc::downgrade(&self.inner),
+        ));
+        let entry = Entry {
+            pid,
+            program,
+            created: SystemTime::now(),
+            size: None,
+            attached: Arc::default(),
+            output,
+            requests,
+            task,
+        };
+        sessions.insert(id, entry);
+        Ok(id)
+    }
+
+    /// Start receiving events of a session, `None` if there is no such session.
+    ///
+    /// The first event is the scrollback as [`SessionEvent::Output`], unless it is empty.
+    pub fn attach(&self, id: SessionId) -> Option<Attachment> {
+        let sessions = self.inner.sessions.lock().unwrap();
+        let entry = sessions.get(&id)?;
+        let (history, events) = entry.output.subscribe();
+        entry.attached.fetch_add(1, Ordering::Relaxed);
+        Some(Attachment {
+            id,
+            manager: Arc::downgrade(&self.inner),
+            history,
+            events,
+            requests: entry.requests.clone(),
+            attached: entry.attached.clone(),
+        })
+    }
+
+    /// Same as dropping `attachment`.
+    pub fn detach(&self, attachment: Attachment) {
+        drop(attachment);
+    }
+
+    /// Send `signal` to every process in the process group of a session.
+    pub fn kill(&self, id: SessionId, signal: Signal) -> Result<(), KillError> {
+        let pid = self
+            .inner
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&id)
+            .and_then(|entry| entry.pid);
+        let pid = pid.ok_or_else(no_such_session).map_err(KillError)?;
+        sys::kill_process_group(pid, signal).map_err(KillError)
+    }
+
+    /// Resize the pseudo terminal of a session and notify every attachment.
+    pub async fn resize(&self, id: SessionId, width: u32, height: u32) -> Result<(), ResizeError> {
+        let requests = self
+            .inner
+            .sessions
+            .lock()
+            .unwrap()
+            .get(&id)
+            .map(|entry| entry.requests.clone());
+        let requests = requests.ok_or_else(no_such_session).map_err(ResizeError)?;
+        resize(&requests, width, height).await?;
+        self.inner.set_size(id, width, height);
+        Ok(())
+    }
+
+    /// Running sessions ordered by id.
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let sessions = self.inner.sessions.lock().unwrap();
+        let mut list: Vec<_> = sessions
+            .iter()
+            .map(|(&id, entry)| SessionInfo {
+                id,
+                pid: entry.pid,
+                program: entry.program.clone(),
+                created: entry.created,
+                attached: entry.attached.load(Ordering::Relaxed),
+                size: entry.size,
+            })
+            .collect();
+        list.sort_unstable_by_key(|info| info.id);
+        list
+    }
+}
+
+impl Default for SessionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Inner {
+    fn set_size(&self, id: SessionId, width: u32, height: u32) {
+        if let Some(entry) = self.sessions.lock().unwrap().get_mut(&id) {
+            entry.size = Some((width, height));
+        }
+    }
+}
+
+impl Output {
+    fn send(&self, bytes: &[u8]) {
+        let mut scrollback = self.scrollback.lock().unwrap();
+        scrollback.push(bytes);
+        // no attachment is not an error
+        let _ = self.events.send(SessionEvent::Output(bytes.into()));
+    }
+
+    fn send_event(&self, event: SessionEvent) {
+        let _ = self.events.send(event);
+    }
+
+    fn subscribe(&self) -> (Option<SessionEvent>, broadcast::Receiver<SessionEvent>) {
+        let scrollback = self.scrollback.lock().unwrap();
+        let history =
+            (!scrollback.is_empty()).then(|| SessionEvent::Output(scrollback.snapshot().into()));
+        (history, self.events.subscribe())
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        // dropping the task drops its session, which hangs up and kills the process group
+        for entry in self.sessions.get_mut().unwrap().values() {
+            entry.task.abort();
+        }
+    }
+}
+
+/// A viewer of a session, detached when dropped.
+pub struct Attachment {
+    id: SessionId,
+    manager: Weak<Inner>,
+    history: Option<SessionEvent>,
+    events: broadcast::Receiver<SessionEvent>,
+    requests: mpsc::Sender<Request>,
+    attached: Arc<AtomicUsize>,
+}
+
+impl Attachment {
+    pub fn id(&self) -> SessionId {
+        self.id
+    }
+
+    /// Wait for the next event, `None` after the session has ended.
+    pub async fn recv(&mut self) -> Option<SessionEvent> {
+        if let Some(history) = self.history.take() {
+            return Some(history);
+        }
+        match self.events.recv().await {
+            Ok(event) => Some(event),
+            Err(broadcast::error::RecvError::Lagged(count)) => Some(SessionEvent::Lagged(count)),
+            Err(broadcast::error::RecvError::Closed) => None,
+        }
+    }
+
+    /// Write input to the session, waiting while too much input is pending.
+    ///
+    /// Fail with [`io::ErrorKind::BrokenPipe`] after the session has ended.
+    pub async fn write(&self, input: &[u8]) -> io::Result<()> {
+        self.requests
+            .send(Request::Input(input.to_vec()))
+            .await
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))
+    }
+
+    /// Resize the pseudo terminal of the session and notify every attachment, including this one.
+    pub async fn resize(&self, width: u32, height: u32) -> Result<(), ResizeError> {
+        resize(&self.requests, width, height).await?;
+        if let Some(manager) = self.manager.upgrade() {
+            manager.set_size(self.id, width, height);
+        }
+        Ok(())
+    }
+
+    pub fn detach(self) {}
+}
+
+impl Drop for Attachment {
+    fn drop(&mut self) {
+        self.attached.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+async fn resize(
+    requests: &mpsc::Sender<Request>,
+    width: u32,
+    height: u32,
+) -> Result<(), ResizeError> {
+    let (reply, reply_rx) = oneshot::channel();
+    let request = Request::Resize {
+        width,
+        height,
+        reply,
+    };
+    requests
+        .send(request)
+        .await
+        .map_err(|_| ResizeError(no_such_session()))?;
+    reply_rx.await.map_err(|_| ResizeError(no_such_session()))?
+}
+
+fn no_such_session() -> io::Error {
+    io::Error::from_raw_os_error(libc::ESRCH)
+}
+
+async fn run(
+    id: SessionId,
+    mut session: PtySession,
+    output: Arc<Output>,
+    mut requests: mpsc::Receiver<Request>,
+    manager: Weak<Inner>,
+) {
+    let (pty, child) = session.parts_mut();
+
+    let read_output = async {
+        let mut pty = pty;
+        let mut buf = vec![0; 4096];
+        loop {
+            // errors end output as well since there is nobody to report them to
+            let len = sys::read_len(pty.read(&mut buf).await).unwrap_or(0);
+            if len == 0 {
+                return std::future::pending::<Infallible>().await;
+            }
+            output.send(&buf[..len]);
+        }
+    };
+
+    let handle_requests = async {
+        let mut writer = pty;
+        while let Some(request) = requests.recv().await {
+            match request {
+                // input written after the child closed its terminal is lost anyway
+                Request::Input(input) => {
+                    let _ = writer.write_all(&input).await;
+                }
+                Request::Resize {
+                    width,
+                    height,
+                    reply,
+                } => {
+                    let result = pty.resize(width, height);
+                    if result.is_ok() {
+                        output.send_event(SessionEvent::Resized { width, height });
+                    }
+                    let _ = reply.send(result);
+                }
+            }
+        }
+        std::future::pending::<Infallible>().await
+    };
+
+    let status = tokio::select! {
+        status = child.wait() => status,
+        never = read_output => match never {},
+        never = handle_requests => match never {},
+    };
+
+    drain(pty, &output).await;
+    if let Ok(status) = status {
+        output.send_event(SessionEvent::Exited(status.into()));
+    }
+    if let Some(manager) = manager.upgrade() {
+        manager.sessions.lock().unwrap().remove(&id);
+    }
+}
+
+/// Keep broadcasting output left by the child, until the terminal is idle.
+async fn drain(pty: &PseudoTerminal, output: &Output) {
+    let mut pty = pty;
+    let mut buf = vec![0; 4096];
+    while let Ok(Ok(len @ 1..)) = tokio::time::timeout(DRAIN_IDLE, pty.read(&mut buf)).await {
+        output.send(&buf[..len]);
+    }
+}
@@ -1,47 +1,24 @@
-use nix::fcntl::OFlag;
 use nix::pty::PtyMaster;
 use std::fs::File;
-use std::io::{self, Read, Write};
-use std::os::fd::AsFd;
+use std::future::Future;
+use std::io::{self, IsTerminal, Read, Write};
+use std::os::fd::{AsFd, BorrowedFd, OwnedFd};
+use std::pin::Pin;
+use std::sync::Mutex;
 use std::task::{ready, Poll};
+use std::time::Duration;
 use tokio::io::unix::AsyncFd;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Child;
+use tokio::time::Sleep;
 
-use crate::sys::get_child_terminal_path;
-use crate::{sys, AllocateError, ResizeError, SpawnError};
-
-pub struct PtyPair {
-    pty_master: PtyMaster,
-    child_pty: File,
-}
+use crate::pair::into_owned_fd;
+use crate::{
+    sys, AllocateError, CursorPositionError, FromFdError, PtyPair, ResizeError, SizeError,
+    SpawnError,
+};
 
 impl PtyPair {
-    /// Allocate a new pseudo terminal with file descriptors for the parent and child end of the terminal.
-    fn new() -> Result<Self, AllocateError> {
-        let pty_master = nix::pty::posix_openpt(
-            OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC,
-        )
-        .map_err(io::Error::from)
-        .map_err(AllocateError::Open)?;
-        nix::pty::grantpt(&pty_master)
-            .map_err(io::Error::from)
-            .map_err(AllocateError::Grant)?;
-        nix::pty::unlockpt(&pty_master)
-            .map_err(io::Error::from)
-            .map_err(AllocateError::Unlock)?;
-        let child_pty_path =
-            get_child_terminal_path(&pty_master).map_err(AllocateError::GetChildName)?;
-        let child_pty = std::fs::OpenOptions::new()
-            .read(true)
-            .write(true)
-            .open(child_pty_path)
-            .map_err(AllocateError::OpenChild)?;
-        Ok(Self {
-            pty_master,
-            child_pty,
-        })
-    }
-
     /// Spawn a child process as the session leader of a new process group with the pseudo terminal as controlling terminal.
     ///
     /// Also returns the parent side of the pseudo terminal as [`PseudoTerminal`] object.
@@ -49,28 +26,10 @@ impl PtyPair {
         self,
         mut command: tokio::process::Command,
     ) -> Result<(PseudoTerminal, Child), SpawnError> {
-        let Self {
-            pty_master,
-            child_pty: child_tty_file,
-        } = self;
-        let stdin = child_tty_file;
-        let stdout = stdin.try_clone().map_err(SpawnError::DuplicateStdio)?;
-        let stderr = stdin.try_clone().map_err(SpawnError::DuplicateStdio)?;
-        command.stdin(stdin);
-        command.stdout(stdout);
-        command.stderr(stderr);
-
-        unsafe {
-            command.pre_exec(move || {
-                sys::create_process_group()
-                    .map_err(SpawnError::CreateSession)
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                sys::set_controlling_terminal_to_stdin()
-                    .map_err(SpawnError::SetControllingTerminal)
-                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
-                Ok(())
-            });
-        };
+        let (pty_master, stdio) = self.prepare(command.as_std_mut())?;
+        command.stdin(stdio.stdin);
+        command.stdout(stdio.stdout);
+        command.stderr(stdio.stderr);
         let child = command.spawn().map_err(SpawnError::Spawn)?;
         let pty = PseudoTerminal::new(pty_master)?;
         Ok((pty, child))
@@ -78,7 +37,10 @@ impl PtyPair {
 }
 
 pub struct PseudoTerminal {
-    inner: AsyncFd<PtyMaster>,
+    inner: AsyncFd<File>,
+    read_timeout: Option<Duration>,
+    /// Timer of a pending read, shared by readers through `&PseudoTerminal`.
+    read_timer: Mutex<Option<Pin<Box<Sleep>>>>,
 }
 
 impl PseudoTerminal {
@@ -88,17 +50,116 @@ impl PseudoTerminal {
     }
 
     fn new(pty_master: PtyMaster) -> Result<Self, SpawnError> {
+        let file = File::from(into_owned_fd(pty_master));
+        Self::with_file(file).map_err(SpawnError::WrapAsyncFd)
+    }
+
+    /// Use the parent end of a pseudo terminal opened by other means, e.g. handed over by a
+    /// container runtime.
+    ///
+    /// The file descriptor is switched to non-blocking mode.
+    pub fn from_fd(fd: OwnedFd) -> Result<Self, FromFdError> {
+        if !fd.is_terminal() {
+            return Err(FromFdError::NotTerminal);
+        }
+        sys::set_nonblocking(fd.as_fd()).map_err(FromFdError::SetNonBlocking)?;
+        Self::with_file(File::from(fd)).map_err(FromFdError::WrapAsyncFd)
+    }
+
+    fn with_file(file: File) -> io::Result<Self> {
         Ok(Self {
-            inner: AsyncFd::new(pty_master).map_err(SpawnError::WrapAsyncFd)?,
+            inner: AsyncFd::new(file)?,
+            read_timeout: None,
+            read_timer: Mutex::new(None),
         })
     }
 
+    /// Fail reads with [`io::ErrorKind::TimedOut`] if no output arrives within `timeout`.
+    ///
+    /// `None`, the default, waits forever.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) {
+        self.read_timeout = timeout;
+    }
+
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
     /// Resize the pseudo-terminal.
     ///
     /// Should be called when the terminal emulator changes size.
     pub fn resize(&self, width: u32, height: u32) -> Result<(), ResizeError> {
         sys::resize_pty(self.inner.as_fd(), width, height).map_err(ResizeError)
     }
+
+    /// Current size as `(width, height)`.
+    pub fn size(&self) -> Result<(u32, u32), SizeError> {
+        sys::get_pty_size(self.inner.as_fd()).map_err(SizeError)
+    }
+
+    /// Ask the terminal on the other end for its cursor position with `ESC [ 6 n`.
+    ///
+    /// Only a terminal emulator replies, e.g. when this was opened with [`from_fd`](Self::from_fd)
+    /// on the controlling terminal in raw mode. On the parent end of a pseudo terminal, the query
+    /// is input of the child program instead. Other input read while waiting for the reply is
+    /// discarded.
+    pub async fn query_cursor_position(
+        &self,
+        timeout: Duration,
+    ) -> Result<CursorPosition, CursorPositionError> {
+        let query = async {
+            let mut pty = self;
+            pty.write_all(b"\x1b[6n")
+                .await
+                .map_err(CursorPositionError::Write)?;
+            let mut reply = Vec::new();
+            let mut buf = [0; 64];
+            loop {
+                let len = match sys::read_len(pty.read(&mut buf).await) {
+                    Ok(0) => return Err(CursorPositionError::Closed),
+                    Ok(len) => len,
+                    Err(err) => return Err(CursorPositionError::Read(err)),
+                };
+                reply.extend_from_slice(&buf[..len]);
+                if let Some(position) = parse_cursor_position(&reply) {
+                    return Ok(position);
+                }
+            }
+        };
+        tokio::time::timeout(timeout, query)
+            .await
+            .map_err(|_elapsed| CursorPositionError::TimedOut)?
+    }
+}
+
+/// Cursor position reported by a terminal, 1-based.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct CursorPosition {
+    pub row: u16,
+    pub column: u16,
+}
+
+/// Find a cursor position report `ESC [ row ; column R` in `bytes`.
+fn parse_cursor_position(bytes: &[u8]) -> Option<CursorPosition> {
+    bytes
+        .windows(2)
+        .enumerate()
+        .filter(|(_, window)| window == b"\x1b[")
+        .find_map(|(start, _)| {
+            let report = &bytes[start + 2..];
+            let end = report.iter().position(|&b| b == b'R')?;
+            let (row, column) = std::str::from_utf8(&report[..end]).ok()?.split_once(';')?;
+            Some(CursorPosition {
+                row: row.parse().ok()?,
+                column: column.parse().ok()?,
+            })
+        })
+}
+
+impl AsFd for PseudoTerminal {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.inner.as_fd()
+    }
 }
 
 impl tokio::io::AsyncRead for PseudoTerminal {
@@ -107,7 +168,7 @@ impl tokio::io::AsyncRead for PseudoTerminal {
         cx: &mut std::task::Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        poll_read_impl(&self.inner, cx, buf)
+        poll_read_timeout(&self, cx, buf)
     }
 }
 
@@ -117,12 +178,35 @@ impl tokio::io::AsyncRead for &PseudoTerminal {
         cx: &mut std::task::Context<'_>,
         buf: &mut tokio::io::ReadBuf<'_>,
     ) -> Poll<io::Result<()>> {
-        poll_read_impl(&self.inner, cx, buf)
+        poll_read_timeout(&self, cx, buf)
+    }
+}
+
+fn poll_read_timeout(
+    pty: &PseudoTerminal,
+    cx: &mut std::task::Context<'_>,
+    buf: &mut tokio::io::ReadBuf<'_>,
+) -> Poll<io::Result<()>> {
+    let poll = poll_read_impl(&pty.inner, cx, buf);
+    let Some(timeout) = pty.read_timeout else {
+        return poll;
+    };
+    let mut timer = pty.read_timer.lock().unwrap_or_else(|e| e.into_inner());
+    if poll.is_ready() {
+        *timer = None;
+        return poll;
     }
+    let sleep = timer.get_or_insert_with(|| Box::pin(tokio::time::sleep(timeout)));
+    ready!(sleep.as_mut().poll(cx));
+    *timer = None;
+    Poll::Ready(Err(io::Error::new(
+        io::ErrorKind::TimedOut,
+        "no output from pseudo terminal within read timeout",
+    )))
 }
 
 fn poll_read_impl(
-    fd: &AsyncFd<PtyMaster>,
+    fd: &AsyncFd<File>,
     cx: &mut std::task::Context<'_>,
     buf: &mut tokio::io::ReadBuf<'_>,
 ) -> Poll<io::Result<()>> {
@@ -190,7 +274,7 @@ impl tokio::io::AsyncWrite for &PseudoTerminal {
 }
 
 fn poll_write_impl(
-    fd: &AsyncFd<PtyMaster>,
+    fd: &AsyncFd<File>,
     cx: &mut std::task::Context<'_>,
     buf: &[u8],
 ) -> Poll<Result<usize, io::Error>> {
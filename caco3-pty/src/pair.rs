@@ -0,0 +1,98 @@
+use nix::fcntl::OFlag;
+use nix::pty::PtyMaster;
+use std::fs::File;
+use std::io;
+use std::os::fd::{FromRawFd, IntoRawFd, OwnedFd};
+use std::os::unix::process::CommandExt;
+
+use crate::sys::get_child_terminal_path;
+use crate::{sys, AllocateError, SpawnError};
+
+/// Both ends of a newly allocated pseudo terminal, ready to spawn a child process in.
+pub struct PtyPair {
+    pty_master: PtyMaster,
+    child_pty: File,
+}
+
+impl PtyPair {
+    /// Allocate a new pseudo terminal with file descriptors for the parent and child end of the terminal.
+    pub(crate) fn new() -> Result<Self, AllocateError> {
+        let pty_master = nix::pty::posix_openpt(
+            OFlag::O_RDWR | OFlag::O_NOCTTY | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC,
+        )
+        .map_err(io::Error::from)
+        .map_err(AllocateError::Open)?;
+        nix::pty::grantpt(&pty_master)
+            .map_err(io::Error::from)
+            .map_err(AllocateError::Grant)?;
+        nix::pty::unlockpt(&pty_master)
+            .map_err(io::Error::from)
+            .map_err(AllocateError::Unlock)?;
+        let child_pty_path =
+            get_child_terminal_path(&pty_master).map_err(AllocateError::GetChildName)?;
+        let child_pty = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(child_pty_path)
+            .map_err(AllocateError::OpenChild)?;
+        Ok(Self {
+            pty_master,
+            child_pty,
+        })
+    }
+
+    /// Split into file descriptors of the parent and child end, e.g. to spawn the child by
+    /// other means and attach to the parent end with `PseudoTerminal::from_fd`.
+    ///
+    /// The parent end is non-blocking and both are close-on-exec.
+    pub fn into_parts(self) -> (OwnedFd, OwnedFd) {
+        (into_owned_fd(self.pty_master), self.child_pty.into())
+    }
+
+    /// Make `command` run as the session leader of a new process group with the child end as
+    /// controlling terminal, returning the parent end and standard I/O for the child.
+    ///
+    /// Standard I/O is returned rather than set, as converting `command` for a runtime may reset it.
+    pub(crate) fn prepare(
+        self,
+        command: &mut std::process::Command,
+    ) -> Result<(PtyMaster, ChildStdio), SpawnError> {
+        let Self {
+            pty_master,
+            child_pty: child_tty_file,
+        } = self;
+        let stdin = child_tty_file;
+        let stdout = stdin.try_clone().map_err(SpawnError::DuplicateStdio)?;
+        let stderr = stdin.try_clone().map_err(SpawnError::DuplicateStdio)?;
+
+        unsafe {
+            command.pre_exec(move || {
+                sys::create_process_group()
+                    .map_err(SpawnError::CreateSession)
+                    .map_err(io::Error::other)?;
+                sys::set_controlling_terminal_to_stdin()
+                    .map_err(SpawnError::SetControllingTerminal)
+                    .map_err(io::Error::other)?;
+                Ok(())
+            });
+        };
+        let stdio = ChildStdio {
+            stdin,
+            stdout,
+            stderr,
+        };
+        Ok((pty_master, stdio))
+    }
+}
+
+pub(crate) fn into_owned_fd(pty_master: PtyMaster) -> OwnedFd {
+    // SAFETY: ownership of the file descriptor is transferred from `pty_master`
+    unsafe { OwnedFd::from_raw_fd(pty_master.into_raw_fd()) }
+}
+
+/// Child end of a pseudo terminal for each standard I/O stream of the child process.
+pub(crate) struct ChildStdio {
+    pub stdin: File,
+    pub stdout: File,
+    pub stderr: File,
+}
@@ -0,0 +1,187 @@
+use std::future::{Future, IntoFuture};
+use std::io;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::time::Instant;
+
+use crate::{sys, PseudoTerminal};
+
+/// Bytes copied so far, updated while pumping.
+#[derive(Debug, Default)]
+pub struct ByteCounters {
+    to_pty: AtomicU64,
+    from_pty: AtomicU64,
+}
+
+impl ByteCounters {
+    /// Bytes copied from upstream to the pseudo terminal.
+    pub fn to_pty(&self) -> u64 {
+        self.to_pty.load(Ordering::Relaxed)
+    }
+
+    /// Bytes copied from the pseudo terminal to upstream.
+    pub fn from_pty(&self) -> u64 {
+        self.from_pty.load(Ordering::Relaxed)
+    }
+
+    fn total(&self) -> u64 {
+        self.to_pty() + self.from_pty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum IdleAction {
+    Continue,
+    Stop,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PumpEnd {
+    /// Upstream reached end of input.
+    UpstreamClosed,
+    /// Every child end of the pseudo terminal is closed, usually the child has exited.
+    PtyClosed,
+    /// The idle hook returned [`IdleAction::Stop`].
+    Idle,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PumpStats {
+    pub to_pty: u64,
+    pub from_pty: u64,
+    pub end: PumpEnd,
+}
+
+type IdleHook = Box<dyn FnMut(Duration) -> IdleAction + Send>;
+
+/// Copy bytes both ways between `pty` and `upstream`, e.g. a websocket adapter, until either ends.
+///
+/// A direction only reads after the previous chunk is completely written, so a slow reader
+/// slows its writer down instead of buffering without limit.
+///
+/// ```ignore
+/// let stats = proxy::pump(session.pty(), websocket)
+///     .idle_timeout(Duration::from_secs(600), |_idle| IdleAction::Stop)
+///     .await?;
+/// ```
+pub fn pump<U>(pty: &PseudoTerminal, upstream: U) -> Pump<'_, U> {
+    Pump {
+        pty,
+        upstream,
+        counters: Arc::default(),
+        idle_timeout: None,
+    }
+}
+
+pub struct Pump<'a, U> {
+    pty: &'a PseudoTerminal,
+    upstream: U,
+    counters: Arc<ByteCounters>,
+    idle_timeout: Option<(Duration, IdleHook)>,
+}
+
+impl<'a, U> Pump<'a, U>
+where
+    U: AsyncRead + AsyncWrite + Send + 'a,
+{
+    /// Call `hook` with idle duration after every `timeout` without traffic in either direction.
+    pub fn idle_timeout(
+        mut self,
+        timeout: Duration,
+        hook: impl FnMut(Duration) -> IdleAction + Send + 'static,
+    ) -> Self {
+        self.idle_timeout = Some((timeout, Box::new(hook)));
+        self
+    }
+
+    /// Live byte counters, e.g. for reporting traffic while pumping.
+    pub fn counters(&self) -> Arc<ByteCounters> {
+        self.counters.clone()
+    }
+
+    pub async fn run(self) -> io::Result<PumpStats> {
+        let Self {
+            pty,
+            upstream,
+            counters,
+            idle_timeout,
+        } = self;
+        let (mut upstream_reader, mut upstream_writer) = tokio::io::split(upstream);
+
+        let to_pty = async {
+            let mut pty = pty;
+            let mut buf = vec![0; 4096];
+            loop {
+                let len = upstream_reader.read(&mut buf).await?;
+                if len == 0 {
+                    return Ok::<_, io::Error>(PumpEnd::UpstreamClosed);
+                }
+                pty.write_all(&buf[..len]).await?;
+                counters.to_pty.fetch_add(len as u64, Ordering::Relaxed);
+            }
+        };
+
+        let from_pty = async {
+            let mut pty = pty;
+            let mut buf = vec![0; 4096];
+            loop {
+                let len = sys::read_len(pty.read(&mut buf).await)?;
+                if len == 0 {
+                    upstream_writer.shutdown().await?;
+                    return Ok(PumpEnd::PtyClosed);
+                }
+                upstream_writer.write_all(&buf[..len]).await?;
+                upstream_writer.flush().await?;
+                counters.from_pty.fetch_add(len as u64, Ordering::Relaxed);
+            }
+        };
+
+        let idle = async {
+            let Some((timeout, mut hook)) = idle_timeout else {
+                return std::future::pending().await;
+            };
+            let mut last_total = counters.total();
+            let mut last_activity = Instant::now();
+            let mut next_check = last_activity + timeout;
+            loop {
+                tokio::time::sleep_until(next_check).await;
+                let now = Instant::now();
+                let total = counters.total();
+                if total != last_total {
+                    last_total = total;
+                    last_activity = now;
+                } else if hook(now - last_activity) == IdleAction::Stop {
+                    return Ok(PumpEnd::Idle);
+                }
+                next_check = now + timeout;
+            }
+        };
+
+        let end = tokio::select! {
+            end = to_pty => end,
+            end = from_pty => end,
+            end = idle => end,
+        }?;
+        Ok(PumpStats {
+            to_pty: counters.to_pty(),
+            from_pty: counters.from_pty(),
+            end,
+        })
+    }
+}
+
+impl<'a, U> IntoFuture for Pump<'a, U>
+where
+    U: AsyncRead + AsyncWrite + Send + 'a,
+{
+    type Output = io::Result<PumpStats>;
+    type IntoFuture = Pin<Box<dyn Future<Output = Self::Output> + Send + 'a>>;
+
+    fn into_future(self) -> Self::IntoFuture {
+        Box::pin(self.run())
+    }
+}
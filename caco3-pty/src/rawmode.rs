@@ -0,0 +1,85 @@
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::panic;
+use std::sync::{Mutex, Once};
+
+use nix::sys::termios::{self, SetArg, Termios};
+
+use crate::RawModeError;
+
+/// Original settings of terminals in raw mode, restored by the panic hook.
+static ORIGINALS: Mutex<Vec<(RawFd, Termios)>> = Mutex::new(Vec::new());
+static INSTALL_PANIC_HOOK: Once = Once::new();
+
+/// Terminal in raw mode, original settings are restored on drop.
+///
+/// Settings are also restored before a panic message is printed, so the message is readable
+/// and the terminal is usable even if the guard is not dropped, e.g. with `panic = "abort"`.
+///
+/// ```ignore
+/// let _raw_mode = RawModeGuard::stdin()?;
+/// proxy::pump(&pty, stdio).await?;
+/// ```
+pub struct RawModeGuard<'fd> {
+    fd: BorrowedFd<'fd>,
+    original: Termios,
+}
+
+impl RawModeGuard<'static> {
+    /// Switch stdin of this process to raw mode.
+    pub fn stdin() -> Result<Self, RawModeError> {
+        let stdin = std::io::stdin();
+        let fd = stdin.as_fd();
+        // stdin is open for the lifetime of the process
+        let fd = unsafe { BorrowedFd::borrow_raw(fd.as_raw_fd()) };
+        Self::new(fd)
+    }
+}
+
+impl<'fd> RawModeGuard<'fd> {
+    pub fn new(fd: BorrowedFd<'fd>) -> Result<Self, RawModeError> {
+        let original = termios::tcgetattr(fd).map_err(|e| RawModeError(e.into()))?;
+        let mut raw = original.clone();
+        termios::cfmakeraw(&mut raw);
+
+        INSTALL_PANIC_HOOK.call_once(|| {
+            let hook = panic::take_hook();
+            panic::set_hook(Box::new(move |info| {
+                restore_all();
+                hook(info);
+            }));
+        });
+        originals().push((fd.as_raw_fd(), original.clone()));
+        if let Err(e) = termios::tcsetattr(fd, SetArg::TCSANOW, &raw) {
+            forget(fd.as_raw_fd());
+            return Err(RawModeError(e.into()));
+        }
+        Ok(Self { fd, original })
+    }
+}
+
+impl Drop for RawModeGuard<'_> {
+    fn drop(&mut self) {
+        let _ = termios::tcsetattr(self.fd, SetArg::TCSANOW, &self.original);
+        forget(self.fd.as_raw_fd());
+    }
+}
+
+fn originals() -> std::sync::MutexGuard<'static, Vec<(RawFd, Termios)>> {
+    ORIGINALS.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Stop restoring settings of `fd` on panic.
+fn forget(fd: RawFd) {
+    let mut originals = originals();
+    if let Some(i) = originals.iter().rposition(|(raw_fd, _)| *raw_fd == fd) {
+        originals.remove(i);
+    }
+}
+
+fn restore_all() {
+    for (fd, original) in originals().iter().rev() {
+        // a guard removes its entry before its file descriptor can be closed
+        let fd = unsafe { BorrowedFd::borrow_raw(*fd) };
+        let _ = termios::tcsetattr(fd, SetArg::TCSANOW, original);
+    }
+}
@@ -0,0 +1,168 @@
+//! Recent output of a terminal program, e.g. to show history to a viewer attaching late.
+use std::collections::VecDeque;
+
+/// Ring buffer keeping the last output of a terminal program, limited by bytes and lines.
+///
+/// When old output is evicted, the remaining partial line at the front is evicted as well,
+/// so a snapshot starts at the beginning of a line unless a single line exceeds the byte
+/// limit.
+///
+/// ```
+/// use caco3_pty::scrollback::Scrollback;
+///
+/// let mut scrollback = Scrollback::new(1024).max_lines(2);
+/// scrollback.push(b"one\ntwo\nthree\nfo");
+/// scrollback.push(b"ur");
+/// assert_eq!(scrollback.snapshot(), b"two\nthree\nfour");
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scrollback {
+    buf: VecDeque<u8>,
+    max_bytes: usize,
+    max_lines: Option<usize>,
+    /// Line feeds in `buf`.
+    lines: usize,
+}
+
+impl Scrollback {
+    /// Keep at most `max_bytes` of output.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            buf: VecDeque::new(),
+            max_bytes,
+            max_lines: None,
+            lines: 0,
+        }
+    }
+
+    /// Also keep at most `max_lines` complete lines, in addition to the incomplete last line.
+    pub fn max_lines(mut self, max_lines: usize) -> Self {
+        self.max_lines = Some(max_lines);
+        self.evict();
+        self
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.lines += count_lines(bytes);
+        self.buf.extend(bytes);
+        self.evict();
+    }
+
+    /// Copy of the kept output, oldest first.
+    pub fn snapshot(&self) -> Vec<u8> {
+        let (front, back) = self.buf.as_slices();
+        [front, back].concat()
+    }
+
+    pub fn len(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.lines = 0;
+    }
+
+    fn evict(&mut self) {
+        if self.buf.len() > self.max_bytes {
+            let excess = self.buf.len() - self.max_bytes;
+            let cut_mid_line = self.buf[excess - 1] != b'\n';
+            self.lines -= count_lines(self.buf.range(..excess));
+            self.buf.drain(..excess);
+            if cut_mid_line && self.lines > 0 {
+                self.evict_line();
+            }
+        }
+        let max_lines = self.max_lines.unwrap_or(usize::MAX);
+        while self.lines > max_lines {
+            self.evict_line();
+        }
+    }
+
+    /// Evict bytes up to and including the first line feed.
+    fn evict_line(&mut self) {
+        match self.buf.iter().position(|&b| b == b'\n') {
+            Some(end) => {
+                self.buf.drain(..=end);
+                self.lines -= 1;
+            }
+            None => self.buf.clear(),
+        }
+    }
+}
+
+fn count_lines<'a>(bytes: impl IntoIterator<Item = &'a u8>) -> usize {
+    bytes.into_iter().filter(|&&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(scrollback: &Scrollback) -> String {
+        String::from_utf8(scrollback.snapshot()).unwrap()
+    }
+
+    #[test]
+    fn test_max_bytes() {
+        let mut scrollback = Scrollback::new(8);
+        scrollback.push(b"one\ntwo\n");
+        assert_eq!(snapshot(&scrollback), "one\ntwo\n");
+        scrollback.push(b"six\n");
+        assert_eq!(snapshot(&scrollback), "two\nsix\n");
+        assert_eq!(scrollback.len(), 8);
+
+        // a single line longer than the limit keeps its last bytes
+        scrollback.push(b"abcdefghij");
+        assert_eq!(snapshot(&scrollback), "cdefghij");
+        // once a line feed arrives, the partial line at the front is evicted
+        scrollback.push(b"\nk");
+        assert_eq!(snapshot(&scrollback), "k");
+    }
+
+    #[test]
+    fn test_zero_max_bytes() {
+        let mut scrollback = Scrollback::new(0);
+        scrollback.push(b"one\ntwo");
+        assert!(scrollback.is_empty());
+        scrollback.push(b"\n");
+        assert!(scrollback.is_empty());
+        assert_eq!(scrollback.lines, 0);
+    }
+
+    #[test]
+    fn test_whole_lines() {
+        // cut right after a line feed, nothing else is evicted
+        let mut scrollback = Scrollback::new(3);
+        scrollback.push(b"ab\ncd\n");
+        assert_eq!(snapshot(&scrollback), "cd\n");
+
+        // cut in the middle of a line, the rest of that line is evicted
+        let mut scrollback = Scrollback::new(6);
+        scrollback.push(b"abc\nde\nfg");
+        assert_eq!(snapshot(&scrollback), "de\nfg");
+        assert_eq!(scrollback.lines, 1);
+
+        // cut in the middle of the last line, which is kept
+        let mut scrollback = Scrollback::new(5);
+        scrollback.push(b"ab\ncdefgh");
+        assert_eq!(snapshot(&scrollback), "defgh");
+        assert_eq!(scrollback.lines, 0);
+    }
+
+    #[test]
+    fn test_max_lines() {
+        let mut scrollback = Scrollback::new(1024).max_lines(0);
+        scrollback.push(b"one\ntwo");
+        assert_eq!(snapshot(&scrollback), "two");
+
+        let mut scrollback = Scrollback::new(1024);
+        scrollback.push(b"one\ntwo\nthree\n");
+        let scrollback = scrollback.max_lines(1);
+        assert_eq!(snapshot(&scrollback), "three\n");
+    }
+}
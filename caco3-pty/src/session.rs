@@ -0,0 +1,231 @@
+use std::fmt::{self, Display};
+use std::io;
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::time::Duration;
+
+use tokio::io::AsyncReadExt;
+use tokio::process::Child;
+use tokio::time::Instant;
+
+pub use nix::sys::signal::Signal;
+
+use crate::{sys, KillError, PseudoTerminal, WaitError};
+
+/// How long to keep reading output after the child has exited.
+///
+/// Background processes may still hold the child terminal open, waiting for them to close it
+/// could take forever.
+pub(crate) const DRAIN_IDLE: Duration = Duration::from_millis(100);
+
+/// Limits of [`PtySession::wait_with_output`].
+#[derive(Debug, Clone, Copy)]
+pub struct OutputLimits {
+    /// Maximum bytes of output kept, further output is read and discarded.
+    pub max_bytes: usize,
+    /// Kill the process group if the child doesn't exit in time.
+    pub timeout: Option<Duration>,
+}
+
+impl Default for OutputLimits {
+    fn default() -> Self {
+        Self {
+            max_bytes: 1024 * 1024,
+            timeout: None,
+        }
+    }
+}
+
+/// How a child process finished.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ExitReason {
+    Code(i32),
+    /// Killed by a signal, raw number is kept as real-time signals have no [`Signal`] variant.
+    Signal {
+        signal: i32,
+        core_dumped: bool,
+    },
+}
+
+impl ExitReason {
+    /// `None` for real-time signals.
+    pub fn signal(&self) -> Option<Signal> {
+        match *self {
+            ExitReason::Code(_) => None,
+            ExitReason::Signal { signal, .. } => Signal::try_from(signal).ok(),
+        }
+    }
+}
+
+impl From<ExitStatus> for ExitReason {
+    fn from(status: ExitStatus) -> Self {
+        match (status.code(), status.signal()) {
+            (Some(code), _) => ExitReason::Code(code),
+            (None, Some(signal)) => ExitReason::Signal {
+                signal,
+                core_dumped: status.core_dumped(),
+            },
+            // a waited child either exited or was killed, stopped status is not reported
+            (None, None) => ExitReason::Code(status.into_raw()),
+        }
+    }
+}
+
+impl Display for ExitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ExitReason::Code(code) => write!(f, "exited with code {code}"),
+            ExitReason::Signal {
+                signal,
+                core_dumped,
+            } => {
+                match self.signal() {
+                    Some(name) => write!(f, "killed by signal {name}")?,
+                    None => write!(f, "killed by signal {signal}")?,
+                }
+                if core_dumped {
+                    f.write_str(" (core dumped)")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SessionOutput {
+    pub status: ExitStatus,
+    pub exit_reason: ExitReason,
+    pub output: Vec<u8>,
+    /// Output exceeded [`OutputLimits::max_bytes`].
+    pub truncated: bool,
+    /// Child was killed after [`OutputLimits::timeout`].
+    pub timed_out: bool,
+}
+
+/// A child process running in a pseudo terminal.
+///
+/// When dropped while the child is still running, its process group is hung up and killed,
+/// the child is then reaped in background by tokio.
+pub struct PtySession {
+    pty: PseudoTerminal,
+    child: Child,
+}
+
+impl PtySession {
+    pub fn new(pty: PseudoTerminal, child: Child) -> Self {
+        Self { pty, child }
+    }
+
+    pub fn pty(&self) -> &PseudoTerminal {
+        &self.pty
+    }
+
+    pub fn child(&self) -> &Child {
+        &self.child
+    }
+
+    /// Borrow both halves, so output can be read while waiting for the child.
+    pub(crate) fn parts_mut(&mut self) -> (&PseudoTerminal, &mut Child) {
+        (&self.pty, &mut self.child)
+    }
+
+    /// Process id of the child, which is also its process group id.
+    ///
+    /// Return `None` once the child has been reaped.
+    pub fn id(&self) -> Option<u32> {
+        self.child.id()
+    }
+
+    /// Wait for the child to exit without reading output.
+    ///
+    /// Output must be read concurrently through [`pty`](Self::pty), otherwise the child may
+    /// block on a full terminal buffer.
+    pub async fn wait(&mut self) -> Result<ExitStatus, WaitError> {
+        self.child.wait().await.map_err(WaitError::Wait)
+    }
+
+    /// Read output of the child until it exits.
+    pub async fn wait_with_output(
+        &mut self,
+        limits: OutputLimits,
+    ) -> Result<SessionOutput, WaitError> {
+        let deadline = limits.timeout.map(|timeout| Instant::now() + timeout);
+        let mut output = Vec::new();
+        let mut truncated = false;
+        let mut timed_out = false;
+        let mut buf = [0; 4096];
+        let mut pty_open = true;
+        let mut pty = &self.pty;
+        let pgid = self.id();
+
+        let status = loop {
+            tokio::select! {
+                result = pty.read(&mut buf), if pty_open => {
+                    let len = sys::read_len(result).map_err(WaitError::Read)?;
+                    pty_open = len > 0;
+                    append(&mut output, &buf[..len], limits.max_bytes, &mut truncated);
+                }
+                status = self.child.wait() => break status.map_err(WaitError::Wait)?,
+                _ = sleep_until(deadline), if !timed_out => {
+                    timed_out = true;
+                    kill_process_group(pgid, Signal::SIGKILL).map_err(WaitError::Kill)?;
+                }
+            }
+        };
+
+        while pty_open {
+            match tokio::time::timeout(DRAIN_IDLE, pty.read(&mut buf)).await {
+                Ok(result) => {
+                    let len = sys::read_len(result).map_err(WaitError::Read)?;
+                    pty_open = len > 0;
+                    append(&mut output, &buf[..len], limits.max_bytes, &mut truncated);
+                }
+                Err(_elapsed) => break,
+            }
+        }
+
+        Ok(SessionOutput {
+            status,
+            exit_reason: status.into(),
+            output,
+            truncated,
+            timed_out,
+        })
+    }
+
+    /// Send `signal` to every process in the process group of the child.
+    pub fn kill_process_group(&self, signal: Signal) -> Result<(), KillError> {
+        kill_process_group(self.id(), signal)
+    }
+}
+
+impl Drop for PtySession {
+    fn drop(&mut self) {
+        if let Ok(None) = self.child.try_wait() {
+            let _ = self.kill_process_group(Signal::SIGHUP);
+            let _ = self.kill_process_group(Signal::SIGKILL);
+            let _ = self.child.try_wait();
+        }
+    }
+}
+
+fn kill_process_group(pgid: Option<u32>, signal: Signal) -> Result<(), KillError> {
+    let pgid = pgid
+        .ok_or_else(|| io::Error::from_raw_os_error(libc::ESRCH))
+        .map_err(KillError)?;
+    sys::kill_process_group(pgid, signal).map_err(KillError)
+}
+
+fn append(output: &mut Vec<u8>, bytes: &[u8], max_bytes: usize, truncated: &mut bool) {
+    let len = bytes.len().min(max_bytes.saturating_sub(output.len()));
+    output.extend_from_slice(&bytes[..len]);
+    *truncated |= len < bytes.len();
+}
+
+async fn sleep_until(deadline: Option<Instant>) {
+    match deadline {
+        Some(deadline) => tokio::time::sleep_until(deadline).await,
+        None => std::future::pending().await,
+    }
+}
@@ -0,0 +1,232 @@
+use std::collections::VecDeque;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+
+use crate::sys;
+
+const ESC: u8 = 0x1b;
+const BEL: u8 = 0x07;
+
+/// Structured output of a terminal program.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OutputEvent {
+    /// Text terminated by a line feed, without the line terminator and control sequences.
+    ///
+    /// Invalid UTF-8 is replaced with `U+FFFD`.
+    Line(String),
+    CursorMove(CursorMove),
+    /// Window title set by `OSC 0` or `OSC 2`.
+    Title(String),
+    /// Other control sequence introduced by `CSI`, e.g. `ESC [ 1 ; 31 m` is
+    /// `Csi { params: vec![1, 31], action: 'm' }`.
+    Csi {
+        params: Vec<u16>,
+        action: char,
+    },
+}
+
+/// Cursor movement, rows and columns are 1-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CursorMove {
+    Up(u16),
+    Down(u16),
+    Forward(u16),
+    Back(u16),
+    NextLine(u16),
+    PreviousLine(u16),
+    Column(u16),
+    Position { row: u16, column: u16 },
+}
+
+/// Stream of [`OutputEvent`] parsed from output of a terminal program, e.g. a [`PseudoTerminal`](crate::PseudoTerminal).
+///
+/// Control sequences are yielded as soon as they are parsed, even in the middle of a line.
+/// Incomplete last line is yielded at the end of output. The stream ends on read error,
+/// see [`error`](Self::error).
+pub struct OutputLines<R> {
+    reader: R,
+    parser: Parser,
+    events: VecDeque<OutputEvent>,
+    buf: Box<[u8]>,
+    done: bool,
+    error: Option<io::Error>,
+}
+
+impl<R> OutputLines<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            parser: Parser::default(),
+            events: VecDeque::new(),
+            buf: vec![0; 4096].into_boxed_slice(),
+            done: false,
+            error: None,
+        }
+    }
+
+    /// Read error ending the stream.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: AsyncRead + Unpin> Stream for OutputLines<R> {
+    type Item = OutputEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(event) = this.events.pop_front() {
+                return Poll::Ready(Some(event));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            let mut buf = ReadBuf::new(&mut this.buf);
+            match ready!(Pin::new(&mut this.reader).poll_read(cx, &mut buf)) {
+                Ok(()) if buf.filled().is_empty() => this.finish(),
+                Ok(()) => {
+                    for &byte in buf.filled() {
+                        this.parser.feed(byte, &mut this.events);
+                    }
+                }
+                Err(err) if sys::is_closed(&err) => this.finish(),
+                Err(err) => {
+                    this.error = Some(err);
+                    this.finish();
+                }
+            }
+        }
+    }
+}
+
+impl<R> OutputLines<R> {
+    fn finish(&mut self) {
+        self.parser.finish(&mut self.events);
+        self.done = true;
+    }
+}
+
+#[derive(Debug, Default)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    /// `ESC` followed by intermediate bytes, e.g. `ESC ( B`.
+    EscapeIntermediate,
+    Csi,
+    Osc,
+    /// `ESC` in OSC, the first byte of string terminator `ESC \`.
+    OscEscape,
+}
+
+#[derive(Debug, Default)]
+struct Parser {
+    state: State,
+    line: Vec<u8>,
+    sequence: Vec<u8>,
+}
+
+impl Parser {
+    fn feed(&mut self, byte: u8, events: &mut VecDeque<OutputEvent>) {
+        match self.state {
+            State::Ground => match byte {
+                ESC => self.state = State::Escape,
+                b'\n' => {
+                    // terminals translate `\n` to `\r\n`, so `\r\n` may arrive as `\r\r\n`
+                    while self.line.last() == Some(&b'\r') {
+                        self.line.pop();
+                    }
+                    let line = String::from_utf8_lossy(&self.line).into_owned();
+                    events.push_back(OutputEvent::Line(line));
+                    self.line.clear();
+                }
+                _ => self.line.push(byte),
+            },
+            State::Escape => {
+                self.sequence.clear();
+                self.state = match byte {
+                    b'[' => State::Csi,
+                    b']' => State::Osc,
+                    0x20..=0x2f => State::EscapeIntermediate,
+                    _ => State::Ground,
+                };
+            }
+            State::EscapeIntermediate => {
+                if !(0x20..=0x2f).contains(&byte) {
+                    self.state = State::Ground;
+                }
+            }
+            State::Csi => match byte {
+                0x40..=0x7e => {
+                    events.push_back(csi_event(&self.sequence, byte as char));
+                    self.state = State::Ground;
+                }
+                _ => self.sequence.push(byte),
+            },
+            State::Osc => match byte {
+                BEL => self.end_osc(events),
+                ESC => self.state = State::OscEscape,
+                _ => self.sequence.push(byte),
+            },
+            State::OscEscape => {
+                // anything other than `\` also terminates OSC, and is dropped
+                self.end_osc(events);
+            }
+        }
+    }
+
+    fn end_osc(&mut self, events: &mut VecDeque<OutputEvent>) {
+        let osc = String::from_utf8_lossy(&self.sequence);
+        if let Some(("0" | "2", title)) = osc.split_once(';') {
+            events.push_back(OutputEvent::Title(title.to_owned()));
+        }
+        self.state = State::Ground;
+    }
+
+    fn finish(&mut self, events: &mut VecDeque<OutputEvent>) {
+        if !self.line.is_empty() {
+            let line = String::from_utf8_lossy(&self.line).into_owned();
+            events.push_back(OutputEvent::Line(line));
+            self.line.clear();
+        }
+    }
+}
+
+fn csi_event(sequence: &[u8], action: char) -> OutputEvent {
+    // private parameters such as `?25` are kept as numbers, their prefix is ignored
+    // omitted parameters are 0, e.g. `ESC [ ; 5 H`
+    let sequence = String::from_utf8_lossy(sequence);
+    let sequence = sequence.trim_start_matches(['?', '>', '<', '=']);
+    let params: Vec<u16> = match sequence {
+        "" => Vec::new(),
+        _ => sequence
+            .split(';')
+            .map(|param| param.parse().unwrap_or(0))
+            .collect(),
+    };
+    let param = |i: usize| params.get(i).copied().filter(|&n| n > 0).unwrap_or(1);
+    let cursor_move = match action {
+        'A' => CursorMove::Up(param(0)),
+        'B' => CursorMove::Down(param(0)),
+        'C' => CursorMove::Forward(param(0)),
+        'D' => CursorMove::Back(param(0)),
+        'E' => CursorMove::NextLine(param(0)),
+        'F' => CursorMove::PreviousLine(param(0)),
+        'G' => CursorMove::Column(param(0)),
+        'H' | 'f' => CursorMove::Position {
+            row: param(0),
+            column: param(1),
+        },
+        _ => return OutputEvent::Csi { params, action },
+    };
+    OutputEvent::CursorMove(cursor_move)
+}
@@ -1,7 +1,8 @@
+//! Terminal ioctls on raw file descriptors.
 use nix::pty::PtyMaster;
 use std::ffi::c_int;
 use std::io;
-use std::os::fd::{AsRawFd, BorrowedFd};
+use std::os::fd::{AsRawFd, BorrowedFd, RawFd};
 use std::path::PathBuf;
 
 /// Resize a pseudo terminal using an ioctl.
@@ -23,8 +24,40 @@ pub fn resize_pty(file: BorrowedFd<'_>, width: u32, height: u32) -> io::Result<(
     }
 }
 
+/// Get the size of a terminal as `(width, height)` using an ioctl.
+///
+/// Works with either end of a pseudo terminal as well as any other terminal device.
+pub fn get_pty_size(file: BorrowedFd<'_>) -> io::Result<(u32, u32)> {
+    unsafe {
+        let mut winsz: libc::winsize = std::mem::zeroed();
+        #[allow(clippy::useless_conversion)] // Not useless on all platforms.
+        check_return(libc::ioctl(
+            file.as_raw_fd(),
+            libc::TIOCGWINSZ.into(),
+            &mut winsz,
+        ))?;
+        Ok((winsz.ws_col.into(), winsz.ws_row.into()))
+    }
+}
+
 /// Set the controlling terminal of the process group.
-pub fn set_controlling_terminal_to_stdin() -> io::Result<()> {
+/// Returns `true` if reading the parent end failed because every child end is closed, which
+/// is reported with `EIO` rather than end of file.
+#[cfg(feature = "tokio")]
+pub(crate) fn is_closed(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::EIO)
+}
+
+/// Result of reading the parent end, with [`is_closed`] mapped to end of file.
+#[cfg(feature = "tokio")]
+pub(crate) fn read_len(result: io::Result<usize>) -> io::Result<usize> {
+    match result {
+        Err(err) if is_closed(&err) => Ok(0),
+        result => result,
+    }
+}
+
+pub(crate) fn set_controlling_terminal_to_stdin() -> io::Result<()> {
     unsafe {
         #[allow(clippy::useless_conversion)] // Not useless on all platforms.
         check_return(libc::ioctl(0, libc::TIOCSCTTY.into(), 0))?;
@@ -42,14 +75,14 @@ fn check_return(value: c_int) -> io::Result<c_int> {
 }
 
 /// Create a new process group of which the calling process will be the session leader.
-pub fn create_process_group() -> io::Result<()> {
+pub(crate) fn create_process_group() -> io::Result<()> {
     let _sid = nix::unistd::setsid()?;
     Ok(())
 }
 
 #[cfg(target_os = "linux")]
 /// Get the path of the child terminal device.
-pub fn get_child_terminal_path(pty_master: &PtyMaster) -> io::Result<PathBuf> {
+pub(crate) fn get_child_terminal_path(pty_master: &PtyMaster) -> io::Result<PathBuf> {
     nix::pty::ptsname_r(pty_master)
         .map(PathBuf::from)
         .map_err(io::Error::from)
@@ -57,7 +90,112 @@ pub fn get_child_terminal_path(pty_master: &PtyMaster) -> io::Result<PathBuf> {
 
 #[cfg(target_os = "macos")]
 /// Get the path of the child terminal device.
-pub fn get_child_terminal_path(pty_master: &PtyMaster) -> io::Result<PathBuf> {
+pub(crate) fn get_child_terminal_path(pty_master: &PtyMaster) -> io::Result<PathBuf> {
     let slave_name = unsafe { nix::pty::ptsname(pty_master) };
     slave_name.map(PathBuf::from).map_err(io::Error::from)
 }
+
+/// Send a signal to every process in a process group.
+#[cfg(feature = "tokio")]
+pub(crate) fn kill_process_group(pgid: u32, signal: nix::sys::signal::Signal) -> io::Result<()> {
+    let pgid = i32::try_from(pgid).map_err(|_| io::Error::from_raw_os_error(libc::ESRCH))?;
+    nix::sys::signal::killpg(nix::unistd::Pid::from_raw(pgid), signal)?;
+    Ok(())
+}
+
+/// Set or clear the close-on-exec flag of a file descriptor.
+pub(crate) fn set_cloexec(fd: RawFd, cloexec: bool) -> io::Result<()> {
+    unsafe {
+        let flags = check_return(libc::fcntl(fd, libc::F_GETFD))?;
+        let flags = if cloexec {
+            flags | libc::FD_CLOEXEC
+        } else {
+            flags & !libc::FD_CLOEXEC
+        };
+        check_return(libc::fcntl(fd, libc::F_SETFD, flags))?;
+        Ok(())
+    }
+}
+
+/// Set the non-blocking flag of a file descriptor.
+#[cfg(feature = "tokio")]
+pub(crate) fn set_nonblocking(fd: BorrowedFd<'_>) -> io::Result<()> {
+    unsafe {
+        let flags = check_return(libc::fcntl(fd.as_raw_fd(), libc::F_GETFL))?;
+        check_return(libc::fcntl(
+            fd.as_raw_fd(),
+            libc::F_SETFL,
+            flags | libc::O_NONBLOCK,
+        ))?;
+        Ok(())
+    }
+}
+
+/// Set the close-on-exec flag of every file descriptor after standard I/O, except `keep`.
+///
+/// `keep` must be sorted. Async-signal-safe, for use in `pre_exec`.
+pub(crate) fn set_cloexec_except(keep: &[RawFd]) -> io::Result<()> {
+    let mut first: RawFd = 3;
+    for &fd in keep.iter().filter(|&&fd| fd >= 3) {
+        if fd > first {
+            set_cloexec_range(first, fd - 1)?;
+        }
+        first = first.max(fd.saturating_add(1));
+    }
+    set_cloexec_range(first, RawFd::MAX)
+}
+
+fn set_cloexec_range(first: RawFd, last: RawFd) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    unsafe {
+        // since Linux 5.11
+        let ret = libc::syscall(
+            libc::SYS_close_range,
+            first as libc::c_uint,
+            last as libc::c_uint,
+            libc::CLOSE_RANGE_CLOEXEC,
+        );
+        if ret == 0 {
+            return Ok(());
+        }
+    }
+    let max_fd = unsafe { libc::sysconf(libc::_SC_OPEN_MAX) }.clamp(0, 65536) as RawFd;
+    for fd in first..=last.min(max_fd - 1) {
+        match set_cloexec(fd, true) {
+            Err(err) if err.raw_os_error() == Some(libc::EBADF) => {}
+            result => result?,
+        }
+    }
+    Ok(())
+}
+
+/// Primary group of a user, looked up in the user database.
+pub(crate) fn primary_gid(uid: u32) -> io::Result<u32> {
+    match nix::unistd::User::from_uid(uid.into())? {
+        Some(user) => Ok(user.gid.as_raw()),
+        None => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no user with uid {uid}, set gid explicitly"),
+        )),
+    }
+}
+
+/// Switch to a user and group, dropping supplementary groups if the caller is root.
+///
+/// `gid` must be given with `uid`, otherwise root would keep its groups.
+/// Async-signal-safe, for use in `pre_exec`.
+pub(crate) fn drop_privileges(uid: Option<u32>, gid: Option<u32>) -> io::Result<()> {
+    unsafe {
+        if let Some(gid) = gid {
+            // only root may change supplementary groups
+            if libc::geteuid() == 0 {
+                check_return(libc::setgroups(1, &gid))?;
+            }
+            check_return(libc::setgid(gid))?;
+        }
+        if let Some(uid) = uid {
+            check_return(libc::setuid(uid))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,91 @@
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+use crate::sys;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    Output(Vec<u8>),
+    /// No output arrived within the window, yielded again after every window of silence.
+    Idle {
+        /// Time since the last output, or since watching started.
+        since: Duration,
+    },
+}
+
+/// Watch output of `reader`, e.g. a [`PseudoTerminal`](crate::PseudoTerminal), for silence longer than `window`.
+///
+/// The stream ends when output ends.
+///
+/// ```ignore
+/// let mut events = watchdog(session.pty(), Duration::from_secs(60));
+/// while let Some(event) = events.next().await {
+///     if let WatchdogEvent::Idle { since } = event? {
+///         warn!("Child is silent for {since:?}, killing it");
+///         session.kill_process_group(Signal::SIGTERM)?;
+///     }
+/// }
+/// ```
+pub fn watchdog<R>(reader: R, window: Duration) -> Watchdog<R> {
+    let now = Instant::now();
+    Watchdog {
+        reader,
+        window,
+        last_output: now,
+        timer: Box::pin(tokio::time::sleep_until(now + window)),
+        buf: vec![0; 4096].into_boxed_slice(),
+        done: false,
+    }
+}
+
+pub struct Watchdog<R> {
+    reader: R,
+    window: Duration,
+    last_output: Instant,
+    timer: Pin<Box<Sleep>>,
+    buf: Box<[u8]>,
+    done: bool,
+}
+
+impl<R: AsyncRead + Unpin> Stream for Watchdog<R> {
+    type Item = io::Result<WatchdogEvent>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        let mut buf = ReadBuf::new(&mut this.buf);
+        if let Poll::Ready(result) = Pin::new(&mut this.reader).poll_read(cx, &mut buf) {
+            let output = match result {
+                Ok(()) => buf.filled(),
+                Err(err) if sys::is_closed(&err) => &[],
+                Err(err) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(err)));
+                }
+            };
+            if output.is_empty() {
+                this.done = true;
+                return Poll::Ready(None);
+            }
+            let output = output.to_vec();
+            this.last_output = Instant::now();
+            this.timer.as_mut().reset(this.last_output + this.window);
+            return Poll::Ready(Some(Ok(WatchdogEvent::Output(output))));
+        }
+        ready!(this.timer.as_mut().poll(cx));
+        let now = Instant::now();
+        this.timer.as_mut().reset(now + this.window);
+        Poll::Ready(Some(Ok(WatchdogEvent::Idle {
+            since: now - this.last_output,
+        })))
+    }
+}
@@ -0,0 +1,99 @@
+//! Serialization of `byte_unit::Byte` in an appropriate unit, generalized over unit type and
+//! precision.
+use byte_unit::{Byte, UnitType};
+use serde::{Serialize, Serializer};
+
+/// Precision of the shortest representation, e.g. `2 KiB` or `1.5 KiB`.
+pub const SHORTEST: u8 = u8::MAX;
+
+pub struct Serde<T, const BINARY: bool, const PRECISION: u8> {
+    inner: T,
+}
+
+impl<T, const BINARY: bool, const PRECISION: u8> Serde<T, BINARY, PRECISION> {
+    pub(super) fn new(inner: T) -> Self {
+        Self { inner }
+    }
+}
+
+impl<const BINARY: bool, const PRECISION: u8> Serialize for Serde<Byte, BINARY, PRECISION> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let unit_type = if BINARY {
+            UnitType::Binary
+        } else {
+            UnitType::Decimal
+        };
+        let byte = self.inner.get_appropriate_unit(unit_type);
+        if PRECISION == SHORTEST {
+            return Serialize::serialize(&byte, serializer);
+        }
+        let precision = usize::from(PRECISION);
+        if serializer.is_human_readable() {
+            serializer.collect_str(&format_args!("{byte:.precision$}"))
+        } else {
+            serializer.collect_str(&format_args!("{byte:-.precision$}"))
+        }
+    }
+}
+
+impl<const BINARY: bool, const PRECISION: u8> Serialize for Serde<Option<Byte>, BINARY, PRECISION> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.inner {
+            Some(byte) => serializer.serialize_some(&<Serde<_, BINARY, PRECISION>>::new(byte)),
+            None => serializer.serialize_none(),
+        }
+    }
+}
+
+// n.b. `$ty` must implement Copy
+macro_rules! impl_serialize_ref {
+    (@deref $expr:expr, $lt:lifetime) => {
+        * $expr
+    };
+    (@deref $expr:expr, $lt0:lifetime, $($lt:lifetime),+) => {
+        * impl_serialize_ref!(@deref $expr, $($lt),+)
+    };
+    ($ty:ty, <$($lt:lifetime),+>) => {
+        impl<$($lt,)+ const BINARY: bool, const PRECISION: u8> Serialize
+            for Serde<$(&$lt)+ $ty, BINARY, PRECISION>
+        {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                let inner: $ty = impl_serialize_ref!(@deref self.inner, $($lt),+);
+                <Serde<_, BINARY, PRECISION>>::new(inner).serialize(serializer)
+            }
+        }
+    };
+}
+
+impl_serialize_ref!(Byte, <'a>);
+impl_serialize_ref!(Byte, <'a, 'b>);
+impl_serialize_ref!(Option<Byte>, <'a>);
+impl_serialize_ref!(Option<Byte>, <'a, 'b>);
+
+macro_rules! declare_serialize_module {
+    ($binary:literal, $precision:expr) => {
+        use serde::{Serialize, Serializer};
+
+        use super::adjusted::Serde;
+
+        pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: Copy,
+            S: Serializer,
+            Serde<T, $binary, { $precision }>: Serialize,
+        {
+            <Serde<_, $binary, { $precision }>>::new(*val).serialize(serializer)
+        }
+    };
+}
+
+pub(super) use declare_serialize_module;
@@ -0,0 +1,13 @@
+use serde::{Serialize, Serializer};
+
+use super::adjusted::{Serde, SHORTEST};
+
+/// Serialize `byte_unit::Byte` using `byte.get_appropriate_unit(UnitType::Decimal)`.
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Copy,
+    S: Serializer,
+    Serde<T, false, SHORTEST>: Serialize,
+{
+    <Serde<_, false, SHORTEST>>::new(*val).serialize(serializer)
+}
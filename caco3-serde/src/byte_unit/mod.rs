@@ -1 +1,112 @@
 pub mod as_appropriate_binary_unit;
+pub mod as_appropriate_decimal_unit;
+
+mod adjusted;
+
+/// Serialize `byte_unit::Byte` in an appropriate binary unit with one decimal place,
+/// e.g. `2.0 KiB`.
+pub mod binary_1dp {
+    super::adjusted::declare_serialize_module!(true, 1);
+}
+
+/// Serialize `byte_unit::Byte` in an appropriate decimal unit with one decimal place,
+/// e.g. `2.0 KB`.
+pub mod decimal_1dp {
+    super::adjusted::declare_serialize_module!(false, 1);
+}
+
+/// Serialize `byte_unit::Byte` in an appropriate unit with `PRECISION` decimal places.
+///
+/// ```rust
+/// use byte_unit::Byte;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Usage {
+///     #[serde(serialize_with = "caco3_serde::byte_unit::with_precision::binary::<2, _, _>")]
+///     memory: Byte,
+///     #[serde(serialize_with = "caco3_serde::byte_unit::with_precision::decimal::<0, _, _>")]
+///     disk: Option<Byte>,
+/// }
+///
+/// let usage = Usage {
+///     memory: Byte::from_u64(1536),
+///     disk: Some(Byte::from_u64(2_400_000)),
+/// };
+/// assert_eq!(
+///     serde_json::to_string(&usage).unwrap(),
+///     r#"{"memory":"1.50 KiB","disk":"2 MB"}"#
+/// );
+/// ```
+pub mod with_precision {
+    use serde::{Serialize, Serializer};
+
+    use super::adjusted::Serde;
+
+    pub fn binary<const PRECISION: u8, T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy,
+        S: Serializer,
+        Serde<T, true, PRECISION>: Serialize,
+    {
+        <Serde<_, true, PRECISION>>::new(*val).serialize(serializer)
+    }
+
+    pub fn decimal<const PRECISION: u8, T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Copy,
+        S: Serializer,
+        Serde<T, false, PRECISION>: Serialize,
+    {
+        <Serde<_, false, PRECISION>>::new(*val).serialize(serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use byte_unit::Byte;
+    use serde::Serialize;
+    use serde_test::{assert_ser_tokens, Configure, Token};
+
+    #[test]
+    fn test_serialize() {
+        #[derive(Serialize)]
+        struct Sizes<'a> {
+            #[serde(serialize_with = "super::as_appropriate_decimal_unit::serialize")]
+            decimal: Byte,
+            #[serde(serialize_with = "super::binary_1dp::serialize")]
+            binary_1dp: &'a Byte,
+            #[serde(serialize_with = "super::decimal_1dp::serialize")]
+            decimal_1dp: Option<Byte>,
+            #[serde(serialize_with = "super::with_precision::binary::<3, _, _>")]
+            binary_3dp: Byte,
+        }
+
+        let byte = Byte::from_u64(1536);
+        let sizes = Sizes {
+            decimal: byte,
+            binary_1dp: &byte,
+            decimal_1dp: Some(byte),
+            binary_3dp: byte,
+        };
+        assert_ser_tokens(
+            &sizes.readable(),
+            &[
+                Token::Struct {
+                    name: "Sizes",
+                    len: 4,
+                },
+                Token::Str("decimal"),
+                Token::Str("1.536 KB"),
+                Token::Str("binary_1dp"),
+                Token::Str("1.5 KiB"),
+                Token::Str("decimal_1dp"),
+                Token::Some,
+                Token::Str("1.5 KB"),
+                Token::Str("binary_3dp"),
+                Token::Str("1.500 KiB"),
+                Token::StructEnd,
+            ],
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Helper module for serializing/deserializing bytes as string
+//!
+//! Modules support `Vec<u8>`, `[u8; N]`, their `Option`, and serializing `&[u8]`.
+//! Deserialized `[u8; N]` must have exactly `N` bytes.
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Keys {
+//!     #[serde(with = "caco3_serde::bytes::base64")]
+//!     secret: Vec<u8>,
+//!     #[serde(with = "caco3_serde::bytes::hex")]
+//!     fingerprint: [u8; 4],
+//!     #[serde(default, with = "caco3_serde::bytes::base64_url")]
+//!     nonce: Option<Vec<u8>>,
+//! }
+//!
+//! let keys = Keys {
+//!     secret: b"key?".to_vec(),
+//!     fingerprint: [0xde, 0xad, 0xbe, 0xef],
+//!     nonce: Some(vec![0xfb, 0xff]),
+//! };
+//! let json = serde_json::to_string(&keys).unwrap();
+//! assert_eq!(json, r#"{"secret":"a2V5Pw==","fingerprint":"deadbeef","nonce":"-_8"}"#);
+//!
+//! let keys: Keys = serde_json::from_str(r#"{"secret":"a2V5Pw","fingerprint":"DEADBEEF"}"#).unwrap();
+//! assert_eq!(keys.secret, b"key?");
+//! assert_eq!(keys.fingerprint, [0xde, 0xad, 0xbe, 0xef]);
+//! assert_eq!(keys.nonce, None);
+//! ```
+
+macro_rules! declare_serde_module {
+    ($encoding:ty) => {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use super::private::*;
+
+        pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+            for<'a> Serde<&'a T, $encoding>: Serialize,
+        {
+            <Serde<_, $encoding>>::new(val).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where
+            D: Deserializer<'de>,
+            Serde<T, $encoding>: Deserialize<'de>,
+        {
+            Serde::deserialize(deserializer).map(Serde::into_inner)
+        }
+    };
+}
+
+/// Standard base64 with padding, padding is optional when deserializing.
+pub mod base64 {
+    declare_serde_module!(Base64);
+}
+
+/// URL-safe base64 without padding, padding is optional when deserializing.
+pub mod base64_url {
+    declare_serde_module!(Base64Url);
+}
+
+/// Lowercase hex, uppercase is also accepted when deserializing.
+pub mod hex {
+    declare_serde_module!(Hex);
+}
+
+mod private {
+    use std::marker::PhantomData;
+
+    use base64::alphabet;
+    use base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+    use base64::engine::DecodePaddingMode;
+    use base64::Engine;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub trait Encoding {
+        fn encode(bytes: &[u8]) -> String;
+
+        fn decode(s: &str) -> Result<Vec<u8>, String>;
+    }
+
+    pub struct Base64;
+    pub struct Base64Url;
+    pub struct Hex;
+
+    const BASE64: GeneralPurpose = GeneralPurpose::new(
+        &alphabet::STANDARD,
+        GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+
+    const BASE64_URL: GeneralPurpose = GeneralPurpose::new(
+        &alphabet::URL_SAFE,
+        GeneralPurposeConfig::new()
+            .with_encode_padding(false)
+            .with_decode_padding_mode(DecodePaddingMode::Indifferent),
+    );
+
+    impl Encoding for Base64 {
+        fn encode(bytes: &[u8]) -> String {
+            BASE64.encode(bytes)
+        }
+
+        fn decode(s: &str) -> Result<Vec<u8>, String> {
+            BASE64.decode(s).map_err(|e| format!("invalid base64, {e}"))
+        }
+    }
+
+    impl Encoding for Base64Url {
+        fn encode(bytes: &[u8]) -> String {
+            BASE64_URL.encode(bytes)
+        }
+
+        fn decode(s: &str) -> Result<Vec<u8>, String> {
+            BASE64_URL
+                .decode(s)
+                .map_err(|e| format!("invalid URL-safe base64, {e}"))
+        }
+    }
+
+    impl Encoding for Hex {
+        fn encode(bytes: &[u8]) -> String {
+            hex::encode(bytes)
+        }
+
+        fn decode(s: &str) -> Result<Vec<u8>, String> {
+            hex::decode(s).map_err(|e| format!("invalid hex, {e}"))
+        }
+    }
+
+    /// Generalizing serialization/deserialization over bytes
+    pub struct Serde<T, E> {
+        inner: T,
+        encoding: PhantomData<E>,
+    }
+
+    impl<T, E> Serde<T, E> {
+        pub(super) fn new(inner: T) -> Self {
+            Self {
+                inner,
+                encoding: PhantomData,
+            }
+        }
+
+        pub(super) fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    fn serialize_bytes<E: Encoding, S: Serializer>(
+        bytes: &[u8],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&E::encode(bytes))
+    }
+
+    fn deserialize_bytes<'de, E: Encoding, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u8>, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        E::decode(&s).map_err(de::Error::custom)
+    }
+
+    impl<E: Encoding> Serialize for Serde<&Vec<u8>, E> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_bytes::<E, _>(self.inner, serializer)
+        }
+    }
+
+    impl<E: Encoding> Serialize for Serde<&&[u8], E> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_bytes::<E, _>(self.inner, serializer)
+        }
+    }
+
+    impl<E: Encoding, const N: usize> Serialize for Serde<&[u8; N], E> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serialize_bytes::<E, _>(self.inner, serializer)
+        }
+    }
+
+    impl<'de, E: Encoding> Deserialize<'de> for Serde<Vec<u8>, E> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserialize_bytes::<E, _>(deserializer).map(Serde::new)
+        }
+    }
+
+    impl<'de, E: Encoding, const N: usize> Deserialize<'de> for Serde<[u8; N], E> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let bytes = deserialize_bytes::<E, _>(deserializer)?;
+            let len = bytes.len();
+            bytes
+                .try_into()
+                .map(Serde::new)
+                .map_err(|_| de::Error::invalid_length(len, &format!("{N} bytes").as_str()))
+        }
+    }
+
+    macro_rules! impl_option {
+        ($ty:ty $(, const $n:ident)?) => {
+            impl<E: Encoding $(, const $n: usize)?> Serialize for Serde<&Option<$ty>, E> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    match self.inner {
+                        Some(bytes) => serializer.serialize_some(&<Serde<_, E>>::new(bytes)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+            }
+
+            impl<'de, E: Encoding $(, const $n: usize)?> Deserialize<'de> for Serde<Option<$ty>, E> {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    match <Option<Serde<$ty, E>>>::deserialize(deserializer)? {
+                        Some(bytes) => Ok(Serde::new(Some(bytes.into_inner()))),
+                        None => Ok(Serde::new(None)),
+                    }
+                }
+            }
+        };
+    }
+
+    impl_option!(Vec<u8>);
+    impl_option!([u8; N], const N);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+
+        use super::super::{base64, base64_url, hex};
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Standard(#[serde(with = "base64")] Vec<u8>);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Url(#[serde(with = "base64_url")] Option<Vec<u8>>);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Key(#[serde(with = "hex")] [u8; 2]);
+
+        #[derive(Serialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Slice<'a>(#[serde(with = "hex")] &'a [u8]);
+
+        #[test]
+        fn serialize_bytes() {
+            assert_ser_tokens(&Standard(vec![0xfb, 0xff]), &[Token::Str("+/8=")]);
+            assert_ser_tokens(
+                &Url(Some(vec![0xfb, 0xff])),
+                &[Token::Some, Token::Str("-_8")],
+            );
+            assert_ser_tokens(&Url(None), &[Token::None]);
+            assert_ser_tokens(&Key([0xab, 0x01]), &[Token::Str("ab01")]);
+            assert_ser_tokens(&Slice(&[0xab, 0x01]), &[Token::Str("ab01")]);
+        }
+
+        #[test]
+        fn deserialize_bytes() {
+            assert_de_tokens(&Standard(vec![0xfb, 0xff]), &[Token::Str("+/8=")]);
+            assert_de_tokens(&Standard(vec![0xfb, 0xff]), &[Token::Str("+/8")]);
+            assert_de_tokens(
+                &Url(Some(vec![0xfb, 0xff])),
+                &[Token::Some, Token::Str("-_8=")],
+            );
+            assert_de_tokens(&Key([0xab, 0x01]), &[Token::Str("AB01")]);
+            assert_de_tokens_error::<Standard>(
+                &[Token::Str("-_8")],
+                "invalid base64, Invalid symbol 45, offset 0.",
+            );
+            assert_de_tokens_error::<Key>(
+                &[Token::Str("ab0102")],
+                "invalid length 3, expected 2 bytes",
+            );
+            assert_de_tokens_error::<Key>(
+                &[Token::Str("abc")],
+                "invalid hex, Odd number of digits",
+            );
+        }
+    }
+}
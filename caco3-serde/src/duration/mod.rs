@@ -0,0 +1,353 @@
+//! Helper module for serializing/deserializing duration as human readable string
+//!
+//! Whole seconds are serialized as components of days, hours, minutes and seconds,
+//! e.g. `"30s"` or `"1h 30m"`, a duration with fraction of second is serialized as seconds in
+//! floating point. Durations are deserialized from strings parsed by
+//! [`HumanDuration`](caco3::time::human_duration::HumanDuration), or numbers of seconds.
+//!
+//! Examples
+//! ```rust
+//! use std::time::Duration;
+//!
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct PoolConfig {
+//!     #[serde(with = "caco3_serde::duration::std")]
+//!     idle_timeout: Duration,
+//!     #[serde(default, with = "caco3_serde::duration::std")]
+//!     max_lifetime: Option<Duration>,
+//! }
+//!
+//! let config: PoolConfig = serde_json::from_str(r#"{"idle_timeout":"1h 30m"}"#).unwrap();
+//! assert_eq!(config.idle_timeout, Duration::from_secs(5400));
+//! assert_eq!(config.max_lifetime, None);
+//!
+//! let config: PoolConfig = serde_json::from_str(r#"{"idle_timeout":30,"max_lifetime":"1d"}"#).unwrap();
+//! assert_eq!(config.idle_timeout, Duration::from_secs(30));
+//! assert_eq!(
+//!     serde_json::to_string(&config).unwrap(),
+//!     r#"{"idle_timeout":"30s","max_lifetime":"1d"}"#
+//! );
+//! ```
+
+macro_rules! declare_serde_module {
+    () => {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use super::private::Serde;
+
+        pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+            Serde<T>: Serialize,
+        {
+            Serde::new_ref(val).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where
+            D: Deserializer<'de>,
+            Serde<T>: Deserialize<'de>,
+        {
+            Serde::deserialize(deserializer).map(Serde::into_inner)
+        }
+    };
+}
+
+/// For `std::time::Duration`.
+pub mod std {
+    declare_serde_module!();
+}
+
+/// For `time::Duration`, negative durations are prefixed with `-`.
+#[cfg(feature = "time")]
+pub mod time {
+    declare_serde_module!();
+}
+
+mod private {
+    use core::fmt;
+    use std::time::Duration;
+
+    use bytemuck::TransparentWrapper;
+    use caco3::time::human_duration::HumanDuration;
+    use serde::de::{self, Unexpected, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[repr(transparent)]
+    #[derive(bytemuck::TransparentWrapper)]
+    pub struct Serde<T>(T);
+
+    impl<T> Serde<T> {
+        pub(super) fn into_inner(self) -> T {
+            self.0
+        }
+
+        pub(super) fn new_ref(inner_ref: &T) -> &Self {
+            Self::wrap_ref(inner_ref)
+        }
+    }
+
+    impl<T> fmt::Debug for Serde<T>
+    where
+        T: fmt::Debug,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    fn serialize_duration<S>(
+        negative: bool,
+        duration: Duration,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        if duration.subsec_nanos() != 0 {
+            let secs = duration.as_secs_f64();
+            return serializer.serialize_f64(if negative { -secs } else { secs });
+        }
+        let sign = if negative { "-" } else { "" };
+        let components: Vec<_> = HumanDuration::from_secs(duration.as_secs())
+            .components()
+            .filter(|c| c.value() != 0)
+            .map(|c| c.to_string())
+            .collect();
+        if components.is_empty() {
+            serializer.serialize_str("0s")
+        } else {
+            serializer.collect_str(&format_args!("{sign}{}", components.join(" ")))
+        }
+    }
+
+    /// Deserialize a possibly negative duration.
+    fn deserialize_duration<'de, D>(deserializer: D) -> Result<(bool, Duration), D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DurationVisitor;
+
+        impl Visitor<'_> for DurationVisitor {
+            type Value = (bool, Duration);
+
+            fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                formatter.write_str("a duration string, e.g. \"1h 30m\", or number of seconds")
+            }
+
+            fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                Ok((v < 0, Duration::from_secs(v.unsigned_abs())))
+            }
+
+            fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok((false, Duration::from_secs(v)))
+            }
+
+            fn visit_f64<E: de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Duration::try_from_secs_f64(v.abs())
+                    .map(|duration| (v < 0.0, duration))
+                    .map_err(|_| E::invalid_value(Unexpected::Float(v), &self))
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                let (negative, s) = match v.trim().strip_prefix('-') {
+                    Some(s) => (true, s),
+                    None => (false, v),
+                };
+                let duration = s.parse::<HumanDuration>().map_err(E::custom)?;
+                Ok((negative, Duration::from_secs(duration.as_secs())))
+            }
+        }
+
+        deserializer.deserialize_any(DurationVisitor)
+    }
+
+    impl Serialize for Serde<Duration> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            serialize_duration(false, self.0, serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<Duration> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match deserialize_duration(deserializer)? {
+                (false, duration) => Ok(Serde(duration)),
+                (true, _) => Err(de::Error::custom("duration must not be negative")),
+            }
+        }
+    }
+
+    #[cfg(feature = "time")]
+    impl Serialize for Serde<time::Duration> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let duration = self.0.unsigned_abs();
+            serialize_duration(self.0.is_negative(), duration, serializer)
+        }
+    }
+
+    #[cfg(feature = "time")]
+    impl<'de> Deserialize<'de> for Serde<time::Duration> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let (negative, duration) = deserialize_duration(deserializer)?;
+            let duration = time::Duration::try_from(duration).map_err(de::Error::custom)?;
+            Ok(Serde(if negative { -duration } else { duration }))
+        }
+    }
+
+    macro_rules! impl_option {
+        ($ty:ty) => {
+            impl Serialize for Serde<Option<$ty>> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    match &self.0 {
+                        Some(val) => serializer.serialize_some(Serde::new_ref(val)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+            }
+
+            impl<'de> Deserialize<'de> for Serde<Option<$ty>> {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    match <Option<Serde<$ty>>>::deserialize(deserializer)? {
+                        Some(Serde(val)) => Ok(Serde(Some(val))),
+                        None => Ok(Serde(None)),
+                    }
+                }
+            }
+        };
+    }
+
+    impl_option!(Duration);
+    #[cfg(feature = "time")]
+    impl_option!(time::Duration);
+
+    macro_rules! impl_serialize_ref {
+        (@deref $expr:expr, $lt:lifetime) => {
+            * $expr
+        };
+        (@deref $expr:expr, $lt0:lifetime, $($lt:lifetime),+) => {
+            * impl_serialize_ref!(@deref $expr, $($lt),+)
+        };
+        ($ty:ty, <$($lt:lifetime),+>) => {
+            impl <$($lt),+> Serialize for Serde<$(&$lt)+ $ty> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let inner_ref: &$ty = &impl_serialize_ref!(@deref self.0, $($lt),+);
+                    let serde_ref: &Serde<$ty> = Serde::new_ref(inner_ref);
+                    serde_ref.serialize(serializer)
+                }
+            }
+        };
+    }
+
+    impl_serialize_ref!(Duration, <'a>);
+    impl_serialize_ref!(Duration, <'a, 'b>);
+    impl_serialize_ref!(Option<Duration>, <'a>);
+    impl_serialize_ref!(Option<Duration>, <'a, 'b>);
+
+    #[cfg(feature = "time")]
+    impl_serialize_ref!(time::Duration, <'a>);
+    #[cfg(feature = "time")]
+    impl_serialize_ref!(time::Duration, <'a, 'b>);
+    #[cfg(feature = "time")]
+    impl_serialize_ref!(Option<time::Duration>, <'a>);
+    #[cfg(feature = "time")]
+    impl_serialize_ref!(Option<time::Duration>, <'a, 'b>);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+
+        use super::super::std as std_duration;
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Std(#[serde(with = "std_duration")] Duration);
+
+        #[derive(Serialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct StdRef<'a>(#[serde(with = "std_duration")] &'a Duration);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct OptionStd(#[serde(with = "std_duration")] Option<Duration>);
+
+        #[test]
+        fn test_new_borrowed_safety() {
+            let duration = Duration::from_secs(1);
+            let _serde = Serde::new_ref(&duration);
+        }
+
+        #[test]
+        fn serialize_std() {
+            assert_ser_tokens(&Std(Duration::from_secs(30)), &[Token::Str("30s")]);
+            assert_ser_tokens(&Std(Duration::from_secs(300)), &[Token::Str("5m")]);
+            assert_ser_tokens(&StdRef(&Duration::from_secs(5400)), &[Token::Str("1h 30m")]);
+            assert_ser_tokens(&Std(Duration::ZERO), &[Token::Str("0s")]);
+            assert_ser_tokens(&Std(Duration::from_millis(1500)), &[Token::F64(1.5)]);
+            assert_ser_tokens(
+                &OptionStd(Some(Duration::from_secs(86400))),
+                &[Token::Some, Token::Str("1d")],
+            );
+            assert_ser_tokens(&OptionStd(None), &[Token::None]);
+        }
+
+        #[test]
+        fn deserialize_std() {
+            assert_de_tokens(&Std(Duration::from_secs(30)), &[Token::Str("30s")]);
+            assert_de_tokens(&Std(Duration::from_secs(5400)), &[Token::Str("1h30m")]);
+            assert_de_tokens(&Std(Duration::from_secs(30)), &[Token::U64(30)]);
+            assert_de_tokens(&Std(Duration::from_millis(500)), &[Token::F64(0.5)]);
+            assert_de_tokens(
+                &OptionStd(Some(Duration::from_secs(300))),
+                &[Token::Some, Token::Str("5m")],
+            );
+            assert_de_tokens(&OptionStd(None), &[Token::None]);
+            assert_de_tokens_error::<Std>(&[Token::I64(-1)], "duration must not be negative");
+            assert_de_tokens_error::<Std>(
+                &[Token::Str("5 minutes")],
+                "invalid duration component \"5\", expected a number followed by d, h, m or s",
+            );
+        }
+
+        #[cfg(feature = "time")]
+        #[test]
+        fn serde_time() {
+            use serde_test::assert_tokens;
+
+            #[derive(Serialize, Deserialize, PartialEq, Debug)]
+            #[serde(transparent)]
+            struct Time(#[serde(with = "super::super::time")] time::Duration);
+
+            assert_tokens(&Time(time::Duration::minutes(90)), &[Token::Str("1h 30m")]);
+            assert_tokens(&Time(time::Duration::seconds(-30)), &[Token::Str("-30s")]);
+            assert_tokens(
+                &Time(time::Duration::milliseconds(-1500)),
+                &[Token::F64(-1.5)],
+            );
+            assert_de_tokens(&Time(time::Duration::seconds(-30)), &[Token::I64(-30)]);
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Deserializers for enums
+//!
+//! Examples
+//! ```rust
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize, PartialEq)]
+//! #[serde(rename_all = "snake_case")]
+//! enum LogFormat {
+//!     Json,
+//!     #[serde(alias = "text")]
+//!     Pretty,
+//! }
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     #[serde(deserialize_with = "caco3_serde::enums::case_insensitive")]
+//!     format: LogFormat,
+//!     #[serde(default, deserialize_with = "caco3_serde::enums::case_insensitive")]
+//!     audit_format: Option<LogFormat>,
+//! }
+//!
+//! let config: Config = serde_json::from_str(r#"{"format":"JSON","audit_format":"Text"}"#).unwrap();
+//! assert_eq!(config.format, LogFormat::Json);
+//! assert_eq!(config.audit_format, Some(LogFormat::Pretty));
+//!
+//! let error = serde_json::from_str::<Config>(r#"{"format":"yaml"}"#).unwrap_err();
+//! assert!(error.to_string().starts_with("unknown variant `yaml`, expected `json` or `pretty`"));
+//! ```
+use serde::{Deserialize, Deserializer};
+
+use private::CaseInsensitive;
+
+/// Match unit variant names of `T` ignoring ASCII case.
+///
+/// Aliases are matched ignoring ASCII case too, as long as they are declared in lowercase.
+///
+/// `T` can also be `Option` of an enum.
+pub fn case_insensitive<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de>,
+    D: Deserializer<'de>,
+{
+    T::deserialize(CaseInsensitive(deserializer))
+}
+
+mod private {
+    use std::fmt;
+
+    use serde::de::value::StrDeserializer;
+    use serde::de::{Deserialize, Deserializer, IntoDeserializer, Visitor};
+
+    /// Deserializer matching enum variants ignoring ASCII case, everything else is forwarded
+    pub struct CaseInsensitive<D>(pub D);
+
+    macro_rules! forward {
+        ($($method:ident($($arg:ident: $ty:ty),*)),* $(,)?) => {
+            $(
+                fn $method<V: Visitor<'de>>(self, $($arg: $ty,)* visitor: V) -> Result<V::Value, D::Error> {
+                    self.0.$method($($arg,)* visitor)
+                }
+            )*
+        };
+    }
+
+    impl<'de, D: Deserializer<'de>> Deserializer<'de> for CaseInsensitive<D> {
+        type Error = D::Error;
+
+        fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, D::Error> {
+            self.0.deserialize_option(OptionVisitor(visitor))
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            _name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, D::Error> {
+            let name = String::deserialize(self.0)?;
+            // aliases are not listed in `variants`, lowercase is the common way to declare them
+            let name = match variants
+                .iter()
+                .find(|variant| variant.eq_ignore_ascii_case(&name))
+            {
+                Some(variant) => variant.to_string(),
+                None => name.to_ascii_lowercase(),
+            };
+            let name: StrDeserializer<D::Error> = name.as_str().into_deserializer();
+            visitor.visit_enum(name)
+        }
+
+        fn is_human_readable(&self) -> bool {
+            self.0.is_human_readable()
+        }
+
+        forward! {
+            deserialize_any(), deserialize_bool(), deserialize_i8(), deserialize_i16(),
+            deserialize_i32(), deserialize_i64(), deserialize_i128(), deserialize_u8(),
+            deserialize_u16(), deserialize_u32(), deserialize_u64(), deserialize_u128(),
+            deserialize_f32(), deserialize_f64(), deserialize_char(), deserialize_str(),
+            deserialize_string(), deserialize_bytes(), deserialize_byte_buf(),
+            deserialize_unit(), deserialize_seq(), deserialize_map(),
+            deserialize_identifier(), deserialize_ignored_any(),
+            deserialize_unit_struct(name: &'static str),
+            deserialize_newtype_struct(name: &'static str),
+            deserialize_tuple(len: usize),
+            deserialize_tuple_struct(name: &'static str, len: usize),
+            deserialize_struct(name: &'static str, fields: &'static [&'static str]),
+        }
+    }
+
+    /// Keep matching ignoring case inside `Some`
+    struct OptionVisitor<V>(V);
+
+    impl<'de, V: Visitor<'de>> Visitor<'de> for OptionVisitor<V> {
+        type Value = V::Value;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            self.0.expecting(formatter)
+        }
+
+        fn visit_none<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+            self.0.visit_none()
+        }
+
+        fn visit_unit<E: serde::de::Error>(self) -> Result<Self::Value, E> {
+            self.0.visit_unit()
+        }
+
+        fn visit_some<D: Deserializer<'de>>(
+            self,
+            deserializer: D,
+        ) -> Result<Self::Value, D::Error> {
+            self.0.visit_some(CaseInsensitive(deserializer))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde::Deserialize;
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
+
+        use super::super::case_insensitive;
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(rename_all = "UPPERCASE")]
+        enum Level {
+            Debug,
+            #[serde(alias = "warn")]
+            Warning,
+        }
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Required(#[serde(deserialize_with = "case_insensitive")] Level);
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Optional(#[serde(deserialize_with = "case_insensitive")] Option<Level>);
+
+        #[test]
+        fn test_case_insensitive() {
+            assert_de_tokens(&Required(Level::Debug), &[Token::Str("debug")]);
+            assert_de_tokens(&Required(Level::Debug), &[Token::Str("DEBUG")]);
+            assert_de_tokens(&Required(Level::Warning), &[Token::Str("Warn")]);
+            assert_de_tokens(
+                &Optional(Some(Level::Warning)),
+                &[Token::Some, Token::Str("wArNiNg")],
+            );
+            assert_de_tokens(&Optional(None), &[Token::None]);
+            assert_de_tokens_error::<Required>(
+                &[Token::Str("Info")],
+                "unknown variant `info`, expected `DEBUG` or `WARNING`",
+            );
+        }
+    }
+}
@@ -0,0 +1,118 @@
+//! Helper module for `Utf8PathBuf` configured by figment
+//!
+//! Paths are resolved relative to the configuration file declaring them, see
+//! [`RelativePathBuf::relative`](figment::value::magic::RelativePathBuf::relative).
+//! Paths are serialized in the same format as [`relative_path_buf`](super::relative_path_buf),
+//! so serialized configuration can be deserialized again.
+//!
+//! Examples
+//! ```rust
+//! use camino::Utf8PathBuf;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     #[serde(
+//!         serialize_with = "caco3_serde::figment::camino::serialize",
+//!         deserialize_with = "caco3_serde::figment::camino::deserialize_relative"
+//!     )]
+//!     data_dir: Utf8PathBuf,
+//!     #[serde(
+//!         default,
+//!         serialize_with = "caco3_serde::figment::camino::serialize",
+//!         deserialize_with = "caco3_serde::figment::camino::deserialize_relative_existing"
+//!     )]
+//!     cert: Option<Utf8PathBuf>,
+//! }
+//!
+//! let config = Config {
+//!     data_dir: "/var/lib/app".into(),
+//!     cert: None,
+//! };
+//! let json = serde_json::to_string(&config).unwrap();
+//! assert_eq!(json, r#"{"data_dir":{"path":"/var/lib/app"},"cert":null}"#);
+//!
+//! let json = r#"{"data_dir":{"path":"/var/lib/app"},"cert":{"path":"/no/such/cert.pem"}}"#;
+//! let error = serde_json::from_str::<Config>(json).err().unwrap();
+//! assert!(error.to_string().starts_with("/no/such/cert.pem does not exist"));
+//! ```
+use std::path::{Path, PathBuf};
+
+use camino::Utf8PathBuf;
+use serde::de;
+
+pub use super::path::{
+    deserialize_relative, deserialize_relative_canonical, deserialize_relative_existing,
+    deserialize_relative_mkdirs, serialize,
+};
+
+impl super::path::ConfigPath for Utf8PathBuf {
+    fn as_path(&self) -> &Path {
+        self.as_std_path()
+    }
+
+    fn from_path_buf<E: de::Error>(path: PathBuf) -> Result<Self, E> {
+        Utf8PathBuf::from_path_buf(path)
+            .map_err(|path| E::custom(format_args!("{} is not valid UTF-8", path.display())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct Path(
+        #[serde(
+            serialize_with = "serialize",
+            deserialize_with = "deserialize_relative"
+        )]
+        Utf8PathBuf,
+    );
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct ExistingPath(
+        #[serde(
+            serialize_with = "serialize",
+            deserialize_with = "deserialize_relative_existing"
+        )]
+        Option<Utf8PathBuf>,
+    );
+
+    #[test]
+    fn serialize_path() {
+        let json = serde_json::to_string(&Path("a/b".into())).unwrap();
+        assert_eq!(json, r#"{"path":"a/b"}"#);
+        let json = serde_json::to_string(&ExistingPath(Some("/tmp".into()))).unwrap();
+        assert_eq!(json, r#"{"path":"/tmp"}"#);
+        let json = serde_json::to_string(&ExistingPath(None)).unwrap();
+        assert_eq!(json, "null");
+    }
+
+    #[test]
+    fn deserialize_path() {
+        let path: Path = serde_json::from_str(r#"{"path":"a/b"}"#).unwrap();
+        assert_eq!(path, Path("a/b".into()));
+
+        let existing = ExistingPath(Some(env!("CARGO_MANIFEST_DIR").into()));
+        let json = serde_json::to_string(&existing).unwrap();
+        assert_eq!(
+            serde_json::from_str::<ExistingPath>(&json).unwrap(),
+            existing
+        );
+        assert_eq!(
+            serde_json::from_str::<ExistingPath>("null").unwrap(),
+            ExistingPath(None)
+        );
+        let error = serde_json::from_str::<ExistingPath>(r#"{"path":"/no/such/file"}"#)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("/no/such/file does not exist"));
+    }
+}
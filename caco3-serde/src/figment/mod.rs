@@ -1 +1,6 @@
-pub mod relative_path_buf;
\ No newline at end of file
+mod path;
+pub mod pathbuf;
+pub mod relative_path_buf;
+
+#[cfg(feature = "camino")]
+pub mod camino;
@@ -0,0 +1,186 @@
+//! Implementation of [`pathbuf`](super::pathbuf) and [`camino`](super::camino),
+//! generic over the path type.
+use std::path::{Path, PathBuf};
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use private::{Canonical, Existing, Mkdirs, Relative, Serde};
+
+/// Path type configured by figment.
+pub trait ConfigPath: Sized {
+    fn as_path(&self) -> &Path;
+
+    fn from_path_buf<E: de::Error>(path: PathBuf) -> Result<Self, E>;
+}
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    Serde<T, Relative>: Serialize,
+{
+    <Serde<_, Relative>>::new_ref(val).serialize(serializer)
+}
+
+/// Resolve path relative to the configuration file declaring it.
+pub fn deserialize_relative<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, Relative>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+/// Same as [`deserialize_relative`], but fail if the resolved path does not exist.
+pub fn deserialize_relative_existing<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, Existing>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+/// Same as [`deserialize_relative`], then canonicalize the path.
+///
+/// The path must exist.
+pub fn deserialize_relative_canonical<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, Canonical>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+/// Same as [`deserialize_relative`], then create missing parent directories of the path.
+pub fn deserialize_relative_mkdirs<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, Mkdirs>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+mod private {
+    use std::fs;
+    use std::marker::PhantomData;
+    use std::path::{Path, PathBuf};
+
+    use bytemuck::TransparentWrapper;
+    use figment::value::magic::RelativePathBuf;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+    use super::ConfigPath;
+
+    pub trait Resolve {
+        fn resolve<E: de::Error>(path: PathBuf) -> Result<PathBuf, E>;
+    }
+
+    pub struct Relative;
+    pub struct Existing;
+    pub struct Canonical;
+    pub struct Mkdirs;
+
+    impl Resolve for Relative {
+        fn resolve<E: de::Error>(path: PathBuf) -> Result<PathBuf, E> {
+            Ok(path)
+        }
+    }
+
+    impl Resolve for Existing {
+        fn resolve<E: de::Error>(path: PathBuf) -> Result<PathBuf, E> {
+            if !path.exists() {
+                return Err(E::custom(format_args!("{} does not exist", path.display())));
+            }
+            Ok(path)
+        }
+    }
+
+    impl Resolve for Canonical {
+        fn resolve<E: de::Error>(path: PathBuf) -> Result<PathBuf, E> {
+            path.canonicalize().map_err(|e| {
+                E::custom(format_args!(
+                    "failed to canonicalize {}, {e}",
+                    path.display()
+                ))
+            })
+        }
+    }
+
+    impl Resolve for Mkdirs {
+        fn resolve<E: de::Error>(path: PathBuf) -> Result<PathBuf, E> {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                fs::create_dir_all(parent).map_err(|e| {
+                    E::custom(format_args!("failed to create {}, {e}", parent.display()))
+                })?;
+            }
+            Ok(path)
+        }
+    }
+
+    /// Generalizing serialization/deserialization over [`ConfigPath`]
+    #[repr(transparent)]
+    #[derive(bytemuck::TransparentWrapper)]
+    #[transparent(T)]
+    pub struct Serde<T, R>(T, PhantomData<R>);
+
+    impl<T, R> Serde<T, R> {
+        pub(super) fn into_inner(self) -> T {
+            self.0
+        }
+
+        pub(super) fn new_ref(inner_ref: &T) -> &Self {
+            Self::wrap_ref(inner_ref)
+        }
+    }
+
+    /// Same format as serialized `RelativePathBuf`
+    #[derive(Serialize)]
+    struct ReadablePath<'a> {
+        path: &'a Path,
+    }
+
+    impl<P: ConfigPath, R> Serialize for Serde<P, R> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ReadablePath {
+                path: self.0.as_path(),
+            }
+            .serialize(serializer)
+        }
+    }
+
+    impl<P: ConfigPath, R> Serialize for Serde<Option<P>, R> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match &self.0 {
+                Some(path) => serializer.serialize_some(<Serde<_, R>>::new_ref(path)),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
+
+    impl<'de, P: ConfigPath, R: Resolve> Deserialize<'de> for Serde<P, R> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let path: RelativePathBuf = super::super::relative_path_buf::deserialize(deserializer)?;
+            let path = P::from_path_buf(R::resolve(path.relative())?)?;
+            Ok(Serde(path, PhantomData))
+        }
+    }
+
+    impl<'de, P: ConfigPath, R: Resolve> Deserialize<'de> for Serde<Option<P>, R> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            match <Option<Serde<P, R>>>::deserialize(deserializer)? {
+                Some(Serde(path, _)) => Ok(Serde(Some(path), PhantomData)),
+                None => Ok(Serde(None, PhantomData)),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_new_ref_safety() {
+            let path = PathBuf::from("/dev/null");
+            let _serde = <Serde<_, Relative>>::new_ref(&path);
+        }
+    }
+}
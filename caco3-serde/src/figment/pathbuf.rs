@@ -0,0 +1,116 @@
+//! Helper module for `PathBuf` configured by figment
+//!
+//! Paths are resolved relative to the configuration file declaring them, see
+//! [`RelativePathBuf::relative`](figment::value::magic::RelativePathBuf::relative).
+//! Paths are serialized in the same format as [`relative_path_buf`](super::relative_path_buf),
+//! so serialized configuration can be deserialized again.
+//!
+//! Examples
+//! ```rust
+//! use std::path::PathBuf;
+//!
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     #[serde(
+//!         serialize_with = "caco3_serde::figment::pathbuf::serialize",
+//!         deserialize_with = "caco3_serde::figment::pathbuf::deserialize_relative_canonical"
+//!     )]
+//!     data_dir: PathBuf,
+//!     #[serde(
+//!         default,
+//!         serialize_with = "caco3_serde::figment::pathbuf::serialize",
+//!         deserialize_with = "caco3_serde::figment::pathbuf::deserialize_relative_mkdirs"
+//!     )]
+//!     log_file: Option<PathBuf>,
+//! }
+//!
+//! let json = r#"{"data_dir":{"path":"/tmp/../tmp"}}"#;
+//! let config: Config = serde_json::from_str(json).unwrap();
+//! assert_eq!(config.data_dir, PathBuf::from("/tmp").canonicalize().unwrap());
+//! assert_eq!(config.log_file, None);
+//! ```
+use std::path::{Path, PathBuf};
+
+use serde::de;
+
+pub use super::path::{
+    deserialize_relative, deserialize_relative_canonical, deserialize_relative_existing,
+    deserialize_relative_mkdirs, serialize,
+};
+
+impl super::path::ConfigPath for PathBuf {
+    fn as_path(&self) -> &Path {
+        self
+    }
+
+    fn from_path_buf<E: de::Error>(path: PathBuf) -> Result<Self, E> {
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct CanonicalPath(
+        #[serde(
+            serialize_with = "serialize",
+            deserialize_with = "deserialize_relative_canonical"
+        )]
+        PathBuf,
+    );
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct LogFile(
+        #[serde(
+            serialize_with = "serialize",
+            deserialize_with = "deserialize_relative_mkdirs"
+        )]
+        Option<PathBuf>,
+    );
+
+    #[test]
+    fn serialize_path() {
+        let json = serde_json::to_string(&CanonicalPath("a/b".into())).unwrap();
+        assert_eq!(json, r#"{"path":"a/b"}"#);
+        let json = serde_json::to_string(&LogFile(None)).unwrap();
+        assert_eq!(json, "null");
+    }
+
+    #[test]
+    fn deserialize_canonical() {
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        let json = serde_json::to_string(&CanonicalPath(manifest_dir.join("src/.."))).unwrap();
+        let path: CanonicalPath = serde_json::from_str(&json).unwrap();
+        assert_eq!(path, CanonicalPath(manifest_dir.canonicalize().unwrap()));
+
+        let error = serde_json::from_str::<CanonicalPath>(r#"{"path":"/no/such/file"}"#)
+            .err()
+            .unwrap();
+        assert!(error
+            .to_string()
+            .starts_with("failed to canonicalize /no/such/file"));
+    }
+
+    #[test]
+    fn deserialize_mkdirs() {
+        let dir = std::env::temp_dir().join(format!("caco3-serde-mkdirs-{}", std::process::id()));
+        let file = dir.join("logs/app.log");
+        let json = serde_json::to_string(&LogFile(Some(file.clone()))).unwrap();
+        let log_file: LogFile = serde_json::from_str(&json).unwrap();
+        assert_eq!(log_file, LogFile(Some(file.clone())));
+        assert!(dir.join("logs").is_dir());
+        assert!(!file.exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
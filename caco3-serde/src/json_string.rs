@@ -0,0 +1,118 @@
+//! Helper module for serializing/deserializing a value as JSON document embedded in a string
+//!
+//! Use [`option`] for `Option` fields, so `null` is not embedded as `"null"`.
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Order {
+//!     id: u64,
+//! }
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Message {
+//!     #[serde(with = "caco3_serde::json_string")]
+//!     payload: Order,
+//!     #[serde(default, with = "caco3_serde::json_string::option")]
+//!     previous: Option<Order>,
+//! }
+//!
+//! let json = r#"{"payload":"{\"id\":1}","previous":null}"#;
+//! let message: Message = serde_json::from_str(json).unwrap();
+//! assert_eq!(message.payload, Order { id: 1 });
+//! assert_eq!(message.previous, None);
+//! assert_eq!(serde_json::to_string(&message).unwrap(), json);
+//! ```
+use serde::de::{DeserializeOwned, Error as _};
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    T: Serialize,
+    S: Serializer,
+{
+    let json = serde_json::to_string(val).map_err(S::Error::custom)?;
+    serializer.serialize_str(&json)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: DeserializeOwned,
+    D: Deserializer<'de>,
+{
+    let json = String::deserialize(deserializer)?;
+    serde_json::from_str(&json)
+        .map_err(|e| D::Error::custom(format_args!("invalid embedded JSON, {e}")))
+}
+
+/// `None` is `null` instead of embedded `"null"`.
+pub mod option {
+    use serde::de::DeserializeOwned;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    #[derive(Serialize)]
+    #[serde(transparent, bound = "T: Serialize")]
+    struct Embedded<'a, T>(#[serde(with = "super")] &'a T);
+
+    #[derive(Deserialize)]
+    #[serde(transparent, bound = "T: DeserializeOwned")]
+    struct Parsed<T>(#[serde(with = "super")] T);
+
+    pub fn serialize<T, S>(val: &Option<T>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        val.as_ref().map(Embedded).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: DeserializeOwned,
+        D: Deserializer<'de>,
+    {
+        let parsed = Option::<Parsed<T>>::deserialize(deserializer)?;
+        Ok(parsed.map(|Parsed(val)| val))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_de_tokens_error, assert_tokens, Token};
+
+    use super::*;
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct Embedded(#[serde(with = "super")] Point);
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct OptionEmbedded(#[serde(with = "super::option")] Option<Vec<u8>>);
+
+    #[test]
+    fn test_json_string() {
+        assert_tokens(
+            &Embedded(Point { x: 1, y: -2 }),
+            &[Token::Str(r#"{"x":1,"y":-2}"#)],
+        );
+        assert_tokens(
+            &OptionEmbedded(Some(vec![1, 2])),
+            &[Token::Some, Token::Str("[1,2]")],
+        );
+        assert_tokens(&OptionEmbedded(None), &[Token::None]);
+        assert_de_tokens_error::<Embedded>(
+            &[Token::Str(r#"{"x":1}"#)],
+            "invalid embedded JSON, missing field `y` at line 1 column 7",
+        );
+    }
+}
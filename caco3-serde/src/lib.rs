@@ -1,6 +1,21 @@
 #[cfg(feature = "byte-unit")]
 pub mod byte_unit;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+#[cfg(feature = "duration")]
+pub mod duration;
+pub mod enums;
 #[cfg(feature = "figment")]
 pub mod figment;
+#[cfg(feature = "json-string")]
+pub mod json_string;
+#[cfg(feature = "net")]
+pub mod net;
+#[cfg(feature = "secret")]
+pub mod secret;
+#[cfg(feature = "semver")]
+pub mod semver;
+pub mod option;
+pub mod string;
 #[cfg(feature = "time")]
 pub mod time;
\ No newline at end of file
@@ -0,0 +1,2 @@
+pub mod socket_addr;
+pub mod url;
@@ -0,0 +1,206 @@
+//! Helper module for serializing/deserializing "host:port" socket address as string
+//!
+//! Modules support `SocketAddr`, which requires an IP address, and `String`, which also
+//! accepts a host name. Host names are only validated, they are not resolved.
+//! `Option` of them are also supported.
+//!
+//! Examples
+//! ```rust
+//! use std::net::SocketAddr;
+//!
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     #[serde(with = "caco3_serde::net::socket_addr")]
+//!     listen: SocketAddr,
+//!     #[serde(with = "caco3_serde::net::socket_addr")]
+//!     upstream: String,
+//!     #[serde(default, with = "caco3_serde::net::socket_addr")]
+//!     metrics: Option<SocketAddr>,
+//! }
+//!
+//! let json = r#"{"listen":"0.0.0.0:8080","upstream":"backend.internal:9000"}"#;
+//! let config: Config = serde_json::from_str(json).unwrap();
+//! assert_eq!(config.listen, SocketAddr::from(([0, 0, 0, 0], 8080)));
+//! assert_eq!(config.upstream, "backend.internal:9000");
+//! assert_eq!(config.metrics, None);
+//!
+//! let json = r#"{"listen":"0.0.0.0:8080","upstream":"backend.internal"}"#;
+//! let error = serde_json::from_str::<Config>(json).err().unwrap();
+//! assert!(error.to_string().starts_with("invalid value: string \"backend.internal\", expected a \"host:port\" address"));
+//! ```
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use private::Serde;
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    for<'a> Serde<&'a T>: Serialize,
+{
+    Serde(val).serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(|Serde(val)| val)
+}
+
+mod private {
+    use std::net::SocketAddr;
+
+    use serde::de::{self, Unexpected};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Generalizing serialization/deserialization over socket address
+    pub struct Serde<T>(pub(super) T);
+
+    const EXPECTED: &str = "a \"host:port\" address";
+
+    /// Letters, digits and hyphens labels, as in RFC 1123.
+    fn is_host_name(host: &str) -> bool {
+        host.len() <= 253
+            && host.split('.').all(|label| {
+                (1..=63).contains(&label.len())
+                    && !label.starts_with('-')
+                    && !label.ends_with('-')
+                    && label
+                        .bytes()
+                        .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            })
+    }
+
+    fn is_host_port(s: &str) -> bool {
+        if s.parse::<SocketAddr>().is_ok() {
+            return true;
+        }
+        match s.rsplit_once(':') {
+            Some((host, port)) => {
+                port.parse::<u16>().is_ok()
+                    && port.bytes().all(|b| b.is_ascii_digit())
+                    && is_host_name(host)
+            }
+            None => false,
+        }
+    }
+
+    impl Serialize for Serde<&SocketAddr> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.collect_str(self.0)
+        }
+    }
+
+    impl Serialize for Serde<&String> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(self.0)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<SocketAddr> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            s.trim().parse().map(Serde).map_err(|_| {
+                de::Error::invalid_value(Unexpected::Str(&s), &"an \"ip:port\" address")
+            })
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<String> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            let trimmed = s.trim();
+            if !is_host_port(trimmed) {
+                return Err(de::Error::invalid_value(Unexpected::Str(&s), &EXPECTED));
+            }
+            Ok(Serde(trimmed.to_owned()))
+        }
+    }
+
+    macro_rules! impl_option {
+        ($ty:ty) => {
+            impl Serialize for Serde<&Option<$ty>> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    match self.0 {
+                        Some(addr) => serializer.serialize_some(&Serde(addr)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+            }
+
+            impl<'de> Deserialize<'de> for Serde<Option<$ty>> {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    match <Option<Serde<$ty>>>::deserialize(deserializer)? {
+                        Some(Serde(addr)) => Ok(Serde(Some(addr))),
+                        None => Ok(Serde(None)),
+                    }
+                }
+            }
+        };
+    }
+
+    impl_option!(SocketAddr);
+    impl_option!(String);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+
+        use super::super::{deserialize, serialize};
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Listen(#[serde(with = "super::super")] SocketAddr);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Upstream(
+            #[serde(serialize_with = "serialize", deserialize_with = "deserialize")] Option<String>,
+        );
+
+        #[test]
+        fn serialize_socket_addr() {
+            assert_ser_tokens(
+                &Listen(([127, 0, 0, 1], 80).into()),
+                &[Token::Str("127.0.0.1:80")],
+            );
+            assert_ser_tokens(
+                &Upstream(Some("db:5432".into())),
+                &[Token::Some, Token::Str("db:5432")],
+            );
+            assert_ser_tokens(&Upstream(None), &[Token::None]);
+        }
+
+        #[test]
+        fn deserialize_socket_addr() {
+            assert_de_tokens(
+                &Listen(([127, 0, 0, 1], 80).into()),
+                &[Token::Str(" 127.0.0.1:80 ")],
+            );
+            assert_de_tokens_error::<Listen>(
+                &[Token::Str("localhost:80")],
+                "invalid value: string \"localhost:80\", expected an \"ip:port\" address",
+            );
+
+            for addr in ["db:5432", "my-db.internal:0", "[::1]:443", "10.0.0.1:65535"] {
+                assert_de_tokens(
+                    &Upstream(Some(addr.into())),
+                    &[Token::Some, Token::Str(addr)],
+                );
+            }
+            assert_de_tokens(&Upstream(None), &[Token::None]);
+            for addr in [
+                "db", "db:", "db:65536", "db:+1", "-db:80", "d_b:80", ":80", "::1:443",
+            ] {
+                assert_de_tokens_error::<Upstream>(
+                    &[Token::Some, Token::Str(addr)],
+                    &format!("invalid value: string {addr:?}, expected a \"host:port\" address"),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,218 @@
+//! Helper module for serializing/deserializing `Url` as string
+//!
+//! Root module accepts any scheme, submodules only accept schemes they allow.
+//! Modules support `Url` and `Option<Url>`.
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use url::Url;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Endpoints {
+//!     #[serde(with = "caco3_serde::net::url::http")]
+//!     api: Url,
+//!     #[serde(default, with = "caco3_serde::net::url::ws")]
+//!     events: Option<Url>,
+//!     #[serde(with = "caco3_serde::net::url")]
+//!     database: Url,
+//! }
+//!
+//! let json = r#"{"api":"https://example.com/v1","database":"postgres://db/app"}"#;
+//! let endpoints: Endpoints = serde_json::from_str(json).unwrap();
+//! assert_eq!(endpoints.api.as_str(), "https://example.com/v1");
+//! assert_eq!(endpoints.events, None);
+//! assert_eq!(serde_json::to_string(&endpoints).unwrap(), r#"{"api":"https://example.com/v1","events":null,"database":"postgres://db/app"}"#);
+//!
+//! let json = r#"{"api":"ftp://example.com","database":"postgres://db/app"}"#;
+//! let error = serde_json::from_str::<Endpoints>(json).err().unwrap();
+//! assert!(error.to_string().starts_with("invalid value: string \"ftp://example.com\", expected a URL with scheme http or https"));
+//! ```
+
+macro_rules! declare_serde_module {
+    ($schemes:ty) => {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use crate::net::url::private::*;
+
+        pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+            for<'a> Serde<&'a T, $schemes>: Serialize,
+        {
+            <Serde<_, $schemes>>::new(val).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where
+            D: Deserializer<'de>,
+            Serde<T, $schemes>: Deserialize<'de>,
+        {
+            Serde::deserialize(deserializer).map(Serde::into_inner)
+        }
+    };
+}
+
+declare_serde_module!(AnyScheme);
+
+/// Only accept `http` and `https` scheme.
+pub mod http {
+    declare_serde_module!(Http);
+}
+
+/// Only accept `ws` and `wss` scheme.
+pub mod ws {
+    declare_serde_module!(WebSocket);
+}
+
+mod private {
+    use std::marker::PhantomData;
+
+    use serde::de::{self, Unexpected};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use url::Url;
+
+    pub trait Schemes {
+        /// Allowed schemes, empty allows any scheme.
+        const SCHEMES: &'static [&'static str];
+    }
+
+    pub struct AnyScheme;
+    pub struct Http;
+    pub struct WebSocket;
+
+    impl Schemes for AnyScheme {
+        const SCHEMES: &'static [&'static str] = &[];
+    }
+
+    impl Schemes for Http {
+        const SCHEMES: &'static [&'static str] = &["http", "https"];
+    }
+
+    impl Schemes for WebSocket {
+        const SCHEMES: &'static [&'static str] = &["ws", "wss"];
+    }
+
+    /// Generalizing serialization/deserialization over `Url`
+    pub struct Serde<T, S> {
+        inner: T,
+        schemes: PhantomData<S>,
+    }
+
+    impl<T, S> Serde<T, S> {
+        pub(crate) fn new(inner: T) -> Self {
+            Self {
+                inner,
+                schemes: PhantomData,
+            }
+        }
+
+        pub(crate) fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    impl<S: Schemes> Serialize for Serde<&Url, S> {
+        fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+            serializer.serialize_str(self.inner.as_str())
+        }
+    }
+
+    impl<S: Schemes> Serialize for Serde<&Option<Url>, S> {
+        fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+            match self.inner {
+                Some(url) => serializer.serialize_some(&<Serde<_, S>>::new(url)),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
+
+    impl<'de, S: Schemes> Deserialize<'de> for Serde<Url, S> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            let s = String::deserialize(deserializer)?;
+            let url = Url::parse(&s).map_err(|e| {
+                de::Error::invalid_value(Unexpected::Str(&s), &format!("a URL, {e}").as_str())
+            })?;
+            if !S::SCHEMES.is_empty() && !S::SCHEMES.contains(&url.scheme()) {
+                let expected = format!("a URL with scheme {}", S::SCHEMES.join(" or "));
+                return Err(de::Error::invalid_value(
+                    Unexpected::Str(&s),
+                    &expected.as_str(),
+                ));
+            }
+            Ok(Serde::new(url))
+        }
+    }
+
+    impl<'de, S: Schemes> Deserialize<'de> for Serde<Option<Url>, S> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            match <Option<Serde<Url, S>>>::deserialize(deserializer)? {
+                Some(url) => Ok(Serde::new(Some(url.into_inner()))),
+                None => Ok(Serde::new(None)),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+
+        use super::*;
+        use crate::net::url as any;
+        use crate::net::url::{http, ws};
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Any(#[serde(with = "any")] Url);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Api(#[serde(with = "http")] Url);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Events(#[serde(with = "ws")] Option<Url>);
+
+        fn url(s: &str) -> Url {
+            Url::parse(s).unwrap()
+        }
+
+        #[test]
+        fn serialize_url() {
+            assert_ser_tokens(
+                &Api(url("https://example.com")),
+                &[Token::Str("https://example.com/")],
+            );
+            assert_ser_tokens(
+                &Events(Some(url("wss://example.com/events"))),
+                &[Token::Some, Token::Str("wss://example.com/events")],
+            );
+            assert_ser_tokens(&Events(None), &[Token::None]);
+        }
+
+        #[test]
+        fn deserialize_url() {
+            assert_de_tokens(
+                &Any(url("redis://cache:6379")),
+                &[Token::Str("redis://cache:6379")],
+            );
+            assert_de_tokens(
+                &Api(url("http://localhost:8080/")),
+                &[Token::Str("http://localhost:8080")],
+            );
+            assert_de_tokens(&Events(None), &[Token::None]);
+            assert_de_tokens_error::<Api>(
+                &[Token::Str("ws://example.com")],
+                "invalid value: string \"ws://example.com\", expected a URL with scheme http or https",
+            );
+            assert_de_tokens_error::<Events>(
+                &[Token::Some, Token::Str("https://example.com")],
+                "invalid value: string \"https://example.com\", expected a URL with scheme ws or wss",
+            );
+            assert_de_tokens_error::<Any>(
+                &[Token::Str("example.com")],
+                "invalid value: string \"example.com\", expected a URL, relative URL without a base",
+            );
+        }
+    }
+}
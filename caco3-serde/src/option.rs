@@ -0,0 +1,49 @@
+//! Deserializers for optional values
+
+use serde::{Deserialize, Deserializer};
+
+/// Deserialize `null` as `T::default()`, for non-`Option` fields.
+///
+/// Combine with `#[serde(default)]` to also accept missing field.
+///
+/// Examples
+/// ```rust
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct User {
+///     #[serde(default, deserialize_with = "caco3_serde::option::null_as_default")]
+///     tags: Vec<String>,
+///     #[serde(deserialize_with = "caco3_serde::option::null_as_default")]
+///     score: u32,
+/// }
+///
+/// let user: User = serde_json::from_str(r#"{"tags":null,"score":null}"#).unwrap();
+/// assert!(user.tags.is_empty());
+/// assert_eq!(user.score, 0);
+/// ```
+pub fn null_as_default<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: Deserialize<'de> + Default,
+    D: Deserializer<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Option::unwrap_or_default)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_de_tokens, Token};
+
+    use super::*;
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(transparent)]
+    struct Name(#[serde(deserialize_with = "null_as_default")] String);
+
+    #[test]
+    fn test_null_as_default() {
+        assert_de_tokens(&Name("".into()), &[Token::None]);
+        assert_de_tokens(&Name("".into()), &[Token::Unit]);
+        assert_de_tokens(&Name("a".into()), &[Token::Some, Token::Str("a")]);
+    }
+}
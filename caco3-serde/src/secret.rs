@@ -0,0 +1,147 @@
+//! Helper module for serializing/deserializing [`Redacted`](caco3::redacted::Redacted) secrets
+//!
+//! Root module serializes secrets as `***`, so serialized configuration is safe to log.
+//! [`expose`] serializes actual values. Both deserialize actual values.
+//! Modules support `Redacted<T>` and `Option<Redacted<T>>`.
+//!
+//! Examples
+//! ```rust
+//! use caco3::redacted::Redacted;
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Config {
+//!     #[serde(with = "caco3_serde::secret")]
+//!     api_key: Redacted<String>,
+//!     #[serde(default, with = "caco3_serde::secret::expose")]
+//!     webhook_token: Option<Redacted<String>>,
+//! }
+//!
+//! let json = r#"{"api_key":"key","webhook_token":"token"}"#;
+//! let config: Config = serde_json::from_str(json).unwrap();
+//! assert_eq!(config.api_key.expose(), "key");
+//!
+//! let json = serde_json::to_string(&config).unwrap();
+//! assert_eq!(json, r#"{"api_key":"***","webhook_token":"token"}"#);
+//! ```
+
+macro_rules! declare_serde_module {
+    ($mode:ty) => {
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use crate::secret::private::*;
+
+        pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+            for<'a> Serde<&'a T, $mode>: Serialize,
+        {
+            <Serde<_, $mode>>::new(val).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where
+            D: Deserializer<'de>,
+            Serde<T, $mode>: Deserialize<'de>,
+        {
+            Serde::deserialize(deserializer).map(Serde::into_inner)
+        }
+    };
+}
+
+declare_serde_module!(Masked);
+
+/// Serialize actual values, for writing configuration consumed by another program.
+pub mod expose {
+    declare_serde_module!(Exposed);
+}
+
+mod private {
+    use std::marker::PhantomData;
+
+    use caco3::redacted::Redacted;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub struct Masked;
+    pub struct Exposed;
+
+    /// Generalizing serialization/deserialization over `Redacted`
+    pub struct Serde<T, M> {
+        inner: T,
+        mode: PhantomData<M>,
+    }
+
+    impl<T, M> Serde<T, M> {
+        pub(crate) fn new(inner: T) -> Self {
+            Self {
+                inner,
+                mode: PhantomData,
+            }
+        }
+
+        pub(crate) fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    impl<T> Serialize for Serde<&Redacted<T>, Masked> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_str(Redacted::<T>::MASK)
+        }
+    }
+
+    impl<T: Serialize> Serialize for Serde<&Redacted<T>, Exposed> {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            self.inner.expose().serialize(serializer)
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, M> Deserialize<'de> for Serde<Redacted<T>, M> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            T::deserialize(deserializer).map(|val| Serde::new(Redacted::new(val)))
+        }
+    }
+
+    impl<T, M> Serialize for Serde<&Option<Redacted<T>>, M>
+    where
+        for<'a> Serde<&'a Redacted<T>, M>: Serialize,
+    {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self.inner {
+                Some(secret) => serializer.serialize_some(&<Serde<_, M>>::new(secret)),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
+
+    impl<'de, T: Deserialize<'de>, M> Deserialize<'de> for Serde<Option<Redacted<T>>, M> {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            Option::<T>::deserialize(deserializer).map(|val| Serde::new(val.map(Redacted::new)))
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_ser_tokens, assert_tokens, Token};
+
+        use super::*;
+        use crate::secret::{self as masked, expose};
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct ApiKey(#[serde(with = "masked")] Option<Redacted<String>>);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Pin(#[serde(with = "expose")] Redacted<u32>);
+
+        #[test]
+        fn test_secret() {
+            let secret = ApiKey(Some(Redacted::new("hunter2".into())));
+            assert_ser_tokens(&secret, &[Token::Some, Token::Str("***")]);
+            assert_de_tokens(&secret, &[Token::Some, Token::Str("hunter2")]);
+            assert_tokens(&ApiKey(None), &[Token::None]);
+            assert_tokens(&Pin(Redacted::new(1234)), &[Token::U32(1234)]);
+        }
+    }
+}
@@ -0,0 +1,139 @@
+//! Helper module for serializing/deserializing `Version` and `VersionReq` as string
+//!
+//! Modules support `Version`, `VersionReq` and their `Option`.
+//! Deserialization error tells what was expected and why parsing failed.
+//!
+//! Examples
+//! ```rust
+//! use semver::{Version, VersionReq};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Plugin {
+//!     #[serde(with = "caco3_serde::semver")]
+//!     version: Version,
+//!     #[serde(default, with = "caco3_serde::semver")]
+//!     requires: Option<VersionReq>,
+//! }
+//!
+//! let json = r#"{"version":"1.2.3-beta.1","requires":">=0.4, <0.6"}"#;
+//! let plugin: Plugin = serde_json::from_str(json).unwrap();
+//! assert_eq!(plugin.version, Version::parse("1.2.3-beta.1").unwrap());
+//! assert!(plugin.requires.unwrap().matches(&Version::new(0, 5, 1)));
+//!
+//! let json = r#"{"version":"1.2"}"#;
+//! let error = serde_json::from_str::<Plugin>(json).err().unwrap();
+//! assert!(error.to_string().starts_with("invalid value: string \"1.2\", expected a semver version, unexpected end of input while parsing minor version number"));
+//! ```
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use private::Serde;
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    for<'a> Serde<&'a T>: Serialize,
+{
+    Serde(val).serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(|Serde(val)| val)
+}
+
+mod private {
+    use semver::{Version, VersionReq};
+    use serde::de::{self, Unexpected};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    /// Generalizing serialization/deserialization over semver types
+    pub struct Serde<T>(pub(super) T);
+
+    macro_rules! impl_serde {
+        ($ty:ty, $expected:literal) => {
+            impl Serialize for Serde<&$ty> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    serializer.collect_str(self.0)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for Serde<$ty> {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    let s = String::deserialize(deserializer)?;
+                    s.trim().parse().map(Serde).map_err(|e| {
+                        let expected = format!(concat!($expected, ", {}"), e);
+                        de::Error::invalid_value(Unexpected::Str(&s), &expected.as_str())
+                    })
+                }
+            }
+
+            impl Serialize for Serde<&Option<$ty>> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    match self.0 {
+                        Some(val) => serializer.serialize_some(&Serde(val)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+            }
+
+            impl<'de> Deserialize<'de> for Serde<Option<$ty>> {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    match <Option<Serde<$ty>>>::deserialize(deserializer)? {
+                        Some(Serde(val)) => Ok(Serde(Some(val))),
+                        None => Ok(Serde(None)),
+                    }
+                }
+            }
+        };
+    }
+
+    impl_serde!(Version, "a semver version");
+    impl_serde!(VersionReq, "a semver version requirement");
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Ver(#[serde(with = "super::super")] Version);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Req(#[serde(with = "super::super")] Option<VersionReq>);
+
+        #[test]
+        fn serialize_semver() {
+            assert_ser_tokens(&Ver(Version::new(1, 2, 3)), &[Token::Str("1.2.3")]);
+            assert_ser_tokens(
+                &Req(Some(VersionReq::parse(">= 1.2, <2").unwrap())),
+                &[Token::Some, Token::Str(">=1.2, <2")],
+            );
+            assert_ser_tokens(&Req(None), &[Token::None]);
+        }
+
+        #[test]
+        fn deserialize_semver() {
+            assert_de_tokens(&Ver(Version::new(1, 2, 3)), &[Token::Str(" 1.2.3 ")]);
+            assert_de_tokens(
+                &Req(Some(VersionReq::parse("^0.4").unwrap())),
+                &[Token::Some, Token::Str("^0.4")],
+            );
+            assert_de_tokens(&Req(None), &[Token::None]);
+            assert_de_tokens_error::<Ver>(
+                &[Token::Str("v1.2.3")],
+                "invalid value: string \"v1.2.3\", expected a semver version, unexpected character 'v' while parsing major version number",
+            );
+            assert_de_tokens_error::<Req>(
+                &[Token::Some, Token::Str(">=1.2 <2")],
+                "invalid value: string \">=1.2 <2\", expected a semver version requirement, expected comma after minor version number, found '<'",
+            );
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Deserializers sanitizing strings, for `String` and `Option<String>`
+//!
+//! Examples
+//! ```rust
+//! use serde::Deserialize;
+//!
+//! #[derive(Debug, Deserialize)]
+//! struct Config {
+//!     #[serde(deserialize_with = "caco3_serde::string::trimmed")]
+//!     name: String,
+//!     #[serde(deserialize_with = "caco3_serde::string::lowercase")]
+//!     log_level: String,
+//!     #[serde(default, deserialize_with = "caco3_serde::string::non_empty")]
+//!     api_key: Option<String>,
+//! }
+//!
+//! let json = r#"{"name":" app ","log_level":"INFO","api_key":" secret "}"#;
+//! let config: Config = serde_json::from_str(json).unwrap();
+//! assert_eq!(config.name, "app");
+//! assert_eq!(config.log_level, "info");
+//! assert_eq!(config.api_key.as_deref(), Some("secret"));
+//!
+//! let json = r#"{"name":"app","log_level":"info","api_key":"  "}"#;
+//! let error = serde_json::from_str::<Config>(json).unwrap_err();
+//! assert!(error.to_string().starts_with("invalid value: blank string, expected a non-blank string"));
+//! ```
+use serde::{Deserialize, Deserializer};
+
+use private::{Lowercase, NonEmpty, Serde, Trimmed};
+
+/// Remove leading and trailing whitespace.
+pub fn trimmed<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, Trimmed>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+/// Convert to lowercase.
+pub fn lowercase<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, Lowercase>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+/// Remove leading and trailing whitespace, and reject blank string.
+///
+/// `None` is still allowed for `Option<String>`.
+pub fn non_empty<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T, NonEmpty>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+mod private {
+    use std::marker::PhantomData;
+
+    use serde::de::{self, Unexpected};
+    use serde::{Deserialize, Deserializer};
+
+    pub trait Sanitize {
+        fn sanitize<E: de::Error>(s: String) -> Result<String, E>;
+    }
+
+    pub struct Trimmed;
+    pub struct Lowercase;
+    pub struct NonEmpty;
+
+    fn trim(s: String) -> String {
+        let trimmed = s.trim();
+        if trimmed.len() == s.len() {
+            s
+        } else {
+            trimmed.to_owned()
+        }
+    }
+
+    impl Sanitize for Trimmed {
+        fn sanitize<E: de::Error>(s: String) -> Result<String, E> {
+            Ok(trim(s))
+        }
+    }
+
+    impl Sanitize for Lowercase {
+        fn sanitize<E: de::Error>(s: String) -> Result<String, E> {
+            Ok(s.to_lowercase())
+        }
+    }
+
+    impl Sanitize for NonEmpty {
+        fn sanitize<E: de::Error>(s: String) -> Result<String, E> {
+            let s = trim(s);
+            if s.is_empty() {
+                return Err(E::invalid_value(
+                    Unexpected::Other("blank string"),
+                    &"a non-blank string",
+                ));
+            }
+            Ok(s)
+        }
+    }
+
+    /// Generalizing deserialization over strings
+    pub struct Serde<T, F> {
+        inner: T,
+        sanitize: PhantomData<F>,
+    }
+
+    impl<T, F> Serde<T, F> {
+        fn new(inner: T) -> Self {
+            Self {
+                inner,
+                sanitize: PhantomData,
+            }
+        }
+
+        pub(super) fn into_inner(self) -> T {
+            self.inner
+        }
+    }
+
+    impl<'de, F: Sanitize> Deserialize<'de> for Serde<String, F> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let s = String::deserialize(deserializer)?;
+            F::sanitize(s).map(Serde::new)
+        }
+    }
+
+    impl<'de, F: Sanitize> Deserialize<'de> for Serde<Option<String>, F> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match <Option<Serde<String, F>>>::deserialize(deserializer)? {
+                Some(s) => Ok(Serde::new(Some(s.into_inner()))),
+                None => Ok(Serde::new(None)),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, Token};
+
+        use super::super::{lowercase, non_empty, trimmed};
+        use super::*;
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Trim(#[serde(deserialize_with = "trimmed")] String);
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Lower(#[serde(deserialize_with = "lowercase")] Option<String>);
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Required(#[serde(deserialize_with = "non_empty")] String);
+
+        #[derive(Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct OptionRequired(#[serde(deserialize_with = "non_empty")] Option<String>);
+
+        #[test]
+        fn test_deserialize() {
+            assert_de_tokens(&Trim("a b".into()), &[Token::Str("\t a b \n")]);
+            assert_de_tokens(&Trim("".into()), &[Token::Str("  ")]);
+            assert_de_tokens(
+                &Lower(Some("ดี debug".into())),
+                &[Token::Some, Token::Str("ดี DEBUG")],
+            );
+            assert_de_tokens(&Lower(None), &[Token::None]);
+            assert_de_tokens(&Required("key".into()), &[Token::Str(" key ")]);
+            assert_de_tokens(&OptionRequired(None), &[Token::None]);
+            assert_de_tokens_error::<Required>(
+                &[Token::Str(" ")],
+                "invalid value: blank string, expected a non-blank string",
+            );
+            assert_de_tokens_error::<OptionRequired>(
+                &[Token::Some, Token::Str("")],
+                "invalid value: blank string, expected a non-blank string",
+            );
+        }
+    }
+}
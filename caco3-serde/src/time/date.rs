@@ -0,0 +1,192 @@
+//! Helper module for serializing/deserializing `time::Date` as `YYYY-MM-DD`
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use time::macros::date;
+//! use time::Date;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Holiday {
+//!     #[serde(with = "caco3_serde::time::date")]
+//!     date: Date,
+//!     #[serde(default, with = "caco3_serde::time::date")]
+//!     observed: Option<Date>,
+//! }
+//!
+//! let holiday: Holiday = serde_json::from_str(r#"{"date":"2022-04-13"}"#).unwrap();
+//! assert_eq!(holiday.date, date!(2022-04-13));
+//! assert_eq!(holiday.observed, None);
+//! assert_eq!(
+//!     serde_json::to_string(&holiday).unwrap(),
+//!     r#"{"date":"2022-04-13","observed":null}"#
+//! );
+//! ```
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use private::Serde;
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    Serde<T>: Serialize,
+{
+    Serde::new_ref(val).serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+mod private {
+    use core::fmt;
+
+    use bytemuck::TransparentWrapper;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use time::format_description::FormatItem;
+    use time::macros::format_description;
+    use time::Date;
+
+    const FORMAT: &[FormatItem<'static>] = format_description!("[year]-[month]-[day]");
+
+    #[repr(transparent)]
+    #[derive(bytemuck::TransparentWrapper)]
+    pub struct Serde<T>(T);
+
+    impl<T> Serde<T> {
+        pub(super) fn into_inner(self) -> T {
+            self.0
+        }
+
+        pub(super) fn new_ref(inner_ref: &T) -> &Self {
+            Self::wrap_ref(inner_ref)
+        }
+    }
+
+    impl<T> fmt::Debug for Serde<T>
+    where
+        T: fmt::Debug,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    impl Serialize for Serde<Date> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let date = self.0.format(FORMAT).map_err(serde::ser::Error::custom)?;
+            serializer.serialize_str(&date)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<Date> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let date = String::deserialize(deserializer)?;
+            Date::parse(&date, FORMAT)
+                .map(Serde)
+                .map_err(de::Error::custom)
+        }
+    }
+
+    impl Serialize for Serde<Option<Date>> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match &self.0 {
+                Some(date) => serializer.serialize_some(Serde::new_ref(date)),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<Option<Date>> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match <Option<Serde<Date>>>::deserialize(deserializer)? {
+                Some(Serde(val)) => Ok(Serde(Some(val))),
+                None => Ok(Serde(None)),
+            }
+        }
+    }
+
+    macro_rules! impl_serialize_ref {
+        (@deref $expr:expr, $lt:lifetime) => {
+            * $expr
+        };
+        (@deref $expr:expr, $lt0:lifetime, $($lt:lifetime),+) => {
+            * impl_serialize_ref!(@deref $expr, $($lt),+)
+        };
+        ($ty:ty, <$($lt:lifetime),+>) => {
+            impl <$($lt),+> Serialize for Serde<$(&$lt)+ $ty> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let inner_ref: &$ty = &impl_serialize_ref!(@deref self.0, $($lt),+);
+                    let serde_ref: &Serde<$ty> = Serde::new_ref(inner_ref);
+                    serde_ref.serialize(serializer)
+                }
+            }
+        };
+    }
+
+    impl_serialize_ref!(Date, <'a>);
+    impl_serialize_ref!(Date, <'a, 'b>);
+    impl_serialize_ref!(Option<Date>, <'a>);
+    impl_serialize_ref!(Option<Date>, <'a, 'b>);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens_error, assert_ser_tokens, assert_tokens, Token};
+        use time::macros::date;
+
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Owned(#[serde(with = "super::super")] Date);
+
+        #[derive(Serialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Ref<'a>(#[serde(with = "super::super")] &'a Date);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct OptionOwned(#[serde(with = "super::super")] Option<Date>);
+
+        #[test]
+        fn test_new_borrowed_safety() {
+            let date = date!(2022 - 01 - 01);
+            let _serde = Serde::new_ref(&date);
+        }
+
+        #[test]
+        fn serde_date() {
+            let date = date!(2022 - 04 - 13);
+            assert_tokens(&Owned(date), &[Token::Str("2022-04-13")]);
+            assert_ser_tokens(&Ref(&date), &[Token::Str("2022-04-13")]);
+            assert_tokens(
+                &OptionOwned(Some(date)),
+                &[Token::Some, Token::Str("2022-04-13")],
+            );
+            assert_tokens(&OptionOwned(None), &[Token::None]);
+            assert_de_tokens_error::<Owned>(
+                &[Token::Str("2022-02-30")],
+                "day must be in the range 1..=28, given values of other parameters",
+            );
+        }
+    }
+}
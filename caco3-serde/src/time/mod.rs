@@ -1 +1,5 @@
+pub mod date;
 pub mod rfc3339;
+pub mod time_of_day;
+pub mod unix;
+pub mod utc_offset;
@@ -25,6 +25,15 @@
 //! assert_eq!(rfc3339_second, r#""2022-01-01T01:23:45+07:00""#);
 //! let actual = serde_json::from_str::<Second>(&rfc3339_second).unwrap().0;
 //! assert_eq!(actual, datetime!(2022-01-01 01:23:45+07:00));
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(default, with = "caco3_serde::time::rfc3339::second::option")]
+//!     expired_at: Option<OffsetDateTime>,
+//! }
+//!
+//! let event = serde_json::from_str::<Event>("{}").unwrap();
+//! assert_eq!(event.expired_at, None);
 //! ```
 
 use serde::{Deserialize, Serialize};
@@ -53,6 +62,32 @@ macro_rules! declare_serde_module {
         {
             Serde::deserialize(deserializer).map(Serde::into_time)
         }
+
+        /// For `Option<OffsetDateTime>` field with `#[serde(default, with = "...")]`,
+        /// a missing field is `None`.
+        pub mod option {
+            use serde::{Deserializer, Serializer};
+            use time::OffsetDateTime;
+
+            pub fn serialize<S>(
+                val: &Option<OffsetDateTime>,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                super::serialize(val, serializer)
+            }
+
+            pub fn deserialize<'de, D>(
+                deserializer: D,
+            ) -> Result<Option<OffsetDateTime>, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                super::deserialize(deserializer)
+            }
+        }
     };
 }
 
@@ -216,6 +251,25 @@ mod private {
         #[serde(transparent)]
         struct OptionOwned(#[serde(with = "millisecond")] Option<OffsetDateTime>);
 
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct OptionField {
+            #[serde(default, with = "millisecond::option")]
+            time: Option<OffsetDateTime>,
+        }
+
+        #[test]
+        fn option_field() {
+            let time = datetime!(2022-01-01 19:00:10.123+07:00);
+            let field: OptionField = serde_json::from_str("{}").unwrap();
+            assert_eq!(field, OptionField { time: None });
+            let field: OptionField = serde_json::from_str(r#"{"time":null}"#).unwrap();
+            assert_eq!(field, OptionField { time: None });
+            let json = r#"{"time":"2022-01-01T19:00:10.123+07:00"}"#;
+            let field: OptionField = serde_json::from_str(json).unwrap();
+            assert_eq!(field, OptionField { time: Some(time) });
+            assert_eq!(serde_json::to_string(&field).unwrap(), json);
+        }
+
         #[test]
         fn deserialize_millisecond() {
             assert_de_tokens(
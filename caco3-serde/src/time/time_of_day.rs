@@ -0,0 +1,195 @@
+//! Helper module for serializing/deserializing `time::Time` as `HH:MM:SS`
+//!
+//! Fraction of second is truncated.
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use time::macros::time;
+//! use time::Time;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Schedule {
+//!     #[serde(with = "caco3_serde::time::time_of_day")]
+//!     start: Time,
+//!     #[serde(default, with = "caco3_serde::time::time_of_day")]
+//!     end: Option<Time>,
+//! }
+//!
+//! let schedule: Schedule = serde_json::from_str(r#"{"start":"09:30:00"}"#).unwrap();
+//! assert_eq!(schedule.start, time!(09:30));
+//! assert_eq!(schedule.end, None);
+//! assert_eq!(
+//!     serde_json::to_string(&schedule).unwrap(),
+//!     r#"{"start":"09:30:00","end":null}"#
+//! );
+//! ```
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use private::Serde;
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    Serde<T>: Serialize,
+{
+    Serde::new_ref(val).serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+mod private {
+    use core::fmt;
+
+    use bytemuck::TransparentWrapper;
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use time::format_description::FormatItem;
+    use time::macros::format_description;
+    use time::Time;
+
+    const FORMAT: &[FormatItem<'static>] = format_description!("[hour]:[minute]:[second]");
+
+    #[repr(transparent)]
+    #[derive(bytemuck::TransparentWrapper)]
+    pub struct Serde<T>(T);
+
+    impl<T> Serde<T> {
+        pub(super) fn into_inner(self) -> T {
+            self.0
+        }
+
+        pub(super) fn new_ref(inner_ref: &T) -> &Self {
+            Self::wrap_ref(inner_ref)
+        }
+    }
+
+    impl<T> fmt::Debug for Serde<T>
+    where
+        T: fmt::Debug,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    impl Serialize for Serde<Time> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let time = self.0.format(FORMAT).map_err(serde::ser::Error::custom)?;
+            serializer.serialize_str(&time)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<Time> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            let time = String::deserialize(deserializer)?;
+            Time::parse(&time, FORMAT)
+                .map(Serde)
+                .map_err(de::Error::custom)
+        }
+    }
+
+    impl Serialize for Serde<Option<Time>> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match &self.0 {
+                Some(time) => serializer.serialize_some(Serde::new_ref(time)),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<Option<Time>> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match <Option<Serde<Time>>>::deserialize(deserializer)? {
+                Some(Serde(val)) => Ok(Serde(Some(val))),
+                None => Ok(Serde(None)),
+            }
+        }
+    }
+
+    macro_rules! impl_serialize_ref {
+        (@deref $expr:expr, $lt:lifetime) => {
+            * $expr
+        };
+        (@deref $expr:expr, $lt0:lifetime, $($lt:lifetime),+) => {
+            * impl_serialize_ref!(@deref $expr, $($lt),+)
+        };
+        ($ty:ty, <$($lt:lifetime),+>) => {
+            impl <$($lt),+> Serialize for Serde<$(&$lt)+ $ty> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let inner_ref: &$ty = &impl_serialize_ref!(@deref self.0, $($lt),+);
+                    let serde_ref: &Serde<$ty> = Serde::new_ref(inner_ref);
+                    serde_ref.serialize(serializer)
+                }
+            }
+        };
+    }
+
+    impl_serialize_ref!(Time, <'a>);
+    impl_serialize_ref!(Time, <'a, 'b>);
+    impl_serialize_ref!(Option<Time>, <'a>);
+    impl_serialize_ref!(Option<Time>, <'a, 'b>);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens_error, assert_ser_tokens, assert_tokens, Token};
+        use time::macros::time;
+
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Owned(#[serde(with = "super::super")] Time);
+
+        #[derive(Serialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Ref<'a>(#[serde(with = "super::super")] &'a Time);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct OptionOwned(#[serde(with = "super::super")] Option<Time>);
+
+        #[test]
+        fn test_new_borrowed_safety() {
+            let time = time!(00:00);
+            let _serde = Serde::new_ref(&time);
+        }
+
+        #[test]
+        fn serde_time_of_day() {
+            let time = time!(19:00:10);
+            assert_tokens(&Owned(time), &[Token::Str("19:00:10")]);
+            assert_ser_tokens(&Owned(time!(19:00:10.999)), &[Token::Str("19:00:10")]);
+            assert_ser_tokens(&Ref(&time), &[Token::Str("19:00:10")]);
+            assert_tokens(
+                &OptionOwned(Some(time)),
+                &[Token::Some, Token::Str("19:00:10")],
+            );
+            assert_tokens(&OptionOwned(None), &[Token::None]);
+            assert_de_tokens_error::<Owned>(
+                &[Token::Str("24:00:00")],
+                "the 'hour' component could not be parsed",
+            );
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Helper module for serializing/deserializing datetime as unix timestamp
+//!
+//! Timestamps are serialized as integers, and deserialized from integers or numeric strings.
+//! Deserialized datetime is in UTC.
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use time::macros::datetime;
+//! use time::OffsetDateTime;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Event {
+//!     #[serde(with = "caco3_serde::time::unix::milliseconds")]
+//!     created_at: OffsetDateTime,
+//!     #[serde(with = "caco3_serde::time::unix::seconds")]
+//!     expired_at: Option<OffsetDateTime>,
+//! }
+//!
+//! let event = Event {
+//!     created_at: datetime!(2022-01-01 01:23:45.123456789 UTC),
+//!     expired_at: None,
+//! };
+//! let json = serde_json::to_string(&event).unwrap();
+//! assert_eq!(json, r#"{"created_at":1641000225123,"expired_at":null}"#);
+//!
+//! let json = r#"{"created_at":"1641000225123","expired_at":1641000225}"#;
+//! let event = serde_json::from_str::<Event>(json).unwrap();
+//! assert_eq!(event.created_at, datetime!(2022-01-01 01:23:45.123 UTC));
+//! assert_eq!(event.expired_at, Some(datetime!(2022-01-01 01:23:45 UTC)));
+//! ```
+
+macro_rules! declare_serde_module {
+    ($unit:ty) => {
+        use serde::de::DeserializeOwned;
+        use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+        use super::private::*;
+
+        pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            T: Copy,
+            S: Serializer,
+            Serde<T, $unit>: Serialize,
+        {
+            <Serde<_, $unit>>::new(*val).serialize(serializer)
+        }
+
+        pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+        where
+            D: Deserializer<'de>,
+            Serde<T, $unit>: DeserializeOwned,
+        {
+            Serde::deserialize(deserializer).map(Serde::into_time)
+        }
+    };
+}
+
+pub mod milliseconds {
+    declare_serde_module!(MillisecondsUnit);
+}
+pub mod seconds {
+    declare_serde_module!(SecondsUnit);
+}
+
+mod private {
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    use serde::de::{self, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use time::OffsetDateTime;
+
+    pub struct MillisecondsUnit;
+    pub struct SecondsUnit;
+
+    const NANOS_PER_MILLISECOND: i128 = 1_000_000;
+    const NANOS_PER_SECOND: i128 = 1_000_000_000;
+
+    /// Generalizing serialization/deserialization over `OffsetDateTime`
+    pub struct Serde<T, U> {
+        time: T,
+        unit: PhantomData<U>,
+    }
+
+    impl<T, U> Serde<T, U> {
+        pub(super) fn into_time(self) -> T {
+            self.time
+        }
+    }
+
+    /// Integer or numeric string.
+    struct TimestampVisitor;
+
+    impl Visitor<'_> for TimestampVisitor {
+        type Value = i64;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("an integer or a numeric string")
+        }
+
+        fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+            Ok(v)
+        }
+
+        fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+            i64::try_from(v).map_err(|_| E::invalid_value(de::Unexpected::Unsigned(v), &self))
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+            v.trim()
+                .parse()
+                .map_err(|_| E::invalid_value(de::Unexpected::Str(v), &self))
+        }
+    }
+
+    macro_rules! impl_serde {
+        ($ty:ty, $unit:ty, $nanos:expr) => {
+            impl<T> Serde<T, $unit> {
+                pub(super) fn new(time: T) -> Self {
+                    Self {
+                        time,
+                        unit: PhantomData,
+                    }
+                }
+            }
+
+            impl Serialize for Serde<$ty, $unit> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let timestamp = self.time.unix_timestamp_nanos().div_euclid($nanos);
+                    // every valid `OffsetDateTime` fits in i64 milliseconds
+                    serializer.serialize_i64(timestamp as i64)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for Serde<$ty, $unit> {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    let timestamp = deserializer.deserialize_any(TimestampVisitor)?;
+                    let datetime = OffsetDateTime::from_unix_timestamp_nanos(
+                        i128::from(timestamp) * $nanos,
+                    )
+                    .map_err(de::Error::custom)?;
+                    Ok(<Serde<_, $unit>>::new(datetime))
+                }
+            }
+
+            impl Serialize for Serde<Option<$ty>, $unit> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    match self.time {
+                        Some(val) => serializer.serialize_some(&<Serde<_, $unit>>::new(val)),
+                        None => serializer.serialize_none(),
+                    }
+                }
+            }
+
+            impl<'de> Deserialize<'de> for Serde<Option<$ty>, $unit> {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: Deserializer<'de>,
+                {
+                    match <Option<Serde<$ty, $unit>>>::deserialize(deserializer)? {
+                        Some(Serde { time, .. }) => Ok(<Serde<_, $unit>>::new(Some(time))),
+                        None => Ok(<Serde<_, $unit>>::new(None)),
+                    }
+                }
+            }
+        };
+    }
+
+    impl_serde!(OffsetDateTime, MillisecondsUnit, NANOS_PER_MILLISECOND);
+    impl_serde!(OffsetDateTime, SecondsUnit, NANOS_PER_SECOND);
+
+    // n.b. `$ty` must implement Copy
+    macro_rules! impl_serialize_ref {
+        (@deref $expr:expr, $lt:lifetime) => {
+            * $expr
+        };
+        (@deref $expr:expr, $lt0:lifetime, $($lt:lifetime),+) => {
+            * impl_serialize_ref!(@deref $expr, $($lt),+)
+        };
+        ($unit:ty, $ty:ty, <$($lt:lifetime),+>) => {
+            impl <$($lt),+> Serialize for Serde<$(&$lt)+ $ty, $unit> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let time: $ty = impl_serialize_ref!(@deref self.time, $($lt),+);
+                    let serde = <Serde<_, $unit>>::new(time);
+                    serde.serialize(serializer)
+                }
+            }
+        };
+    }
+
+    impl_serialize_ref!(MillisecondsUnit, OffsetDateTime, <'a>);
+    impl_serialize_ref!(MillisecondsUnit, OffsetDateTime, <'a, 'b>);
+    impl_serialize_ref!(MillisecondsUnit, Option<OffsetDateTime>, <'a>);
+    impl_serialize_ref!(MillisecondsUnit, Option<OffsetDateTime>, <'a, 'b>);
+
+    impl_serialize_ref!(SecondsUnit, OffsetDateTime, <'a>);
+    impl_serialize_ref!(SecondsUnit, OffsetDateTime, <'a, 'b>);
+    impl_serialize_ref!(SecondsUnit, Option<OffsetDateTime>, <'a>);
+    impl_serialize_ref!(SecondsUnit, Option<OffsetDateTime>, <'a, 'b>);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+        use time::macros::datetime;
+
+        use super::super::{milliseconds, seconds};
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Millis(#[serde(with = "milliseconds")] OffsetDateTime);
+
+        #[derive(Serialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct MillisRef<'a>(#[serde(with = "milliseconds")] &'a OffsetDateTime);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct OptionSeconds(#[serde(with = "seconds")] Option<OffsetDateTime>);
+
+        #[test]
+        fn deserialize_unix() {
+            let datetime = datetime!(2022-01-01 01:23:45.123 UTC);
+            assert_de_tokens(&Millis(datetime), &[Token::I64(1641000225123)]);
+            assert_de_tokens(&Millis(datetime), &[Token::U64(1641000225123)]);
+            assert_de_tokens(&Millis(datetime), &[Token::Str("1641000225123")]);
+            assert_de_tokens(
+                &Millis(datetime!(1969-12-31 23:59:59.999 UTC)),
+                &[Token::I64(-1)],
+            );
+            assert_de_tokens_error::<Millis>(
+                &[Token::Str("now")],
+                "invalid value: string \"now\", expected an integer or a numeric string",
+            );
+
+            assert_de_tokens(
+                &OptionSeconds(Some(datetime!(2022-01-01 01:23:45 UTC))),
+                &[Token::Some, Token::Str("1641000225")],
+            );
+            assert_de_tokens(&OptionSeconds(None), &[Token::None]);
+        }
+
+        #[test]
+        fn serialize_unix() {
+            let datetime = datetime!(2022-01-01 08:23:45.123456789 +07:00);
+            assert_ser_tokens(&Millis(datetime), &[Token::I64(1641000225123)]);
+            assert_ser_tokens(&MillisRef(&datetime), &[Token::I64(1641000225123)]);
+            assert_ser_tokens(
+                &Millis(datetime!(1969-12-31 23:59:59.9995 UTC)),
+                &[Token::I64(-1)],
+            );
+
+            assert_ser_tokens(
+                &OptionSeconds(Some(datetime)),
+                &[Token::Some, Token::I64(1641000225)],
+            );
+            assert_ser_tokens(&OptionSeconds(None), &[Token::None]);
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Helper module for serializing/deserializing `time::UtcOffset` as `+HH:MM`
+//!
+//! Offsets are deserialized from `+HH:MM`, `+HHMM`, `+HH:MM:SS` or whole seconds.
+//! Seconds are serialized only if they are not zero.
+//!
+//! Examples
+//! ```rust
+//! use serde::{Deserialize, Serialize};
+//! use time::macros::offset;
+//! use time::UtcOffset;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Region {
+//!     #[serde(with = "caco3_serde::time::utc_offset")]
+//!     offset: UtcOffset,
+//!     #[serde(default, with = "caco3_serde::time::utc_offset")]
+//!     report_offset: Option<UtcOffset>,
+//! }
+//!
+//! let region: Region = serde_json::from_str(r#"{"offset":"+0700","report_offset":-18000}"#).unwrap();
+//! assert_eq!(region.offset, offset!(+7));
+//! assert_eq!(region.report_offset, Some(offset!(-5)));
+//! assert_eq!(
+//!     serde_json::to_string(&region).unwrap(),
+//!     r#"{"offset":"+07:00","report_offset":"-05:00"}"#
+//! );
+//! ```
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use private::Serde;
+
+pub fn serialize<T, S>(val: &T, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    Serde<T>: Serialize,
+{
+    Serde::new_ref(val).serialize(serializer)
+}
+
+pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    Serde<T>: Deserialize<'de>,
+{
+    Serde::deserialize(deserializer).map(Serde::into_inner)
+}
+
+mod private {
+    use core::fmt;
+
+    use bytemuck::TransparentWrapper;
+    use serde::de::{self, Unexpected, Visitor};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use time::UtcOffset;
+
+    #[repr(transparent)]
+    #[derive(bytemuck::TransparentWrapper)]
+    pub struct Serde<T>(T);
+
+    impl<T> Serde<T> {
+        pub(super) fn into_inner(self) -> T {
+            self.0
+        }
+
+        pub(super) fn new_ref(inner_ref: &T) -> &Self {
+            Self::wrap_ref(inner_ref)
+        }
+    }
+
+    impl<T> fmt::Debug for Serde<T>
+    where
+        T: fmt::Debug,
+    {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
+    /// Parse `+HH:MM`, `+HHMM` or `+HH:MM:SS`.
+    fn parse(s: &str) -> Option<UtcOffset> {
+        let (sign, rest) = match s.as_bytes().first()? {
+            b'+' => (1, &s[1..]),
+            b'-' => (-1, &s[1..]),
+            _ => return None,
+        };
+        let (hours, minutes, seconds) = match rest.as_bytes() {
+            [h1, h2, b':', m1, m2] | [h1, h2, m1, m2] => ([h1, h2], [m1, m2], [&b'0', &b'0']),
+            [h1, h2, b':', m1, m2, b':', s1, s2] => ([h1, h2], [m1, m2], [s1, s2]),
+            _ => return None,
+        };
+        let component = |[tens, ones]: [&u8; 2]| {
+            (tens.is_ascii_digit() && ones.is_ascii_digit())
+                .then(|| ((tens - b'0') * 10 + ones - b'0') as i8)
+        };
+        let (hours, minutes, seconds) =
+            (component(hours)?, component(minutes)?, component(seconds)?);
+        UtcOffset::from_hms(sign * hours, sign * minutes, sign * seconds).ok()
+    }
+
+    impl Serialize for Serde<UtcOffset> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            let (hours, minutes, seconds) = self.0.as_hms();
+            let sign = if self.0.is_negative() { '-' } else { '+' };
+            let (hours, minutes, seconds) = (hours.abs(), minutes.abs(), seconds.abs());
+            if seconds == 0 {
+                serializer.collect_str(&format_args!("{sign}{hours:02}:{minutes:02}"))
+            } else {
+                serializer.collect_str(&format_args!("{sign}{hours:02}:{minutes:02}:{seconds:02}"))
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<UtcOffset> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            struct OffsetVisitor;
+
+            impl Visitor<'_> for OffsetVisitor {
+                type Value = UtcOffset;
+
+                fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    formatter.write_str("an offset like \"+07:00\", or whole seconds")
+                }
+
+                fn visit_i64<E: de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                    i32::try_from(v)
+                        .ok()
+                        .and_then(|seconds| UtcOffset::from_whole_seconds(seconds).ok())
+                        .ok_or_else(|| E::invalid_value(Unexpected::Signed(v), &self))
+                }
+
+                fn visit_u64<E: de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                    i64::try_from(v)
+                        .map_err(|_| E::invalid_value(Unexpected::Unsigned(v), &self))
+                        .and_then(|v| self.visit_i64(v))
+                }
+
+                fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                    parse(v.trim()).ok_or_else(|| E::invalid_value(Unexpected::Str(v), &self))
+                }
+            }
+
+            deserializer.deserialize_any(OffsetVisitor).map(Serde)
+        }
+    }
+
+    impl Serialize for Serde<Option<UtcOffset>> {
+        fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where
+            S: Serializer,
+        {
+            match &self.0 {
+                Some(offset) => serializer.serialize_some(Serde::new_ref(offset)),
+                None => serializer.serialize_none(),
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Serde<Option<UtcOffset>> {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            match <Option<Serde<UtcOffset>>>::deserialize(deserializer)? {
+                Some(Serde(val)) => Ok(Serde(Some(val))),
+                None => Ok(Serde(None)),
+            }
+        }
+    }
+
+    macro_rules! impl_serialize_ref {
+        (@deref $expr:expr, $lt:lifetime) => {
+            * $expr
+        };
+        (@deref $expr:expr, $lt0:lifetime, $($lt:lifetime),+) => {
+            * impl_serialize_ref!(@deref $expr, $($lt),+)
+        };
+        ($ty:ty, <$($lt:lifetime),+>) => {
+            impl <$($lt),+> Serialize for Serde<$(&$lt)+ $ty> {
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: Serializer,
+                {
+                    let inner_ref: &$ty = &impl_serialize_ref!(@deref self.0, $($lt),+);
+                    let serde_ref: &Serde<$ty> = Serde::new_ref(inner_ref);
+                    serde_ref.serialize(serializer)
+                }
+            }
+        };
+    }
+
+    impl_serialize_ref!(UtcOffset, <'a>);
+    impl_serialize_ref!(UtcOffset, <'a, 'b>);
+    impl_serialize_ref!(Option<UtcOffset>, <'a>);
+    impl_serialize_ref!(Option<UtcOffset>, <'a, 'b>);
+
+    #[cfg(test)]
+    mod tests {
+        use serde_test::{assert_de_tokens, assert_de_tokens_error, assert_ser_tokens, Token};
+        use time::macros::offset;
+
+        use super::*;
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Owned(#[serde(with = "super::super")] UtcOffset);
+
+        #[derive(Serialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct Ref<'a>(#[serde(with = "super::super")] &'a UtcOffset);
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        #[serde(transparent)]
+        struct OptionOwned(#[serde(with = "super::super")] Option<UtcOffset>);
+
+        #[test]
+        fn test_new_borrowed_safety() {
+            let offset = UtcOffset::UTC;
+            let _serde = Serde::new_ref(&offset);
+        }
+
+        #[test]
+        fn serialize_utc_offset() {
+            assert_ser_tokens(&Owned(offset!(+7)), &[Token::Str("+07:00")]);
+            assert_ser_tokens(&Ref(&offset!(-3:30)), &[Token::Str("-03:30")]);
+            assert_ser_tokens(&Owned(UtcOffset::UTC), &[Token::Str("+00:00")]);
+            assert_ser_tokens(&Owned(offset!(+0:00:30)), &[Token::Str("+00:00:30")]);
+            assert_ser_tokens(
+                &OptionOwned(Some(offset!(+5:45))),
+                &[Token::Some, Token::Str("+05:45")],
+            );
+            assert_ser_tokens(&OptionOwned(None), &[Token::None]);
+        }
+
+        #[test]
+        fn deserialize_utc_offset() {
+            assert_de_tokens(&Owned(offset!(+7)), &[Token::Str("+07:00")]);
+            assert_de_tokens(&Owned(offset!(+7)), &[Token::Str("+0700")]);
+            assert_de_tokens(&Owned(offset!(-3:30)), &[Token::Str("-03:30")]);
+            assert_de_tokens(&Owned(offset!(-3:30:15)), &[Token::Str("-03:30:15")]);
+            assert_de_tokens(&Owned(offset!(+7)), &[Token::I64(25200)]);
+            assert_de_tokens(&Owned(offset!(-5)), &[Token::I64(-18000)]);
+            assert_de_tokens(
+                &OptionOwned(Some(offset!(+7))),
+                &[Token::Some, Token::U64(25200)],
+            );
+            assert_de_tokens(&OptionOwned(None), &[Token::None]);
+            for invalid in ["07:00", "+7", "+07:0", "+07-00", "+0700:00", "+26:00"] {
+                assert_de_tokens_error::<Owned>(
+                    &[Token::Str(invalid)],
+                    &format!(
+                        "invalid value: string \"{invalid}\", expected an offset like \"+07:00\", or whole seconds"
+                    ),
+                );
+            }
+            assert_de_tokens_error::<Owned>(
+                &[Token::I64(100_000)],
+                "invalid value: integer `100000`, expected an offset like \"+07:00\", or whole seconds",
+            );
+        }
+    }
+}
@@ -0,0 +1,166 @@
+use std::any::Any;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use caco3::cache::TtlCache;
+
+/// Number of results a `#[cached]` query method keeps unless `capacity` is given.
+pub const DEFAULT_QUERY_CACHE_CAPACITY: usize = 1024;
+
+/// Results of a `#[cached]` query method, keyed by pool and a copy of the query struct.
+pub struct QueryCache<T> {
+    entries: Mutex<TtlCache<CacheKey, T>>,
+}
+
+impl<T: Clone> QueryCache<T> {
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Mutex::new(TtlCache::new(capacity, ttl)),
+        }
+    }
+
+    pub fn get(&self, key: &CacheKey) -> Option<T> {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.get(key).cloned()
+    }
+
+    pub fn insert(&self, key: CacheKey, value: T) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key, value);
+    }
+}
+
+/// Query struct and pool of a cached result, compared by value rather than by hash.
+#[derive(Clone)]
+pub struct CacheKey {
+    scope: usize,
+    query: Arc<dyn DynKey>,
+}
+
+impl CacheKey {
+    pub fn new<K>(scope: usize, query: &K) -> Self
+    where
+        K: Hash + Eq + Clone + Send + Sync + 'static,
+    {
+        Self {
+            scope,
+            query: Arc::new(query.clone()),
+        }
+    }
+}
+
+impl PartialEq for CacheKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.scope == other.scope && self.query.dyn_eq(&*other.query)
+    }
+}
+
+impl Eq for CacheKey {}
+
+impl Hash for CacheKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.scope.hash(state);
+        self.query.dyn_hash(state);
+    }
+}
+
+/// Object safe `Hash + Eq`, a static cache can't name type of the query struct.
+trait DynKey: Send + Sync {
+    fn as_any(&self) -> &dyn Any;
+    fn dyn_eq(&self, other: &dyn DynKey) -> bool;
+    fn dyn_hash(&self, state: &mut dyn Hasher);
+}
+
+impl<K: Hash + Eq + Send + Sync + 'static> DynKey for K {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn dyn_eq(&self, other: &dyn DynKey) -> bool {
+        other.as_any().downcast_ref::<K>() == Some(self)
+    }
+
+    fn dyn_hash(&self, mut state: &mut dyn Hasher) {
+        self.hash(&mut state);
+    }
+}
+
+/// Executor whose results can be shared by a `#[cached]` query method.
+///
+/// Only pools are cached, a connection or a transaction may see data which other connections
+/// don't. Results of different pools are kept apart.
+#[diagnostic::on_unimplemented(
+    message = "`#[cached]` query must run on a pool or `&ReadWritePools`, not `{Self}`"
+)]
+pub trait CacheScope {
+    /// Identity of the pool, results of one pool are never returned for another pool.
+    fn cache_scope(&self) -> usize;
+}
+
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> CacheScope for &sqlx::Pool<DB> {
+    fn cache_scope(&self) -> usize {
+        // options live in the shared state of a pool, so every clone of a pool has the same address
+        std::ptr::from_ref(self.options()) as usize
+    }
+}
+
+#[cfg(feature = "sqlx")]
+impl<DB: sqlx::Database> CacheScope for &crate::sql::ReadWritePools<DB> {
+    fn cache_scope(&self) -> usize {
+        // fetch queries of `&ReadWritePools` run on replica unless they are marked `#[primary]`
+        self.replica().cache_scope()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Clone, Hash, PartialEq, Eq)]
+    struct Find(&'static str, i64);
+
+    /// Every value has the same hash.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    struct Collide(i64);
+
+    impl Hash for Collide {
+        fn hash<H: Hasher>(&self, _state: &mut H) {}
+    }
+
+    #[test]
+    fn test_query_cache() {
+        let cache = QueryCache::new(2, Duration::from_secs(60));
+        let key = CacheKey::new(1, &Find("nui", 1));
+        assert!(key == CacheKey::new(1, &Find("nui", 1)));
+        assert!(key != CacheKey::new(1, &Find("nui", 2)));
+        assert!(key != CacheKey::new(2, &Find("nui", 1)));
+        assert_eq!(cache.get(&key), None);
+        cache.insert(key.clone(), "value");
+        assert_eq!(cache.get(&key), Some("value"));
+        assert_eq!(cache.get(&CacheKey::new(2, &Find("nui", 1))), None);
+
+        cache.insert(CacheKey::new(1, &Find("nui", 2)), "other");
+        cache.insert(CacheKey::new(1, &Find("nui", 3)), "evict");
+        assert_eq!(cache.get(&key), None);
+    }
+
+    #[test]
+    fn test_hash_collision() {
+        let cache = QueryCache::new(10, Duration::from_secs(60));
+        cache.insert(CacheKey::new(0, &Collide(1)), 1);
+        assert_eq!(cache.get(&CacheKey::new(0, &Collide(2))), None);
+        cache.insert(CacheKey::new(0, &Collide(2)), 2);
+        assert_eq!(cache.get(&CacheKey::new(0, &Collide(1))), Some(1));
+        assert_eq!(cache.get(&CacheKey::new(0, &Collide(2))), Some(2));
+    }
+
+    #[test]
+    fn test_zero_ttl() {
+        let cache = QueryCache::new(10, Duration::ZERO);
+        let key = CacheKey::new(0, &Find("nui", 1));
+        cache.insert(key.clone(), 1);
+        assert_eq!(cache.get(&key), None);
+    }
+}
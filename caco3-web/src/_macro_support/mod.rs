@@ -2,6 +2,13 @@
 //!
 //! Macro implementation detail belongs here.
 
+mod cache;
 mod duration;
+mod query;
 
+pub use cache::{CacheKey, CacheScope, QueryCache, DEFAULT_QUERY_CACHE_CAPACITY};
 pub use duration::AutoUnitDuration;
+pub use query::{
+    check_placeholders, check_soft_delete_marker, observe_query, placeholder_count,
+    soft_delete_sql, trace_query, DEFAULT_SLOW_QUERY_THRESHOLD,
+};
@@ -0,0 +1,203 @@
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tracing::Instrument;
+
+use super::AutoUnitDuration;
+use crate::sql::metrics::QueryEvent;
+
+/// Queries taking longer than this are logged at warn level by `#[traced]` query methods.
+pub const DEFAULT_SLOW_QUERY_THRESHOLD: Duration = Duration::from_secs(1);
+
+/// Run `query` in a span tagged with `tag`, and log its elapsed time.
+pub async fn trace_query<F: Future>(tag: &'static str, slow: Duration, query: F) -> F::Output {
+    let span = tracing::debug_span!("sql_query", tag);
+    let start = Instant::now();
+    let output = query.instrument(span.clone()).await;
+    let elapsed = start.elapsed();
+    if elapsed >= slow {
+        tracing::warn!(
+            parent: &span,
+            "Slow query {} in {}",
+            tag,
+            AutoUnitDuration::from(elapsed)
+        );
+    } else {
+        tracing::debug!(parent: &span, "{} in {}", tag, AutoUnitDuration::from(elapsed));
+    }
+    output
+}
+
+/// Report elapsed time of `query` to hook of [`crate::sql::metrics`], if set.
+pub async fn observe_query<T, E, F>(tag: &'static str, query: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+{
+    let Some(hook) = crate::sql::metrics::hook() else {
+        return query.await;
+    };
+    let start = Instant::now();
+    let output = query.await;
+    hook(&QueryEvent {
+        tag,
+        elapsed: start.elapsed(),
+        success: output.is_ok(),
+    });
+    output
+}
+
+const fn bytes_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < a.len() {
+        if a[i] != b[i] {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Number of binds expected by placeholders of `sql`.
+///
+/// PostgreSQL placeholders are `$1, $2, ...`, a number may be used more than once.
+/// Other databases use `?`, SQLite also supports numbered `?1` and `$1`.
+/// Quoted strings, quoted identifiers and comments are skipped.
+pub const fn placeholder_count(database: &str, sql: &str) -> usize {
+    let postgres = bytes_eq(database.as_bytes(), b"PostgreSQL");
+    let sql = sql.as_bytes();
+    let len = sql.len();
+    let mut anonymous = 0;
+    let mut numbered = 0;
+    let mut i = 0;
+    while i < len {
+        let c = sql[i];
+        i += 1;
+        match c {
+            b'\'' | b'"' | b'`' => {
+                while i < len && sql[i] != c {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'-' if i < len && sql[i] == b'-' => {
+                while i < len && sql[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if i < len && sql[i] == b'*' => {
+                i += 1;
+                while i + 1 < len && !(sql[i] == b'*' && sql[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 2;
+            }
+            // `?` is an operator of jsonb in PostgreSQL
+            b'$' | b'?' if !(postgres && c == b'?') => {
+                let mut n = 0;
+                let start = i;
+                while i < len && sql[i].is_ascii_digit() {
+                    n = n * 10 + (sql[i] - b'0') as usize;
+                    i += 1;
+                }
+                if i > start {
+                    if n > numbered {
+                        numbered = n;
+                    }
+                } else if c == b'?' {
+                    anonymous += 1;
+                }
+            }
+            _ => {}
+        }
+    }
+    if anonymous > 0 {
+        anonymous
+    } else {
+        numbered
+    }
+}
+
+/// Fail compilation if number of placeholders of `sql` doesn't match `binds`.
+pub const fn check_placeholders(database: &str, sql: &str, binds: usize) {
+    if placeholder_count(database, sql) != binds {
+        panic!("number of SQL placeholders doesn't match number of binds");
+    }
+}
+
+/// Marker in SQL of `#[soft_delete]` queries, replaced by filter of soft deleted rows.
+pub const SOFT_DELETE_MARKER: &str = "{soft_delete}";
+
+const fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    let mut i = 0;
+    while i + needle.len() <= haystack.len() {
+        let mut j = 0;
+        while j < needle.len() && haystack[i + j] == needle[j] {
+            j += 1;
+        }
+        if j == needle.len() {
+            return true;
+        }
+        i += 1;
+    }
+    false
+}
+
+/// Fail compilation if `sql` of a `#[soft_delete]` query has no marker.
+pub const fn check_soft_delete_marker(sql: &str) {
+    if !contains(sql.as_bytes(), SOFT_DELETE_MARKER.as_bytes()) {
+        panic!("SQL of #[soft_delete] query must contain {{soft_delete}}");
+    }
+}
+
+/// Replace markers of `sql` with `<column> IS NULL`.
+pub fn soft_delete_sql(sql: &str, column: &str) -> Result<Box<str>, String> {
+    if !sql.contains(SOFT_DELETE_MARKER) {
+        return Err(format!(
+            "SQL of #[soft_delete] query must contain {SOFT_DELETE_MARKER}"
+        ));
+    }
+    Ok(sql
+        .replace(SOFT_DELETE_MARKER, &format!("{column} IS NULL"))
+        .into_boxed_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholder_count() {
+        let pg = |sql| placeholder_count("PostgreSQL", sql);
+        let sqlite = |sql| placeholder_count("SQLite", sql);
+        assert_eq!(pg("SELECT 1"), 0);
+        assert_eq!(pg("SELECT * FROM t WHERE a = $1 AND b = $2 OR a = $1"), 2);
+        assert_eq!(pg("SELECT data ? 'key', '$3' FROM t WHERE id = $10"), 10);
+        assert_eq!(pg("-- $2\nSELECT $1 /* $3 */"), 1);
+        assert_eq!(sqlite("SELECT * FROM t WHERE a = ? AND b = ?"), 2);
+        assert_eq!(
+            sqlite("SELECT '?', \"?\" FROM t WHERE a = ?1 AND b = ?2"),
+            2
+        );
+        assert_eq!(
+            placeholder_count("MySQL", "SELECT `?` FROM t WHERE a = ?"),
+            1
+        );
+    }
+    #[test]
+    fn test_soft_delete_sql() {
+        assert_eq!(
+            &*soft_delete_sql(
+                "SELECT * FROM t WHERE (a = 1 OR b = 2) AND {soft_delete} ORDER BY id",
+                "deleted_at"
+            )
+            .unwrap(),
+            "SELECT * FROM t WHERE (a = 1 OR b = 2) AND deleted_at IS NULL ORDER BY id"
+        );
+        assert!(soft_delete_sql("SELECT * FROM t ORDER BY id", "deleted_at").is_err());
+        assert!(contains(b"a {soft_delete}", SOFT_DELETE_MARKER.as_bytes()));
+        assert!(!contains(b"{soft_delete", SOFT_DELETE_MARKER.as_bytes()));
+    }
+}
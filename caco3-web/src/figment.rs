@@ -1,7 +1,32 @@
-use figment::providers::Serialized;
-use figment::Figment;
+use std::any::type_name;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::routing::get;
+use axum::Router;
+
+use figment::error::Kind;
+use figment::providers::{Env, Format, Serialized, Toml};
+use figment::value::{Dict, Map, Tag, Value as FigmentValue};
+use figment::{Figment, Metadata, Profile, Provider, Source};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use serde_json::Value;
 use thiserror::Error;
+use tracing::warn;
+
+#[cfg(feature = "remote-http")]
+pub use remote::HttpJsonConfig;
+pub use remote::{PollingGuard, RemoteConfig, RemoteConfigError, RemoteConfigProvider};
+pub use secrets::SecretsDir;
+
+use crate::json::ApiJson;
+
+mod remote;
+mod secrets;
 
 mod private {
     pub trait Sealed {}
@@ -14,22 +39,280 @@ mod private {
 pub enum RemoveExistingKeyError<'a> {
     #[error("key {0} not found")]
     NotFound(&'a str),
+    #[error(transparent)]
+    Figment(Box<figment::Error>),
+}
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RelocateKeyError<'a> {
+    #[error("key {0} not found")]
+    NotFound(&'a str),
+    #[error("key {0} is not a dictionary")]
+    NotDict(&'a str),
+    #[error(transparent)]
+    Figment(Box<figment::Error>),
+}
+
+impl From<figment::Error> for RelocateKeyError<'_> {
+    fn from(e: figment::Error) -> Self {
+        Self::Figment(Box::new(e))
+    }
+}
+
+/// Every error found by [`FigmentExt::extract_validated`].
+///
+/// Display is a report of errors, a line per key with its provider, e.g.
+///
+/// ```text
+/// 2 invalid configuration values
+///   server.port: invalid type: found string "http", expected u16, from TOML file /etc/app.toml
+///   database.url: missing field `url`, from TOML file /etc/app.toml
+/// ```
+#[derive(Debug)]
+pub struct ExtractErrors {
+    errors: Vec<figment::Error>,
+}
+
+impl ExtractErrors {
+    pub fn errors(&self) -> &[figment::Error] {
+        &self.errors
+    }
+}
+
+impl fmt::Display for ExtractErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.len() {
+            1 => write!(f, "1 invalid configuration value")?,
+            n => write!(f, "{n} invalid configuration values")?,
+        }
+        for error in &self.errors {
+            let mut key = error.path.join(".");
+            if let Kind::MissingField(field) = &error.kind {
+                if !key.is_empty() {
+                    key.push('.');
+                }
+                key.push_str(field);
+            }
+            write!(f, "\n  {key}: {}", error.kind)?;
+            if let Some(metadata) = &error.metadata {
+                write!(f, ", from {}", metadata.name)?;
+                if let Some(source) = &metadata.source {
+                    write!(f, " {source}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ExtractErrors {}
+
+/// Stop collecting errors of [`FigmentExt::extract_validated`] after this many errors.
+const MAX_EXTRACT_ERRORS: usize = 32;
+
+/// Replacement of redacted values.
+const REDACTED: &str = "***";
+
+/// Source of values merged by [`FigmentExt::with_defaults_from`].
+const TYPED_DEFAULTS_SOURCE: &str = "Default::default()";
+
+/// Separator of nested keys in names of environment variables.
+const ENV_KEY_SEPARATOR: &str = "__";
+
+/// Provider of environment variables prefixed with `prefix` and `__`, e.g. `APP__DB__POOL_SIZE`
+/// of prefix `APP` is key `db.pool_size`.
+///
+/// Values are parsed like [`Env`] does, and `yes`, `on`, `no` and `off` are also booleans,
+/// case-insensitive. An array is written in brackets, e.g. `APP__HOSTS=[a.local, b.local]`,
+/// an array element can't be set by index, `APP__HOSTS__0` is key `hosts.0` of a dictionary.
+///
+/// ```
+/// use caco3_web::figment::env_provider;
+/// use figment::providers::Serialized;
+/// use figment::Figment;
+///
+/// let figment = Figment::from(Serialized::default("db.pool_size", 10)).merge(env_provider("APP"));
+/// ```
+pub fn env_provider(prefix: &str) -> EnvProvider {
+    let env = Env::prefixed(&format!("{prefix}{ENV_KEY_SEPARATOR}")).split(ENV_KEY_SEPARATOR);
+    EnvProvider { env }
+}
+
+/// Provider returned by [`env_provider`].
+#[derive(Debug, Clone)]
+pub struct EnvProvider {
+    env: Env,
+}
+
+impl EnvProvider {
+    /// Configure underlying [`Env`], e.g. to ignore some keys.
+    pub fn with_env(self, f: impl FnOnce(Env) -> Env) -> Self {
+        Self { env: f(self.env) }
+    }
+}
+
+impl Provider for EnvProvider {
+    fn metadata(&self) -> Metadata {
+        self.env.metadata()
+    }
+
+    fn data(&self) -> figment::Result<Map<Profile, Dict>> {
+        fn coerce(value: &mut FigmentValue) {
+            match value {
+                FigmentValue::String(tag, s) => {
+                    let b = match s.to_ascii_lowercase().as_str() {
+                        "yes" | "on" => true,
+                        "no" | "off" => false,
+                        _ => return,
+                    };
+                    *value = FigmentValue::Bool(*tag, b);
+                }
+                FigmentValue::Dict(_, dict) => dict.values_mut().for_each(coerce),
+                FigmentValue::Array(_, array) => array.iter_mut().for_each(coerce),
+                _ => {}
+            }
+        }
+
+        let mut data = self.env.data()?;
+        for dict in data.values_mut() {
+            dict.values_mut().for_each(coerce);
+        }
+        Ok(data)
+    }
 }
 
 /// Extension trait for `figment::Figment`.
 pub trait FigmentExt: Sized + private::Sealed {
     /// Remove existing keys.
     ///
+    /// A key may contain array indices, e.g. `servers.0.host`. Keys are removed in order,
+    /// removing an array element shifts indices of following elements.
+    /// Remaining values keep metadata of their providers.
+    ///
     /// This method error if key doesn't exist.
     fn remove_existing_keys<'a, T: AsRef<str>>(
         &self,
         keys: &'a [T],
     ) -> Result<Self, RemoveExistingKeyError<'a>>;
 
-    /// Check for key existent.
+    /// Remove keys like [`remove_existing_keys`], missing keys are ignored.
+    ///
+    /// If values of figment can't be read, figment is returned as is,
+    /// error is reported when a value is extracted.
+    ///
+    /// [`remove_existing_keys`]: FigmentExt::remove_existing_keys
+    fn remove_keys_if_present<T: AsRef<str>>(&self, keys: &[T]) -> Self;
+
+    /// Check for key existent, a key may contain array indices.
     ///
     /// blank key return `false`.
     fn has_key(&self, key: &str) -> bool;
+
+    /// Move value of `old` key to `new` key, replacing existing value of `new` key.
+    ///
+    /// Moved values keep metadata of their providers, e.g. an error of a renamed key still
+    /// points to the file it came from. This method error if `old` key doesn't exist.
+    ///
+    /// ```
+    /// use caco3_web::figment::FigmentExt;
+    /// use figment::providers::Serialized;
+    /// use figment::Figment;
+    ///
+    /// // `listen_addr` was renamed to `server.address`
+    /// let figment = Figment::from(Serialized::default("listen_addr", "0.0.0.0:8080"));
+    /// let figment = if figment.has_key("listen_addr") {
+    ///     figment.rename_key("listen_addr", "server.address").unwrap()
+    /// } else {
+    ///     figment
+    /// };
+    /// assert_eq!(figment.extract_inner::<String>("server.address").unwrap(), "0.0.0.0:8080");
+    /// ```
+    fn rename_key<'a>(&self, old: &'a str, new: &str) -> Result<Self, RelocateKeyError<'a>>;
+
+    /// Move keys of `src` dictionary into `dst` dictionary, keys of `src` replace existing keys
+    /// of `dst`.
+    ///
+    /// Moved values keep metadata of their providers.
+    /// This method error if `src` key doesn't exist or is not a dictionary.
+    fn move_subtree<'a>(&self, src: &'a str, dst: &str) -> Result<Self, RelocateKeyError<'a>>;
+
+    /// Extract `T` like [`Figment::extract`], but report every invalid value instead of the first.
+    ///
+    /// After an error, its key is removed and `T` is extracted again to find next error.
+    /// Errors after a missing required key can't be found this way, e.g. an invalid value of
+    /// a required field stops extraction with both errors reported.
+    ///
+    /// ```ignore
+    /// let config: Config = figment.extract_validated().unwrap_or_else(|e| {
+    ///     eprintln!("{e}");
+    ///     std::process::exit(1);
+    /// });
+    /// ```
+    fn extract_validated<T: DeserializeOwned>(&self) -> Result<T, ExtractErrors>;
+
+    /// Merge configuration files of `stage` in current directory, see [`with_stage_in`].
+    ///
+    /// [`with_stage_in`]: FigmentExt::with_stage_in
+    fn with_stage(self, stage: &str) -> Self;
+
+    /// Merge configuration files of `stage` in `dir`, in order of precedence from lowest
+    /// * `config.toml`
+    /// * `config.{stage}.toml`
+    /// * `config.local.toml`, which is not committed to repository.
+    ///
+    /// Missing files are skipped. With `strict`, `config.toml` and `config.{stage}.toml` must exist,
+    /// otherwise extracting a value fails.
+    ///
+    /// ```ignore
+    /// let stage = std::env::var("APP_STAGE").unwrap_or_else(|_| "development".to_owned());
+    /// let figment = Figment::new()
+    ///     .with_stage_in("config", &stage, stage == "production")
+    ///     .merge(env_provider("APP"));
+    /// ```
+    fn with_stage_in(self, dir: impl AsRef<Path>, stage: &str, strict: bool) -> Self;
+
+    /// Merged configuration, with values of `redact_keys` replaced with `***`.
+    ///
+    /// A redacted key of a dictionary hides the whole dictionary, missing keys are ignored.
+    #[allow(clippy::result_large_err)]
+    fn to_redacted_value<T: AsRef<str>>(&self, redact_keys: &[T]) -> figment::Result<Value>;
+
+    /// Log a warning for each present deprecated key of `(old, new)` pairs, with its provider
+    /// and source, e.g. file path.
+    ///
+    /// With `rename`, values of deprecated keys are moved to new keys like [`rename_key`],
+    /// replacing values of new keys, otherwise figment is returned as is.
+    ///
+    /// ```ignore
+    /// let figment = figment.warn_deprecated(&[("listen_addr", "server.address")], true);
+    /// ```
+    ///
+    /// [`rename_key`]: FigmentExt::rename_key
+    fn warn_deprecated(&self, keys: &[(&str, &str)], rename: bool) -> Self;
+
+    /// Join `T::default()` as values of lowest precedence, so every field of `T` has a value.
+    ///
+    /// ```
+    /// use caco3_web::figment::FigmentExt;
+    /// use figment::providers::Serialized;
+    /// use figment::Figment;
+    ///
+    /// #[derive(Default, serde::Serialize)]
+    /// struct Config {
+    ///     port: u16,
+    ///     workers: usize,
+    /// }
+    ///
+    /// let figment = Figment::from(Serialized::default("port", 8080)).with_defaults_from::<Config>();
+    /// assert_eq!(figment.defaulted_keys(), ["workers"]);
+    /// ```
+    fn with_defaults_from<T: Default + Serialize>(self) -> Self;
+
+    /// Keys of values from [`with_defaults_from`], i.e. keys which are not configured.
+    ///
+    /// [`with_defaults_from`]: FigmentExt::with_defaults_from
+    fn defaulted_keys(&self) -> Vec<String>;
 }
 
 impl FigmentExt for Figment {
@@ -37,47 +320,379 @@ impl FigmentExt for Figment {
         &self,
         keys: &'a [T],
     ) -> Result<Self, RemoveExistingKeyError<'a>> {
-        let mut value = self.extract::<Value>().expect("json serializable value");
-        let mut pointer = String::new();
-        let mut parts = vec![];
+        let root = self
+            .find_value("")
+            .map_err(|e| RemoveExistingKeyError::Figment(Box::new(e)))?;
         for key in keys {
             let key = key.as_ref();
-            if !self.has_key(key) {
+            if key.is_empty() || find_in(&root, key).is_none() {
                 return Err(RemoveExistingKeyError::NotFound(key));
             }
-            pointer.clear();
-            parts.clear();
-            parts.extend(key.split('.'));
-            // note: .expect("object") should never fail because we already check key existent
-            match parts.as_slice() {
-                [] => {
-                    // we already check key existent
-                    unreachable!("empty parts");
+        }
+        remove_keys(self, keys).map_err(RemoveExistingKeyError::Figment)
+    }
+
+    fn remove_keys_if_present<T: AsRef<str>>(&self, keys: &[T]) -> Self {
+        remove_keys(self, keys).unwrap_or_else(|_| self.clone())
+    }
+
+    fn has_key(&self, key: &str) -> bool {
+        !key.is_empty()
+            && self
+                .find_value("")
+                .is_ok_and(|root| find_in(&root, key).is_some())
+    }
+
+    fn rename_key<'a>(&self, old: &'a str, new: &str) -> Result<Self, RelocateKeyError<'a>> {
+        if !self.has_key(old) {
+            return Err(RelocateKeyError::NotFound(old));
+        }
+        let mut data = self.data()?;
+        for dict in data.values_mut() {
+            if let Some(value) = take_value(dict, old) {
+                *entry_mut(dict, new) = value;
+            }
+        }
+        rebuild(self, data).map_err(RelocateKeyError::Figment)
+    }
+
+    fn move_subtree<'a>(&self, src: &'a str, dst: &str) -> Result<Self, RelocateKeyError<'a>> {
+        if !self.has_key(src) {
+            return Err(RelocateKeyError::NotFound(src));
+        }
+        let mut data = self.data()?;
+        for dict in data.values_mut() {
+            match take_value(dict, src) {
+                Some(FigmentValue::Dict(_, children)) => {
+                    let target = entry_mut(dict, dst);
+                    if !matches!(target, FigmentValue::Dict(..)) {
+                        *target = Dict::new().into();
+                    }
+                    if let FigmentValue::Dict(_, target) = target {
+                        target.extend(children);
+                    }
                 }
-                [field] => {
-                    value.as_object_mut().expect("object").remove(*field);
+                Some(_) => return Err(RelocateKeyError::NotDict(src)),
+                None => {}
+            }
+        }
+        rebuild(self, data).map_err(RelocateKeyError::Figment)
+    }
+
+    fn extract_validated<T: DeserializeOwned>(&self) -> Result<T, ExtractErrors> {
+        let mut errors = Vec::new();
+        let mut removed = Vec::new();
+        let mut figment = self.clone();
+        loop {
+            let error = match figment.extract::<T>() {
+                Ok(value) if errors.is_empty() => return Ok(value),
+                Ok(_) => break,
+                Err(error) => error,
+            };
+            let mut next = None;
+            for error in error {
+                let key = error.path.join(".");
+                if let Kind::MissingField(field) = &error.kind {
+                    // a removed key is reported already
+                    let field_key = if key.is_empty() {
+                        field.to_string()
+                    } else {
+                        format!("{key}.{field}")
+                    };
+                    if removed.contains(&field_key) {
+                        continue;
+                    }
+                } else if next.is_none() && !key.is_empty() && !removed.contains(&key) {
+                    next = Some(key);
+                }
+                errors.push(error);
+            }
+            let Some(key) = next.filter(|_| errors.len() < MAX_EXTRACT_ERRORS) else {
+                break;
+            };
+            match remove_key(&figment, &key) {
+                Some(f) => figment = f,
+                None => break,
+            }
+            removed.push(key);
+        }
+        Err(ExtractErrors { errors })
+    }
+
+    fn with_stage(self, stage: &str) -> Self {
+        self.with_stage_in(".", stage, false)
+    }
+
+    fn with_stage_in(self, dir: impl AsRef<Path>, stage: &str, strict: bool) -> Self {
+        let dir = dir.as_ref();
+        let files = [
+            (dir.join("config.toml"), strict),
+            (dir.join(format!("config.{stage}.toml")), strict),
+            (dir.join("config.local.toml"), false),
+        ];
+        files.into_iter().fold(self, |figment, (path, required)| {
+            if required || path.is_file() {
+                figment.merge(Toml::file_exact(path))
+            } else {
+                figment
+            }
+        })
+    }
+
+    fn to_redacted_value<T: AsRef<str>>(&self, redact_keys: &[T]) -> figment::Result<Value> {
+        let mut value = self.extract::<Value>()?;
+        for key in redact_keys {
+            let key = key.as_ref();
+            if key.is_empty() {
+                continue;
+            }
+            let pointer = format!("/{}", key.replace('.', "/"));
+            if let Some(v) = value.pointer_mut(&pointer) {
+                *v = Value::from(REDACTED);
+            }
+        }
+        Ok(value)
+    }
+
+    fn warn_deprecated(&self, keys: &[(&str, &str)], rename: bool) -> Self {
+        let mut figment = self.clone();
+        for &(old, new) in keys {
+            let Some(metadata) = figment.find_metadata(old) else {
+                continue;
+            };
+            let source = metadata.source.as_ref().map(ToString::to_string);
+            warn!(
+                key = old,
+                replacement = new,
+                provider = %metadata.name,
+                source = source.as_deref().unwrap_or("-"),
+                "Configuration key {old} is deprecated, use {new} instead"
+            );
+            if rename {
+                if let Ok(f) = figment.rename_key(old, new) {
+                    figment = f;
                 }
-                [components @ .., field] => {
-                    for c in components {
-                        pointer.push('/');
-                        pointer.push_str(c);
+            }
+        }
+        figment
+    }
+
+    fn with_defaults_from<T: Default + Serialize>(self) -> Self {
+        self.join(TypedDefaults(T::default()))
+    }
+
+    fn defaulted_keys(&self) -> Vec<String> {
+        fn collect(
+            figment: &Figment,
+            path: &mut Vec<String>,
+            value: &FigmentValue,
+            keys: &mut Vec<String>,
+        ) {
+            match value {
+                FigmentValue::Dict(_, dict) if !dict.is_empty() => {
+                    for (key, value) in dict {
+                        path.push(key.clone());
+                        collect(figment, path, value, keys);
+                        path.pop();
+                    }
+                }
+                value => {
+                    let defaulted = figment.get_metadata(value.tag()).is_some_and(|metadata| {
+                        matches!(&metadata.source, Some(Source::Custom(s)) if s == TYPED_DEFAULTS_SOURCE)
+                    });
+                    if defaulted {
+                        keys.push(path.join("."));
                     }
-                    value
-                        .pointer_mut(&pointer)
-                        .and_then(Value::as_object_mut)
-                        .expect("object")
-                        .remove(*field);
                 }
             }
         }
-        Ok(Figment::from(Serialized::defaults(value)))
+
+        let mut keys = Vec::new();
+        if let Ok(root) = self.find_value("") {
+            collect(self, &mut vec![], &root, &mut keys);
+        }
+        keys
     }
+}
 
-    fn has_key(&self, key: &str) -> bool {
-        self.find_metadata(key).is_some() && !key.is_empty()
+/// Provider of `T::default()`, see [`FigmentExt::with_defaults_from`].
+struct TypedDefaults<T>(T);
+
+impl<T: Serialize> Provider for TypedDefaults<T> {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(format!("{} defaults", type_name::<T>()))
+            .source(Source::Custom(TYPED_DEFAULTS_SOURCE.to_owned()))
+    }
+
+    fn data(&self) -> figment::Result<Map<Profile, Dict>> {
+        Serialized::defaults(&self.0).data()
+    }
+}
+
+async fn debug_config(State(config): State<Arc<Value>>) -> ApiJson<Value> {
+    ApiJson::ok(Value::clone(&config))
+}
+
+/// Router serving `/debug/config`, `config` in [`ApiJson`] format.
+///
+/// `config` is usually effective configuration from [`FigmentExt::to_redacted_value`].
+/// Route is not protected, apply authentication middleware to the returned router, e.g.
+///
+/// ```ignore
+/// let config = figment.to_redacted_value(&["database.url", "jwt.secret"])?;
+/// let debug = figment::router(config).route_layer(AdminAuthLayer::from_type_map(&map));
+/// ```
+pub fn router<S>(config: Value) -> Router<S> {
+    Router::new()
+        .route("/debug/config", get(debug_config))
+        .with_state(Arc::new(config))
+}
+
+/// Figment without value of `key`, `None` if `key` isn't found.
+fn remove_key(figment: &Figment, key: &str) -> Option<Figment> {
+    let mut data = figment.data().ok()?;
+    let mut found = false;
+    for dict in data.values_mut() {
+        found |= take_value(dict, key).is_some();
+    }
+    found.then(|| rebuild(figment, data).ok()).flatten()
+}
+
+fn remove_keys<T: AsRef<str>>(
+    figment: &Figment,
+    keys: &[T],
+) -> Result<Figment, Box<figment::Error>> {
+    let mut data = figment.data().map_err(Box::new)?;
+    for key in keys {
+        for dict in data.values_mut() {
+            take_value(dict, key.as_ref());
+        }
+    }
+    rebuild(figment, data)
+}
+
+/// Value at dotted `key` of `value`, a part of key is an index of array.
+fn find_in<'a>(value: &'a FigmentValue, key: &str) -> Option<&'a FigmentValue> {
+    key.split('.').try_fold(value, |value, part| match value {
+        FigmentValue::Dict(_, dict) => dict.get(part),
+        FigmentValue::Array(_, array) => array.get(part.parse::<usize>().ok()?),
+        _ => None,
+    })
+}
+
+/// Remove value at dotted `key` of `dict`, a part of key is an index of array.
+fn take_value(dict: &mut Dict, key: &str) -> Option<FigmentValue> {
+    fn take(value: &mut FigmentValue, key: &str) -> Option<FigmentValue> {
+        let (head, rest) = match key.split_once('.') {
+            Some((head, rest)) => (head, Some(rest)),
+            None => (key, None),
+        };
+        match (value, rest) {
+            (FigmentValue::Dict(_, dict), None) => dict.remove(head),
+            (FigmentValue::Dict(_, dict), Some(rest)) => take(dict.get_mut(head)?, rest),
+            (FigmentValue::Array(_, array), rest) => {
+                let index = head.parse::<usize>().ok().filter(|&i| i < array.len())?;
+                match rest {
+                    None => Some(array.remove(index)),
+                    Some(rest) => take(&mut array[index], rest),
+                }
+            }
+            _ => None,
+        }
+    }
+
+    match key.split_once('.') {
+        None => dict.remove(key),
+        Some((head, rest)) => take(dict.get_mut(head)?, rest),
+    }
+}
+
+/// Value at dotted `key` of `dict`, missing or non-dictionary parents are replaced with
+/// empty dictionaries.
+fn entry_mut<'a>(dict: &'a mut Dict, key: &str) -> &'a mut FigmentValue {
+    let (head, rest) = match key.split_once('.') {
+        Some((head, rest)) => (head, Some(rest)),
+        None => (key, None),
+    };
+    let value = dict
+        .entry(head.to_owned())
+        .or_insert_with(|| Dict::new().into());
+    let Some(rest) = rest else {
+        return value;
+    };
+    if !matches!(value, FigmentValue::Dict(..)) {
+        *value = Dict::new().into();
+    }
+    match value {
+        FigmentValue::Dict(_, dict) => entry_mut(dict, rest),
+        _ => unreachable!("dictionary"),
+    }
+}
+
+/// Provider of values which came from a provider with `metadata`.
+struct Relocated {
+    metadata: Metadata,
+    data: Map<Profile, Dict>,
+}
+
+impl Provider for Relocated {
+    fn metadata(&self) -> Metadata {
+        self.metadata.clone()
+    }
+
+    fn data(&self) -> figment::Result<Map<Profile, Dict>> {
+        Ok(self.data.clone())
     }
 }
 
+/// Figment of `data`, values are merged from a provider per metadata of `figment`.
+fn rebuild(figment: &Figment, data: Map<Profile, Dict>) -> Result<Figment, Box<figment::Error>> {
+    fn collect(
+        groups: &mut BTreeMap<Tag, Map<Profile, Dict>>,
+        profile: &Profile,
+        path: &mut Vec<String>,
+        value: FigmentValue,
+    ) -> Result<(), Box<figment::Error>> {
+        match value {
+            FigmentValue::Dict(_, dict) if !dict.is_empty() => {
+                for (key, value) in dict {
+                    path.push(key);
+                    collect(groups, profile, path, value)?;
+                    path.pop();
+                }
+            }
+            value => {
+                let tag = value.tag();
+                // values of default tag are tagged by provider which merges them
+                let value = FigmentValue::serialize(value).map_err(Box::new)?;
+                let dict = groups
+                    .entry(tag)
+                    .or_default()
+                    .entry(profile.clone())
+                    .or_default();
+                *entry_mut(dict, &path.join(".")) = value;
+            }
+        }
+        Ok(())
+    }
+
+    let mut groups = BTreeMap::new();
+    for (profile, dict) in data {
+        for (key, value) in dict {
+            collect(&mut groups, &profile, &mut vec![key], value)?;
+        }
+    }
+    let mut rebuilt = Figment::new();
+    for (tag, data) in groups {
+        let metadata = figment
+            .get_metadata(tag)
+            .cloned()
+            .unwrap_or_else(|| Metadata::named("relocated"));
+        rebuilt = rebuilt.merge(Relocated { metadata, data });
+    }
+    Ok(rebuilt.select(figment.profile().clone()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -126,9 +741,268 @@ mod tests {
         assert!(matches!(err, RemoveExistingKeyError::NotFound(_)));
     }
 
+    #[test]
+    fn remove_array_element() {
+        let figment = get_test_figment().merge(Serialized::default(
+            "servers",
+            serde_json::json!([{ "host": "a", "port": 80 }, { "host": "b" }]),
+        ));
+        assert!(figment.has_key("servers.0.host"));
+        assert!(figment.has_key("vec.2"));
+        assert!(!figment.has_key("vec.3"));
+        let keys = ["servers.0.port", "vec.0", "vec.0"];
+        let f = figment.remove_existing_keys(&keys).expect("keys removed");
+        assert!(!f.has_key("servers.0.port"));
+        assert_eq!(f.extract_inner::<Vec<String>>("vec").unwrap(), ["baz"]);
+        let servers = f.extract_inner::<Value>("servers").unwrap();
+        assert_eq!(
+            servers,
+            serde_json::json!([{ "host": "a" }, { "host": "b" }])
+        );
+        // provenance is kept
+        let name = |f: &Figment, key| f.find_metadata(key).unwrap().name.clone();
+        assert_eq!(name(&f, "servers"), name(&figment, "servers"));
+
+        let keys = ["vec.3"];
+        let err = figment
+            .remove_existing_keys(&keys)
+            .expect_err("out of bound");
+        assert!(matches!(err, RemoveExistingKeyError::NotFound("vec.3")));
+    }
+
+    #[test]
+    fn remove_keys_if_present() {
+        let figment = get_test_figment();
+        let f = figment.remove_keys_if_present(&["foo.s", "foo.not_exist", "vec.10", "vec.x"]);
+        assert!(!f.has_key("foo.s"));
+        assert!(f.has_key("foo.bar.baz.name"));
+        assert_eq!(f.extract_inner::<Vec<String>>("vec").unwrap().len(), 3);
+    }
+
     #[test]
     fn has_key() {
         let figment = get_test_figment();
         assert!(!figment.has_key(""));
     }
+
+    #[test]
+    fn rename_key() {
+        let figment = get_test_figment().merge(Serialized::default("old.name", "Old"));
+        let f = figment
+            .rename_key("old.name", "foo.s")
+            .expect("key renamed");
+        assert!(!f.has_key("old.name"));
+        assert_eq!(f.extract_inner::<String>("foo.s").unwrap(), "Old");
+        assert_eq!(
+            f.extract_inner::<String>("foo.bar.baz.name").unwrap(),
+            "Baz"
+        );
+        // provenance is kept
+        let name = |f: &Figment, key| f.find_metadata(key).unwrap().name.clone();
+        assert_eq!(name(&f, "foo.s"), name(&figment, "old.name"));
+        assert_eq!(name(&f, "vec"), name(&figment, "vec"));
+
+        let err = figment.rename_key("foo.not_exist", "foo.s");
+        assert!(matches!(err, Err(RelocateKeyError::NotFound(_))));
+    }
+
+    #[test]
+    fn extract_validated() {
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Config {
+            port: Option<u16>,
+            host: String,
+            foo: Foo,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        #[allow(dead_code)]
+        struct Foo {
+            s: Option<u32>,
+            bar: Option<u64>,
+            vec: Vec<String>,
+        }
+
+        let figment = get_test_figment()
+            .merge(Serialized::default("port", "http"))
+            .merge(Serialized::default("foo.vec", ["a"]));
+        let err = figment.extract_validated::<Config>().expect_err("invalid");
+        let keys: Vec<_> = err.errors().iter().map(|e| e.path.join(".")).collect();
+        assert_eq!(keys, ["foo.bar", "foo.s", "port", ""]);
+        let report = err.to_string();
+        assert!(
+            report.starts_with("4 invalid configuration values\n"),
+            "{report}"
+        );
+        assert!(report.contains("\n  port: invalid type: "), "{report}");
+        assert!(
+            report.contains("\n  host: missing field `host`"),
+            "{report}"
+        );
+
+        // missing required key stops extraction
+        let figment = get_test_figment();
+        let err = figment.extract_validated::<Config>().expect_err("invalid");
+        let report = err.to_string();
+        assert!(
+            report.starts_with("3 invalid configuration values\n"),
+            "{report}"
+        );
+        assert!(
+            report.contains("\n  foo.vec: missing field `vec`, from "),
+            "{report}"
+        );
+
+        let figment = Figment::from(Serialized::defaults(serde_json::json!({
+            "host": "localhost",
+            "foo": { "vec": [] },
+        })));
+        let config = figment.extract_validated::<Config>().expect("valid");
+        assert_eq!(config.host, "localhost");
+    }
+
+    #[test]
+    fn env_provider() {
+        #[derive(Debug, serde::Deserialize)]
+        struct Config {
+            db: Db,
+            hosts: Vec<String>,
+        }
+
+        #[derive(Debug, serde::Deserialize)]
+        struct Db {
+            pool_size: u32,
+            verbose: bool,
+            log: bool,
+        }
+
+        std::env::set_var("CACO3_TEST_ENV__DB__POOL_SIZE", "42");
+        std::env::set_var("CACO3_TEST_ENV__DB__VERBOSE", "Yes");
+        std::env::set_var("CACO3_TEST_ENV__DB__LOG", "off");
+        std::env::set_var("CACO3_TEST_ENV__HOSTS", "[a.local, b.local]");
+        let figment = Figment::new().merge(super::env_provider("CACO3_TEST_ENV"));
+        let config = figment.extract::<Config>().unwrap();
+        assert_eq!(config.db.pool_size, 42);
+        assert!(config.db.verbose);
+        assert!(!config.db.log);
+        assert_eq!(config.hosts, ["a.local", "b.local"]);
+    }
+
+    #[test]
+    fn with_stage() {
+        let dir = std::env::temp_dir().join(format!("caco3-with-stage-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("config.toml"), "name = 'base'\nport = 80").unwrap();
+        std::fs::write(dir.join("config.production.toml"), "name = 'production'").unwrap();
+        std::fs::write(dir.join("config.local.toml"), "port = 8080").unwrap();
+
+        let figment = Figment::new().with_stage_in(&dir, "production", true);
+        assert_eq!(
+            figment.extract_inner::<String>("name").unwrap(),
+            "production"
+        );
+        assert_eq!(figment.extract_inner::<u16>("port").unwrap(), 8080);
+
+        let figment = Figment::new().with_stage_in(&dir, "staging", false);
+        assert_eq!(figment.extract_inner::<String>("name").unwrap(), "base");
+
+        let figment = Figment::new().with_stage_in(&dir, "staging", true);
+        let err = figment.extract_inner::<String>("name").unwrap_err();
+        assert!(err.to_string().contains("config.staging.toml"), "{err}");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn warn_deprecated() {
+        let figment = get_test_figment().merge(Serialized::default("name", "Old"));
+        let keys = [("name", "foo.s"), ("not_exist", "foo.bar")];
+        let f = figment.warn_deprecated(&keys, false);
+        assert!(f.has_key("name"));
+        assert_eq!(f.extract_inner::<String>("foo.s").unwrap(), "Foo string");
+        let f = figment.warn_deprecated(&keys, true);
+        assert!(!f.has_key("name"));
+        assert_eq!(f.extract_inner::<String>("foo.s").unwrap(), "Old");
+        assert!(f.has_key("foo.bar.baz.name"));
+    }
+
+    #[test]
+    fn with_defaults_from() {
+        #[derive(Debug, Default, Serialize, serde::Deserialize)]
+        struct Config {
+            foo: Foo,
+            port: u16,
+        }
+
+        #[derive(Debug, Default, Serialize, serde::Deserialize)]
+        struct Foo {
+            s: String,
+            size: usize,
+        }
+
+        let figment = get_test_figment().with_defaults_from::<Config>();
+        assert_eq!(figment.defaulted_keys(), ["foo.size", "port"]);
+        let config = figment.extract::<Config>().unwrap();
+        assert_eq!(config.foo.s, "Foo string");
+        assert_eq!(config.port, 0);
+        assert!(get_test_figment().defaulted_keys().is_empty());
+    }
+
+    #[test]
+    fn to_redacted_value() {
+        let figment = get_test_figment();
+        let value = figment
+            .to_redacted_value(&["foo.bar", "vec.1", "foo.not_exist", ""])
+            .unwrap();
+        let expected = serde_json::json!({
+            "foo": {
+                "bar": "***",
+                "s": "Foo string",
+            },
+            "vec": ["foo", "***", "baz"],
+        });
+        assert_eq!(value, expected);
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        use axum::body::Body;
+        use axum::http::{Request, StatusCode};
+        use tower::ServiceExt;
+
+        let config = get_test_figment().to_redacted_value(&["foo.s"]).unwrap();
+        let req = Request::get("/debug/config").body(Body::empty()).unwrap();
+        let res = router(config).oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["data"]["foo"]["s"], "***");
+        assert_eq!(json["data"]["foo"]["bar"]["baz"]["name"], "Baz");
+    }
+
+    #[test]
+    fn move_subtree() {
+        let figment = get_test_figment();
+        let f = figment
+            .move_subtree("foo.bar", "foo")
+            .expect("subtree moved");
+        assert!(!f.has_key("foo.bar"));
+        assert_eq!(f.extract_inner::<String>("foo.baz.name").unwrap(), "Baz");
+        assert_eq!(f.extract_inner::<String>("foo.s").unwrap(), "Foo string");
+
+        let f = figment
+            .move_subtree("foo", "new.foo")
+            .expect("subtree moved");
+        assert!(!f.has_key("foo"));
+        assert_eq!(
+            f.extract_inner::<String>("new.foo.s").unwrap(),
+            "Foo string"
+        );
+
+        let err = figment.move_subtree("foo.s", "bar");
+        assert!(matches!(err, Err(RelocateKeyError::NotDict(_))));
+    }
 }
@@ -0,0 +1,432 @@
+//! Centrally managed configuration, fetched from a remote key/value store.
+//!
+//! ```ignore
+//! let remote = RemoteConfig::new(HttpJsonConfig::new("https://config.internal/v1/app").pointer("/data"));
+//! remote.refresh().await?;
+//! let _polling = remote.spawn_polling(Duration::from_secs(30));
+//!
+//! let figment = Figment::new().with_stage(&stage).merge(remote.clone());
+//! // rebuild configuration after remote values are changed
+//! let mut changes = remote.subscribe();
+//! while changes.changed().await.is_ok() {
+//!     let figment = Figment::new().with_stage(&stage).merge(remote.clone());
+//! }
+//! ```
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use figment::value::{Dict, Map, Value as FigmentValue};
+use figment::{Error, Metadata, Profile, Provider};
+#[cfg(feature = "remote-http")]
+use http::header::{HeaderMap, HeaderName, HeaderValue, ACCEPT};
+#[cfg(feature = "remote-http")]
+use http::StatusCode;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::warn;
+
+#[cfg(feature = "remote-http")]
+const DEFAULT_HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+#[cfg(feature = "remote-http")]
+const DEFAULT_MAX_BODY_SIZE: usize = 1024 * 1024;
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum RemoteConfigError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("request timed out")]
+    Timeout,
+    #[error("unexpected status {0}")]
+    Status(u16),
+    #[error("invalid response: {0}")]
+    InvalidResponse(String),
+    #[error("response body is larger than {0} bytes")]
+    BodyTooLarge(usize),
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    #[error("configuration is not an object")]
+    NotObject,
+    #[error(transparent)]
+    Other(Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// A remote key/value store.
+pub trait RemoteConfigProvider: Send + Sync + 'static {
+    /// Name of store shown in metadata of its values.
+    fn name(&self) -> String;
+
+    /// Fetch configuration subtree, a JSON object.
+    fn fetch(&self) -> impl Future<Output = Result<Value, RemoteConfigError>> + Send;
+}
+
+/// Latest configuration fetched from `P`, provides nothing until it is fetched.
+///
+/// Clones share the same configuration.
+pub struct RemoteConfig<P> {
+    source: Arc<P>,
+    snapshot: Arc<watch::Sender<Option<Dict>>>,
+}
+
+impl<P> Clone for RemoteConfig<P> {
+    fn clone(&self) -> Self {
+        Self {
+            source: Arc::clone(&self.source),
+            snapshot: Arc::clone(&self.snapshot),
+        }
+    }
+}
+
+impl<P: RemoteConfigProvider> RemoteConfig<P> {
+    pub fn new(source: P) -> Self {
+        Self {
+            source: Arc::new(source),
+            snapshot: Arc::new(watch::Sender::new(None)),
+        }
+    }
+
+    /// Fetch configuration, returns `true` if it is changed.
+    pub async fn refresh(&self) -> Result<bool, RemoteConfigError> {
+        let value = self.source.fetch().await?;
+        if !value.is_object() {
+            return Err(RemoteConfigError::NotObject);
+        }
+        let dict = FigmentValue::serialize(value)
+            .ok()
+            .and_then(FigmentValue::into_dict)
+            .ok_or(RemoteConfigError::NotObject)?;
+        Ok(self.snapshot.send_if_modified(|snapshot| {
+            let modified = snapshot.as_ref() != Some(&dict);
+            *snapshot = Some(dict);
+            modified
+        }))
+    }
+
+    /// Refresh configuration every `interval` until the returned guard is dropped, errors are logged.
+    pub fn spawn_polling(&self, interval: Duration) -> PollingGuard {
+        let this = self.clone();
+        let handle = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                if let Err(e) = this.refresh().await {
+                    warn!(
+                        "Failed to refresh configuration from {}: {e}",
+                        this.source.name()
+                    );
+                }
+            }
+        });
+        PollingGuard { handle }
+    }
+
+    /// Receiver notified when configuration is changed.
+    pub fn subscribe(&self) -> watch::Receiver<Option<Dict>> {
+        self.snapshot.subscribe()
+    }
+}
+
+/// Polling task of [`RemoteConfig::spawn_polling`], aborted when dropped.
+#[derive(Debug)]
+#[must_use = "polling stops when guard is dropped"]
+pub struct PollingGuard {
+    handle: JoinHandle<()>,
+}
+
+impl Drop for PollingGuard {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+impl<P: RemoteConfigProvider> Provider for RemoteConfig<P> {
+    fn metadata(&self) -> Metadata {
+        Metadata::named(self.source.name())
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+        let snapshot = self.snapshot.borrow();
+        Ok(snapshot
+            .as_ref()
+            .map(|dict| Profile::Default.collect(dict.clone()))
+            .unwrap_or_default())
+    }
+}
+
+/// JSON document served by an HTTP endpoint, e.g. a key/value store with HTTP API.
+///
+/// Both `http` and `https` are supported, certificates are verified against Mozilla's root
+/// certificates.
+#[cfg(feature = "remote-http")]
+#[derive(Debug, Clone)]
+pub struct HttpJsonConfig {
+    client: reqwest::Client,
+    url: String,
+    pointer: Option<String>,
+    headers: HeaderMap,
+    timeout: Duration,
+    max_body_size: usize,
+}
+
+#[cfg(feature = "remote-http")]
+impl HttpJsonConfig {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url: url.into(),
+            pointer: None,
+            headers: HeaderMap::new(),
+            timeout: DEFAULT_HTTP_TIMEOUT,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+        }
+    }
+
+    /// Use subtree at JSON pointer, e.g. `/data/data` of a Vault KV v2 secret.
+    pub fn pointer(mut self, pointer: impl Into<String>) -> Self {
+        self.pointer = Some(pointer.into());
+        self
+    }
+
+    /// Add a request header, e.g. a token.
+    pub fn header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// Limit of the whole request, from connecting until the body is read, 10 seconds by default.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Reject responses with a larger body, 1 MiB by default.
+    pub fn max_body_size(mut self, max_body_size: usize) -> Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Use `client` instead of a default one, e.g. to trust a private certificate authority.
+    pub fn client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    async fn get(&self) -> Result<Vec<u8>, RemoteConfigError> {
+        let mut response = self
+            .client
+            .get(&self.url)
+            .headers(self.headers.clone())
+            .header(ACCEPT, "application/json")
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(RemoteConfigError::from_reqwest)?;
+        let status = response.status();
+        if status != StatusCode::OK {
+            return Err(RemoteConfigError::Status(status.as_u16()));
+        }
+        let max_body_size = self.max_body_size;
+        let too_large = || RemoteConfigError::BodyTooLarge(max_body_size);
+        if response
+            .content_length()
+            .is_some_and(|len| len > max_body_size as u64)
+        {
+            return Err(too_large());
+        }
+        let mut body = Vec::new();
+        while let Some(chunk) = response
+            .chunk()
+            .await
+            .map_err(RemoteConfigError::from_reqwest)?
+        {
+            if body.len() + chunk.len() > max_body_size {
+                return Err(too_large());
+            }
+            body.extend_from_slice(&chunk);
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(feature = "remote-http")]
+impl RemoteConfigError {
+    fn from_reqwest(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            Self::Timeout
+        } else {
+            Self::Other(Box::new(e))
+        }
+    }
+}
+
+#[cfg(feature = "remote-http")]
+impl RemoteConfigProvider for HttpJsonConfig {
+    fn name(&self) -> String {
+        format!("HTTP JSON {}", self.url)
+    }
+
+    async fn fetch(&self) -> Result<Value, RemoteConfigError> {
+        let body = self.get().await?;
+        let mut value: Value = serde_json::from_slice(&body)?;
+        if let Some(pointer) = &self.pointer {
+            value = value.pointer_mut(pointer).map(Value::take).ok_or_else(|| {
+                RemoteConfigError::InvalidResponse(format!("{pointer} not found"))
+            })?;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use super::*;
+
+    struct Counter(AtomicU32);
+
+    impl RemoteConfigProvider for Counter {
+        fn name(&self) -> String {
+            "counter".to_owned()
+        }
+
+        async fn fetch(&self) -> Result<Value, RemoteConfigError> {
+            let count = self.0.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(serde_json::json!({ "count": count }))
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_polling_stops_when_dropped() {
+        let remote = RemoteConfig::new(Counter(AtomicU32::new(0)));
+        let polling = remote.spawn_polling(Duration::from_secs(1));
+        tokio::time::sleep(Duration::from_millis(2500)).await;
+        assert_eq!(remote.source.0.load(Ordering::SeqCst), 2);
+
+        drop(polling);
+        tokio::time::sleep(Duration::from_secs(5)).await;
+        assert_eq!(remote.source.0.load(Ordering::SeqCst), 2);
+        assert_eq!(Arc::strong_count(&remote.source), 1);
+    }
+
+    #[cfg(feature = "remote-http")]
+    mod http_json {
+        use std::sync::Mutex;
+
+        use axum::extract::State;
+        use axum::routing::get;
+        use axum::{Json, Router};
+        use figment::Figment;
+
+        use super::super::*;
+
+        async fn serve(addr: &str, value: Arc<Mutex<Value>>) -> Option<String> {
+            async fn config(State(value): State<Arc<Mutex<Value>>>) -> Json<Value> {
+                Json(value.lock().unwrap().clone())
+            }
+
+            async fn token(headers: HeaderMap) -> Json<Value> {
+                let token = headers["x-token"].to_str().unwrap();
+                Json(serde_json::json!({ "token": token }))
+            }
+
+            let app = Router::new()
+                .route("/config", get(config))
+                .route("/token", get(token))
+                .with_state(value);
+            let listener = tokio::net::TcpListener::bind(addr).await.ok()?;
+            let addr = listener.local_addr().unwrap();
+            tokio::spawn(async move { axum::serve(listener, app).await });
+            Some(format!("http://{addr}/config"))
+        }
+
+        #[tokio::test]
+        async fn test_http_json_config() {
+            let value = Arc::new(Mutex::new(
+                serde_json::json!({ "data": { "db": { "pool_size": 5 } } }),
+            ));
+            let url = serve("127.0.0.1:0", Arc::clone(&value)).await.unwrap();
+            let remote = RemoteConfig::new(HttpJsonConfig::new(&url).pointer("/data"));
+            let mut changes = remote.subscribe();
+            assert!(Figment::from(remote.clone()).find_value("db").is_err());
+
+            assert!(remote.refresh().await.unwrap());
+            assert!(changes.has_changed().unwrap());
+            let figment = Figment::from(remote.clone());
+            assert_eq!(figment.extract_inner::<u32>("db.pool_size").unwrap(), 5);
+            let metadata = figment.find_metadata("db.pool_size").unwrap();
+            assert_eq!(metadata.name, format!("HTTP JSON {url}"));
+
+            changes.mark_unchanged();
+            assert!(!remote.refresh().await.unwrap());
+            *value.lock().unwrap() = serde_json::json!({ "data": { "db": { "pool_size": 10 } } });
+            assert!(remote.refresh().await.unwrap());
+            let figment = Figment::from(remote.clone());
+            assert_eq!(figment.extract_inner::<u32>("db.pool_size").unwrap(), 10);
+
+            *value.lock().unwrap() = serde_json::json!({ "data": [] });
+            assert!(matches!(
+                remote.refresh().await,
+                Err(RemoteConfigError::NotObject)
+            ));
+            let remote = RemoteConfig::new(HttpJsonConfig::new(url.replace("/config", "/missing")));
+            assert!(matches!(
+                remote.refresh().await,
+                Err(RemoteConfigError::Status(404))
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_http_json_config_limits() {
+            let value = Arc::new(Mutex::new(serde_json::json!({ "data": "x".repeat(100) })));
+            let url = serve("127.0.0.1:0", value).await.unwrap();
+
+            let config = HttpJsonConfig::new(url.replace("/config", "/token")).header(
+                HeaderName::from_static("x-token"),
+                HeaderValue::from_static("s3cret"),
+            );
+            assert_eq!(
+                config.fetch().await.unwrap(),
+                serde_json::json!({ "token": "s3cret" })
+            );
+            assert!(HeaderValue::from_str("s3cret\r\nx-injected: 1").is_err());
+
+            let config = HttpJsonConfig::new(&url).max_body_size(100);
+            assert!(matches!(
+                config.fetch().await,
+                Err(RemoteConfigError::BodyTooLarge(100))
+            ));
+            assert!(HttpJsonConfig::new(&url).fetch().await.is_ok());
+
+            // accept connections but never respond
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let _silent = tokio::spawn(async move {
+                let mut connections = vec![];
+                while let Ok((stream, _)) = listener.accept().await {
+                    connections.push(stream);
+                }
+            });
+            let config =
+                HttpJsonConfig::new(format!("http://{addr}/")).timeout(Duration::from_millis(100));
+            assert!(matches!(
+                config.fetch().await,
+                Err(RemoteConfigError::Timeout)
+            ));
+        }
+
+        #[tokio::test]
+        async fn test_http_json_config_ipv6() {
+            let value = Arc::new(Mutex::new(serde_json::json!({ "db": { "pool_size": 5 } })));
+            // skip if IPv6 is not available
+            let Some(url) = serve("[::1]:0", value).await else {
+                return;
+            };
+            assert!(url.starts_with("http://[::1]:"));
+            let remote = RemoteConfig::new(HttpJsonConfig::new(url));
+            assert!(remote.refresh().await.unwrap());
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use figment::value::{Dict, Map, Value};
+use figment::{Error, Metadata, Profile, Provider};
+
+/// Provider of secrets mounted as files of a directory, e.g. Kubernetes or Docker secrets.
+///
+/// A file is a key, `_` in file name separates nested keys, e.g. `db_password` is key
+/// `db.password`. Values are strings with surrounding whitespace trimmed.
+/// Hidden files, e.g. `..data` of Kubernetes, and directories are skipped.
+/// A missing directory provides nothing.
+///
+/// ```ignore
+/// let figment = Figment::new()
+///     .with_stage(&stage)
+///     .merge(SecretsDir::new("/run/secrets").split("__"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SecretsDir {
+    dir: PathBuf,
+    separator: String,
+    base64: bool,
+}
+
+impl SecretsDir {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            separator: "_".to_owned(),
+            base64: false,
+        }
+    }
+
+    /// Separate nested keys by `separator` instead of `_`.
+    pub fn split(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// Decode content of files as base64, decoded content must be UTF-8.
+    pub fn base64(mut self, base64: bool) -> Self {
+        self.base64 = base64;
+        self
+    }
+
+    fn read(&self, path: &Path) -> Result<String, String> {
+        let content = fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let content = content.trim();
+        if !self.base64 {
+            return Ok(content.to_owned());
+        }
+        let bytes = STANDARD
+            .decode(content)
+            .map_err(|e| format!("{}: invalid base64, {e}", path.display()))?;
+        String::from_utf8(bytes).map_err(|e| format!("{}: invalid UTF-8, {e}", path.display()))
+    }
+}
+
+impl Provider for SecretsDir {
+    fn metadata(&self) -> Metadata {
+        Metadata::from("secrets directory", self.dir.as_path())
+    }
+
+    fn data(&self) -> Result<Map<Profile, Dict>, Error> {
+        let mut dict = Dict::new();
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Map::new()),
+            Err(e) => return Err(format!("{}: {e}", self.dir.display()).into()),
+        };
+        for entry in entries {
+            let path = entry.map_err(|e| Error::from(e.to_string()))?.path();
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            if name.starts_with('.') || !path.is_file() {
+                continue;
+            }
+            let value = Value::from(self.read(&path)?);
+            let keys: Vec<_> = name.split(self.separator.as_str()).collect();
+            insert(&mut dict, &keys, value);
+        }
+        Ok(Profile::Default.collect(dict))
+    }
+}
+
+fn insert(dict: &mut Dict, keys: &[&str], value: Value) {
+    match keys {
+        [] => {}
+        [key] => {
+            dict.insert((*key).to_owned(), value);
+        }
+        [key, rest @ ..] => {
+            let entry = dict
+                .entry((*key).to_owned())
+                .or_insert_with(|| Dict::new().into());
+            if !matches!(entry, Value::Dict(..)) {
+                *entry = Dict::new().into();
+            }
+            if let Value::Dict(_, dict) = entry {
+                insert(dict, rest, value);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use figment::Figment;
+
+    use super::*;
+
+    #[test]
+    fn test_secrets_dir() {
+        let dir = std::env::temp_dir().join(format!("caco3-secrets-{}", std::process::id()));
+        fs::create_dir_all(dir.join("..data")).unwrap();
+        fs::write(dir.join("db_password"), "s3cret\n").unwrap();
+        fs::write(dir.join("api_key"), "a2V5").unwrap();
+        fs::write(dir.join(".hidden"), "hidden").unwrap();
+
+        let figment = Figment::from(SecretsDir::new(&dir));
+        assert_eq!(
+            figment.extract_inner::<String>("db.password").unwrap(),
+            "s3cret"
+        );
+        assert_eq!(figment.extract_inner::<String>("api.key").unwrap(), "a2V5");
+        assert!(figment.find_value("hidden").is_err());
+
+        fs::remove_file(dir.join("db_password")).unwrap();
+        let figment = Figment::from(SecretsDir::new(&dir).base64(true));
+        assert_eq!(figment.extract_inner::<String>("api.key").unwrap(), "key");
+        let figment = Figment::from(SecretsDir::new(&dir).split("__"));
+        assert_eq!(figment.extract_inner::<String>("api_key").unwrap(), "a2V5");
+
+        let figment = Figment::from(SecretsDir::new(dir.join("missing")));
+        assert!(figment.find_value("api").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
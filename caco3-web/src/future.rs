@@ -1,5 +1,37 @@
+use std::borrow::Cow;
 use std::future::Future;
+use std::pin::{pin, Pin};
+use std::task::{ready, Context, Poll};
 use std::thread::panicking;
+use std::time::Duration;
+
+use futures_util::future::{select, Either};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use pin_project::pin_project;
+use thiserror::Error;
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+pub use batched::{batched, Batched};
+pub use blocking::{blocking_guard, BlockingGuard};
+pub use bounded::{bounded_for_each, ErrorPolicy};
+pub use circuit_breaker::{CircuitBreaker, CircuitOpen, CircuitState};
+pub use interval::{interval_task, IntervalTask};
+pub use lazy::AsyncLazy;
+pub use promise::{Promise, PromiseError, Resolver};
+pub use task_set::TaskSet;
+
+pub mod context;
+
+mod batched;
+mod blocking;
+mod bounded;
+mod circuit_breaker;
+mod interval;
+mod lazy;
+mod promise;
+mod task_set;
 
 impl<Fut> OnUncompletedDrop for Fut where Fut: Future + Send {}
 
@@ -81,3 +113,186 @@ where
         }
     }
 }
+
+impl<Fut> OnCancel for Fut where Fut: Future + Send {}
+
+pub trait OnCancel: Future + Send + Sized {
+    /// Stop future and call given closure `f` if `token` is cancelled before future completes.
+    ///
+    /// Returns `None` if future is stopped.
+    ///
+    /// ```ignore
+    /// let handle = tokio::spawn(consume(queue).on_cancel(shutdown.clone(), || info!("Consumer stopped")));
+    /// shutdown.cancel();
+    /// ```
+    fn on_cancel<F>(
+        self,
+        token: CancellationToken,
+        f: F,
+    ) -> impl Future<Output = Option<Self::Output>> + Send
+    where
+        F: FnOnce() + Send,
+    {
+        async move {
+            let cancelled = pin!(token.cancelled());
+            match select(pin!(self), cancelled).await {
+                Either::Left((output, _)) => Some(output),
+                Either::Right(((), _)) => {
+                    f();
+                    None
+                }
+            }
+        }
+    }
+}
+
+/// Error of a future that didn't complete in time, see [`TimeoutExt::timeout_ctx`].
+#[derive(Debug, Clone, Error)]
+#[error("{label} timed out after {elapsed:?}")]
+pub struct TimedOut {
+    pub label: Cow<'static, str>,
+    pub elapsed: Duration,
+}
+
+impl<Fut> TimeoutExt for Fut where Fut: Future {}
+
+pub trait TimeoutExt: Future + Sized {
+    /// Require future to complete within `duration`, otherwise log a warning and return
+    /// [`TimedOut`] error with `label`.
+    ///
+    /// ```ignore
+    /// let user = fetch_user(id).timeout_ctx(Duration::from_secs(3), "fetch user").await??;
+    /// ```
+    fn timeout_ctx(
+        self,
+        duration: Duration,
+        label: impl Into<Cow<'static, str>>,
+    ) -> TimeoutCtx<Self> {
+        TimeoutCtx {
+            inner: tokio::time::timeout(duration, self),
+            label: Some(label.into()),
+            start: Instant::now(),
+        }
+    }
+}
+
+/// Future returned by [`TimeoutExt::timeout_ctx`].
+#[pin_project]
+pub struct TimeoutCtx<Fut> {
+    #[pin]
+    inner: tokio::time::Timeout<Fut>,
+    label: Option<Cow<'static, str>>,
+    start: Instant,
+}
+
+impl<Fut: Future> Future for TimeoutCtx<Fut> {
+    type Output = Result<Fut::Output, TimedOut>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match ready!(this.inner.poll(cx)) {
+            Ok(output) => Poll::Ready(Ok(output)),
+            Err(_) => {
+                let label = this
+                    .label
+                    .take()
+                    .expect("TimeoutCtx polled after completion");
+                let elapsed = this.start.elapsed();
+                warn!(label = %label, ?elapsed, "{label} timed out after {elapsed:?}");
+                Poll::Ready(Err(TimedOut { label, elapsed }))
+            }
+        }
+    }
+}
+
+/// Run `futures` concurrently, return output of the first one that succeeds.
+///
+/// Remaining futures are dropped before returning, so their uncompleted-drop hooks, e.g.
+/// [`OnUncompletedDrop::on_uncompleted_drop`], are called. If every future fails, errors are
+/// returned in order of completion.
+///
+/// ```ignore
+/// let response = race_ok([fetch(&primary), fetch(&replica)]).await?;
+/// ```
+pub async fn race_ok<I, T, E>(futures: I) -> Result<T, Vec<E>>
+where
+    I: IntoIterator,
+    I::Item: Future<Output = Result<T, E>>,
+{
+    let mut futures: FuturesUnordered<_> = futures.into_iter().collect();
+    let mut errors = Vec::new();
+    while let Some(result) = futures.next().await {
+        match result {
+            Ok(output) => {
+                drop(futures);
+                return Ok(output);
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    Err(errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_on_cancel() {
+        let token = CancellationToken::new();
+        let cleaned = AtomicBool::new(false);
+        let output = async { 42 }
+            .on_cancel(token.clone(), || cleaned.store(true, Ordering::Relaxed))
+            .await;
+        assert_eq!(output, Some(42));
+        assert!(!cleaned.load(Ordering::Relaxed));
+
+        let stopped = std::future::pending::<()>()
+            .on_cancel(token.clone(), || cleaned.store(true, Ordering::Relaxed));
+        token.cancel();
+        assert_eq!(stopped.await, None);
+        assert!(cleaned.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout_ctx() {
+        let output = async { 42 }
+            .timeout_ctx(Duration::from_secs(1), "answer")
+            .await;
+        assert_eq!(output.unwrap(), 42);
+
+        let err = tokio::time::sleep(Duration::from_secs(2))
+            .timeout_ctx(Duration::from_secs(1), format!("sleep {}", 2))
+            .await
+            .unwrap_err();
+        assert_eq!(err.label, "sleep 2");
+        assert_eq!(err.elapsed, Duration::from_secs(1));
+        assert_eq!(err.to_string(), "sleep 2 timed out after 1s");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_race_ok() {
+        let cancelled = AtomicUsize::new(0);
+        let upstream = |delay: u64, result: Result<u64, u64>| {
+            let cancelled = &cancelled;
+            async move {
+                tokio::time::sleep(Duration::from_secs(delay)).await;
+                result
+            }
+            .on_uncompleted_drop(false, move || {
+                cancelled.fetch_add(1, Ordering::SeqCst);
+            })
+        };
+
+        let output = race_ok([upstream(1, Err(1)), upstream(2, Ok(2)), upstream(3, Ok(3))]).await;
+        assert_eq!(output, Ok(2));
+        assert_eq!(cancelled.load(Ordering::SeqCst), 1);
+
+        let output = race_ok([upstream(2, Err(2)), upstream(1, Err(1))]).await;
+        assert_eq!(output, Err(vec![1, 2]));
+        let output = race_ok(Vec::<std::future::Ready<Result<(), ()>>>::new()).await;
+        assert_eq!(output, Err(vec![]));
+    }
+}
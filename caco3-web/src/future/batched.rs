@@ -0,0 +1,117 @@
+use std::future::Future;
+use std::mem;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::Stream;
+use pin_project::pin_project;
+use tokio::time::{Instant, Sleep};
+
+/// Group items of `stream` into batches, a batch is yielded when it has `max_items` items or
+/// `max_wait` has elapsed since its first item arrived.
+///
+/// Remaining items are yielded when `stream` ends.
+///
+/// ```ignore
+/// let mut batches = pin!(batched(events, 500, Duration::from_millis(200)));
+/// while let Some(batch) = batches.next().await {
+///     insert_events(&pool, &batch).await?;
+/// }
+/// ```
+///
+/// panic if `max_items` is zero.
+pub fn batched<S: Stream>(stream: S, max_items: usize, max_wait: Duration) -> Batched<S> {
+    assert!(max_items > 0, "max_items must be positive");
+    Batched {
+        stream,
+        items: Vec::new(),
+        max_items,
+        max_wait,
+        sleep: tokio::time::sleep(max_wait),
+        done: false,
+    }
+}
+
+/// Stream returned by [`batched`].
+#[pin_project]
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Batched<S: Stream> {
+    #[pin]
+    stream: S,
+    items: Vec<S::Item>,
+    max_items: usize,
+    max_wait: Duration,
+    #[pin]
+    sleep: Sleep,
+    done: bool,
+}
+
+impl<S: Stream> Stream for Batched<S> {
+    type Item = Vec<S::Item>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut this = self.project();
+        if *this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            match this.stream.as_mut().poll_next(cx) {
+                Poll::Ready(Some(item)) => {
+                    if this.items.is_empty() {
+                        this.sleep.as_mut().reset(Instant::now() + *this.max_wait);
+                    }
+                    this.items.push(item);
+                    if this.items.len() >= *this.max_items {
+                        return Poll::Ready(Some(mem::take(this.items)));
+                    }
+                }
+                Poll::Ready(None) => {
+                    *this.done = true;
+                    return Poll::Ready((!this.items.is_empty()).then(|| mem::take(this.items)));
+                }
+                Poll::Pending => break,
+            }
+        }
+        if !this.items.is_empty() && this.sleep.poll(cx).is_ready() {
+            return Poll::Ready(Some(mem::take(this.items)));
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::{stream, StreamExt};
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_batched() {
+        let batches: Vec<_> = batched(stream::iter(1..=5), 2, Duration::from_secs(1))
+            .collect()
+            .await;
+        assert_eq!(batches, [vec![1, 2], vec![3, 4], vec![5]]);
+
+        // items arrive at 0.0s, 0.4s, 0.8s, 1.2s, ...
+        let items = stream::iter(1..=6).then(|n| async move {
+            if n > 1 {
+                tokio::time::sleep(Duration::from_millis(400)).await;
+            }
+            n
+        });
+        let start = Instant::now();
+        let batches: Vec<_> = batched(items, 10, Duration::from_secs(1))
+            .map(|batch| (batch, start.elapsed()))
+            .collect()
+            .await;
+        assert_eq!(
+            batches,
+            [
+                (vec![1, 2, 3], Duration::from_secs(1)),
+                (vec![4, 5, 6], Duration::from_millis(2000)),
+            ]
+        );
+    }
+}
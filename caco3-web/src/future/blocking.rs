@@ -0,0 +1,119 @@
+use std::borrow::Cow;
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant};
+
+use tracing::warn;
+
+use crate::_macro_support::AutoUnitDuration;
+
+const DEFAULT_THRESHOLD: Duration = Duration::from_millis(10);
+
+/// Mark a blocking section, see [`BlockingGuard`].
+///
+/// ```ignore
+/// async fn handler() -> ApiResult<Json<Report>> {
+///     let report = {
+///         let _guard = blocking_guard("render report");
+///         render_report()
+///     };
+///     Ok(Json(report))
+/// }
+/// ```
+pub fn blocking_guard(label: impl Into<Cow<'static, str>>) -> BlockingGuard {
+    BlockingGuard {
+        label: label.into(),
+        threshold: DEFAULT_THRESHOLD,
+        start: Instant::now(),
+        thread: thread::current().id(),
+        on_runtime: tokio::runtime::Handle::try_current().is_ok(),
+    }
+}
+
+/// Guard of a blocking section, it logs a warning when dropped if
+/// - it was held across an `.await` which resumed on another thread, or
+/// - it was created on a thread of Tokio runtime and held longer than threshold, 10 ms by default.
+///
+/// An `.await` resumed on the same thread can't be detected, the threshold covers it if the
+/// section is slow.
+#[derive(Debug)]
+#[must_use = "section ends when guard is dropped"]
+pub struct BlockingGuard {
+    label: Cow<'static, str>,
+    threshold: Duration,
+    start: Instant,
+    thread: ThreadId,
+    on_runtime: bool,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+enum Violation {
+    HeldAcrossAwait,
+    TooLong,
+}
+
+impl BlockingGuard {
+    pub fn threshold(mut self, threshold: Duration) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    fn violation(&self, elapsed: Duration) -> Option<Violation> {
+        if thread::current().id() != self.thread {
+            Some(Violation::HeldAcrossAwait)
+        } else if self.on_runtime && elapsed > self.threshold {
+            Some(Violation::TooLong)
+        } else {
+            None
+        }
+    }
+}
+
+impl Drop for BlockingGuard {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        let label = &self.label;
+        match self.violation(elapsed) {
+            Some(Violation::HeldAcrossAwait) => warn!(
+                "Blocking section {label} was held across an .await, took {}",
+                AutoUnitDuration::from(elapsed)
+            ),
+            Some(Violation::TooLong) => warn!(
+                "Blocking section {label} blocked runtime thread for {}",
+                AutoUnitDuration::from(elapsed)
+            ),
+            None => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocking_guard() {
+        let guard = blocking_guard("outside runtime");
+        assert_eq!(guard.violation(Duration::from_secs(1)), None);
+
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(2)
+            .build()
+            .unwrap();
+        runtime.block_on(async {
+            let guard = blocking_guard("quick");
+            assert_eq!(guard.violation(Duration::from_millis(1)), None);
+            let guard = guard.threshold(Duration::from_millis(100));
+            assert_eq!(guard.violation(Duration::from_millis(50)), None);
+            assert_eq!(
+                guard.violation(Duration::from_millis(150)),
+                Some(Violation::TooLong)
+            );
+
+            let guard = blocking_guard(format!("section {}", 1));
+            let violation = thread::spawn(move || guard.violation(Duration::ZERO))
+                .join()
+                .unwrap();
+            assert_eq!(violation, Some(Violation::HeldAcrossAwait));
+        });
+    }
+}
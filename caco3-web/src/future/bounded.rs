@@ -0,0 +1,109 @@
+use std::future::Future;
+use std::sync::Mutex;
+
+use futures_core::Stream;
+use futures_util::{StreamExt, TryStreamExt};
+
+/// What [`bounded_for_each`] does after an error.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum ErrorPolicy {
+    /// Stop at first error, running instances are dropped.
+    #[default]
+    FailFast,
+    /// Process every item and return all errors.
+    Collect,
+}
+
+/// Call `f` for each item of `stream`, with at most `limit` instances of `f` running concurrently.
+///
+/// Returns errors of `f`, a single error with [`ErrorPolicy::FailFast`].
+///
+/// ```ignore
+/// let ids = futures_util::stream::iter(account_ids);
+/// bounded_for_each(ids, 8, ErrorPolicy::Collect, |id| sync_account(&pool, id)).await?;
+/// ```
+///
+/// panic if `limit` is zero.
+pub async fn bounded_for_each<S, F, Fut, E>(
+    stream: S,
+    limit: usize,
+    policy: ErrorPolicy,
+    f: F,
+) -> Result<(), Vec<E>>
+where
+    S: Stream,
+    F: Fn(S::Item) -> Fut,
+    Fut: Future<Output = Result<(), E>>,
+{
+    assert!(limit > 0, "limit must be positive");
+    match policy {
+        ErrorPolicy::FailFast => stream
+            .map(Ok)
+            .try_for_each_concurrent(limit, f)
+            .await
+            .map_err(|e| vec![e]),
+        ErrorPolicy::Collect => {
+            let errors = Mutex::new(Vec::new());
+            stream
+                .for_each_concurrent(limit, |item| async {
+                    if let Err(e) = f(item).await {
+                        errors.lock().unwrap_or_else(|e| e.into_inner()).push(e);
+                    }
+                })
+                .await;
+            let errors = errors.into_inner().unwrap_or_else(|e| e.into_inner());
+            if errors.is_empty() {
+                Ok(())
+            } else {
+                Err(errors)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    use futures_util::stream;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_bounded_for_each() {
+        let running = AtomicUsize::new(0);
+        let max_running = AtomicUsize::new(0);
+        let processed = AtomicUsize::new(0);
+        let f = |n: u32| {
+            let (running, max_running, processed) = (&running, &max_running, &processed);
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                max_running.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(10)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                processed.fetch_add(1, Ordering::SeqCst);
+                if n % 3 == 0 {
+                    Err(n)
+                } else {
+                    Ok(())
+                }
+            }
+        };
+
+        let result = bounded_for_each(stream::iter(1..=10), 3, ErrorPolicy::Collect, f).await;
+        let mut errors = result.unwrap_err();
+        errors.sort();
+        assert_eq!(errors, [3, 6, 9]);
+        assert_eq!(max_running.load(Ordering::SeqCst), 3);
+        assert_eq!(processed.load(Ordering::SeqCst), 10);
+
+        processed.store(0, Ordering::SeqCst);
+        let result = bounded_for_each(stream::iter(1..=10), 2, ErrorPolicy::FailFast, f).await;
+        assert_eq!(result.unwrap_err(), [3]);
+        assert!(processed.load(Ordering::SeqCst) < 10);
+
+        let result = bounded_for_each(stream::iter([1, 2]), 2, ErrorPolicy::default(), f).await;
+        assert!(result.is_ok());
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::time::Instant;
+use tracing::{info, warn};
+
+/// Error of a call rejected by an open [`CircuitBreaker`].
+#[derive(Debug, Clone, Copy, Error)]
+#[error("circuit breaker is open")]
+pub struct CircuitOpen;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Calls are allowed, outcomes are tracked.
+    Closed,
+    /// Calls are rejected until open duration elapsed.
+    Open,
+    /// A limited number of trial calls are allowed to decide if dependency has recovered.
+    HalfOpen,
+}
+
+/// Stop calling a failing dependency for a while, so it can recover and callers fail fast.
+///
+/// Outcomes of the latest `window` calls are tracked. When at least `minimum_calls` are tracked
+/// and their failure rate reaches `failure_rate`, the circuit opens and calls are rejected with
+/// [`CircuitOpen`]. After `open_duration`, up to `half_open_calls` trial calls are allowed;
+/// if all of them succeed the circuit closes, otherwise it opens again.
+///
+/// ```ignore
+/// static BREAKER: LazyLock<CircuitBreaker> = LazyLock::new(CircuitBreaker::new);
+///
+/// let rates = BREAKER.call(fetch_rates(&client)).await??;
+/// ```
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_rate: f64,
+    minimum_calls: usize,
+    window: usize,
+    open_duration: Duration,
+    half_open_calls: usize,
+    inner: Mutex<Inner>,
+}
+
+#[derive(Debug)]
+struct Inner {
+    state: State,
+    /// `true` is a failure.
+    outcomes: VecDeque<bool>,
+}
+
+#[derive(Debug)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { started: usize, succeeded: usize },
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self {
+            failure_rate: 0.5,
+            minimum_calls: 10,
+            window: 100,
+            open_duration: Duration::from_secs(30),
+            half_open_calls: 1,
+            inner: Mutex::new(Inner {
+                state: State::Closed,
+                outcomes: VecDeque::new(),
+            }),
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Failure rate in `0.0..=1.0` opening the circuit, default is `0.5`.
+    pub fn failure_rate(mut self, rate: f64) -> Self {
+        self.failure_rate = rate;
+        self
+    }
+
+    /// Minimum number of tracked calls before failure rate is checked, default is 10.
+    pub fn minimum_calls(mut self, calls: usize) -> Self {
+        self.minimum_calls = calls;
+        self
+    }
+
+    /// Number of latest calls tracked, default is 100.
+    pub fn window(mut self, calls: usize) -> Self {
+        self.window = calls.max(1);
+        self
+    }
+
+    /// Duration calls are rejected after the circuit opens, default is 30 seconds.
+    pub fn open_duration(mut self, duration: Duration) -> Self {
+        self.open_duration = duration;
+        self
+    }
+
+    /// Number of trial calls in half-open state, default is 1.
+    pub fn half_open_calls(mut self, calls: usize) -> Self {
+        self.half_open_calls = calls.max(1);
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        let mut inner = self.lock();
+        self.poll_open(&mut inner);
+        match inner.state {
+            State::Closed => CircuitState::Closed,
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Await `future` if the circuit allows, `Err` output of `future` is a failure.
+    ///
+    /// A call dropped before completion is not tracked.
+    pub async fn call<Fut, T, E>(&self, future: Fut) -> Result<Result<T, E>, CircuitOpen>
+    where
+        Fut: Future<Output = Result<T, E>>,
+    {
+        self.acquire()?;
+        let mut permit = Permit {
+            breaker: self,
+            done: false,
+        };
+        let output = future.await;
+        permit.done = true;
+        self.record(output.is_err());
+        Ok(output)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Move to half-open state if open duration has elapsed.
+    fn poll_open(&self, inner: &mut Inner) {
+        if let State::Open { until } = inner.state {
+            if Instant::now() >= until {
+                inner.state = State::HalfOpen {
+                    started: 0,
+                    succeeded: 0,
+                };
+            }
+        }
+    }
+
+    fn acquire(&self) -> Result<(), CircuitOpen> {
+        let mut inner = self.lock();
+        self.poll_open(&mut inner);
+        match &mut inner.state {
+            State::Closed => Ok(()),
+            State::Open { .. } => Err(CircuitOpen),
+            State::HalfOpen { started, .. } if *started < self.half_open_calls => {
+                *started += 1;
+                Ok(())
+            }
+            State::HalfOpen { .. } => Err(CircuitOpen),
+        }
+    }
+
+    fn record(&self, failed: bool) {
+        let mut inner = self.lock();
+        match &mut inner.state {
+            State::Closed => {
+                if inner.outcomes.len() == self.window {
+                    inner.outcomes.pop_front();
+                }
+                inner.outcomes.push_back(failed);
+                let calls = inner.outcomes.len();
+                let failures = inner.outcomes.iter().filter(|failed| **failed).count();
+                if calls >= self.minimum_calls
+                    && failures as f64 / calls as f64 >= self.failure_rate
+                {
+                    warn!(
+                        "Circuit opened for {:?}, {failures} of {calls} calls failed",
+                        self.open_duration
+                    );
+                    self.open(&mut inner);
+                }
+            }
+            // outcome of a call started before the circuit opened
+            State::Open { .. } => {}
+            State::HalfOpen { .. } if failed => {
+                warn!("Circuit opened again for {:?}", self.open_duration);
+                self.open(&mut inner);
+            }
+            State::HalfOpen { succeeded, .. } => {
+                *succeeded += 1;
+                if *succeeded >= self.half_open_calls {
+                    info!("Circuit closed");
+                    inner.state = State::Closed;
+                }
+            }
+        }
+    }
+
+    fn open(&self, inner: &mut Inner) {
+        inner.state = State::Open {
+            until: Instant::now() + self.open_duration,
+        };
+        inner.outcomes.clear();
+    }
+
+    /// Allow another trial call in place of a dropped one.
+    fn release(&self) {
+        if let State::HalfOpen { started, .. } = &mut self.lock().state {
+            *started = started.saturating_sub(1);
+        }
+    }
+}
+
+struct Permit<'a> {
+    breaker: &'a CircuitBreaker,
+    done: bool,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.breaker.release();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::FutureExt;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::new()
+            .failure_rate(0.5)
+            .minimum_calls(4)
+            .window(4)
+            .open_duration(Duration::from_secs(10));
+        for result in [Ok(1), Err(2), Ok(3)] {
+            assert_eq!(breaker.call(async { result }).await.unwrap(), result);
+        }
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        assert_eq!(
+            breaker.call(async { Err::<(), _>(4) }).await.unwrap(),
+            Err(4)
+        );
+        assert_eq!(breaker.state(), CircuitState::Open);
+        assert!(breaker.call(async { Ok::<_, ()>(5) }).await.is_err());
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        assert!(breaker.call(async { Err::<(), _>(6) }).await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        tokio::time::sleep(Duration::from_secs(10)).await;
+        // a dropped trial call doesn't take the only trial slot
+        let trial = breaker.call(std::future::pending::<Result<(), ()>>());
+        assert!(trial.now_or_never().is_none());
+        assert!(breaker.call(async { Ok::<_, ()>(7) }).await.is_ok());
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+}
@@ -0,0 +1,94 @@
+//! Request scoped context of a task.
+//!
+//! ```ignore
+//! async fn handler(State(pool): State<PgPool>) -> ApiResult<Json<User>> {
+//!     let deadline = Instant::now() + Duration::from_secs(5);
+//!     with_deadline(deadline, async {
+//!         // in data access layer
+//!         let timeout = context::timeout_within(Duration::from_secs(3));
+//!         tokio::time::timeout(timeout, fetch_user(&pool)).await
+//!     })
+//!     .await
+//! }
+//! ```
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+tokio::task_local! {
+    static DEADLINE: Instant;
+}
+
+/// Run `future` with request `deadline`, readable by [`deadline`] and [`remaining_budget`]
+/// while `future` is polled.
+///
+/// A nested deadline can't extend an outer one, the earlier deadline is used.
+pub async fn with_deadline<Fut: Future>(deadline: Instant, future: Fut) -> Fut::Output {
+    let deadline = match self::deadline() {
+        Some(outer) => outer.min(deadline),
+        None => deadline,
+    };
+    DEADLINE.scope(deadline, future).await
+}
+
+/// Deadline of current request, `None` outside of [`with_deadline`].
+pub fn deadline() -> Option<Instant> {
+    DEADLINE.try_with(|deadline| *deadline).ok()
+}
+
+/// Time left until deadline of current request, zero if it has passed.
+///
+/// Returns `None` outside of [`with_deadline`].
+pub fn remaining_budget() -> Option<Duration> {
+    deadline().map(|deadline| deadline.saturating_duration_since(Instant::now()))
+}
+
+/// `timeout` shrunk to remaining budget of current request.
+pub fn timeout_within(timeout: Duration) -> Duration {
+    match remaining_budget() {
+        Some(budget) => budget.min(timeout),
+        None => timeout,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_with_deadline() {
+        assert_eq!(remaining_budget(), None);
+        assert_eq!(
+            timeout_within(Duration::from_secs(3)),
+            Duration::from_secs(3)
+        );
+
+        let request_deadline = Instant::now() + Duration::from_secs(5);
+        with_deadline(request_deadline, async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            assert_eq!(remaining_budget(), Some(Duration::from_secs(4)));
+            assert_eq!(
+                timeout_within(Duration::from_secs(3)),
+                Duration::from_secs(3)
+            );
+            assert_eq!(
+                timeout_within(Duration::from_secs(10)),
+                Duration::from_secs(4)
+            );
+
+            let later = Instant::now() + Duration::from_secs(60);
+            with_deadline(later, async {
+                assert_eq!(deadline(), Some(request_deadline))
+            })
+            .await;
+            let earlier = Instant::now() + Duration::from_secs(1);
+            with_deadline(earlier, async { assert_eq!(deadline(), Some(earlier)) }).await;
+
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            assert_eq!(remaining_budget(), Some(Duration::ZERO));
+        })
+        .await;
+        assert_eq!(deadline(), None);
+    }
+}
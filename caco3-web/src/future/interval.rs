@@ -0,0 +1,135 @@
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+
+use crate::_macro_support::AutoUnitDuration;
+use crate::future::OnCancel;
+use crate::retry::{Backoff, Jitter};
+
+/// Run `f` every `period`, each run is delayed by a random duration up to `jitter`.
+///
+/// Runs never overlap. If a run takes longer than `period`, missed runs are coalesced into
+/// one run starting right after it, then the schedule continues at multiples of `period`
+/// from the start, so it doesn't drift. Use [`IntervalTask::missed_tick`] to change this behavior.
+///
+/// ```ignore
+/// let shutdown = CancellationToken::new();
+/// interval_task(Duration::from_secs(60), Duration::from_secs(5), move || refresh_rates(pool.clone()))
+///     .name("refresh rates")
+///     .spawn(shutdown.clone());
+/// ```
+pub fn interval_task<F>(period: Duration, jitter: Duration, f: F) -> IntervalTask<F> {
+    IntervalTask {
+        name: Cow::Borrowed("interval task"),
+        period,
+        jitter,
+        missed_tick: MissedTickBehavior::Skip,
+        f,
+    }
+}
+
+/// A periodic task, see [`interval_task`].
+pub struct IntervalTask<F> {
+    name: Cow<'static, str>,
+    period: Duration,
+    jitter: Duration,
+    missed_tick: MissedTickBehavior,
+    f: F,
+}
+
+impl<F, Fut, E> IntervalTask<F>
+where
+    F: FnMut() -> Fut + Send + 'static,
+    Fut: Future<Output = Result<(), E>> + Send,
+    E: Display,
+{
+    /// Name of task in logs.
+    pub fn name(mut self, name: impl Into<Cow<'static, str>>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    pub fn missed_tick(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick = behavior;
+        self
+    }
+
+    /// Run until `shutdown` is cancelled, a running run is completed before stopping.
+    pub async fn run(mut self, shutdown: CancellationToken) {
+        let mut interval = tokio::time::interval(self.period);
+        interval.set_missed_tick_behavior(self.missed_tick);
+        // constant backoff with full jitter, a random delay up to `jitter`
+        let mut jitter = Backoff::new(self.jitter)
+            .multiplier(1.0)
+            .jitter(Jitter::Full);
+        loop {
+            let delay = jitter.next().unwrap_or_default();
+            let tick = async {
+                interval.tick().await;
+                tokio::time::sleep(delay).await;
+            };
+            if tick.on_cancel(shutdown.clone(), || {}).await.is_none() {
+                break;
+            }
+            let start = Instant::now();
+            let name = &self.name;
+            match (self.f)().await {
+                Ok(()) => debug!("{name} finished in {}", AutoUnitDuration::from(start)),
+                Err(e) => warn!("{name} failed in {}: {e}", AutoUnitDuration::from(start)),
+            }
+        }
+        debug!("{} stopped", self.name);
+    }
+
+    /// Spawn task running until `shutdown` is cancelled.
+    pub fn spawn(self, shutdown: CancellationToken) -> JoinHandle<()>
+    where
+        Fut: 'static,
+        E: 'static,
+    {
+        tokio::spawn(self.run(shutdown))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_interval_task() {
+        let runs = Arc::new(AtomicUsize::new(0));
+        let running = Arc::new(AtomicUsize::new(0));
+        let shutdown = CancellationToken::new();
+        let (counter, running_) = (Arc::clone(&runs), Arc::clone(&running));
+        let handle = interval_task(Duration::from_secs(10), Duration::from_secs(1), move || {
+            let (counter, running) = (Arc::clone(&counter), Arc::clone(&running_));
+            async move {
+                counter.fetch_add(1, Ordering::SeqCst);
+                assert_eq!(running.fetch_add(1, Ordering::SeqCst), 0, "runs overlap");
+                // longer than period, next run starts right after this one
+                tokio::time::sleep(Duration::from_secs(12)).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                Err::<(), _>("failed")
+            }
+        })
+        .name("test task")
+        .spawn(shutdown.clone());
+
+        tokio::time::sleep(Duration::from_secs(45)).await;
+        // runs start at about 0s, 12s, 24s and 36s
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        shutdown.cancel();
+        handle.await.unwrap();
+        assert_eq!(runs.load(Ordering::SeqCst), 4);
+        assert_eq!(running.load(Ordering::SeqCst), 0);
+    }
+}
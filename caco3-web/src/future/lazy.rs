@@ -0,0 +1,152 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use tokio::sync::OnceCell;
+
+use crate::retry::{RetryIf, RetryPolicy};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type Init<T, E> = Arc<dyn Fn() -> BoxFuture<Result<T, E>> + Send + Sync>;
+
+/// A value initialized by an async initializer on first access.
+///
+/// Initializer runs once, concurrent callers wait for the running initializer instead of
+/// running their own. If initializer fails, the error is returned and next access runs it again.
+///
+/// ```ignore
+/// static POOL: LazyLock<AsyncLazy<PgPool, sqlx::Error>> = LazyLock::new(|| {
+///     AsyncLazy::try_new(|| PgPoolOptions::new().connect(DATABASE_URL))
+///         .retry(RetryPolicy::new(5))
+/// });
+///
+/// let pool = POOL.try_get().await?;
+/// ```
+pub struct AsyncLazy<T, E = Infallible> {
+    cell: OnceCell<T>,
+    init: Init<T, E>,
+}
+
+impl<T> AsyncLazy<T> {
+    pub fn new<F, Fut>(init: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+    {
+        Self::try_new(move || {
+            let future = init();
+            async move { Ok(future.await) }
+        })
+    }
+
+    /// Value, initialize it if needed.
+    pub async fn get(&self) -> &T {
+        match self.try_get().await {
+            Ok(value) => value,
+            Err(e) => match e {},
+        }
+    }
+}
+
+impl<T, E> AsyncLazy<T, E> {
+    /// Create with a fallible initializer.
+    pub fn try_new<F, Fut>(init: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<T, E>> + Send + 'static,
+    {
+        Self {
+            cell: OnceCell::new(),
+            init: Arc::new(move || Box::pin(init())),
+        }
+    }
+
+    /// Retry failed initializer with `policy`, error of the last attempt is returned.
+    pub fn retry<R>(self, policy: RetryPolicy<R>) -> Self
+    where
+        T: Send + 'static,
+        E: Send + 'static,
+        R: RetryIf<E> + Send + Sync + 'static,
+    {
+        let init = self.init;
+        let policy = Arc::new(policy);
+        Self {
+            cell: self.cell,
+            init: Arc::new(move || {
+                let (init, policy) = (Arc::clone(&init), Arc::clone(&policy));
+                Box::pin(async move { policy.run(|| init()).await })
+            }),
+        }
+    }
+
+    /// Value, initialize it if needed.
+    pub async fn try_get(&self) -> Result<&T, E> {
+        self.cell.get_or_try_init(|| (self.init)()).await
+    }
+
+    /// Value if it is initialized.
+    pub fn peek(&self) -> Option<&T> {
+        self.cell.get()
+    }
+}
+
+impl<T: fmt::Debug, E> fmt::Debug for AsyncLazy<T, E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncLazy")
+            .field("value", &self.cell.get())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_async_lazy() {
+        let calls = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&calls);
+        let lazy = AsyncLazy::new(move || {
+            let counter = Arc::clone(&counter);
+            async move {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                counter.fetch_add(1, Ordering::SeqCst) + 42
+            }
+        });
+        assert_eq!(lazy.peek(), None);
+        let (a, b) = tokio::join!(lazy.get(), lazy.get());
+        assert_eq!((*a, *b), (42, 42));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        assert_eq!(lazy.peek(), Some(&42));
+
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = Arc::clone(&attempts);
+        let make = move || {
+            let counter = Arc::clone(&counter);
+            AsyncLazy::try_new(move || {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                async move {
+                    if attempt % 3 == 0 {
+                        Ok(attempt)
+                    } else {
+                        Err(attempt)
+                    }
+                }
+            })
+        };
+        let lazy = make();
+        assert_eq!(lazy.try_get().await, Err(1));
+        assert_eq!(lazy.try_get().await, Err(2));
+        assert_eq!(lazy.try_get().await, Ok(&3));
+        assert_eq!(lazy.try_get().await, Ok(&3));
+
+        let lazy = make().retry(RetryPolicy::new(3));
+        assert_eq!(lazy.try_get().await, Ok(&6));
+        assert_eq!(attempts.load(Ordering::SeqCst), 6);
+    }
+}
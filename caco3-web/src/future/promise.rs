@@ -0,0 +1,148 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::panic::Location;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::sync::oneshot;
+use tracing::warn;
+
+#[derive(Debug, Clone, Error)]
+#[non_exhaustive]
+pub enum PromiseError {
+    #[error("promise {name} created at {location} was dropped without resolving")]
+    Dropped {
+        name: Cow<'static, str>,
+        location: &'static Location<'static>,
+    },
+    #[error("promise {name} created at {location} timed out after {timeout:?}")]
+    TimedOut {
+        name: Cow<'static, str>,
+        location: &'static Location<'static>,
+        timeout: Duration,
+    },
+}
+
+/// Receiving half of a oneshot channel, resolved by its [`Resolver`].
+///
+/// Name and creation site of a promise are logged when its resolver is dropped without
+/// resolving, and included in errors.
+///
+/// ```ignore
+/// let (resolver, promise) = Promise::new("fetch user");
+/// requests.send((id, resolver)).await?;
+/// let user = promise.timeout(Duration::from_secs(3)).await?;
+/// ```
+#[derive(Debug)]
+pub struct Promise<T> {
+    rx: oneshot::Receiver<T>,
+    name: Cow<'static, str>,
+    location: &'static Location<'static>,
+}
+
+/// Sending half of a [`Promise`].
+#[derive(Debug)]
+pub struct Resolver<T> {
+    tx: Option<oneshot::Sender<T>>,
+    name: Cow<'static, str>,
+    location: &'static Location<'static>,
+}
+
+impl<T> Promise<T> {
+    #[track_caller]
+    pub fn new(name: impl Into<Cow<'static, str>>) -> (Resolver<T>, Promise<T>) {
+        let (tx, rx) = oneshot::channel();
+        let name = name.into();
+        let location = Location::caller();
+        let resolver = Resolver {
+            tx: Some(tx),
+            name: name.clone(),
+            location,
+        };
+        (resolver, Promise { rx, name, location })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Wait for value up to `timeout`.
+    pub async fn timeout(self, timeout: Duration) -> Result<T, PromiseError> {
+        let name = self.name.clone();
+        let location = self.location;
+        tokio::time::timeout(timeout, self)
+            .await
+            .unwrap_or_else(|_| {
+                Err(PromiseError::TimedOut {
+                    name,
+                    location,
+                    timeout,
+                })
+            })
+    }
+}
+
+impl<T> Future for Promise<T> {
+    type Output = Result<T, PromiseError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let result = ready!(Pin::new(&mut self.rx).poll(cx));
+        Poll::Ready(result.map_err(|_| PromiseError::Dropped {
+            name: self.name.clone(),
+            location: self.location,
+        }))
+    }
+}
+
+impl<T> Resolver<T> {
+    /// Resolve promise with `value`, returns `value` back if the promise was dropped.
+    pub fn resolve(mut self, value: T) -> Result<(), T> {
+        let tx = self.tx.take().expect("resolver is resolved once");
+        tx.send(value)
+    }
+
+    /// Whether the promise was dropped, so resolving it is pointless.
+    pub fn is_closed(&self) -> bool {
+        self.tx.as_ref().map_or(true, oneshot::Sender::is_closed)
+    }
+}
+
+impl<T> Drop for Resolver<T> {
+    fn drop(&mut self) {
+        if self.tx.is_some() && !std::thread::panicking() {
+            warn!(
+                "Promise {} created at {} was dropped without resolving",
+                self.name, self.location
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_promise() {
+        let (resolver, promise) = Promise::new("answer");
+        assert!(!resolver.is_closed());
+        tokio::spawn(async move { resolver.resolve(42) });
+        assert_eq!(promise.await.unwrap(), 42);
+
+        let (resolver, promise) = Promise::<()>::new(format!("request {}", 1));
+        drop(resolver);
+        let err = promise.await.unwrap_err();
+        assert!(matches!(&err, PromiseError::Dropped { name, .. } if name == "request 1"));
+        assert!(err.to_string().contains(file!()), "{err}");
+
+        let (resolver, promise) = Promise::<()>::new("slow");
+        let err = promise.timeout(Duration::from_secs(1)).await.unwrap_err();
+        assert!(
+            matches!(err, PromiseError::TimedOut { timeout, .. } if timeout == Duration::from_secs(1))
+        );
+        assert!(resolver.is_closed());
+        assert_eq!(resolver.resolve(()), Err(()));
+    }
+}
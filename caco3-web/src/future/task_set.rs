@@ -0,0 +1,161 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::future::Future;
+use std::time::Duration;
+
+use tokio::task::{AbortHandle, Id, JoinError, JoinSet};
+use tracing::{debug, error, warn};
+
+/// A set of named tasks, a wrapper of [`JoinSet`] logging failed tasks with their names.
+///
+/// Tasks are aborted when the set is dropped.
+///
+/// ```ignore
+/// let mut tasks = TaskSet::new();
+/// tasks.spawn("consumer", consume(queue));
+/// tasks.spawn("reporter", report(metrics));
+///
+/// shutdown_signal().await;
+/// if !tasks.shutdown(Duration::from_secs(10)).await {
+///     warn!("Some tasks didn't stop in time");
+/// }
+/// ```
+pub struct TaskSet<T = ()> {
+    set: JoinSet<T>,
+    names: HashMap<Id, Cow<'static, str>>,
+    completed: usize,
+    failed: usize,
+}
+
+impl<T> Default for TaskSet<T> {
+    fn default() -> Self {
+        Self {
+            set: JoinSet::new(),
+            names: HashMap::new(),
+            completed: 0,
+            failed: 0,
+        }
+    }
+}
+
+impl<T: Send + 'static> TaskSet<T> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn `future` as a task named `name`.
+    pub fn spawn<F>(&mut self, name: impl Into<Cow<'static, str>>, future: F) -> AbortHandle
+    where
+        F: Future<Output = T> + Send + 'static,
+    {
+        let handle = self.set.spawn(future);
+        self.names.insert(handle.id(), name.into());
+        handle
+    }
+
+    /// Number of tasks which are not joined yet.
+    pub fn running(&self) -> usize {
+        self.set.len()
+    }
+
+    /// Number of joined tasks which returned output.
+    pub fn completed(&self) -> usize {
+        self.completed
+    }
+
+    /// Number of joined tasks which panicked or were aborted.
+    pub fn failed(&self) -> usize {
+        self.failed
+    }
+
+    /// Wait for next task to finish, returns its name and output.
+    ///
+    /// Returns `None` if the set is empty.
+    pub async fn join_next(&mut self) -> Option<(Cow<'static, str>, Result<T, JoinError>)> {
+        let result = self.set.join_next_with_id().await?;
+        let id = match &result {
+            Ok((id, _)) => *id,
+            Err(e) => e.id(),
+        };
+        let name = self.names.remove(&id).unwrap_or(Cow::Borrowed("unnamed"));
+        let result = match result {
+            Ok((_, output)) => {
+                debug!("Task {name} completed");
+                self.completed += 1;
+                Ok(output)
+            }
+            Err(e) => {
+                if e.is_panic() {
+                    error!("Task {name} panicked: {e}");
+                } else {
+                    warn!("Task {name} was aborted");
+                }
+                self.failed += 1;
+                Err(e)
+            }
+        };
+        Some((name, result))
+    }
+
+    /// Wait for every task to finish.
+    pub async fn join_all(&mut self) {
+        while self.join_next().await.is_some() {}
+    }
+
+    /// Abort every task, tasks are stopped at their next `.await`.
+    pub fn abort_all(&mut self) {
+        self.set.abort_all();
+    }
+
+    /// Wait up to `timeout` for every task to finish, then abort remaining tasks.
+    ///
+    /// Returns `true` if every task finished in time.
+    pub async fn shutdown(&mut self, timeout: Duration) -> bool {
+        let finished = tokio::time::timeout(timeout, self.join_all()).await.is_ok();
+        if !finished {
+            let names: Vec<_> = self.names.values().map(|name| name.as_ref()).collect();
+            warn!("Abort tasks which didn't finish in {timeout:?}: {names:?}");
+            self.abort_all();
+            self.join_all().await;
+        }
+        finished
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn test_task_set() {
+        let mut tasks = TaskSet::new();
+        tasks.spawn("answer", async { 42 });
+        tasks.spawn(format!("panic {}", 1), async { panic!("boom") });
+        assert_eq!(tasks.running(), 2);
+        let mut outputs = vec![];
+        while let Some((name, result)) = tasks.join_next().await {
+            outputs.push((name, result.ok()));
+        }
+        outputs.sort();
+        assert_eq!(
+            outputs,
+            [("answer".into(), Some(42)), ("panic 1".into(), None)]
+        );
+        assert_eq!(
+            (tasks.running(), tasks.completed(), tasks.failed()),
+            (0, 1, 1)
+        );
+
+        tasks.spawn("quick", async { 1 });
+        tasks.spawn("slow", async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+            2
+        });
+        assert!(!tasks.shutdown(Duration::from_secs(1)).await);
+        assert_eq!(
+            (tasks.running(), tasks.completed(), tasks.failed()),
+            (0, 2, 2)
+        );
+        assert!(tasks.shutdown(Duration::from_secs(1)).await);
+    }
+}
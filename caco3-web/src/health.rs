@@ -0,0 +1,333 @@
+//! Health and readiness endpoints.
+//!
+//! ```ignore
+//! struct Database { pool: PgPool }
+//!
+//! impl HealthCheck for Database {
+//!     fn name(&self) -> &str {
+//!         "database"
+//!     }
+//!
+//!     async fn check(&self) -> Result<(), String> {
+//!         sqlx::query("SELECT 1").execute(&self.pool).await.map_err(|e| e.to_string())?;
+//!         Ok(())
+//!     }
+//! }
+//!
+//! let mut registry = HealthRegistry::new();
+//! registry.register_instance::<Database>(&type_map);
+//! let app = Router::new().merge(health::router(registry));
+//! ```
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use futures_core::future::BoxFuture;
+use futures_util::future::join_all;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::di::{Dep, TypeMap};
+use crate::json::{ApiJson, DEFAULT_ERROR_CODE};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Which endpoints a check participates in.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CheckKind {
+    /// Checked by both `/healthz` and `/readyz`.
+    Liveness,
+    /// Checked by `/readyz` only.
+    Readiness,
+}
+
+/// A health check of a component.
+pub trait HealthCheck: Send + Sync + 'static {
+    /// Component name shown in report.
+    fn name(&self) -> &str;
+
+    /// Returns `Err` with reason if component is unhealthy.
+    fn check(&self) -> impl Future<Output = Result<(), String>> + Send;
+
+    fn kind(&self) -> CheckKind {
+        CheckKind::Readiness
+    }
+}
+
+// object safe version of HealthCheck
+trait DynHealthCheck: Send + Sync + 'static {
+    fn name(&self) -> &str;
+    fn check(&self) -> BoxFuture<'_, Result<(), String>>;
+    fn kind(&self) -> CheckKind;
+}
+
+// an unbound `Dep::lazy` is reported down under its type name
+impl<T: HealthCheck> DynHealthCheck for Dep<T> {
+    fn name(&self) -> &str {
+        Dep::try_as_ref(self).map_or(std::any::type_name::<T>(), HealthCheck::name)
+    }
+
+    fn check(&self) -> BoxFuture<'_, Result<(), String>> {
+        match Dep::try_as_ref(self) {
+            Ok(check) => Box::pin(HealthCheck::check(check)),
+            Err(err) => Box::pin(std::future::ready(Err(err.to_string()))),
+        }
+    }
+
+    fn kind(&self) -> CheckKind {
+        Dep::try_as_ref(self).map_or(CheckKind::Readiness, HealthCheck::kind)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Status {
+    Up,
+    Down,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ComponentStatus {
+    pub status: Status,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub elapsed_ms: u128,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HealthReport {
+    pub status: Status,
+    pub components: BTreeMap<String, ComponentStatus>,
+}
+
+impl HealthReport {
+    pub fn is_up(&self) -> bool {
+        self.status == Status::Up
+    }
+}
+
+impl IntoResponse for HealthReport {
+    fn into_response(self) -> Response {
+        if self.is_up() {
+            ApiJson::ok(self).into_response()
+        } else {
+            let json = ApiJson::data_with_code(self, DEFAULT_ERROR_CODE.into());
+            (StatusCode::SERVICE_UNAVAILABLE, json).into_response()
+        }
+    }
+}
+
+/// A collection of health checks.
+pub struct HealthRegistry {
+    checks: Vec<Box<dyn DynHealthCheck>>,
+    timeout: Duration,
+}
+
+impl Default for HealthRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HealthRegistry {
+    pub fn new() -> Self {
+        Self {
+            checks: vec![],
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Set timeout of each check, a check that doesn't finish in time is considered down.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Register a check, names are keys of report so they must be unique.
+    ///
+    /// panic if a check with the same name is already registered, the name of a [`Dep::lazy`]
+    /// that isn't bound yet is not checked.
+    pub fn register<T: HealthCheck>(&mut self, check: impl Into<Dep<T>>) -> &mut Self {
+        let check = check.into();
+        if let Ok(check) = Dep::try_as_ref(&check) {
+            let name = check.name();
+            assert!(
+                self.checks.iter().all(|c| c.name() != name),
+                "health check {name} is already registered"
+            );
+        }
+        self.checks.push(Box::new(check));
+        self
+    }
+
+    /// Register check of type `T` from dependency type map.
+    ///
+    /// panic if an instance of `Dep<T>` doesn't exist, or a check with the same name is already registered.
+    pub fn register_instance<T: HealthCheck>(&mut self, map: &TypeMap) -> &mut Self {
+        let dep: &Dep<T> = map.get_instance();
+        self.register::<T>(dep.clone())
+    }
+
+    /// Run checks of given kind concurrently.
+    pub async fn check(&self, kind: CheckKind) -> HealthReport {
+        let checks = self
+            .checks
+            .iter()
+            .filter(|c| kind == CheckKind::Readiness || c.kind() == CheckKind::Liveness)
+            .map(|c| async move {
+                let start = Instant::now();
+                let result = match tokio::time::timeout(self.timeout, c.check()).await {
+                    Ok(result) => result,
+                    Err(_) => Err(format!("timed out after {:?}", self.timeout)),
+                };
+                let elapsed_ms = start.elapsed().as_millis();
+                let status = match result {
+                    Ok(()) => ComponentStatus {
+                        status: Status::Up,
+                        error: None,
+                        elapsed_ms,
+                    },
+                    Err(error) => {
+                        warn!("Health check {} is down: {error}", c.name());
+                        ComponentStatus {
+                            status: Status::Down,
+                            error: Some(error),
+                            elapsed_ms,
+                        }
+                    }
+                };
+                (c.name().to_owned(), status)
+            });
+        let components: BTreeMap<_, _> = join_all(checks).await.into_iter().collect();
+        let status = if components.values().all(|c| c.status == Status::Up) {
+            Status::Up
+        } else {
+            Status::Down
+        };
+        HealthReport { status, components }
+    }
+}
+
+async fn healthz(State(registry): State<Arc<HealthRegistry>>) -> HealthReport {
+    registry.check(CheckKind::Liveness).await
+}
+
+async fn readyz(State(registry): State<Arc<HealthRegistry>>) -> HealthReport {
+    registry.check(CheckKind::Readiness).await
+}
+
+/// Router serving `/healthz` (liveness) and `/readyz` (readiness).
+///
+/// Both respond `200 OK` if all checks are up, otherwise `503 Service Unavailable`.
+pub fn router<S>(registry: HealthRegistry) -> Router<S> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .with_state(Arc::new(registry))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    struct Check {
+        name: &'static str,
+        kind: CheckKind,
+        result: Result<(), &'static str>,
+        delay: Duration,
+    }
+
+    impl Check {
+        fn up(name: &'static str, kind: CheckKind) -> Self {
+            Self {
+                name,
+                kind,
+                result: Ok(()),
+                delay: Duration::ZERO,
+            }
+        }
+    }
+
+    impl HealthCheck for Check {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        async fn check(&self) -> Result<(), String> {
+            tokio::time::sleep(self.delay).await;
+            self.result.map_err(str::to_owned)
+        }
+
+        fn kind(&self) -> CheckKind {
+            self.kind
+        }
+    }
+
+    async fn status_of(app: &Router, path: &str) -> StatusCode {
+        let req = Request::get(path).body(Body::empty()).unwrap();
+        app.clone().oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_health_router() {
+        let mut registry = HealthRegistry::new();
+        registry.register(Check::up("app", CheckKind::Liveness));
+        registry.register(Check {
+            result: Err("connection refused"),
+            ..Check::up("database", CheckKind::Readiness)
+        });
+        let app = router(registry);
+        assert_eq!(status_of(&app, "/healthz").await, StatusCode::OK);
+        assert_eq!(status_of(&app, "/readyz").await, StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_timeout() {
+        let mut registry = HealthRegistry::new().timeout(Duration::from_secs(1));
+        registry.register(Check {
+            delay: Duration::from_secs(2),
+            ..Check::up("slow", CheckKind::Readiness)
+        });
+        let report = registry.check(CheckKind::Readiness).await;
+        assert!(!report.is_up());
+        assert!(report.components["slow"].error.is_some());
+    }
+
+    #[test]
+    fn test_register_instance() {
+        let mut map = TypeMap::new();
+        map.insert(Dep::new(Check::up("app", CheckKind::Liveness)));
+        let mut registry = HealthRegistry::new();
+        registry.register_instance::<Check>(&map);
+        assert_eq!(registry.checks.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_register_lazy() {
+        let mut registry = HealthRegistry::new();
+        registry.register(Check::up("app", CheckKind::Liveness));
+        registry.register::<Check>(Dep::lazy());
+        let report = registry.check(CheckKind::Readiness).await;
+        assert!(!report.is_up());
+        let name = std::any::type_name::<Check>();
+        assert!(report.components[name].error.is_some());
+    }
+
+    #[test]
+    #[should_panic(expected = "health check database is already registered")]
+    fn test_duplicate_name() {
+        let mut registry = HealthRegistry::new();
+        registry.register(Check::up("database", CheckKind::Readiness));
+        registry.register(Check::up("database", CheckKind::Liveness));
+    }
+}
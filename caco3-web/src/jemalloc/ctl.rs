@@ -0,0 +1,96 @@
+//! Thin wrappers around `mallctl`.
+use std::ffi::{c_char, c_void, CString};
+use std::{io, mem, ptr};
+
+use thiserror::Error;
+
+/// Error of a `mallctl` call.
+#[derive(Debug, Error)]
+#[error("jemalloc: mallctl {name}: {source}")]
+pub struct MallctlError {
+    pub name: String,
+    #[source]
+    pub source: io::Error,
+}
+
+impl MallctlError {
+    /// Returns `true` if the name doesn't exist, or refers to an uninitialized arena.
+    pub fn is_not_found(&self) -> bool {
+        matches!(
+            self.source.raw_os_error(),
+            Some(libc::ENOENT | libc::EFAULT)
+        )
+    }
+
+    pub(crate) fn invalid_name(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            source: io::Error::from_raw_os_error(libc::EINVAL),
+        }
+    }
+}
+
+unsafe fn mallctl(
+    name: &str,
+    oldp: *mut c_void,
+    oldlenp: *mut usize,
+    newp: *mut c_void,
+    newlen: usize,
+) -> Result<(), MallctlError> {
+    let c_name = CString::new(name).map_err(|_| MallctlError::invalid_name(name))?;
+    let code = tikv_jemalloc_sys::mallctl(
+        c_name.as_ptr() as *const c_char,
+        oldp,
+        oldlenp,
+        newp,
+        newlen,
+    );
+    if code == 0 {
+        Ok(())
+    } else {
+        Err(MallctlError {
+            name: name.to_owned(),
+            source: io::Error::from_raw_os_error(code),
+        })
+    }
+}
+
+/// Read value of `name`.
+///
+/// # Safety
+///
+/// `T` must be the type of `name`, as documented by jemalloc.
+pub(crate) unsafe fn read<T: Copy>(name: &str) -> Result<T, MallctlError> {
+    let mut value = mem::MaybeUninit::<T>::uninit();
+    let mut len = mem::size_of::<T>();
+    mallctl(
+        name,
+        value.as_mut_ptr().cast(),
+        &mut len,
+        ptr::null_mut(),
+        0,
+    )?;
+    debug_assert_eq!(len, mem::size_of::<T>());
+    Ok(value.assume_init())
+}
+
+/// Write `value` to `name`.
+///
+/// # Safety
+///
+/// `T` must be the type of `name`, as documented by jemalloc.
+pub(crate) unsafe fn write<T>(name: &str, mut value: T) -> Result<(), MallctlError> {
+    mallctl(
+        name,
+        ptr::null_mut(),
+        ptr::null_mut(),
+        (&mut value as *mut T).cast(),
+        mem::size_of::<T>(),
+    )
+}
+
+/// Invoke `name` that neither reads nor writes, e.g. `arena.<i>.purge`.
+pub(crate) fn call(name: &str) -> Result<(), MallctlError> {
+    // SAFETY: no pointer is passed
+    unsafe { mallctl(name, ptr::null_mut(), ptr::null_mut(), ptr::null_mut(), 0) }
+}
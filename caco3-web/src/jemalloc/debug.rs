@@ -0,0 +1,115 @@
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+
+use crate::di::{Dep, TypeMap};
+use crate::jemalloc::info::{JemallocInfo, JemallocRawData};
+use crate::json::ApiJson;
+
+/// Reader of jemalloc statistics, a function generated with
+/// [`generate_read_jemalloc_raw_data`](crate::generate_read_jemalloc_raw_data).
+#[derive(Debug, Clone, Copy)]
+pub struct JemallocReader(pub fn() -> Option<JemallocRawData>);
+
+fn unavailable() -> Response {
+    let json = ApiJson::unit_error_builder()
+        .error("Jemalloc statistics are unavailable")
+        .build();
+    (StatusCode::SERVICE_UNAVAILABLE, json).into_response()
+}
+
+async fn memory(State(reader): State<Dep<JemallocReader>>) -> Response {
+    match (reader.0)().and_then(JemallocInfo::from_raw) {
+        Some(info) => ApiJson::ok(info).into_response(),
+        None => unavailable(),
+    }
+}
+
+async fn memory_raw(State(reader): State<Dep<JemallocReader>>) -> Response {
+    match (reader.0)() {
+        Some(raw) => ApiJson::ok(raw).into_response(),
+        None => unavailable(),
+    }
+}
+
+#[cfg(feature = "jemalloc-ctl")]
+async fn memory_stats() -> String {
+    crate::jemalloc::info::stats_print()
+}
+
+/// Router serving jemalloc debug endpoints
+/// * `/debug/memory`, [`JemallocInfo`] in [`ApiJson`] format.
+/// * `/debug/memory/raw`, [`JemallocRawData`] in [`ApiJson`] format.
+/// * `/debug/memory/stats`, text output of `malloc_stats_print`, requires `jemalloc-ctl` feature.
+///
+/// Reader is taken from `Dep<JemallocReader>` of `map`.
+/// Routes are not protected, apply authentication middleware to the returned router, e.g.
+///
+/// ```ignore
+/// map.insert(Dep::new(JemallocReader(read_jemalloc_raw_data)));
+/// let debug = jemalloc::router(&map).route_layer(AdminAuthLayer::from_type_map(&map));
+/// ```
+///
+/// panic if an instance of `Dep<JemallocReader>` doesn't exist.
+pub fn router<S>(map: &TypeMap) -> Router<S> {
+    let reader: &Dep<JemallocReader> = map.get_instance();
+    let router = Router::new()
+        .route("/debug/memory", get(memory))
+        .route("/debug/memory/raw", get(memory_raw));
+    #[cfg(feature = "jemalloc-ctl")]
+    let router = router.route("/debug/memory/stats", get(memory_stats));
+    router.with_state(reader.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn read() -> Option<JemallocRawData> {
+        Some(JemallocRawData {
+            active_bytes: 0,
+            allocated_bytes: 1024,
+            mapped_bytes: 0,
+            metadata_bytes: 0,
+            resident_bytes: 2048,
+            retained_bytes: 0,
+            background_thread: None,
+            number_of_arenas: 1,
+        })
+    }
+
+    async fn get_json(app: &Router, uri: &str) -> (StatusCode, Value) {
+        let req = Request::get(uri).body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_router() {
+        let mut map = TypeMap::new();
+        map.insert(Dep::new(JemallocReader(read)));
+        let app = router(&map);
+        let (status, json) = get_json(&app, "/debug/memory").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["data"]["stats"]["allocated"], "1.00 KiB");
+        let (_, json) = get_json(&app, "/debug/memory/raw").await;
+        assert_eq!(json["data"]["resident_bytes"], 2048);
+    }
+
+    #[tokio::test]
+    async fn test_unavailable() {
+        let mut map = TypeMap::new();
+        map.insert(Dep::new(JemallocReader(|| None)));
+        let (status, _) = get_json(&router(&map), "/debug/memory").await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
@@ -1,9 +1,14 @@
 use std::fmt::Write;
+#[cfg(feature = "jemalloc-ctl")]
+use std::marker::PhantomData;
 
 use arrayvec::ArrayString;
 use byte_unit::{Byte, UnitType};
-use serde::ser::Error as _;
-use serde::{Serialize, Serializer};
+use serde::ser::{Error as _, SerializeStruct};
+use serde::{Deserialize, Serialize, Serializer};
+
+#[cfg(feature = "jemalloc-ctl")]
+use crate::jemalloc::ctl::{self, MallctlError};
 
 #[derive(Serialize)]
 pub struct JemallocInfo {
@@ -11,33 +16,134 @@ pub struct JemallocInfo {
     pub stats: Stats,
 }
 
-#[derive(Serialize)]
 pub struct Stats {
     // these two are the most interested
-    #[serde(serialize_with = "serialize_byte")]
     pub allocated: Byte,
-    #[serde(serialize_with = "serialize_byte")]
     pub resident: Byte,
     // other values
-    #[serde(serialize_with = "serialize_byte")]
     pub active: Byte,
-    #[serde(serialize_with = "serialize_byte")]
     pub mapped: Byte,
-    #[serde(serialize_with = "serialize_byte")]
     pub metadata: Byte,
-    #[serde(serialize_with = "serialize_byte")]
     pub retained: Byte,
 }
 
-fn serialize_byte<S>(this: &Byte, serializer: S) -> Result<S::Ok, S::Error>
+impl Serialize for Stats {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.with_format(StatsFormat::default())
+            .serialize(serializer)
+    }
+}
+
+impl JemallocInfo {
+    /// Serialize with byte values of [`Stats`] in `format`.
+    pub fn with_format(&self, format: StatsFormat) -> WithFormat<'_, Self> {
+        WithFormat {
+            value: self,
+            format,
+        }
+    }
+}
+
+impl Stats {
+    /// Serialize with byte values in `format`.
+    pub fn with_format(&self, format: StatsFormat) -> WithFormat<'_, Self> {
+        WithFormat {
+            value: self,
+            format,
+        }
+    }
+}
+
+/// Serializable view of `T` with byte values in a [`StatsFormat`].
+pub struct WithFormat<'a, T> {
+    value: &'a T,
+    format: StatsFormat,
+}
+
+impl Serialize for WithFormat<'_, JemallocInfo> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("JemallocInfo", 2)?;
+        state.serialize_field("options", &self.value.options)?;
+        state.serialize_field("stats", &self.value.stats.with_format(self.format))?;
+        state.end()
+    }
+}
+
+impl Serialize for WithFormat<'_, Stats> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        struct Formatted<'a>(&'a Byte, StatsFormat);
+
+        impl Serialize for Formatted<'_> {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                self.1.serialize_byte(self.0, serializer)
+            }
+        }
+
+        let Self { value, format } = *self;
+        let mut state = serializer.serialize_struct("Stats", 6)?;
+        state.serialize_field("allocated", &Formatted(&value.allocated, format))?;
+        state.serialize_field("resident", &Formatted(&value.resident, format))?;
+        state.serialize_field("active", &Formatted(&value.active, format))?;
+        state.serialize_field("mapped", &Formatted(&value.mapped, format))?;
+        state.serialize_field("metadata", &Formatted(&value.metadata, format))?;
+        state.serialize_field("retained", &Formatted(&value.retained, format))?;
+        state.end()
+    }
+}
+
+/// Serialization format of byte values in [`Stats`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatsFormat {
+    /// String with binary unit, e.g. `1.50 MiB`.
+    Binary { precision: usize },
+    /// String with decimal unit, e.g. `1.57 MB`.
+    Decimal { precision: usize },
+    /// Integer number of bytes.
+    Bytes,
+}
+
+impl Default for StatsFormat {
+    fn default() -> Self {
+        Self::Binary { precision: 2 }
+    }
+}
+
+impl StatsFormat {
+    fn serialize_byte<S>(self, byte: &Byte, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let (unit_type, precision) = match self {
+            Self::Binary { precision } => (UnitType::Binary, precision),
+            Self::Decimal { precision } => (UnitType::Decimal, precision),
+            Self::Bytes => return serializer.serialize_u64(byte.as_u64()),
+        };
+        let mut buffer: ArrayString<256> = ArrayString::new();
+        let adjusted_byte = byte.get_appropriate_unit(unit_type);
+        write!(&mut buffer, "{adjusted_byte:.precision$}")
+            .map_err(|_| S::Error::custom(format!("serialize adjusted byte: {adjusted_byte}")))?;
+        serializer.serialize_str(buffer.as_str())
+    }
+}
+
+pub(crate) fn serialize_byte<S>(this: &Byte, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    let mut buffer: ArrayString<256> = ArrayString::new();
-    let adjusted_byte = this.get_appropriate_unit(UnitType::Binary);
-    write!(&mut buffer, "{adjusted_byte:.2}")
-        .map_err(|_| S::Error::custom(format!("serialize adjusted byte: {adjusted_byte}")))?;
-    serializer.serialize_str(buffer.as_str())
+    StatsFormat::default().serialize_byte(this, serializer)
 }
 
 #[derive(Serialize)]
@@ -54,6 +160,7 @@ pub struct BackgroundThread {
 }
 
 #[doc(hidden)]
+#[derive(Serialize)]
 pub struct JemallocRawData {
     // stats
     pub active_bytes: usize,
@@ -101,3 +208,163 @@ impl JemallocInfo {
         Some(jemalloc)
     }
 }
+
+/// Cumulative allocation statistics of the calling thread.
+#[cfg(feature = "jemalloc-ctl")]
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Serialize)]
+pub struct ThreadStats {
+    pub allocated_bytes: u64,
+    pub deallocated_bytes: u64,
+}
+
+/// Read allocation statistics of the calling thread.
+#[cfg(feature = "jemalloc-ctl")]
+pub fn thread_stats() -> Result<ThreadStats, MallctlError> {
+    // SAFETY: `thread.allocated` and `thread.deallocated` are uint64_t
+    unsafe {
+        Ok(ThreadStats {
+            allocated_bytes: ctl::read("thread.allocated")?,
+            deallocated_bytes: ctl::read("thread.deallocated")?,
+        })
+    }
+}
+
+/// Measure bytes allocated by the calling thread within a scope, logged at debug level on drop.
+///
+/// Statistics are per-thread, so this is not `Send`. In async code, measure synchronous
+/// sections only, a task may be moved to another thread at any `.await`.
+///
+/// ```ignore
+/// let scope = AllocationScope::new("render_report")?;
+/// let report = render_report(&data);
+/// metrics::histogram!("report_allocated_bytes").record(scope.allocated_bytes() as f64);
+/// ```
+#[cfg(feature = "jemalloc-ctl")]
+pub struct AllocationScope {
+    label: &'static str,
+    start: ThreadStats,
+    _not_send: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "jemalloc-ctl")]
+impl AllocationScope {
+    pub fn new(label: &'static str) -> Result<Self, MallctlError> {
+        Ok(Self {
+            label,
+            start: thread_stats()?,
+            _not_send: PhantomData,
+        })
+    }
+
+    /// Statistics since this scope is created.
+    pub fn stats(&self) -> ThreadStats {
+        // reading stats can't fail after it succeeded once
+        let now = thread_stats().unwrap_or(self.start);
+        ThreadStats {
+            allocated_bytes: now.allocated_bytes.wrapping_sub(self.start.allocated_bytes),
+            deallocated_bytes: now
+                .deallocated_bytes
+                .wrapping_sub(self.start.deallocated_bytes),
+        }
+    }
+
+    /// Bytes allocated since this scope is created.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.stats().allocated_bytes
+    }
+}
+
+#[cfg(feature = "jemalloc-ctl")]
+impl Drop for AllocationScope {
+    fn drop(&mut self) {
+        let ThreadStats {
+            allocated_bytes,
+            deallocated_bytes,
+        } = self.stats();
+        tracing::debug!(
+            label = self.label,
+            allocated_bytes,
+            deallocated_bytes,
+            "Allocation scope finished"
+        );
+    }
+}
+
+/// Text output of jemalloc `malloc_stats_print`.
+#[cfg(feature = "jemalloc-ctl")]
+pub fn stats_print() -> String {
+    use std::ffi::{c_char, c_void, CStr};
+
+    unsafe extern "C" fn write_cb(opaque: *mut c_void, s: *const c_char) {
+        // SAFETY: `opaque` is `buf` below, and `s` is a C string provided by jemalloc
+        let buf = &mut *(opaque as *mut Vec<u8>);
+        buf.extend_from_slice(CStr::from_ptr(s).to_bytes());
+    }
+
+    let mut buf: Vec<u8> = Vec::with_capacity(16 * 1024);
+    // SAFETY: `buf` outlives the call, empty options is a valid C string
+    unsafe {
+        tikv_jemalloc_sys::malloc_stats_print(
+            Some(write_cb),
+            &mut buf as *mut Vec<u8> as *mut c_void,
+            c"".as_ptr(),
+        );
+    }
+    String::from_utf8_lossy(&buf).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_data() -> JemallocRawData {
+        JemallocRawData {
+            active_bytes: 0,
+            allocated_bytes: 1536 * 1024,
+            mapped_bytes: 0,
+            metadata_bytes: 0,
+            resident_bytes: 0,
+            retained_bytes: 0,
+            background_thread: None,
+            number_of_arenas: 1,
+        }
+    }
+
+    #[test]
+    fn test_stats_format() {
+        let info = JemallocInfo::from_raw(raw_data()).unwrap();
+        let allocated = |format| {
+            let json = serde_json::to_value(info.with_format(format)).unwrap();
+            json["stats"]["allocated"].clone()
+        };
+        let json = serde_json::to_value(&info).unwrap();
+        assert_eq!(json["stats"]["allocated"], "1.50 MiB");
+        assert_eq!(json["options"]["number_of_arenas"], 1);
+        assert_eq!(allocated(StatsFormat::default()), "1.50 MiB");
+        assert_eq!(allocated(StatsFormat::Binary { precision: 0 }), "2 MiB");
+        assert_eq!(allocated(StatsFormat::Decimal { precision: 3 }), "1.573 MB");
+        assert_eq!(allocated(StatsFormat::Bytes), 1536 * 1024);
+    }
+
+    #[cfg(feature = "jemalloc-ctl")]
+    #[test]
+    fn test_allocation_scope() {
+        let scope = AllocationScope::new("test").unwrap();
+        // allocate through jemalloc directly, it may not be the global allocator of tests
+        unsafe {
+            let ptr = tikv_jemalloc_sys::malloc(64 * 1024);
+            assert!(!ptr.is_null());
+            tikv_jemalloc_sys::free(ptr);
+        }
+        let stats = scope.stats();
+        assert!(stats.allocated_bytes >= 64 * 1024);
+        assert!(stats.deallocated_bytes >= 64 * 1024);
+    }
+
+    #[cfg(feature = "jemalloc-ctl")]
+    #[test]
+    fn test_stats_print() {
+        let text = stats_print();
+        assert!(text.contains("jemalloc statistics"), "{text}");
+    }
+}
@@ -1,10 +1,18 @@
 use std::env;
+use std::ffi::OsStr;
 use std::fmt::Display;
 use std::fmt::Write;
 use std::os::unix::prelude::CommandExt;
 use std::process::Command;
 
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "jemalloc-ctl")]
+use thiserror::Error;
+use tracing::warn;
+
+#[cfg(feature = "jemalloc-ctl")]
+use crate::jemalloc::ctl::{self, MallctlError};
+use crate::jemalloc::ValidateError;
 
 /// Jemalloc configuration.
 #[derive(Clone, Debug, Deserialize, Serialize, Default)]
@@ -36,26 +44,127 @@ pub const fn is_background_thread_supported() -> bool {
     true
 }
 
-/// Re-execute current process to apply jemalloc configuration.
-pub fn apply_config(config: &Jemalloc, f: impl FnOnce(&str)) -> ! {
+/// Environment variable set on re-executed process, to re-execute at most once.
+pub const APPLY_CONFIG_GUARD_ENVIRONMENT_VARIABLE: &str = "CACO3_JEMALLOC_CONFIG_APPLIED";
+
+/// Options of [`apply_config_with`].
+#[derive(Clone, Debug, Default)]
+pub struct ApplyConfigOptions {
+    /// Don't re-execute, only compute `MALLOC_CONF`.
+    pub dry_run: bool,
+    /// If set, only these environment variables are passed to re-executed process.
+    pub preserve_env: Option<Vec<String>>,
+    /// Environment variables not passed to re-executed process.
+    pub strip_env: Vec<String>,
+    /// Reject unknown keys of `extra_conf`, see [`Jemalloc::validate_strict`].
+    pub strict: bool,
+}
+
+/// Result of [`apply_config_with`] that didn't re-execute.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ApplyConfigOutcome {
+    /// Current process is the re-executed one.
+    AlreadyApplied,
+    DryRun {
+        malloc_conf: String,
+    },
+}
+
+/// Re-execute current process to apply jemalloc configuration, at most once.
+///
+/// Returns if current process is already re-executed, see [`apply_config_with`].
+/// Problems found by [`Jemalloc::validate`] are logged, use [`apply_config_with`] to handle them.
+pub fn apply_config(config: &Jemalloc, f: impl FnOnce(&str)) {
+    if let Err(err) = config.validate() {
+        warn!("{err}");
+    }
+    apply_once(config.to_config(), &ApplyConfigOptions::default(), f);
+}
+
+/// Re-execute current process to apply jemalloc configuration, like [`apply_config`] but
+/// with options and validation errors are returned.
+///
+/// Returns error if configuration is rejected by [`Jemalloc::validate`], or by
+/// [`Jemalloc::validate_strict`] if [`strict`](ApplyConfigOptions::strict) is set.
+/// `f` is called with `MALLOC_CONF` right before re-executing. Call this early in main program,
+/// before other threads are started, it removes [`APPLY_CONFIG_GUARD_ENVIRONMENT_VARIABLE`]
+/// from environment of re-executed process, so child processes don't inherit it.
+pub fn apply_config_with(
+    config: &Jemalloc,
+    options: &ApplyConfigOptions,
+    f: impl FnOnce(&str),
+) -> Result<ApplyConfigOutcome, ValidateError> {
+    if options.strict {
+        config.validate_strict()?;
+    } else {
+        config.validate()?;
+    }
+    Ok(apply_once(config.to_config(), options, f))
+}
+
+fn apply_once(
+    malloc_conf: String,
+    options: &ApplyConfigOptions,
+    f: impl FnOnce(&str),
+) -> ApplyConfigOutcome {
+    let guard = env::var_os(APPLY_CONFIG_GUARD_ENVIRONMENT_VARIABLE);
+    match skip_reexec(options, guard.as_deref(), &malloc_conf) {
+        Some(ApplyConfigOutcome::AlreadyApplied) => {
+            env::remove_var(APPLY_CONFIG_GUARD_ENVIRONMENT_VARIABLE);
+            ApplyConfigOutcome::AlreadyApplied
+        }
+        Some(outcome) => outcome,
+        None => reexec(&malloc_conf, options, f),
+    }
+}
+
+/// Outcome if current process should not be re-executed, given value of the guard variable.
+fn skip_reexec(
+    options: &ApplyConfigOptions,
+    guard: Option<&OsStr>,
+    malloc_conf: &str,
+) -> Option<ApplyConfigOutcome> {
+    if options.dry_run {
+        let malloc_conf = malloc_conf.to_owned();
+        return Some(ApplyConfigOutcome::DryRun { malloc_conf });
+    }
+    // guard against exec loop if this is called again by re-executed process
+    guard.map(|_| ApplyConfigOutcome::AlreadyApplied)
+}
+
+fn reexec(malloc_conf: &str, options: &ApplyConfigOptions, f: impl FnOnce(&str)) -> ! {
     // Some configuration of jemalloc need to be configured before main program is started.
     // But at this point, main program has been started, how do we solve this?
     //
     // We replace current process with itself but with properly jemalloc configuration.
-    let malloc_conf = config.to_config();
-
     let mut args = env::args_os();
     let program = args.next().expect("Process name");
     let mut cmd = Command::new(program);
     cmd.args(args);
-    for name in POSSIBLE_MALLOC_CONF_ENVIRONMENT_VARIABLES {
-        cmd.env(name, &malloc_conf);
-    }
-    f(&malloc_conf);
+    configure_env(&mut cmd, malloc_conf, options);
+    f(malloc_conf);
     let err = cmd.exec();
     panic!("jemalloc: exec error: {:?}", err);
 }
 
+fn configure_env(cmd: &mut Command, malloc_conf: &str, options: &ApplyConfigOptions) {
+    if let Some(preserve_env) = &options.preserve_env {
+        cmd.env_clear();
+        for name in preserve_env {
+            if let Some(value) = env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+    }
+    for name in &options.strip_env {
+        cmd.env_remove(name);
+    }
+    for name in POSSIBLE_MALLOC_CONF_ENVIRONMENT_VARIABLES {
+        cmd.env(name, malloc_conf);
+    }
+    cmd.env(APPLY_CONFIG_GUARD_ENVIRONMENT_VARIABLE, "1");
+}
+
 /// Returns `true` if jemalloc is configured.
 pub fn is_configured() -> bool {
     POSSIBLE_MALLOC_CONF_ENVIRONMENT_VARIABLES
@@ -73,10 +182,8 @@ impl Jemalloc {
             write!(&mut config, ",{}:{}", key, value)
                 .expect("a Display implementation returned an error unexpectedly");
         };
-        if self.background_thread {
-            // Do nothing, this is intended.
-            // background thread should be enabled at runtime to avoid deadlock.
-        }
+        // `background_thread` is not configured here, enabling it before main program is started
+        // may deadlock. It is enabled at runtime by `configure_runtime` instead.
         if let Some(v) = self.max_background_threads {
             write_config("max_background_threads", &v);
         }
@@ -91,6 +198,49 @@ impl Jemalloc {
     }
 }
 
+#[cfg(feature = "jemalloc-ctl")]
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum BackgroundThreadError {
+    #[error("jemalloc: background thread is not supported on this platform")]
+    Unsupported,
+    #[error("jemalloc: failed to set max background threads to {max}")]
+    SetMax {
+        max: usize,
+        #[source]
+        source: MallctlError,
+    },
+    #[error("jemalloc: failed to enable background threads")]
+    Enable(#[source] MallctlError),
+}
+
+/// Enable jemalloc background threads, optionally limiting number of threads to `max`.
+#[cfg(feature = "jemalloc-ctl")]
+pub fn enable_background_threads(max: Option<usize>) -> Result<(), BackgroundThreadError> {
+    if !is_background_thread_supported() {
+        return Err(BackgroundThreadError::Unsupported);
+    }
+    if let Some(max) = max {
+        // SAFETY: `max_background_threads` is size_t
+        unsafe { ctl::write("max_background_threads", max) }
+            .map_err(|source| BackgroundThreadError::SetMax { max, source })?;
+    }
+    // SAFETY: `background_thread` is bool
+    unsafe { ctl::write("background_thread", true) }.map_err(BackgroundThreadError::Enable)
+}
+
+/// Apply configuration that must be applied after main program is started.
+///
+/// Call this early in main program, after [`apply_config`] if it is used.
+#[cfg(feature = "jemalloc-ctl")]
+pub fn configure_runtime(config: &Jemalloc) -> Result<(), BackgroundThreadError> {
+    if config.background_thread {
+        let max = config.max_background_threads.map(|v| v as usize);
+        enable_background_threads(max)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -148,4 +298,94 @@ mod tests {
             "abort_conf:true,max_background_threads:8,narenas:64"
         );
     }
+
+    #[test]
+    fn test_apply_config_dry_run() {
+        let config = Jemalloc {
+            number_of_arenas: Some(4),
+            ..Default::default()
+        };
+        let options = ApplyConfigOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let outcome = apply_config_with(&config, &options, |_| unreachable!());
+        let malloc_conf = "abort_conf:true,narenas:4".to_owned();
+        assert_eq!(outcome, Ok(ApplyConfigOutcome::DryRun { malloc_conf }));
+
+        let config = Jemalloc {
+            extra_conf: Some("unknown:1".to_owned()),
+            ..Default::default()
+        };
+        let outcome = apply_config_with(&config, &options, |_| unreachable!());
+        let malloc_conf = "abort_conf:true,unknown:1".to_owned();
+        assert_eq!(outcome, Ok(ApplyConfigOutcome::DryRun { malloc_conf }));
+        let options = ApplyConfigOptions {
+            strict: true,
+            ..options
+        };
+        let outcome = apply_config_with(&config, &options, |_| unreachable!());
+        assert_eq!(
+            outcome,
+            Err(ValidateError::UnknownOption("unknown".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_skip_reexec() {
+        let options = ApplyConfigOptions::default();
+        let guard = Some(OsStr::new("1"));
+        assert_eq!(skip_reexec(&options, None, "abort_conf:true"), None);
+        assert_eq!(
+            skip_reexec(&options, guard, "abort_conf:true"),
+            Some(ApplyConfigOutcome::AlreadyApplied)
+        );
+        let options = ApplyConfigOptions {
+            dry_run: true,
+            ..Default::default()
+        };
+        let malloc_conf = "abort_conf:true".to_owned();
+        assert_eq!(
+            skip_reexec(&options, guard, &malloc_conf),
+            Some(ApplyConfigOutcome::DryRun { malloc_conf })
+        );
+    }
+
+    #[test]
+    fn test_configure_env() {
+        let mut cmd = Command::new("true");
+        let options = ApplyConfigOptions {
+            preserve_env: Some(vec!["PATH".to_owned(), "SECRET".to_owned()]),
+            strip_env: vec!["SECRET".to_owned()],
+            ..Default::default()
+        };
+        configure_env(&mut cmd, "abort_conf:true", &options);
+        let envs: Vec<_> = cmd
+            .get_envs()
+            .filter_map(|(k, v)| Some((k.to_str()?, v?.to_str()?)))
+            .collect();
+        assert!(!envs.iter().any(|(k, _)| *k == "SECRET"));
+        assert!(envs.contains(&("MALLOC_CONF", "abort_conf:true")));
+        assert!(envs.contains(&(APPLY_CONFIG_GUARD_ENVIRONMENT_VARIABLE, "1")));
+    }
+
+    #[cfg(feature = "jemalloc-ctl")]
+    #[test]
+    fn test_configure_runtime() {
+        configure_runtime(&Jemalloc::default()).unwrap();
+        if !is_background_thread_supported() {
+            return;
+        }
+        let config = Jemalloc {
+            background_thread: true,
+            max_background_threads: Some(1),
+            ..Default::default()
+        };
+        configure_runtime(&config).unwrap();
+        // SAFETY: these are the documented types
+        unsafe {
+            assert!(ctl::read::<bool>("background_thread").unwrap());
+            assert_eq!(ctl::read::<usize>("max_background_threads").unwrap(), 1);
+        }
+    }
 }
@@ -0,0 +1,187 @@
+//! Jemalloc memory statistics as metrics.
+//!
+//! Following gauges, in bytes, are exported
+//! * `jemalloc_allocated_bytes`
+//! * `jemalloc_active_bytes`
+//! * `jemalloc_resident_bytes`
+//! * `jemalloc_metadata_bytes`
+//! * `jemalloc_mapped_bytes`
+//! * `jemalloc_retained_bytes`
+//!
+//! Raw data is read by a function generated with
+//! [`generate_read_jemalloc_raw_data`](crate::generate_read_jemalloc_raw_data).
+//!
+//! ```ignore
+//! caco3_web::generate_read_jemalloc_raw_data!(fn read_jemalloc_raw_data);
+//!
+//! // through the metrics facade
+//! jemalloc::metrics::register_collector(read_jemalloc_raw_data, Duration::from_secs(15));
+//! // or without it
+//! let app = Router::new().route("/metrics/jemalloc", jemalloc::metrics::handler(read_jemalloc_raw_data));
+//! ```
+use std::fmt::Write;
+#[cfg(feature = "metrics")]
+use std::time::Duration;
+
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, MethodRouter};
+
+use crate::jemalloc::info::JemallocRawData;
+
+pub const JEMALLOC_ALLOCATED_BYTES: &str = "jemalloc_allocated_bytes";
+pub const JEMALLOC_ACTIVE_BYTES: &str = "jemalloc_active_bytes";
+pub const JEMALLOC_RESIDENT_BYTES: &str = "jemalloc_resident_bytes";
+pub const JEMALLOC_METADATA_BYTES: &str = "jemalloc_metadata_bytes";
+pub const JEMALLOC_MAPPED_BYTES: &str = "jemalloc_mapped_bytes";
+pub const JEMALLOC_RETAINED_BYTES: &str = "jemalloc_retained_bytes";
+
+const PROMETHEUS_TEXT_FORMAT: HeaderValue =
+    HeaderValue::from_static("text/plain; version=0.0.4; charset=utf-8");
+
+/// Name and help text of each gauge, in the same order as [`values`].
+const GAUGES: [(&str, &str); 6] = [
+    (
+        JEMALLOC_ALLOCATED_BYTES,
+        "Total number of bytes allocated by the application.",
+    ),
+    (
+        JEMALLOC_ACTIVE_BYTES,
+        "Total number of bytes in active pages allocated by the application.",
+    ),
+    (
+        JEMALLOC_RESIDENT_BYTES,
+        "Total number of bytes in physically resident data pages mapped by the allocator.",
+    ),
+    (
+        JEMALLOC_METADATA_BYTES,
+        "Total number of bytes dedicated to allocator metadata.",
+    ),
+    (
+        JEMALLOC_MAPPED_BYTES,
+        "Total number of bytes in active extents mapped by the allocator.",
+    ),
+    (
+        JEMALLOC_RETAINED_BYTES,
+        "Total number of bytes in virtual memory mappings retained by the allocator.",
+    ),
+];
+
+fn values(raw: &JemallocRawData) -> [usize; 6] {
+    [
+        raw.allocated_bytes,
+        raw.active_bytes,
+        raw.resident_bytes,
+        raw.metadata_bytes,
+        raw.mapped_bytes,
+        raw.retained_bytes,
+    ]
+}
+
+/// Render raw data in Prometheus exposition format.
+pub fn render(raw: &JemallocRawData) -> String {
+    let mut buf = String::with_capacity(1024);
+    for ((name, help), value) in GAUGES.into_iter().zip(values(raw)) {
+        writeln!(buf, "# HELP {name} {help}").unwrap();
+        writeln!(buf, "# TYPE {name} gauge").unwrap();
+        writeln!(buf, "{name} {value}").unwrap();
+    }
+    buf
+}
+
+/// Axum handler rendering jemalloc statistics in Prometheus exposition format,
+/// without going through the [`metrics`](https://docs.rs/metrics) facade.
+///
+/// Respond `503 Service Unavailable` if statistics can't be read.
+pub fn handler<S, F>(read: F) -> MethodRouter<S>
+where
+    S: Clone + Send + Sync + 'static,
+    F: Fn() -> Option<JemallocRawData> + Clone + Send + Sync + 'static,
+{
+    get(move || async move {
+        match read() {
+            Some(raw) => Response::builder()
+                .header(header::CONTENT_TYPE, PROMETHEUS_TEXT_FORMAT)
+                .body(render(&raw).into())
+                .unwrap(),
+            None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+        }
+    })
+}
+
+/// Record raw data to gauges of the [`metrics`] facade.
+#[cfg(feature = "metrics")]
+pub fn record(raw: &JemallocRawData) {
+    for ((name, _), value) in GAUGES.into_iter().zip(values(raw)) {
+        metrics::gauge!(name).set(value as f64);
+    }
+}
+
+/// Spawn a task that reads statistics every `interval` and records them with [`record`].
+///
+/// Must be called within a tokio runtime, abort returned handle to stop the collector.
+#[cfg(feature = "metrics")]
+pub fn register_collector<F>(read: F, interval: Duration) -> tokio::task::JoinHandle<()>
+where
+    F: Fn() -> Option<JemallocRawData> + Send + 'static,
+{
+    for (name, help) in GAUGES {
+        metrics::describe_gauge!(name, metrics::Unit::Bytes, help);
+    }
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            match read() {
+                Some(raw) => record(&raw),
+                None => tracing::warn!("jemalloc: failed to read statistics"),
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::Request;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn read() -> Option<JemallocRawData> {
+        Some(JemallocRawData {
+            active_bytes: 2048,
+            allocated_bytes: 1024,
+            mapped_bytes: 8192,
+            metadata_bytes: 256,
+            resident_bytes: 4096,
+            retained_bytes: 0,
+            background_thread: None,
+            number_of_arenas: 4,
+        })
+    }
+
+    #[test]
+    fn test_render() {
+        let text = render(&read().unwrap());
+        assert!(
+            text.contains("# TYPE jemalloc_allocated_bytes gauge\njemalloc_allocated_bytes 1024\n")
+        );
+        assert!(text.contains("\njemalloc_resident_bytes 4096\n"));
+    }
+
+    #[tokio::test]
+    async fn test_handler() {
+        let app: Router = Router::new()
+            .route("/ok", handler(read))
+            .route("/unavailable", handler(|| None));
+        let req = Request::get("/ok").body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.headers()[header::CONTENT_TYPE], PROMETHEUS_TEXT_FORMAT);
+        let req = Request::get("/unavailable").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+}
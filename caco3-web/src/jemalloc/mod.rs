@@ -1,4 +1,17 @@
+#[cfg(feature = "jemalloc-ctl")]
+mod ctl;
+mod debug;
 pub mod info;
-mod init;
+pub mod init;
+pub mod metrics;
+pub mod pressure;
+pub mod trend;
+#[cfg(feature = "jemalloc-ctl")]
+pub mod tuning;
+mod validate;
 
+#[cfg(feature = "jemalloc-ctl")]
+pub use ctl::MallctlError;
+pub use debug::{router, JemallocReader};
 pub use init::*;
+pub use validate::ValidateError;
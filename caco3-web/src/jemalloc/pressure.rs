@@ -0,0 +1,154 @@
+//! React to memory pressure.
+//!
+//! ```ignore
+//! let mut monitor = Monitor::new();
+//! monitor.on_pressure(Metric::Resident, 2 << 30, move |event| {
+//!     let cache = cache.clone();
+//!     async move {
+//!         warn!("memory pressure: {event:?}");
+//!         cache.clear();
+//!     }
+//! });
+//! let tracker = StatsTracker::new(capacity).monitor(monitor);
+//! tracker.spawn(read_jemalloc_raw_data, Duration::from_secs(10));
+//! ```
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use futures_core::future::BoxFuture;
+use serde::Serialize;
+
+use crate::jemalloc::trend::Sample;
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Active,
+    Resident,
+}
+
+impl Metric {
+    fn value(self, sample: &Sample) -> usize {
+        match self {
+            Metric::Active => sample.active_bytes,
+            Metric::Resident => sample.resident_bytes,
+        }
+    }
+}
+
+/// A threshold is crossed.
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct PressureEvent {
+    pub metric: Metric,
+    pub threshold_bytes: usize,
+    pub current_bytes: usize,
+}
+
+type Callback = Box<dyn Fn(PressureEvent) -> BoxFuture<'static, ()> + Send + Sync>;
+
+struct Watch {
+    metric: Metric,
+    threshold_bytes: usize,
+    above: AtomicBool,
+    callback: Callback,
+}
+
+/// Invoke callbacks when memory usage crosses thresholds.
+///
+/// A callback is invoked once when its threshold is crossed upward,
+/// and again only after usage goes below the threshold and crosses it again.
+#[derive(Default)]
+pub struct Monitor {
+    watches: Vec<Watch>,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Register `callback` invoked when `metric` crosses `threshold_bytes`.
+    pub fn on_pressure<F, Fut>(
+        &mut self,
+        metric: Metric,
+        threshold_bytes: usize,
+        callback: F,
+    ) -> &mut Self
+    where
+        F: Fn(PressureEvent) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        self.watches.push(Watch {
+            metric,
+            threshold_bytes,
+            above: AtomicBool::new(false),
+            callback: Box::new(move |event| Box::pin(callback(event))),
+        });
+        self
+    }
+
+    /// Check `sample` against thresholds, and invoke callbacks of crossed ones in registration order.
+    pub async fn observe(&self, sample: &Sample) {
+        for watch in &self.watches {
+            let current_bytes = watch.metric.value(sample);
+            let above = current_bytes >= watch.threshold_bytes;
+            let was_above = watch.above.swap(above, Ordering::Relaxed);
+            if above && !was_above {
+                let event = PressureEvent {
+                    metric: watch.metric,
+                    threshold_bytes: watch.threshold_bytes,
+                    current_bytes,
+                };
+                (watch.callback)(event).await;
+            }
+        }
+    }
+
+    /// Returns `true` if any threshold is currently crossed.
+    pub fn is_under_pressure(&self) -> bool {
+        self.watches.iter().any(|w| w.above.load(Ordering::Relaxed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+    use std::time::Instant;
+
+    use super::*;
+
+    fn sample(resident_bytes: usize) -> Sample {
+        Sample {
+            at: Instant::now(),
+            allocated_bytes: 0,
+            active_bytes: 0,
+            resident_bytes,
+            metadata_bytes: 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_observe() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut monitor = Monitor::new();
+        let counter = calls.clone();
+        monitor.on_pressure(Metric::Resident, 100, move |event| {
+            assert_eq!(event.current_bytes, 150);
+            counter.fetch_add(1, Ordering::SeqCst);
+            async {}
+        });
+
+        monitor.observe(&sample(50)).await;
+        assert!(!monitor.is_under_pressure());
+        monitor.observe(&sample(150)).await;
+        assert!(monitor.is_under_pressure());
+        // still above, not invoked again
+        monitor.observe(&sample(150)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        monitor.observe(&sample(50)).await;
+        monitor.observe(&sample(150)).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,264 @@
+//! Track growth of jemalloc memory usage over time, to spot slow leaks.
+//!
+//! ```ignore
+//! caco3_web::generate_read_jemalloc_raw_data!(fn read_jemalloc_raw_data);
+//!
+//! let tracker = StatsTracker::new(NonZeroUsize::new(720).unwrap())
+//!     .window(Duration::from_secs(5 * 60))
+//!     .window(Duration::from_secs(60 * 60));
+//! tracker.spawn(read_jemalloc_raw_data, Duration::from_secs(10));
+//! let admin = Router::new()
+//!     .route("/debug/memory/trend", get(trend::render))
+//!     .with_state(tracker);
+//! ```
+use std::collections::VecDeque;
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use axum::extract::State;
+use serde::Serialize;
+use tracing::warn;
+
+use crate::jemalloc::info::JemallocRawData;
+use crate::jemalloc::pressure::Monitor;
+use crate::json::ApiJson;
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct Sample {
+    #[serde(skip)]
+    pub at: Instant,
+    pub allocated_bytes: usize,
+    pub active_bytes: usize,
+    pub resident_bytes: usize,
+    pub metadata_bytes: usize,
+}
+
+impl Sample {
+    pub fn new(raw: &JemallocRawData, at: Instant) -> Self {
+        Self {
+            at,
+            allocated_bytes: raw.allocated_bytes,
+            active_bytes: raw.active_bytes,
+            resident_bytes: raw.resident_bytes,
+            metadata_bytes: raw.metadata_bytes,
+        }
+    }
+}
+
+/// Growth rates over a window, in bytes per second.
+#[derive(Debug, Clone, Serialize)]
+pub struct WindowTrend {
+    pub window_secs: u64,
+    /// Time span actually covered by samples, less than window if there are not enough samples.
+    pub covered_secs: f64,
+    pub allocated_bytes_per_sec: f64,
+    pub active_bytes_per_sec: f64,
+    pub resident_bytes_per_sec: f64,
+    pub metadata_bytes_per_sec: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrendSnapshot {
+    pub samples: usize,
+    pub latest: Option<Sample>,
+    /// Trends of configured windows, a window is omitted if there are less than two samples in it.
+    pub windows: Vec<WindowTrend>,
+}
+
+struct Samples {
+    buffer: VecDeque<Sample>,
+    capacity: NonZeroUsize,
+}
+
+/// Keep recent samples of jemalloc statistics in a ring buffer and compute growth rates.
+///
+/// Cloning is cheap, clones share the same samples.
+#[derive(Clone)]
+pub struct StatsTracker {
+    samples: Arc<Mutex<Samples>>,
+    windows: Arc<[Duration]>,
+    monitor: Option<Arc<Monitor>>,
+}
+
+fn rate(new: usize, old: usize, secs: f64) -> f64 {
+    (new as f64 - old as f64) / secs
+}
+
+impl StatsTracker {
+    /// Create tracker keeping at most `capacity` samples.
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            samples: Arc::new(Mutex::new(Samples {
+                buffer: VecDeque::with_capacity(capacity.get()),
+                capacity,
+            })),
+            windows: Arc::from([]),
+            monitor: None,
+        }
+    }
+
+    /// Add a window to compute growth rate over.
+    pub fn window(mut self, window: Duration) -> Self {
+        let mut windows = self.windows.to_vec();
+        windows.push(window);
+        self.windows = windows.into();
+        self
+    }
+
+    /// Check each sample recorded by [`spawn`](Self::spawn) against thresholds of `monitor`.
+    pub fn monitor(mut self, monitor: Monitor) -> Self {
+        self.monitor = Some(Arc::new(monitor));
+        self
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Samples> {
+        // Poisoned state is not a problem for us.
+        self.samples.lock().unwrap_or_else(|x| x.into_inner())
+    }
+
+    /// Record a sample, the oldest sample is dropped if the buffer is full.
+    pub fn record(&self, sample: Sample) {
+        let mut samples = self.lock();
+        if samples.buffer.len() == samples.capacity.get() {
+            samples.buffer.pop_front();
+        }
+        samples.buffer.push_back(sample);
+    }
+
+    /// Spawn a task recording a sample read by `read` every `interval`.
+    ///
+    /// Pressure callbacks of [`monitor`](Self::monitor) are awaited in this task,
+    /// sampling is delayed until they finish.
+    ///
+    /// Must be called within a tokio runtime, abort returned handle to stop sampling.
+    pub fn spawn<F>(&self, read: F, interval: Duration) -> tokio::task::JoinHandle<()>
+    where
+        F: Fn() -> Option<JemallocRawData> + Send + 'static,
+    {
+        let tracker = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                ticker.tick().await;
+                let Some(raw) = read() else {
+                    warn!("jemalloc: failed to read statistics");
+                    continue;
+                };
+                let sample = Sample::new(&raw, Instant::now());
+                tracker.record(sample);
+                if let Some(monitor) = &tracker.monitor {
+                    monitor.observe(&sample).await;
+                }
+            }
+        })
+    }
+
+    pub fn snapshot(&self) -> TrendSnapshot {
+        let samples = self.lock();
+        let buffer = &samples.buffer;
+        let latest = buffer.back().copied();
+        let windows = latest
+            .map(|latest| {
+                self.windows
+                    .iter()
+                    .filter_map(|&window| {
+                        let oldest = buffer
+                            .iter()
+                            .find(|s| latest.at.duration_since(s.at) <= window)?;
+                        let secs = latest.at.duration_since(oldest.at).as_secs_f64();
+                        (secs > 0.0).then(|| WindowTrend {
+                            window_secs: window.as_secs(),
+                            covered_secs: secs,
+                            allocated_bytes_per_sec: rate(
+                                latest.allocated_bytes,
+                                oldest.allocated_bytes,
+                                secs,
+                            ),
+                            active_bytes_per_sec: rate(
+                                latest.active_bytes,
+                                oldest.active_bytes,
+                                secs,
+                            ),
+                            resident_bytes_per_sec: rate(
+                                latest.resident_bytes,
+                                oldest.resident_bytes,
+                                secs,
+                            ),
+                            metadata_bytes_per_sec: rate(
+                                latest.metadata_bytes,
+                                oldest.metadata_bytes,
+                                secs,
+                            ),
+                        })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        TrendSnapshot {
+            samples: buffer.len(),
+            latest,
+            windows,
+        }
+    }
+}
+
+/// Axum handler responding [`StatsTracker::snapshot`].
+pub async fn render(State(tracker): State<StatsTracker>) -> ApiJson<TrendSnapshot> {
+    ApiJson::ok(tracker.snapshot())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(at: Instant, allocated_bytes: usize) -> Sample {
+        Sample {
+            at,
+            allocated_bytes,
+            active_bytes: allocated_bytes,
+            resident_bytes: 2 * allocated_bytes,
+            metadata_bytes: 0,
+        }
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let tracker = StatsTracker::new(NonZeroUsize::new(3).unwrap())
+            .window(Duration::from_secs(10))
+            .window(Duration::from_secs(60));
+        assert!(tracker.snapshot().windows.is_empty());
+
+        let start = Instant::now();
+        for (secs, allocated) in [(0, 0), (10, 1000), (20, 1500), (30, 2500)] {
+            tracker.record(sample(start + Duration::from_secs(secs), allocated));
+        }
+        let snapshot = tracker.snapshot();
+        // the first sample is dropped
+        assert_eq!(snapshot.samples, 3);
+        assert_eq!(snapshot.latest.unwrap().allocated_bytes, 2500);
+
+        let short = &snapshot.windows[0];
+        assert_eq!(short.covered_secs, 10.0);
+        assert_eq!(short.allocated_bytes_per_sec, 100.0);
+        assert_eq!(short.resident_bytes_per_sec, 200.0);
+
+        let long = &snapshot.windows[1];
+        assert_eq!(long.covered_secs, 20.0);
+        assert_eq!(long.allocated_bytes_per_sec, 75.0);
+    }
+
+    #[test]
+    fn test_shrinking() {
+        let tracker =
+            StatsTracker::new(NonZeroUsize::new(8).unwrap()).window(Duration::from_secs(60));
+        let start = Instant::now();
+        tracker.record(sample(start, 1000));
+        tracker.record(sample(start + Duration::from_secs(10), 0));
+        assert_eq!(
+            tracker.snapshot().windows[0].allocated_bytes_per_sec,
+            -100.0
+        );
+    }
+}
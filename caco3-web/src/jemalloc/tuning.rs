@@ -0,0 +1,120 @@
+//! Runtime tuning of jemalloc arenas.
+//!
+//! Useful for returning memory to the OS after a large batch job.
+//!
+//! ```ignore
+//! run_batch_job().await;
+//! jemalloc::tuning::purge_all()?;
+//! ```
+use crate::jemalloc::ctl::{self, MallctlError};
+
+/// Arena index which refers to all arenas.
+pub const MALLCTL_ARENAS_ALL: u32 = 4096;
+// refers to destroyed arenas, purging it crashes when there is none
+const MALLCTL_ARENAS_DESTROYED: u32 = 4097;
+
+#[derive(Debug, Clone, Copy)]
+enum Decay {
+    Dirty,
+    Muzzy,
+}
+
+impl Decay {
+    fn as_str(self) -> &'static str {
+        match self {
+            Decay::Dirty => "dirty_decay_ms",
+            Decay::Muzzy => "muzzy_decay_ms",
+        }
+    }
+
+    fn read(self) -> Result<isize, MallctlError> {
+        // SAFETY: `arenas.{dirty,muzzy}_decay_ms` is ssize_t
+        unsafe { ctl::read(&format!("arenas.{}", self.as_str())) }
+    }
+
+    fn write(self, ms: isize) -> Result<(), MallctlError> {
+        // SAFETY: `arenas.{dirty,muzzy}_decay_ms` is ssize_t
+        unsafe { ctl::write(&format!("arenas.{}", self.as_str()), ms)? };
+        // the default above only applies to arenas created afterward
+        for index in 0..number_of_arenas()? {
+            let name = format!("arena.{index}.{}", self.as_str());
+            // SAFETY: `arena.<i>.{dirty,muzzy}_decay_ms` is ssize_t
+            match unsafe { ctl::write(&name, ms) } {
+                Ok(()) => {}
+                // arena is not initialized yet, it will use the new default
+                Err(err) if err.is_not_found() => {}
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn number_of_arenas() -> Result<u32, MallctlError> {
+    // SAFETY: `arenas.narenas` is unsigned
+    unsafe { ctl::read("arenas.narenas") }
+}
+
+/// Default `dirty_decay_ms` for arenas, `-1` means dirty pages are never purged.
+pub fn dirty_decay_ms() -> Result<isize, MallctlError> {
+    Decay::Dirty.read()
+}
+
+/// Set `dirty_decay_ms` of every arena, including arenas created afterward.
+///
+/// `0` purges dirty pages immediately, `-1` disables purging.
+pub fn set_dirty_decay_ms(ms: isize) -> Result<(), MallctlError> {
+    Decay::Dirty.write(ms)
+}
+
+/// Default `muzzy_decay_ms` for arenas, `-1` means muzzy pages are never purged.
+pub fn muzzy_decay_ms() -> Result<isize, MallctlError> {
+    Decay::Muzzy.read()
+}
+
+/// Set `muzzy_decay_ms` of every arena, including arenas created afterward.
+///
+/// `0` purges muzzy pages immediately, `-1` disables purging.
+pub fn set_muzzy_decay_ms(ms: isize) -> Result<(), MallctlError> {
+    Decay::Muzzy.write(ms)
+}
+
+/// Purge all unused dirty pages of arena `index`, see [`MALLCTL_ARENAS_ALL`].
+pub fn purge_arena(index: u32) -> Result<(), MallctlError> {
+    let name = format!("arena.{index}.purge");
+    if index == MALLCTL_ARENAS_DESTROYED {
+        return Err(MallctlError::invalid_name(&name));
+    }
+    ctl::call(&name)
+}
+
+/// Purge all unused dirty pages of all arenas.
+pub fn purge_all() -> Result<(), MallctlError> {
+    purge_arena(MALLCTL_ARENAS_ALL)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decay_ms() {
+        let dirty = dirty_decay_ms().unwrap();
+        set_dirty_decay_ms(1000).unwrap();
+        assert_eq!(dirty_decay_ms().unwrap(), 1000);
+        set_dirty_decay_ms(dirty).unwrap();
+
+        let muzzy = muzzy_decay_ms().unwrap();
+        set_muzzy_decay_ms(-1).unwrap();
+        assert_eq!(muzzy_decay_ms().unwrap(), -1);
+        set_muzzy_decay_ms(muzzy).unwrap();
+    }
+
+    #[test]
+    fn test_purge() {
+        purge_all().unwrap();
+        purge_arena(0).unwrap();
+        let narenas = number_of_arenas().unwrap();
+        assert!(purge_arena(narenas + 1).unwrap_err().is_not_found());
+    }
+}
@@ -0,0 +1,259 @@
+use thiserror::Error;
+use tracing::warn;
+
+use crate::jemalloc::Jemalloc;
+
+/// Error of [`Jemalloc::validate`].
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ValidateError {
+    #[error("jemalloc: {0} must be greater than zero")]
+    Zero(&'static str),
+    #[error("jemalloc: invalid extra_conf entry {0:?}, expect key:value")]
+    MalformedEntry(String),
+    #[error("jemalloc: unknown option {0:?}")]
+    UnknownOption(String),
+    #[error("jemalloc: invalid value {value:?} of option {key}, expect {expected}")]
+    InvalidValue {
+        key: String,
+        value: String,
+        expected: &'static str,
+    },
+    #[error("jemalloc: option {key} of extra_conf conflicts with field {field}")]
+    Conflict { key: String, field: &'static str },
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Kind {
+    Bool,
+    Unsigned,
+    /// Signed integer, `-1` usually disables the feature.
+    AtLeastMinusOne,
+    OneOf(&'static [&'static str]),
+    Any,
+}
+
+impl Kind {
+    fn expected(self) -> &'static str {
+        match self {
+            Kind::Bool => "true or false",
+            Kind::Unsigned => "an unsigned integer",
+            Kind::AtLeastMinusOne => "an integer greater than or equal to -1",
+            Kind::OneOf(_) => "one of documented values",
+            Kind::Any => "any value",
+        }
+    }
+
+    fn is_valid(self, value: &str) -> bool {
+        match self {
+            Kind::Bool => matches!(value, "true" | "false"),
+            Kind::Unsigned => value.parse::<u64>().is_ok(),
+            Kind::AtLeastMinusOne => value.parse::<i64>().is_ok_and(|v| v >= -1),
+            Kind::OneOf(values) => values.contains(&value),
+            Kind::Any => true,
+        }
+    }
+}
+
+/// Options of jemalloc 5.3, see `man jemalloc`.
+const KNOWN_OPTIONS: &[(&str, Kind)] = &[
+    ("abort", Kind::Bool),
+    ("abort_conf", Kind::Bool),
+    ("background_thread", Kind::Bool),
+    ("bin_shards", Kind::Any),
+    ("cache_oblivious", Kind::Bool),
+    ("confirm_conf", Kind::Bool),
+    ("dirty_decay_ms", Kind::AtLeastMinusOne),
+    ("debug_double_free_max_scan", Kind::Unsigned),
+    ("dss", Kind::OneOf(&["disabled", "primary", "secondary"])),
+    ("experimental_infallible_new", Kind::Bool),
+    ("hpa", Kind::Bool),
+    ("hpa_dirty_mult", Kind::Any),
+    ("hpa_hugification_threshold", Kind::Unsigned),
+    ("hpa_hugification_threshold_ratio", Kind::Any),
+    ("hpa_hugify_delay_ms", Kind::Unsigned),
+    ("hpa_min_purge_interval_ms", Kind::Unsigned),
+    ("hpa_sec_batch_fill_extra", Kind::Unsigned),
+    ("hpa_sec_bytes_after_flush", Kind::Unsigned),
+    ("hpa_sec_max_alloc", Kind::Unsigned),
+    ("hpa_sec_max_bytes", Kind::Unsigned),
+    ("hpa_sec_nshards", Kind::Unsigned),
+    ("hpa_slab_max_alloc", Kind::Unsigned),
+    ("junk", Kind::OneOf(&["true", "false", "alloc", "free"])),
+    ("lg_extent_max_active_fit", Kind::Unsigned),
+    ("lg_prof_interval", Kind::AtLeastMinusOne),
+    ("lg_prof_sample", Kind::Unsigned),
+    ("lg_tcache_flush_large_div", Kind::Unsigned),
+    ("lg_tcache_flush_small_div", Kind::Unsigned),
+    ("lg_tcache_max", Kind::Unsigned),
+    ("lg_tcache_nslots_mul", Kind::Any),
+    ("max_background_threads", Kind::Unsigned),
+    ("metadata_thp", Kind::OneOf(&["disabled", "auto", "always"])),
+    ("muzzy_decay_ms", Kind::AtLeastMinusOne),
+    ("mutex_max_spin", Kind::AtLeastMinusOne),
+    ("narenas", Kind::Unsigned),
+    ("oversize_threshold", Kind::Unsigned),
+    (
+        "percpu_arena",
+        Kind::OneOf(&["disabled", "percpu", "phycpu"]),
+    ),
+    ("prof", Kind::Bool),
+    ("prof_accum", Kind::Bool),
+    ("prof_active", Kind::Bool),
+    ("prof_final", Kind::Bool),
+    ("prof_gdump", Kind::Bool),
+    ("prof_leak", Kind::Bool),
+    ("prof_leak_error", Kind::Bool),
+    ("prof_prefix", Kind::Any),
+    ("prof_recent_alloc_max", Kind::AtLeastMinusOne),
+    ("prof_stats", Kind::Bool),
+    ("prof_sys_thread_name", Kind::Bool),
+    ("prof_thread_active_init", Kind::Bool),
+    ("retain", Kind::Bool),
+    ("san_guard_large", Kind::Unsigned),
+    ("san_guard_small", Kind::Unsigned),
+    ("slab_sizes", Kind::Any),
+    ("stats_interval", Kind::AtLeastMinusOne),
+    ("stats_interval_opts", Kind::Any),
+    ("stats_print", Kind::Bool),
+    ("stats_print_opts", Kind::Any),
+    ("tcache", Kind::Bool),
+    ("tcache_gc_delay_bytes", Kind::Unsigned),
+    ("tcache_gc_incr_bytes", Kind::Unsigned),
+    ("tcache_max", Kind::Unsigned),
+    ("tcache_nslots_large", Kind::Unsigned),
+    ("tcache_nslots_small_max", Kind::Unsigned),
+    ("tcache_nslots_small_min", Kind::Unsigned),
+    ("thp", Kind::OneOf(&["default", "always", "never"])),
+    ("trust_madvise", Kind::Bool),
+    ("utrace", Kind::Bool),
+    ("xmalloc", Kind::Bool),
+    ("zero", Kind::Bool),
+    ("zero_realloc", Kind::OneOf(&["alloc", "free", "abort"])),
+];
+
+impl Jemalloc {
+    /// Validate configuration, so mistakes are reported instead of aborting process at startup.
+    ///
+    /// Unknown keys of `extra_conf` are only logged, they may be options of other jemalloc versions,
+    /// use [`validate_strict`](Self::validate_strict) to reject them.
+    pub fn validate(&self) -> Result<(), ValidateError> {
+        self.validate_with(false)
+    }
+
+    /// Same as [`validate`](Self::validate), but unknown keys of `extra_conf` are rejected.
+    pub fn validate_strict(&self) -> Result<(), ValidateError> {
+        self.validate_with(true)
+    }
+
+    fn validate_with(&self, strict: bool) -> Result<(), ValidateError> {
+        if self.number_of_arenas == Some(0) {
+            return Err(ValidateError::Zero("number_of_arenas"));
+        }
+        if self.max_background_threads == Some(0) {
+            return Err(ValidateError::Zero("max_background_threads"));
+        }
+        let Some(extra_conf) = self.extra_conf.as_deref() else {
+            return Ok(());
+        };
+        for entry in extra_conf.split(',').filter(|e| !e.is_empty()) {
+            let Some((key, value)) = entry.split_once(':') else {
+                return Err(ValidateError::MalformedEntry(entry.to_owned()));
+            };
+            let Some(&(_, kind)) = KNOWN_OPTIONS.iter().find(|(name, _)| *name == key) else {
+                if strict {
+                    return Err(ValidateError::UnknownOption(key.to_owned()));
+                }
+                warn!("jemalloc: unknown option {key:?} of extra_conf");
+                continue;
+            };
+            if !kind.is_valid(value) {
+                return Err(ValidateError::InvalidValue {
+                    key: key.to_owned(),
+                    value: value.to_owned(),
+                    expected: kind.expected(),
+                });
+            }
+            let field = match key {
+                "narenas" if self.number_of_arenas.is_some() => "number_of_arenas",
+                "max_background_threads" if self.max_background_threads.is_some() => {
+                    "max_background_threads"
+                }
+                "background_thread" if self.background_thread => "background_thread",
+                _ => continue,
+            };
+            return Err(ValidateError::Conflict {
+                key: key.to_owned(),
+                field,
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_extra_conf(extra_conf: &str) -> Jemalloc {
+        Jemalloc {
+            extra_conf: Some(extra_conf.to_owned()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        assert_eq!(Jemalloc::default().validate(), Ok(()));
+        let valid = with_extra_conf("tcache:false,dirty_decay_ms:-1,thp:never,prof_prefix:/tmp/x");
+        assert_eq!(valid.validate(), Ok(()));
+
+        let config = Jemalloc {
+            number_of_arenas: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ValidateError::Zero("number_of_arenas"))
+        );
+        assert_eq!(with_extra_conf("tcach:false").validate(), Ok(()));
+        assert_eq!(
+            with_extra_conf("tcach:false").validate_strict(),
+            Err(ValidateError::UnknownOption("tcach".to_owned()))
+        );
+        let valid = with_extra_conf("hpa:true,prof_stats:false,slab_sizes:1-4096:1|8192-8192:4");
+        assert_eq!(valid.validate_strict(), Ok(()));
+        assert_eq!(
+            with_extra_conf("tcache").validate(),
+            Err(ValidateError::MalformedEntry("tcache".to_owned()))
+        );
+        assert!(matches!(
+            with_extra_conf("muzzy_decay_ms:-2").validate(),
+            Err(ValidateError::InvalidValue { .. })
+        ));
+
+        let config = Jemalloc {
+            number_of_arenas: Some(4),
+            extra_conf: Some("narenas:8".to_owned()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.validate(),
+            Err(ValidateError::Conflict { .. })
+        ));
+
+        assert_eq!(with_extra_conf("background_thread:true").validate(), Ok(()));
+        let config = Jemalloc {
+            background_thread: true,
+            extra_conf: Some("background_thread:false".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.validate(),
+            Err(ValidateError::Conflict {
+                key: "background_thread".to_owned(),
+                field: "background_thread",
+            })
+        );
+    }
+}
@@ -1,12 +1,16 @@
 use std::borrow::Cow;
 use std::marker::PhantomData;
 
+use axum::response::{IntoResponse, Response};
+use axum::Json;
 use serde::{Serialize, Serializer};
 
+use crate::sql::page::Page;
+
 type StrCow = Cow<'static, str>;
 
 const DEFAULT_SUCCESS_CODE: &str = "0";
-const DEFAULT_ERROR_CODE: &str = "-1";
+pub(crate) const DEFAULT_ERROR_CODE: &str = "-1";
 const DEFAULT_ERROR_MESSAGE: &str = "Internal server error";
 
 #[derive(Default)]
@@ -93,6 +97,13 @@ impl<T: Serialize> ApiJson<T> {
     }
 }
 
+impl<T: Serialize> ApiJson<Page<T>> {
+    /// Response of list endpoint, see [`page`](crate::sql::page).
+    pub fn page(page: Page<T>) -> Self {
+        Self::ok(page)
+    }
+}
+
 impl ApiJson<()> {
     /// Convenience method to build error without specifying generic type parameter
     pub fn unit_error_builder() -> ApiJsonErrorBuilder<()> {
@@ -123,6 +134,12 @@ impl<T: Serialize> Serialize for ApiJson<T> {
     }
 }
 
+impl<T: Serialize> IntoResponse for ApiJson<T> {
+    fn into_response(self) -> Response {
+        Json(self).into_response()
+    }
+}
+
 #[derive(Serialize)]
 struct ApiJsonSerializable<'a, T> {
     code: &'a str,
@@ -209,6 +226,23 @@ mod tests {
         assert_eq!(actual, expect);
     }
 
+    #[test]
+    fn test_page() {
+        let page = Page {
+            items: vec![1, 2],
+            next_cursor: Some(crate::sql::page::Cursor::After(2)),
+        };
+        let actual = serde_json::to_value(ApiJson::page(page)).unwrap();
+        let expect = json!({
+            "code": DEFAULT_SUCCESS_CODE,
+            "data": {
+                "items": [1, 2],
+                "next_cursor": "k2",
+            },
+        });
+        assert_eq!(actual, expect);
+    }
+
     #[test]
     fn test_no_content() {
         let json = ApiJson::no_content();
@@ -4,10 +4,13 @@ pub mod _macro_support;
 pub mod di;
 pub mod figment;
 pub mod future;
+pub mod health;
 pub mod jemalloc;
 pub mod json;
 pub mod macros;
+pub mod memory;
 pub mod middleware;
+pub mod retry;
 pub mod sql;
 
 #[doc(hidden)]
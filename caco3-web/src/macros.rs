@@ -18,11 +18,70 @@
 /// }
 /// ```
 ///
+/// Options to leave measurement enabled in hot paths, given before tag in any order
+/// * `level = INFO` logs at given [`Level`](tracing::Level) instead of debug.
+/// * `slow = Duration::from_millis(100)` logs only if expression takes at least given duration.
+///
+/// ```ignore
+/// let rows = measure_time!(level = WARN, slow = Duration::from_millis(100), "Load rows", load().await);
+/// ```
+///
+/// Use `span = field` to record elapsed time into a field of current span instead of logging,
+/// the field must be declared when span is created.
+///
+/// ```ignore
+/// #[tracing::instrument(fields(load_time = tracing::field::Empty))]
+/// async fn handle() {
+///     let rows = measure_time!(span = load_time, load().await);
+/// }
+/// ```
+///
 /// NOTE:
 /// * Use `;` as a unit separator cause rustfmt at call site not working properly.
 /// * `$tag` can be anything that implement `std::fmt::Display`.
 #[macro_export]
 macro_rules! measure_time {
+    // Record elapsed time into a span field
+    (span = $field:ident, $expr:expr) => {
+        {
+            let start = ::std::time::Instant::now();
+            let value = $expr;
+            $crate::re::tracing::Span::current().record(
+                ::core::stringify!($field),
+                $crate::re::tracing::field::display(
+                    $crate::_macro_support::AutoUnitDuration::from(start),
+                ),
+            );
+            value
+        }
+    };
+    // Collect options
+    (level = $($rest:tt)+) => { $crate::measure_time!(@options [DEBUG] [] level = $($rest)+) };
+    (slow = $($rest:tt)+) => { $crate::measure_time!(@options [DEBUG] [] slow = $($rest)+) };
+    (@options [$level:ident] [$($slow:expr)?] level = $new_level:ident, $($rest:tt)+) => {
+        $crate::measure_time!(@options [$new_level] [$($slow)?] $($rest)+)
+    };
+    (@options [$level:ident] [$($slow:expr)?] slow = $new_slow:expr, $($rest:tt)+) => {
+        $crate::measure_time!(@options [$level] [$new_slow] $($rest)+)
+    };
+    (@options [$level:ident] [$($slow:expr)?] $tag:expr, $expr:expr) => {
+        {
+            let start = ::std::time::Instant::now();
+            let value = $expr;
+            let elapsed = start.elapsed();
+            if $crate::measure_time!(@is_slow elapsed $(, $slow)?) {
+                $crate::re::tracing::event!(
+                    $crate::re::tracing::Level::$level,
+                    "{} in {}",
+                    $tag,
+                    $crate::_macro_support::AutoUnitDuration::from(elapsed),
+                );
+            }
+            value
+        }
+    };
+    (@is_slow $elapsed:ident) => { true };
+    (@is_slow $elapsed:ident, $slow:expr) => { $elapsed >= $slow };
     // Custom unit implementation
     (@unit [$unit:literal, $as_unit:ident]; $tag:expr, $expr:expr) => {
         {
@@ -152,6 +211,145 @@ macro_rules! measure_time {
 ///     }
 /// }
 /// ```
+///
+/// Other variants
+/// * `fetch_one_scalar`, `fetch_optional_scalar` and `fetch_all_scalar` return single column.
+/// * `execute` returns query result.
+/// * `execute_rows` returns number of affected rows as `u64`.
+/// * `upsert` and `insert_returning` return the row of `INSERT ... ON CONFLICT ... RETURNING` and
+///   `INSERT ... RETURNING` statements, they are `fetch_one` queries.
+/// * `fetch_page` returns [`Page`](crate::sql::page::Page), generated method takes
+///   [`PageRequest`](crate::sql::page::PageRequest) after executor.
+///   * `fetch_page(SQL) -> Account` appends `LIMIT` and `OFFSET` to SQL, which should have an
+///     `ORDER BY` clause for stable pages.
+///   * `fetch_page(SQL, key = id) -> Account` is keyset pagination on integer column `id`,
+///     SQL is wrapped as `SELECT * FROM (SQL) AS page WHERE id > ? ORDER BY id LIMIT ?`,
+///     add `desc`, e.g. `key = id desc`, for descending order. Column and field of `Account`
+///     must have the same name, and the column must be selected by SQL.
+///   * SQL must not have its own `LIMIT` or `OFFSET`, otherwise the query fails with `InvalidArgument`.
+///
+/// ```ignore
+/// impl NewAccount {
+///     // -- source --
+///     postgres_query! {
+///         upsert("insert into accounts (email, name) values ($1, $2)
+///                 on conflict (email) do update set name = excluded.name
+///                 returning *") -> Account,
+///         pub async fn upsert {
+///             email,
+///             name,
+///         }
+///     }
+///     // -- expanded --
+///     pub async fn upsert<'c, E>(&self, executor: E) -> sqlx::Result<Account>
+///     where
+///         E: sqlx::Executor<'c, Database = sqlx::Postgres>,
+///     {
+///         sqlx::query_as(SQL)
+///             .bind(&self.email)
+///             .bind(&self.name)
+///             .fetch_one(executor)
+///             .await
+///     }
+///
+///     // `insert_returning` generates the same signature
+///     postgres_query! {
+///         insert_returning("insert into accounts (email, name) values ($1, $2) returning *") -> Account,
+///         pub async fn insert {
+///             email,
+///             name,
+///         }
+///     }
+/// }
+/// ```
+///
+/// Bind entries of named struct are bound in order, an entry can be
+/// * a field, e.g. `id`, bound by reference.
+/// * a renamed expression, e.g. `user_id = self.user.id`, name is only for readability.
+/// * an expression, e.g. `lower(&self.email)` or `true`.
+///
+/// Expressions are bound by value. Because of macro hygiene, an expression can refer to `self`
+/// only if receiver is written explicitly.
+///
+/// ```ignore
+/// postgres_query! {
+///     fetch_optional("select * from accounts where owner_id = $1 and email = $2 and active = $3") -> Account,
+///     pub async fn find_active(&self) {
+///         owner_id = self.owner.id,
+///         lower(&self.email),
+///         true,
+///     }
+/// }
+/// ```
+///
+/// If SQL is a string literal, number of placeholders is checked against number of bind entries
+/// at compile time. SQL given as a constant or other expressions is not checked.
+///
+/// SQL can use named parameters, e.g. `:owner_id`, if it is prefixed with `named`, see
+/// [`sql::named`](crate::sql::named). Parameters are bound from entries with the same name,
+/// an entry can be a field or a renamed expression, and may be bound more than once.
+///
+/// ```ignore
+/// postgres_query! {
+///     fetch_all(named "select * from accounts where owner_id = :owner_id or created_by = :owner_id
+///                      and email = :email") -> Account,
+///     pub async fn list_owned(&self) {
+///         owner_id,
+///         email = lower(&self.email),
+///     }
+/// }
+/// ```
+///
+/// Add `#[traced]` to run query in a span tagged with path of generated method and log elapsed time,
+/// queries slower than 1 second are logged at warn level. Use `#[traced(slow_ms = 200)]` to
+/// change the threshold.
+///
+/// ```ignore
+/// postgres_query! {
+///     fetch_all(FindAccount::FETCH_SQL) -> Account,
+///     #[traced(slow_ms = 200)]
+///     pub async fn list {
+///         id,
+///         active,
+///     }
+/// }
+/// ```
+///
+/// Rows can be soft deleted by setting a timestamp column, `deleted_at` by default.
+/// * `soft_delete("accounts", "id = $1")` sets `deleted_at` of matched rows to current timestamp.
+/// * `restore("accounts", "id = $1")` clears `deleted_at` of matched rows.
+/// * Both return number of affected rows, add `column = "removed_at"` to use other column.
+/// * Add `#[soft_delete]` or `#[soft_delete(column = "removed_at")]` to a `fetch_*` query to
+///   replace `{soft_delete}` in SQL with `deleted_at IS NULL`. SQL without the marker fails to
+///   compile if it is a literal, otherwise the query fails with `InvalidArgument`.
+///
+/// ```ignore
+/// postgres_query! {
+///     fetch_optional("select * from accounts where id = $1 and {soft_delete}") -> Account,
+///     #[soft_delete]
+///     pub async fn find { id }
+/// }
+///
+/// postgres_query! {
+///     soft_delete("accounts", "id = $1"),
+///     pub async fn delete { id }
+/// }
+/// ```
+///
+/// Add `#[cached(ttl = Duration::from_secs(60))]` to a `fetch_*` query to memoize its results
+/// in memory, keyed by pool and value of the struct, so the struct must implement `Hash`, `Eq`
+/// and `Clone` without borrowed fields, and result must implement `Clone`. At most 1024 results
+/// are kept, least recently used first to go, add `capacity = n` to change it.
+/// Errors are not cached. Cached methods only take a pool or `&ReadWritePools`, not a connection
+/// or a transaction, so they need `sqlx` feature. This is for small reference data e.g. countries
+/// or currencies, cached results are not invalidated by writes.
+///
+/// Elapsed time of every query is reported to [`sql::metrics`](crate::sql::metrics) hook if it is set.
+///
+/// Generated methods take any `sqlx::Executor`, e.g. a pool, a connection, a transaction or
+/// [`&ReadWritePools`](crate::sql::ReadWritePools) which runs `SELECT` queries on replica.
+/// Add `#[primary]` to run a `fetch_*` query on primary, it needs `sqlx` feature and takes
+/// a [`RouteExecutor`](crate::sql::RouteExecutor).
 #[macro_export]
 macro_rules! postgres_query {
     // Hide distracting implementation details from the generated rustdoc.
@@ -163,321 +361,1368 @@ macro_rules! postgres_query {
 #[doc(hidden)]
 #[macro_export]
 macro_rules! postgres_query_internal {
-    // internal rules
-    (
-        @query_impl
-        ($query_fn:ident, $execute_fn:ident -> $from_row:ty),
-        $sql:expr,
-        $(#[$fn_meta:meta])*
-        $fn_vis:vis async fn $fn_name:ident ($($field:tt),* $(,)?)
-    ) => {
-        $(#[$fn_meta])*
-        $fn_vis async fn $fn_name<'c, E>(&self, executor: E) -> ::sqlx::Result<$from_row>
-        where
-            E: ::sqlx::Executor<'c, Database = ::sqlx::Postgres>,
-        {
-            use ::std::sync::OnceLock;
-            use $crate::sql::SqlTrimBoxed;
-
-            // we choose this name to avoid shadowing outer SQL (if exist)
-            static __BOXED_QUERY__: OnceLock<Box<str>> = OnceLock::new();
-
-            ::sqlx::$query_fn(
-                    &**__BOXED_QUERY__.get_or_init(|| {
-                        $sql.sql_trim_boxed()
-                    })
-                )
-                $(.bind(&self.$field))*
-                .$execute_fn(executor)
-                .await
-        }
-    };
-    // support named struct
-    (
-        @query
-        ($query_fn:ident, $execute_fn:ident -> $from_row:ty),
-        $sql:expr,
-        $(#[$fn_meta:meta])*
-        $fn_vis:vis async fn $fn_name:ident {$($field:ident),* $(,)?}
-    ) => {
-        $crate::postgres_query_internal! {
-            @query_impl
-            ($query_fn, $execute_fn -> $from_row),
-            $sql,
-            $(#[$fn_meta])*
-            $fn_vis async fn $fn_name ($($field),*)
-        }
+    ($($body:tt)+) => {
+        $crate::sql_query_internal! { ::sqlx::Postgres, $($body)+ }
     };
-    // support tuple struct
-    (
-        @query
-        ($query_fn:ident, $execute_fn:ident -> $from_row:ty),
-        $sql:expr,
-        $(#[$fn_meta:meta])*
-        $fn_vis:vis async fn $fn_name:ident ($($field:tt),* $(,)?)
-    ) => {
-        $crate::postgres_query_internal! {
-            @query_impl
-            ($query_fn, $execute_fn -> $from_row),
-            $sql,
-            $(#[$fn_meta])*
-            $fn_vis async fn $fn_name ($($field),*)
-        }
+}
+
+#[macro_export]
+macro_rules! sqlite_query {
+    // Hide distracting implementation details from the generated rustdoc.
+    ($($body:tt)+) => {
+        $crate::sqlite_query_internal! {$($body)+}
     };
-    // get one row
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! sqlite_query_internal {
+    // get one entity
     (
-        fetch_one($sql:expr) -> $from_row:ty,
+        get($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::postgres_query_internal! {
-            @query
-            (query_as, fetch_one -> $from_row),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_one($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-    // get one row with single column
+    // get one entity (scalar)
     (
-        fetch_one_scalar($sql:expr) -> $from_row:ty,
+        get_scalar($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::postgres_query_internal! {
-            @query
-            (query_scalar, fetch_one -> $from_row),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_one_scalar($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-    // find one row
+    // find one entity
     (
-        fetch_optional($sql:expr) -> $from_row:ty,
+        find($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::postgres_query_internal! {
-            @query
-            (query_as, fetch_optional -> ::std::option::Option<$from_row>),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_optional($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-    // find one row with single column
+    // find one entity (scalar)
     (
-        fetch_optional_scalar($sql:expr) -> $from_row:ty,
+        find_scalar($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::postgres_query_internal! {
-            @query
-            (query_scalar, fetch_optional -> ::std::option::Option<$from_row>),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_optional_scalar($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
     // fetch all
     (
-        fetch_all($sql:expr) -> $from_row:ty,
+        list($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::postgres_query_internal! {
-            @query
-            (query_as, fetch_all -> ::std::vec::Vec<$from_row>),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_all($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-    // execute
+    // fetch all (scalar)
     (
-        execute($sql:expr),
+        list_scalar($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::postgres_query_internal! {
-            @query
-            (query, execute -> ::sqlx::postgres::PgQueryResult),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_all_scalar($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-}
-
-#[macro_export]
-macro_rules! sqlite_query {
-    // Hide distracting implementation details from the generated rustdoc.
-    ($($body:tt)+) => {
-        $crate::sqlite_query_internal! {$($body)+}
-    };
-}
-
-#[doc(hidden)]
-#[macro_export]
-macro_rules! sqlite_query_internal {
-    // internal rules
-    (
-        @query_impl
-        ($query_fn:ident, $execute_fn:ident -> $entity:ty),
-        $sql:expr,
-        $(#[$fn_meta:meta])*
-        $fn_vis:vis async fn $fn_name:ident ($($field:tt),* $(,)?)
-    ) => {
-        $(#[$fn_meta])*
-        $fn_vis async fn $fn_name<'c, E>(&self, executor: E) -> ::sqlx::Result<$entity>
-        where
-            E: ::sqlx::Executor<'c, Database = ::sqlx::Sqlite>,
-        {
-            use ::std::sync::OnceLock;
-            use $crate::sql::SqlTrimBoxed;
-
-            // we choose this name to avoid shadowing outer SQL (if exist)
-            static __BOXED_QUERY__: OnceLock<Box<str>> = OnceLock::new();
-            ::sqlx::$query_fn(&**__BOXED_QUERY__.get_or_init(|| {
-                        $sql.sql_trim_boxed()
-                    })
-                )
-                $(.bind(&self.$field))*
-                .$execute_fn(executor)
-                .await
-        }
-    };
-    // support named struct
-    (
-        @query
-        ($query_fn:ident, $execute_fn:ident -> $entity:ty),
-        $sql:expr,
-        $(#[$fn_meta:meta])*
-        $fn_vis:vis async fn $fn_name:ident {$($field:ident),* $(,)?}
-    ) => {
-        $crate::sqlite_query_internal! {
-            @query_impl
-            ($query_fn, $execute_fn -> $entity),
-            $sql,
-            $(#[$fn_meta])*
-            $fn_vis async fn $fn_name ($($field),*)
-        }
-    };
-    // support tuple struct
+    // fetch a page
     (
-        @query
-        ($query_fn:ident, $execute_fn:ident -> $entity:ty),
-        $sql:expr,
-        $(#[$fn_meta:meta])*
-        $fn_vis:vis async fn $fn_name:ident ($($field:tt),* $(,)?)
+        list_page($($sql:tt)*) -> $entity:ty,
+        $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query_impl
-            ($query_fn, $execute_fn -> $entity),
-            $sql,
-            $(#[$fn_meta])*
-            $fn_vis async fn $fn_name ($($field),*)
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            fetch_page($($sql)*) -> $entity,
+            $($fn_spec)*
         }
     };
-    // get one entity
+    // execute
     (
-        get($sql:expr) -> $entity:ty,
+        execute($($sql:tt)+),
         $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query
-            (query_as, fetch_one -> $entity),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            execute($($sql)+),
             $($fn_spec)*
         }
     };
-    // get one entity (scalar)
+    // execute and return number of affected rows
     (
-        get_scalar($sql:expr) -> $entity:ty,
+        execute_rows($($sql:tt)+),
         $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query
-            (query_scalar, fetch_one -> $entity),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            execute_rows($($sql)+),
             $($fn_spec)*
         }
     };
-    // find one entity
+    // upsert
     (
-        find($sql:expr) -> $entity:ty,
+        upsert($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query
-            (query_as, fetch_optional -> ::std::option::Option<$entity>),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            upsert($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-    // find one entity (scalar)
+    // insert and return inserted row
     (
-        find_scalar($sql:expr) -> $entity:ty,
+        insert_returning($($sql:tt)+) -> $entity:ty,
         $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query
-            (query_scalar, fetch_optional -> ::std::option::Option<$entity>),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            insert_returning($($sql)+) -> $entity,
             $($fn_spec)*
         }
     };
-    // fetch all
+    // soft delete
     (
-        list($sql:expr) -> $entity:ty,
+        soft_delete($($args:tt)+),
         $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query
-            (query_as, fetch_all -> ::std::vec::Vec<$entity>),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            soft_delete($($args)+),
             $($fn_spec)*
         }
     };
-    // execute
+    // restore soft deleted
     (
-        execute($sql:expr),
+        restore($($args:tt)+),
         $($fn_spec:tt)*
     ) => {
-        $crate::sqlite_query_internal! {
-            @query
-            (query, execute -> ::sqlx::sqlite::SqliteQueryResult),
-            $sql,
+        $crate::sql_query_internal! {
+            ::sqlx::Sqlite,
+            restore($($args)+),
             $($fn_spec)*
         }
     };
 }
 
-/// Generate `builder()` method which return builder with default values.
+/// Generate database access layer method on given struct for MySQL and MariaDB.
+///
+/// Same as [`postgres_query!`] but for [`sqlx::MySql`](https://docs.rs/sqlx/latest/sqlx/struct.MySql.html).
+///
+/// ```ignore
+/// impl FindAccount {
+///     mysql_query! {
+///         fetch_optional("select * from accounts where id = ? and active = ?") -> Account,
+///         pub async fn find {
+///             id,
+///             active,
+///         }
+///     }
+/// }
+/// ```
 #[macro_export]
-macro_rules! with_builder {
-    ($builder:ty => $ty:ty) => {
-        impl $ty {
-            pub fn builder() -> $builder {
-                <$builder as ::core::default::Default>::default()
-            }
-        }
+macro_rules! mysql_query {
+    // Hide distracting implementation details from the generated rustdoc.
+    ($($body:tt)+) => {
+        $crate::mysql_query_internal! {$($body)+}
     };
 }
 
-/// Generating function used for reading jemalloc stats.
-///
-/// Unfortunately we couldn't re-export jemalloc struct so we hard coded its path here.
+#[doc(hidden)]
 #[macro_export]
-macro_rules! generate_read_jemalloc_raw_data {
-    ($vis:vis fn $name:ident) => {
-        $vis fn $name() -> ::core::option::Option<$crate::jemalloc::info::JemallocRawData> {
-            use ::std::prelude::*;
-            use ::std::sync::OnceLock;
-
-            use ::tikv_jemalloc_ctl::{
-                arenas, background_thread, background_thread_mib, epoch, epoch_mib, max_background_threads,
-                max_background_threads_mib, stats,
-            };
-
-            use $crate::jemalloc::info::{JemallocRawData, BackgroundThread};
+macro_rules! mysql_query_internal {
+    ($($body:tt)+) => {
+        $crate::sql_query_internal! { ::sqlx::MySql, $($body)+ }
+    };
+}
 
-            struct Mib {
-                epoch: epoch_mib,
-                max_background_threads: max_background_threads_mib,
-                background_thread: background_thread_mib,
-                narenas: arenas::narenas_mib,
-                active: stats::active_mib,
-                allocated: stats::allocated_mib,
+/// Generate database access layer method on given struct for any database driver.
+///
+/// Same as [`postgres_query!`] but database is given as the first argument,
+/// so a shared repository crate can target different databases with one macro invocation,
+/// e.g. PostgreSQL in production and SQLite in tests.
+///
+/// Placeholder syntax is not translated, SQL must be valid for every targeted database.
+///
+/// ```ignore
+/// #[cfg(not(test))]
+/// type Db = sqlx::Postgres;
+/// #[cfg(test)]
+/// type Db = sqlx::Sqlite;
+///
+/// impl FindAccount {
+///     sql_query! {
+///         Db,
+///         fetch_optional("select * from accounts where id = $1") -> Account,
+///         pub async fn find {
+///             id,
+///         }
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! sql_query {
+    // Hide distracting implementation details from the generated rustdoc.
+    ($($body:tt)+) => {
+        $crate::sql_query_internal! {$($body)+}
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! sql_query_internal {
+    // internal rules
+    (
+        @query_impl
+        $db:ty,
+        (@page [$($key:ident $($order:ident)?)?] -> $entity:ty),
+        $sql:expr,
+        [$($check:tt)*],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident ($self:ident) [$($bind:expr,)*]
+    ) => {
+        $crate::sql_query_internal! {
+            @executor_fn [$($route)*] [] $db,
+            {
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name<'c, E>(
+                    &$self,
+                    executor: E,
+                    page: &$crate::sql::page::PageRequest,
+                ) -> ::sqlx::Result<$crate::sql::page::Page<$entity>>
+            }
+            {
+                use ::std::sync::OnceLock;
+                // unused by offset pagination without bind entries
+                #[allow(unused_imports)]
+                use ::sqlx::Arguments as _;
+                use $crate::sql::SqlTrimBoxed;
+
+                // we choose this name to avoid shadowing outer SQL (if exist)
+                static __BOXED_QUERY__: OnceLock<::core::result::Result<Box<str>, String>> = OnceLock::new();
+
+                $crate::sql_query_internal!(@check [$($check)*] $db, $sql, [$($bind,)*]);
+                $crate::sql_query_internal!(@check_filter [$($check)*] [$($filter)*] $sql);
+                $crate::sql_query_internal!(@uncached [$($cache)*]);
+
+                let sql = $crate::sql_query_internal!(
+                    @init_sql __BOXED_QUERY__, [$($filter)*] $sql, $crate::sql::page::check_page_sql
+                );
+                #[allow(unused_mut)]
+                let mut arguments = <<$db as ::sqlx::Database>::Arguments<'_> as ::core::default::Default>::default();
+                $(arguments.add($bind).map_err(::sqlx::Error::Encode)?;)*
+                let query = $crate::sql_query_internal!(@page_sql [$($key $($order)?)?] page, sql, arguments);
+                let executor = $crate::sql_query_internal!(@route [$($route)*] executor);
+                let query = ::sqlx::query_as_with::<$db, $entity, _>(&query, arguments).fetch_all(executor);
+                let query = $crate::sql_query_internal!(@trace [$($trace)*] $fn_name, query);
+                let rows = $crate::sql_query_internal!(@observe $fn_name, query).await?;
+                ::core::result::Result::Ok($crate::sql_query_internal!(@page_result [$($key)?] page, rows))
+            }
+        }
+    };
+    (@page_sql [] $page:ident, $sql:ident, $arguments:ident) => {
+        $page
+            .offset_sql($sql)
+            .map_err(|e| ::sqlx::Error::InvalidArgument(e.to_string()))?
+    };
+    (@page_sql [$key:ident $($order:ident)?] $page:ident, $sql:ident, $arguments:ident) => {{
+        let descending = $crate::sql_query_internal!(@descending $($order)?);
+        let mut query = $page.keyset_select($sql);
+        let after = $page
+            .after_key()
+            .map_err(|e| ::sqlx::Error::InvalidArgument(e.to_string()))?;
+        if let ::core::option::Option::Some(after) = after {
+            $arguments.add(after).map_err(::sqlx::Error::Encode)?;
+            $page.push_keyset_filter(&mut query, ::core::stringify!($key), descending);
+            $arguments
+                .format_placeholder(&mut query)
+                .map_err(|e| ::sqlx::Error::Encode(e.into()))?;
+        }
+        $page.push_keyset_suffix(&mut query, ::core::stringify!($key), descending);
+        query
+    }};
+    (@descending) => {
+        false
+    };
+    (@descending asc) => {
+        false
+    };
+    (@descending desc) => {
+        true
+    };
+    (@page_result [] $page:ident, $rows:ident) => {
+        $page.offset_page($rows)
+    };
+    (@page_result [$key:ident $($order:ident)?] $page:ident, $rows:ident) => {
+        $page.keyset_page($rows, |row| ::core::convert::From::from(row.$key))
+    };
+    (
+        @query_impl
+        $db:ty,
+        ($query_fn:ident, $execute_fn:ident -> $from_row:ty $(=> $map:expr)?),
+        $sql:expr,
+        [$($check:tt)*],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident ($self:ident) [$($bind:expr,)*]
+    ) => {
+        $crate::sql_query_internal! {
+            @executor_fn [$($route)*] [$($cache)*] $db,
+            {
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name<'c, E>(&$self, executor: E) -> ::sqlx::Result<$from_row>
+            }
+            {
+                use ::std::sync::OnceLock;
+                use $crate::sql::SqlTrimBoxed;
+
+                // we choose this name to avoid shadowing outer SQL (if exist)
+                static __BOXED_QUERY__: OnceLock<::core::result::Result<Box<str>, String>> = OnceLock::new();
+
+                $crate::sql_query_internal!(@check [$($check)*] $db, $sql, [$($bind,)*]);
+                $crate::sql_query_internal!(@check_filter [$($check)*] [$($filter)*] $sql);
+
+                let executor = $crate::sql_query_internal!(@route [$($route)*] executor);
+                $crate::sql_query_internal!(@cached [$($cache)*] $execute_fn -> $from_row, $self, executor, {
+                    let sql = $crate::sql_query_internal!(@init_sql __BOXED_QUERY__, [$($filter)*] $sql);
+                    let query = ::sqlx::$query_fn(sql)
+                        $(.bind($bind))*
+                        .$execute_fn(executor);
+                    let query = $crate::sql_query_internal!(@trace [$($trace)*] $fn_name, query);
+                    $crate::sql_query_internal!(@observe $fn_name, query)
+                        .await
+                        $(.map($map))?
+                })
+            }
+        }
+    };
+    // memoize results of fetch queries
+    (@cached [] $execute_fn:ident -> $from_row:ty, $self:ident, $executor:ident, $query:block) => {
+        $query
+    };
+    (@cached [cached($($args:tt)*)] execute -> $from_row:ty, $self:ident, $executor:ident, $query:block) => {
+        ::core::compile_error!("`#[cached]` is only supported by fetch queries")
+    };
+    (
+        @cached [cached($ttl:expr, $($capacity:expr)?)]
+        $execute_fn:ident -> $from_row:ty, $self:ident, $executor:ident, $query:block
+    ) => {{
+        static __QUERY_CACHE__: OnceLock<$crate::_macro_support::QueryCache<$from_row>> =
+            OnceLock::new();
+
+        let cache = __QUERY_CACHE__.get_or_init(|| {
+            let capacity: usize = $crate::sql_query_internal!(@cache_capacity $($capacity)?);
+            $crate::_macro_support::QueryCache::new(capacity, $ttl)
+        });
+        let scope = $crate::_macro_support::CacheScope::cache_scope(&$executor);
+        let key = $crate::_macro_support::CacheKey::new(scope, $self);
+        match cache.get(&key) {
+            ::core::option::Option::Some(value) => ::core::result::Result::Ok(value),
+            ::core::option::Option::None => {
+                let result: ::sqlx::Result<$from_row> = $query;
+                if let ::core::result::Result::Ok(value) = &result {
+                    cache.insert(key, ::core::clone::Clone::clone(value));
+                }
+                result
+            }
+        }
+    }};
+    (@cache_capacity) => {
+        $crate::_macro_support::DEFAULT_QUERY_CACHE_CAPACITY
+    };
+    (@cache_capacity $capacity:expr) => {
+        $capacity
+    };
+    (@uncached []) => {};
+    (@uncached [$($cache:tt)+]) => {
+        ::core::compile_error!("`#[cached]` is only supported by fetch queries");
+    };
+    // prepare SQL once, return early if it is invalid
+    (@init_sql $cell:ident, [$($filter:tt)*] $sql:expr $(, $check:path)?) => {
+        match $cell.get_or_init(|| {
+            $crate::sql_query_internal!(@sql [$($filter)*] $sql) $(.and_then(|sql| $check(&sql)))?
+        }) {
+            ::core::result::Result::Ok(sql) => &**sql,
+            ::core::result::Result::Err(e) => {
+                return ::core::result::Result::Err(::sqlx::Error::InvalidArgument(
+                    ::core::clone::Clone::clone(e),
+                ));
+            }
+        }
+    };
+    // replace marker with filter of soft deleted rows
+    (@sql [] $sql:expr) => {
+        ::core::result::Result::<Box<str>, String>::Ok($sql.sql_trim_boxed())
+    };
+    (@sql [soft_delete($column:literal $($default:literal)?)] $sql:expr) => {
+        $crate::_macro_support::soft_delete_sql(&$sql.sql_trim_boxed(), $column)
+    };
+    (@check_filter [check] [soft_delete($($column:literal)+)] $sql:expr) => {
+        const _: () = $crate::_macro_support::check_soft_delete_marker($sql);
+    };
+    (@check_filter [$($check:tt)*] [$($filter:tt)*] $sql:expr) => {};
+    // executor bounds, plain `sqlx::Executor` unless `#[primary]` or `#[cached]` needs more
+    (@executor_fn [] [] $db:ty, {$($sig:tt)*} $body:block) => {
+        $($sig)*
+        where
+            E: ::sqlx::Executor<'c, Database = $db>,
+        $body
+    };
+    (@executor_fn [] [$($cache:tt)+] $db:ty, {$($sig:tt)*} $body:block) => {
+        $($sig)*
+        where
+            E: ::sqlx::Executor<'c, Database = $db> + $crate::_macro_support::CacheScope,
+        $body
+    };
+    (@executor_fn [primary] [] $db:ty, {$($sig:tt)*} $body:block) => {
+        $($sig)*
+        where
+            E: $crate::sql::RouteExecutor<'c, $db>,
+        $body
+    };
+    (@executor_fn [primary] [$($cache:tt)+] $db:ty, {$($sig:tt)*} $body:block) => {
+        $($sig)*
+        where
+            E: $crate::sql::RouteExecutor<'c, $db>,
+            <E as $crate::sql::RouteExecutor<'c, $db>>::Executor: $crate::_macro_support::CacheScope,
+        $body
+    };
+    // choose executor, `&ReadWritePools` runs queries on primary if they are marked `#[primary]`
+    (@route [primary] $executor:ident) => {
+        $crate::sql::RouteExecutor::primary($executor)
+    };
+    (@route [] $executor:ident) => {
+        $executor
+    };
+    // placeholders validation
+    (@check [] $db:ty, $sql:expr, [$($bind:expr,)*]) => {};
+    (@check [check] $db:ty, $sql:expr, [$($bind:expr,)*]) => {
+        const _: () = $crate::_macro_support::check_placeholders(
+            <$db as ::sqlx::Database>::NAME,
+            $sql,
+            0 $(+ $crate::sql_query_internal!(@one $bind))*,
+        );
+    };
+    (@one $bind:expr) => {
+        1
+    };
+    // instrumentation
+    (@observe $fn_name:ident, $query:expr) => {
+        $crate::_macro_support::observe_query(
+            ::core::concat!(::core::module_path!(), "::", ::core::stringify!($fn_name)),
+            $query,
+        )
+    };
+    (@trace [] $fn_name:ident, $query:expr) => {
+        $query
+    };
+    (@trace [traced] $fn_name:ident, $query:expr) => {
+        $crate::_macro_support::trace_query(
+            ::core::concat!(::core::module_path!(), "::", ::core::stringify!($fn_name)),
+            $crate::_macro_support::DEFAULT_SLOW_QUERY_THRESHOLD,
+            $query,
+        )
+    };
+    (@trace [traced(slow_ms = $slow_ms:expr)] $fn_name:ident, $query:expr) => {
+        $crate::_macro_support::trace_query(
+            ::core::concat!(::core::module_path!(), "::", ::core::stringify!($fn_name)),
+            ::std::time::Duration::from_millis($slow_ms),
+            $query,
+        )
+    };
+    // collect bind entries
+    (@bind ($($head:tt)*) $self:ident [$($bind:expr,)*]) => {
+        $crate::sql_query_internal! {
+            @query_impl
+            $($head)* ($self) [$($bind,)*]
+        }
+    };
+    // literal, checked before field since `true` and `false` are also identifiers
+    (
+        @bind ($($head:tt)*) $self:ident [$($bind:expr,)*]
+        $value:literal $(, $($rest:tt)*)?
+    ) => {
+        $crate::sql_query_internal! {
+            @bind ($($head)*) $self [$($bind,)* $value,]
+            $($($rest)*)?
+        }
+    };
+    // struct field
+    (
+        @bind ($($head:tt)*) $self:ident [$($bind:expr,)*]
+        $field:ident $(, $($rest:tt)*)?
+    ) => {
+        $crate::sql_query_internal! {
+            @bind ($($head)*) $self [$($bind,)* &$self.$field,]
+            $($($rest)*)?
+        }
+    };
+    // renamed expression, name is only for documentation
+    (
+        @bind ($($head:tt)*) $self:ident [$($bind:expr,)*]
+        $name:ident = $value:expr $(, $($rest:tt)*)?
+    ) => {
+        $crate::sql_query_internal! {
+            @bind ($($head)*) $self [$($bind,)* $value,]
+            $($($rest)*)?
+        }
+    };
+    // expression
+    (
+        @bind ($($head:tt)*) $self:ident [$($bind:expr,)*]
+        $value:expr $(, $($rest:tt)*)?
+    ) => {
+        $crate::sql_query_internal! {
+            @bind ($($head)*) $self [$($bind,)* $value,]
+            $($($rest)*)?
+        }
+    };
+    // collect attributes, `#[traced]`, `#[primary]`, `#[soft_delete]` and `#[cached]` are flags of this macro
+    // check placeholders only if SQL is a literal, named SQL is checked at runtime
+    (@query $db:ty, $query:tt, (named $sql:expr), $($fn_spec:tt)*) => {
+        $crate::sql_query_internal! {
+            @attrs ($db, $query, $sql, [named]) [] [] [] [] []
+            $($fn_spec)*
+        }
+    };
+    (@query $db:ty, $query:tt, ($sql:literal), $($fn_spec:tt)*) => {
+        $crate::sql_query_internal! {
+            @attrs ($db, $query, $sql, [check]) [] [] [] [] []
+            $($fn_spec)*
+        }
+    };
+    (@query $db:ty, $query:tt, ($sql:expr), $($fn_spec:tt)*) => {
+        $crate::sql_query_internal! {
+            @attrs ($db, $query, $sql, []) [] [] [] [] []
+            $($fn_spec)*
+        }
+    };
+    (
+        @attrs ($($head:tt)*)
+        [$($trace:tt)*] [$($route:tt)*] [$($filter:tt)*] [$($cache:tt)*] [$($fn_meta:tt)*]
+        #[traced $(($($option:tt)*))?]
+        $($rest:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @attrs ($($head)*)
+            [traced $(($($option)*))?] [$($route)*] [$($filter)*] [$($cache)*] [$($fn_meta)*]
+            $($rest)*
+        }
+    };
+    (
+        @attrs ($($head:tt)*)
+        [$($trace:tt)*] [$($route:tt)*] [$($filter:tt)*] [$($cache:tt)*] [$($fn_meta:tt)*]
+        #[primary]
+        $($rest:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @attrs ($($head)*)
+            [$($trace)*] [primary] [$($filter)*] [$($cache)*] [$($fn_meta)*]
+            $($rest)*
+        }
+    };
+    (
+        @attrs ($($head:tt)*)
+        [$($trace:tt)*] [$($route:tt)*] [$($filter:tt)*] [$($cache:tt)*] [$($fn_meta:tt)*]
+        #[soft_delete $((column = $column:literal))?]
+        $($rest:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @attrs ($($head)*)
+            [$($trace)*] [$($route)*] [soft_delete($($column)? "deleted_at")] [$($cache)*] [$($fn_meta)*]
+            $($rest)*
+        }
+    };
+    (
+        @attrs ($($head:tt)*)
+        [$($trace:tt)*] [$($route:tt)*] [$($filter:tt)*] [$($cache:tt)*] [$($fn_meta:tt)*]
+        #[cached(ttl = $ttl:expr $(, capacity = $capacity:expr)?)]
+        $($rest:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @attrs ($($head)*)
+            [$($trace)*] [$($route)*] [$($filter)*] [cached($ttl, $($capacity)?)] [$($fn_meta)*]
+            $($rest)*
+        }
+    };
+    (
+        @attrs ($($head:tt)*)
+        [$($trace:tt)*] [$($route:tt)*] [$($filter:tt)*] [$($cache:tt)*] [$($fn_meta:tt)*]
+        #[$meta:meta]
+        $($rest:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @attrs ($($head)*)
+            [$($trace)*] [$($route)*] [$($filter)*] [$($cache)*] [$($fn_meta)* #[$meta]]
+            $($rest)*
+        }
+    };
+    (
+        @attrs ($($head:tt)*)
+        [$($trace:tt)*] [$($route:tt)*] [$($filter:tt)*] [$($cache:tt)*] [$($fn_meta:tt)*]
+        $($rest:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @fn
+            $($head)*,
+            [$($trace)*],
+            [$($route)*],
+            [$($filter)*],
+            [$($cache)*],
+            $($fn_meta)* $($rest)*
+        }
+    };
+    // named SQL, binds are looked up by name
+    (
+        @fn
+        $db:ty,
+        $query:tt,
+        $sql:expr,
+        [named],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident (&$self:ident) {$($entry:tt)*}
+    ) => {
+        $crate::sql_query_internal! {
+            @named_bind
+            (
+                $db,
+                $query,
+                $sql,
+                [$($trace)*],
+                [$($route)*],
+                [$($filter)*],
+                [$($cache)*],
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name
+            )
+            $self []
+            $($entry)*
+        }
+    };
+    (
+        @fn
+        $db:ty,
+        $query:tt,
+        $sql:expr,
+        [named],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident {$($entry:tt)*}
+    ) => {
+        $crate::sql_query_internal! {
+            @named_bind
+            (
+                $db,
+                $query,
+                $sql,
+                [$($trace)*],
+                [$($route)*],
+                [$($filter)*],
+                [$($cache)*],
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name
+            )
+            self []
+            $($entry)*
+        }
+    };
+    (@fn $db:ty, $query:tt, $sql:expr, [named], $($rest:tt)*) => {
+        ::core::compile_error!("named SQL requires a struct with named bind entries");
+    };
+    (@named_bind ($($head:tt)*) $self:ident [$($name:ident = $value:expr,)*]) => {
+        $crate::sql_query_internal! {
+            @named_impl
+            $($head)* ($self) [$($name = $value,)*]
+        }
+    };
+    (
+        @named_bind ($($head:tt)*) $self:ident [$($name:ident = $value:expr,)*]
+        $field:ident $(, $($rest:tt)*)?
+    ) => {
+        $crate::sql_query_internal! {
+            @named_bind ($($head)*) $self [$($name = $value,)* $field = &$self.$field,]
+            $($($rest)*)?
+        }
+    };
+    (
+        @named_bind ($($head:tt)*) $self:ident [$($name:ident = $value:expr,)*]
+        $entry_name:ident = $entry_value:expr $(, $($rest:tt)*)?
+    ) => {
+        $crate::sql_query_internal! {
+            @named_bind ($($head)*) $self [$($name = $value,)* $entry_name = $entry_value,]
+            $($($rest)*)?
+        }
+    };
+    (@named_bind ($($head:tt)*) $self:ident [$($bind:tt)*] $($rest:tt)*) => {
+        ::core::compile_error!("bind entries of named SQL must be fields or `name = value`");
+    };
+    (@named_impl $db:ty, (@page $($page:tt)*), $($rest:tt)*) => {
+        ::core::compile_error!("named SQL is not supported by fetch_page");
+    };
+    (
+        @named_impl
+        $db:ty,
+        ($query_fn:ident, $execute_fn:ident -> $from_row:ty $(=> $map:expr)?),
+        $sql:expr,
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident ($self:ident) [$($name:ident = $value:expr,)*]
+    ) => {
+        $crate::sql_query_internal! {
+            @executor_fn [$($route)*] [$($cache)*] $db,
+            {
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name<'c, E>(&$self, executor: E) -> ::sqlx::Result<$from_row>
+            }
+            {
+                use ::std::sync::OnceLock;
+                use ::sqlx::Arguments as _;
+                use $crate::sql::SqlTrimBoxed;
+
+                // we choose this name to avoid shadowing outer SQL (if exist)
+                static __NAMED_QUERY__: OnceLock<::core::result::Result<$crate::sql::named::NamedSql, String>> =
+                    OnceLock::new();
+
+                let executor = $crate::sql_query_internal!(@route [$($route)*] executor);
+                $crate::sql_query_internal!(@cached [$($cache)*] $execute_fn -> $from_row, $self, executor, {
+                    let named = __NAMED_QUERY__.get_or_init(|| {
+                        $crate::sql_query_internal!(@sql [$($filter)*] $sql).map(|sql| {
+                            $crate::sql::named::NamedSql::parse(&sql, <$db as ::sqlx::Database>::NAME)
+                        })
+                    });
+                    let named = match named {
+                        ::core::result::Result::Ok(named) => named,
+                        ::core::result::Result::Err(e) => {
+                            return ::core::result::Result::Err(::sqlx::Error::InvalidArgument(
+                                ::core::clone::Clone::clone(e),
+                            ));
+                        }
+                    };
+                    let mut arguments = <<$db as ::sqlx::Database>::Arguments<'_> as ::core::default::Default>::default();
+                    for param in named.params() {
+                        $(
+                            if param == ::core::stringify!($name) {
+                                arguments.add($value).map_err(::sqlx::Error::Encode)?;
+                                continue;
+                            }
+                        )*
+                        return ::core::result::Result::Err(::sqlx::Error::InvalidArgument(
+                            ::std::format!("no bind entry of SQL parameter :{param}"),
+                        ));
+                    }
+                    let query = $crate::sql_query_internal!(@query_with $query_fn, $db, named.sql(), arguments)
+                        .$execute_fn(executor);
+                    let query = $crate::sql_query_internal!(@trace [$($trace)*] $fn_name, query);
+                    $crate::sql_query_internal!(@observe $fn_name, query)
+                        .await
+                        $(.map($map))?
+                })
+            }
+        }
+    };
+    (@query_with query_as, $db:ty, $sql:expr, $arguments:ident) => {
+        ::sqlx::query_as_with::<$db, _, _>($sql, $arguments)
+    };
+    (@query_with query_scalar, $db:ty, $sql:expr, $arguments:ident) => {
+        ::sqlx::query_scalar_with::<$db, _, _>($sql, $arguments)
+    };
+    (@query_with query, $db:ty, $sql:expr, $arguments:ident) => {
+        ::sqlx::query_with::<$db, _>($sql, $arguments)
+    };
+    // support named struct with explicit receiver, so expressions can refer to `self`
+    (
+        @fn
+        $db:ty,
+        $query:tt,
+        $sql:expr,
+        [$($check:tt)*],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident (&$self:ident) {$($entry:tt)*}
+    ) => {
+        $crate::sql_query_internal! {
+            @bind
+            (
+                $db,
+                $query,
+                $sql,
+                [$($check)*],
+                [$($trace)*],
+                [$($route)*],
+                [$($filter)*],
+                [$($cache)*],
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name
+            )
+            $self []
+            $($entry)*
+        }
+    };
+    // support named struct
+    (
+        @fn
+        $db:ty,
+        $query:tt,
+        $sql:expr,
+        [$($check:tt)*],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident {$($entry:tt)*}
+    ) => {
+        $crate::sql_query_internal! {
+            @bind
+            (
+                $db,
+                $query,
+                $sql,
+                [$($check)*],
+                [$($trace)*],
+                [$($route)*],
+                [$($filter)*],
+                [$($cache)*],
+                $(#[$fn_meta])*
+                $fn_vis async fn $fn_name
+            )
+            self []
+            $($entry)*
+        }
+    };
+    // support tuple struct
+    (
+        @fn
+        $db:ty,
+        $query:tt,
+        $sql:expr,
+        [$($check:tt)*],
+        [$($trace:tt)*],
+        [$($route:tt)*],
+        [$($filter:tt)*],
+        [$($cache:tt)*],
+        $(#[$fn_meta:meta])*
+        $fn_vis:vis async fn $fn_name:ident ($($field:tt),* $(,)?)
+    ) => {
+        $crate::sql_query_internal! {
+            @query_impl
+            $db,
+            $query,
+            $sql,
+            [$($check)*],
+            [$($trace)*],
+            [$($route)*],
+            [$($filter)*],
+            [$($cache)*],
+            $(#[$fn_meta])*
+            $fn_vis async fn $fn_name (self) [$(&self.$field,)*]
+        }
+    };
+    // update deleted column, used by `soft_delete` and `restore`
+    (
+        @soft_delete
+        $db:ty,
+        ($table:literal, $condition:literal, $column:literal $($default:literal)?),
+        $value:literal,
+        $state:literal,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @attrs
+            (
+                $db,
+                (query, execute -> u64 => |result| result.rows_affected()),
+                ::core::concat!(
+                    "UPDATE ", $table, " SET ", $column, " = ", $value,
+                    " WHERE (", $condition, ") AND ", $column, " ", $state
+                ),
+                [check]
+            )
+            [] [] [] [] []
+            $($fn_spec)*
+        }
+    };
+    // get a page of rows with keyset pagination
+    (
+        $db:ty,
+        fetch_page($sql:literal, key = $key:ident $($order:ident)?) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (@page [$key $($order)?] -> $from_row),
+            ($sql),
+            $($fn_spec)*
+        }
+    };
+    // get a page of rows with keyset pagination
+    (
+        $db:ty,
+        fetch_page($sql:expr, key = $key:ident $($order:ident)?) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (@page [$key $($order)?] -> $from_row),
+            ($sql),
+            $($fn_spec)*
+        }
+    };
+    // get a page of rows with offset pagination
+    (
+        $db:ty,
+        fetch_page($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (@page [] -> $from_row),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // get one row
+    (
+        $db:ty,
+        fetch_one($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_as, fetch_one -> $from_row),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // get one row with single column
+    (
+        $db:ty,
+        fetch_one_scalar($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_scalar, fetch_one -> $from_row),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // find one row
+    (
+        $db:ty,
+        fetch_optional($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_as, fetch_optional -> ::std::option::Option<$from_row>),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // find one row with single column
+    (
+        $db:ty,
+        fetch_optional_scalar($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_scalar, fetch_optional -> ::std::option::Option<$from_row>),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // fetch all
+    (
+        $db:ty,
+        fetch_all($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_as, fetch_all -> ::std::vec::Vec<$from_row>),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // fetch all with single column
+    (
+        $db:ty,
+        fetch_all_scalar($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_scalar, fetch_all -> ::std::vec::Vec<$from_row>),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // execute
+    (
+        $db:ty,
+        execute($($sql:tt)+),
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query, execute -> <$db as ::sqlx::Database>::QueryResult),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // execute and return number of affected rows
+    (
+        $db:ty,
+        execute_rows($($sql:tt)+),
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query, execute -> u64 => |result| result.rows_affected()),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // insert or update a row and return it
+    (
+        $db:ty,
+        upsert($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_as, fetch_one -> $from_row),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // insert a row and return it
+    (
+        $db:ty,
+        insert_returning($($sql:tt)+) -> $from_row:ty,
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @query
+            $db,
+            (query_as, fetch_one -> $from_row),
+            ($($sql)+),
+            $($fn_spec)*
+        }
+    };
+    // set deleted column of matched rows to current timestamp
+    (
+        $db:ty,
+        soft_delete($table:literal, $condition:literal $(, column = $column:literal)?),
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @soft_delete
+            $db,
+            ($table, $condition, $($column)? "deleted_at"),
+            "CURRENT_TIMESTAMP",
+            "IS NULL",
+            $($fn_spec)*
+        }
+    };
+    // clear deleted column of matched rows
+    (
+        $db:ty,
+        restore($table:literal, $condition:literal $(, column = $column:literal)?),
+        $($fn_spec:tt)*
+    ) => {
+        $crate::sql_query_internal! {
+            @soft_delete
+            $db,
+            ($table, $condition, $($column)? "deleted_at"),
+            "NULL",
+            "IS NOT NULL",
+            $($fn_spec)*
+        }
+    };
+}
+
+/// Run a block in a transaction, see [`TxExt`](crate::sql::TxExt).
+///
+/// Block is run in `async move` block and may be run more than once if transaction is retried,
+/// clone captured values inside the block if needed.
+///
+/// ```ignore
+/// let id = transactional!(pool, |tx| {
+///     let id = account.insert(&mut **tx).await?;
+///     Audit::created(id).insert(&mut **tx).await?;
+///     Ok::<_, sqlx::Error>(id)
+/// })
+/// .await?;
+///
+/// // with custom retry policy
+/// transactional!(pool, retry = TxRetry::none(), |tx| { ... }).await?;
+/// ```
+#[cfg(feature = "sqlx")]
+#[macro_export]
+macro_rules! transactional {
+    ($pool:expr, retry = $retry:expr, |$tx:ident| $body:block) => {{
+        use $crate::sql::TxExt as _;
+        $pool.with_tx_retry($retry, |$tx| ::std::boxed::Box::pin(async move $body))
+    }};
+    ($pool:expr, |$tx:ident| $body:block) => {{
+        use $crate::sql::TxExt as _;
+        $pool.with_tx(|$tx| ::std::boxed::Box::pin(async move $body))
+    }};
+}
+
+/// Embed migration files into binary, see [`migrate`](crate::sql::migrate).
+///
+/// Directory is relative to `CARGO_MANIFEST_DIR`, macro can't read a directory so files are listed,
+/// returns `Result<Migrator, MigrateError>`.
+///
+/// ```ignore
+/// let migrator = embed_migrations!("migrations" => [
+///     "0001_create_accounts.sql",
+///     "0002_add_account_email.sql",
+/// ])?;
+/// ```
+#[cfg(feature = "sqlx")]
+#[macro_export]
+macro_rules! embed_migrations {
+    ($dir:literal => [$($file:literal),* $(,)?]) => {
+        $crate::sql::migrate::Migrator::from_files([
+            $((
+                $file,
+                ::core::include_str!(::core::concat!(
+                    ::core::env!("CARGO_MANIFEST_DIR"),
+                    "/",
+                    $dir,
+                    "/",
+                    $file
+                )),
+            )),*
+        ])
+    };
+}
+
+/// Evaluate a fallible expression until it succeeds or policy stops retrying,
+/// see [`RetryPolicy`](crate::retry::RetryPolicy).
+///
+/// Expression is evaluated in place, so it can borrow local variables and use `.await`,
+/// the macro must be used in async context.
+///
+/// ```ignore
+/// let policy = RetryPolicy::new(5).retry_if(|e: &reqwest::Error| e.is_timeout());
+/// let response = retry!(policy, client.get(url).send().await)?;
+/// ```
+#[macro_export]
+macro_rules! retry {
+    ($policy:expr, $expr:expr) => {{
+        let policy = &$policy;
+        let mut backoff = policy.backoff();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match $expr {
+                ::core::result::Result::Ok(value) => break ::core::result::Result::Ok(value),
+                ::core::result::Result::Err(error) => match policy.next_backoff(attempt, &error, &mut backoff) {
+                    ::core::option::Option::Some(backoff) => {
+                        $crate::retry::sleep_before_retry(attempt, backoff).await
+                    }
+                    ::core::option::Option::None => break ::core::result::Result::Err(error),
+                },
+            }
+        }
+    }};
+}
+
+/// Generate `builder()` method which return builder with default values.
+#[macro_export]
+macro_rules! with_builder {
+    ($builder:ty => $ty:ty) => {
+        impl $ty {
+            pub fn builder() -> $builder {
+                <$builder as ::core::default::Default>::default()
+            }
+        }
+    };
+}
+
+/// Generate builder struct of a struct from a field list.
+///
+/// Fields without default value are required, they are arguments of generated `builder()`,
+/// so a required field can't be missed. Other fields have setters, arguments of
+/// `builder()` and setters accept `impl Into<T>`. Attributes of a field, e.g. doc comments,
+/// are added to its setter, or to field of builder struct if the field is required.
+///
+/// ```ignore
+/// pub struct Server {
+///     host: String,
+///     port: u16,
+///     name: Option<String>,
+/// }
+///
+/// builder_for! {
+///     #[derive(Debug, Clone)]
+///     pub struct ServerBuilder for Server {
+///         /// Host name or address.
+///         host: String,
+///         /// Port to listen on.
+///         port: u16 = 8080,
+///         name: Option<String> = None,
+///     }
+/// }
+///
+/// let server = Server::builder("localhost").name("api".to_owned()).build();
+/// ```
+#[macro_export]
+macro_rules! builder_for {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $builder:ident for $ty:ident {
+            $($fields:tt)*
+        }
+    ) => {
+        $crate::builder_for! {
+            @fields ($(#[$meta])* $vis $builder $ty) [] []
+            $($fields)*
+        }
+    };
+    // optional field
+    (
+        @fields $head:tt [$($required:tt)*] [$($optional:tt)*]
+        $(#[$field_meta:meta])*
+        $field:ident : $field_ty:ty = $default:expr
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::builder_for! {
+            @fields $head [$($required)*] [$($optional)* ($(#[$field_meta])* $field: $field_ty = $default)]
+            $($($rest)*)?
+        }
+    };
+    // required field
+    (
+        @fields $head:tt [$($required:tt)*] [$($optional:tt)*]
+        $(#[$field_meta:meta])*
+        $field:ident : $field_ty:ty
+        $(, $($rest:tt)*)?
+    ) => {
+        $crate::builder_for! {
+            @fields $head [$($required)* ($(#[$field_meta])* $field: $field_ty)] [$($optional)*]
+            $($($rest)*)?
+        }
+    };
+    (
+        @fields ($(#[$meta:meta])* $vis:vis $builder:ident $ty:ident)
+        [$(($(#[$required_meta:meta])* $required:ident : $required_ty:ty))*]
+        [$(($(#[$field_meta:meta])* $optional:ident : $optional_ty:ty = $default:expr))*]
+    ) => {
+        $(#[$meta])*
+        $vis struct $builder {
+            $($(#[$required_meta])* $required: $required_ty,)*
+            $($optional: $optional_ty,)*
+        }
+
+        impl $ty {
+            #[allow(clippy::too_many_arguments)]
+            $vis fn builder($($required: impl ::core::convert::Into<$required_ty>),*) -> $builder {
+                $builder {
+                    $($required: $required.into(),)*
+                    $($optional: $default,)*
+                }
+            }
+        }
+
+        impl $builder {
+            $(
+                $(#[$field_meta])*
+                $vis fn $optional(mut self, $optional: impl ::core::convert::Into<$optional_ty>) -> Self {
+                    self.$optional = $optional.into();
+                    self
+                }
+            )*
+
+            $vis fn build(self) -> $ty {
+                $ty {
+                    $($required: self.$required,)*
+                    $($optional: self.$optional,)*
+                }
+            }
+        }
+    };
+}
+
+/// Generating function used for reading jemalloc stats.
+///
+/// Unfortunately we couldn't re-export jemalloc struct so we hard coded its path here.
+#[macro_export]
+macro_rules! generate_read_jemalloc_raw_data {
+    ($vis:vis fn $name:ident) => {
+        $vis fn $name() -> ::core::option::Option<$crate::jemalloc::info::JemallocRawData> {
+            use ::std::prelude::*;
+            use ::std::sync::OnceLock;
+
+            use ::tikv_jemalloc_ctl::{
+                arenas, background_thread, background_thread_mib, epoch, epoch_mib, max_background_threads,
+                max_background_threads_mib, stats,
+            };
+
+            use $crate::jemalloc::info::{JemallocRawData, BackgroundThread};
+
+            struct Mib {
+                epoch: epoch_mib,
+                max_background_threads: max_background_threads_mib,
+                background_thread: background_thread_mib,
+                narenas: arenas::narenas_mib,
+                active: stats::active_mib,
+                allocated: stats::allocated_mib,
                 mapped: stats::mapped_mib,
                 metadata: stats::metadata_mib,
                 resident: stats::resident_mib,
@@ -532,3 +1777,514 @@ macro_rules! generate_read_jemalloc_raw_data {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    #[derive(Debug, PartialEq)]
+    struct Server {
+        host: String,
+        port: u16,
+        name: Option<String>,
+    }
+
+    crate::builder_for! {
+        #[derive(Debug)]
+        struct ServerBuilder for Server {
+            /// Host name or address.
+            host: String,
+            /// Port to listen on.
+            port: u16 = 8080,
+            name: Option<String> = None,
+        }
+    }
+
+    #[test]
+    fn test_builder_for() {
+        let server = Server::builder("localhost").name("api".to_owned()).build();
+        let expect = Server {
+            host: "localhost".to_owned(),
+            port: 8080,
+            name: Some("api".to_owned()),
+        };
+        assert_eq!(server, expect);
+        assert_eq!(Server::builder("a").port(80u16).build().port, 80);
+    }
+
+    struct Endpoint {
+        url: String,
+        retries: u32,
+    }
+
+    crate::builder_for! {
+        #[derive(serde::Serialize)]
+        struct EndpointBuilder for Endpoint {
+            #[serde(rename = "address")]
+            url: String,
+            retries: u32 = 3,
+        }
+    }
+
+    #[test]
+    fn test_builder_for_required_attributes() {
+        let builder = Endpoint::builder("http://localhost");
+        let json = serde_json::to_value(&builder).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"address": "http://localhost", "retries": 3})
+        );
+        let endpoint = builder.retries(1u32).build();
+        assert_eq!((endpoint.url.as_str(), endpoint.retries), ("http://localhost", 1));
+    }
+
+    #[test]
+    fn test_measure_time() {
+        assert_eq!(crate::measure_time!("Add", 1 + 2), 3);
+        assert_eq!(crate::measure_time!(MILLI, "Add", 1 + 2), 3);
+        assert_eq!(crate::measure_time!(level = INFO, "Add", 1 + 2), 3);
+        let slow = Duration::from_secs(1);
+        let value = crate::measure_time!(slow = slow, level = WARN, "Add", 1 + 2);
+        assert_eq!(value, 3);
+        let span = tracing::info_span!("test", elapsed = tracing::field::Empty);
+        let value = span.in_scope(|| crate::measure_time!(span = elapsed, 1 + 2));
+        assert_eq!(value, 3);
+    }
+
+    #[derive(Debug, PartialEq, sqlx::FromRow)]
+    struct Account {
+        id: i64,
+        name: String,
+    }
+
+    struct FindAccount {
+        id: i64,
+    }
+
+    impl FindAccount {
+        const SQL: &'static str = "SELECT id, name FROM accounts WHERE id = ?";
+
+        crate::mysql_query! {
+            fetch_one(Self::SQL) -> Account,
+            pub async fn get_mysql { id }
+        }
+
+        crate::mysql_query! {
+            execute("DELETE FROM accounts WHERE id = ?"),
+            pub async fn delete_mysql { id }
+        }
+
+        crate::sqlite_query! {
+            find(Self::SQL) -> Account,
+            pub async fn find_sqlite { id }
+        }
+
+        crate::sql_query! {
+            Db,
+            fetch_optional(Self::SQL) -> Account,
+            pub async fn find_db { id }
+        }
+
+        crate::sql_query! {
+            Db,
+            execute("DELETE FROM accounts WHERE id = ?"),
+            pub async fn delete_db { id }
+        }
+
+        crate::sqlite_query! {
+            execute_rows("DELETE FROM accounts WHERE id = ?"),
+            pub async fn delete_sqlite { id }
+        }
+
+        crate::sqlite_query! {
+            get(Self::SQL) -> Account,
+            /// Traced query.
+            #[traced]
+            #[inline]
+            pub async fn get_traced { id }
+        }
+
+        crate::sqlite_query! {
+            find(Self::SQL) -> Account,
+            #[traced(slow_ms = 0)]
+            pub async fn find_slow { id }
+        }
+    }
+
+    struct FindByName {
+        account: Account,
+    }
+
+    fn lower(s: &str) -> String {
+        s.to_lowercase()
+    }
+
+    impl FindByName {
+        crate::sqlite_query! {
+            get_scalar("SELECT count(*) FROM accounts WHERE id = ? AND name = ? AND ? AND ?") -> i64,
+            pub async fn count(&self) {
+                account_id = self.account.id,
+                lower(&self.account.name),
+                true,
+                !self.account.name.is_empty(),
+            }
+        }
+
+        crate::sqlite_query! {
+            get_scalar("SELECT ? || ?") -> String,
+            async fn concat { "a", "b" }
+        }
+    }
+
+    struct ListAccounts {
+        min_id: i64,
+    }
+
+    impl ListAccounts {
+        crate::sqlite_query! {
+            list_page("SELECT id, name FROM accounts WHERE id >= ? ORDER BY id") -> Account,
+            pub async fn list_offset { min_id }
+        }
+
+        crate::sqlite_query! {
+            list_page("SELECT id, name FROM accounts WHERE id >= ?", key = id) -> Account,
+            #[traced]
+            pub async fn list_keyset { min_id }
+        }
+
+        crate::sqlite_query! {
+            list_page("SELECT id, name FROM accounts ORDER BY name;", key = id desc) -> Account,
+            pub async fn list_keyset_desc ()
+        }
+
+        crate::sqlite_query! {
+            list_page("SELECT id, name FROM accounts ORDER BY id LIMIT 2") -> Account,
+            pub async fn list_limited ()
+        }
+    }
+
+    struct SoftDeleteAccount {
+        id: i64,
+    }
+
+    impl SoftDeleteAccount {
+        const WITHOUT_MARKER: &'static str = "SELECT id FROM accounts ORDER BY id";
+
+        crate::sqlite_query! {
+            find("SELECT id, name FROM accounts WHERE (id = ? OR name = 'none') AND {soft_delete}") -> Account,
+            #[soft_delete]
+            pub async fn find { id }
+        }
+
+        crate::sqlite_query! {
+            list_scalar("SELECT id FROM accounts WHERE {soft_delete} ORDER BY id DESC LIMIT 10") -> i64,
+            #[soft_delete(column = "deleted_at")]
+            pub async fn list_ids ()
+        }
+
+        crate::sqlite_query! {
+            list_scalar(SoftDeleteAccount::WITHOUT_MARKER) -> i64,
+            #[soft_delete]
+            pub async fn list_without_marker ()
+        }
+
+        crate::sqlite_query! {
+            soft_delete("accounts", "id = ?"),
+            pub async fn delete { id }
+        }
+
+        crate::sqlite_query! {
+            restore("accounts", "id = ?", column = "deleted_at"),
+            pub async fn restore { id }
+        }
+    }
+
+    // cached queries only run on pools, which need `sqlx` feature
+    #[cfg(feature = "sqlx")]
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct CachedAccount {
+        id: i64,
+    }
+
+    #[cfg(feature = "sqlx")]
+    impl CachedAccount {
+        crate::sqlite_query! {
+            find_scalar("SELECT name FROM accounts WHERE id = ?") -> String,
+            #[cached(ttl = std::time::Duration::from_secs(60), capacity = 1)]
+            pub async fn find_name { id }
+        }
+    }
+
+    struct NewAccount {
+        id: i64,
+        name: &'static str,
+    }
+
+    impl NewAccount {
+        crate::sqlite_query! {
+            insert_returning("INSERT INTO accounts (id, name) VALUES (?, ?) RETURNING id, name") -> Account,
+            pub async fn insert { id, name }
+        }
+
+        crate::sqlite_query! {
+            upsert(
+                "INSERT INTO accounts (id, name) VALUES (?, ?)
+                 ON CONFLICT (id) DO UPDATE SET name = excluded.name
+                 RETURNING id, name"
+            ) -> Account,
+            pub async fn upsert { id, name }
+        }
+    }
+
+    struct FindNamed {
+        id: i64,
+        name: &'static str,
+    }
+
+    impl FindNamed {
+        crate::sqlite_query! {
+            get_scalar(named "SELECT count(*) FROM accounts WHERE (id = :id OR :id = 0) AND name = :name") -> i64,
+            pub async fn count(&self) {
+                name = self.name.to_lowercase(),
+                id,
+            }
+        }
+
+        crate::sqlite_query! {
+            get_scalar(named "SELECT :missing") -> i64,
+            pub async fn missing { id }
+        }
+
+        crate::postgres_query! {
+            fetch_optional(named "SELECT id, name FROM accounts WHERE id = :id AND name <> :name::text") -> Account,
+            pub async fn find_postgres { id, name }
+        }
+    }
+
+    struct ListNames;
+
+    impl ListNames {
+        crate::sqlite_query! {
+            list_scalar("SELECT name FROM accounts ORDER BY id") -> String,
+            pub async fn list_sqlite ()
+        }
+
+        crate::postgres_query! {
+            fetch_all_scalar("SELECT name FROM accounts ORDER BY id") -> String,
+            pub async fn list_postgres ()
+        }
+    }
+
+    type Db = sqlx::Sqlite;
+
+    async fn sqlite() -> sqlx::SqlitePool {
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE accounts (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO accounts (id, name) VALUES (1, 'nui')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_query() {
+        let pool = sqlite().await;
+        let account = FindAccount { id: 1 }.find_sqlite(&pool).await.unwrap();
+        let expect = Account {
+            id: 1,
+            name: "nui".to_owned(),
+        };
+        assert_eq!(account, Some(expect));
+        assert_eq!(FindAccount { id: 2 }.find_sqlite(&pool).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sql_query() {
+        let pool = sqlite().await;
+        let find = FindAccount { id: 1 };
+        assert!(find.find_db(&pool).await.unwrap().is_some());
+        let result = find.delete_db(&pool).await.unwrap();
+        assert_eq!(result.rows_affected(), 1);
+        assert_eq!(find.find_db(&pool).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_query_rows() {
+        let pool = sqlite().await;
+        assert_eq!(ListNames.list_sqlite(&pool).await.unwrap(), ["nui"]);
+        assert_eq!(FindAccount { id: 1 }.delete_sqlite(&pool).await.unwrap(), 1);
+        assert_eq!(FindAccount { id: 1 }.delete_sqlite(&pool).await.unwrap(), 0);
+        assert!(ListNames.list_sqlite(&pool).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_traced() {
+        let pool = sqlite().await;
+        let find = FindAccount { id: 1 };
+        assert_eq!(find.get_traced(&pool).await.unwrap().name, "nui");
+        assert!(find.find_slow(&pool).await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_bind_expressions() {
+        let pool = sqlite().await;
+        let find = FindByName {
+            account: Account {
+                id: 1,
+                name: "NUI".to_owned(),
+            },
+        };
+        assert_eq!(find.count(&pool).await.unwrap(), 1);
+        assert_eq!(find.concat(&pool).await.unwrap(), "ab");
+    }
+
+    #[tokio::test]
+    async fn test_fetch_page() {
+        use crate::sql::page::{Cursor, PageRequest};
+
+        let pool = sqlite().await;
+        for id in 2..=5 {
+            sqlx::query("INSERT INTO accounts (id, name) VALUES (?, 'x')")
+                .bind(id)
+                .execute(&pool)
+                .await
+                .unwrap();
+        }
+        let list = ListAccounts { min_id: 2 };
+        let ids = |page: crate::sql::page::Page<Account>| -> Vec<i64> {
+            page.items.iter().map(|a| a.id).collect()
+        };
+
+        let request = PageRequest::new(3);
+        let page = list.list_offset(&pool, &request).await.unwrap();
+        assert_eq!(page.next_cursor, Some(Cursor::Offset(3)));
+        assert_eq!(ids(page), [2, 3, 4]);
+        let request = request.cursor(Cursor::Offset(3));
+        let page = list.list_offset(&pool, &request).await.unwrap();
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(ids(page), [5]);
+
+        let request = PageRequest::new(2);
+        let page = list.list_keyset(&pool, &request).await.unwrap();
+        assert_eq!(page.next_cursor, Some(Cursor::After(3)));
+        assert_eq!(ids(page), [2, 3]);
+        let request = request.cursor(Cursor::After(3));
+        let page = list.list_keyset(&pool, &request).await.unwrap();
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(ids(page), [4, 5]);
+
+        let request = request.cursor(Cursor::Offset(3));
+        assert!(list.list_keyset(&pool, &request).await.is_err());
+
+        let request = PageRequest::new(3);
+        let page = list.list_keyset_desc(&pool, &request).await.unwrap();
+        assert_eq!(page.next_cursor, Some(Cursor::After(3)));
+        assert_eq!(ids(page), [5, 4, 3]);
+        let request = request.cursor(Cursor::After(3));
+        let page = list.list_keyset_desc(&pool, &request).await.unwrap();
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(ids(page), [2, 1]);
+
+        let error = list.list_limited(&pool, &request).await.unwrap_err();
+        assert!(matches!(error, sqlx::Error::InvalidArgument(_)));
+    }
+
+    #[tokio::test]
+    async fn test_soft_delete() {
+        let pool = sqlite().await;
+        sqlx::query("ALTER TABLE accounts ADD COLUMN deleted_at TIMESTAMP")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let account = SoftDeleteAccount { id: 1 };
+        assert!(account.find(&pool).await.unwrap().is_some());
+        assert_eq!(account.delete(&pool).await.unwrap(), 1);
+        assert_eq!(account.delete(&pool).await.unwrap(), 0);
+        assert_eq!(account.find(&pool).await.unwrap(), None);
+        assert!(account.list_ids(&pool).await.unwrap().is_empty());
+        assert_eq!(account.restore(&pool).await.unwrap(), 1);
+        assert_eq!(account.restore(&pool).await.unwrap(), 0);
+        assert_eq!(account.list_ids(&pool).await.unwrap(), [1]);
+
+        let error = account.list_without_marker(&pool).await.unwrap_err();
+        assert!(matches!(error, sqlx::Error::InvalidArgument(_)));
+    }
+
+    #[cfg(feature = "sqlx")]
+    #[tokio::test]
+    async fn test_cached() {
+        let pool = sqlite().await;
+        let name = CachedAccount { id: 1 }.find_name(&pool).await.unwrap();
+        assert_eq!(name.as_deref(), Some("nui"));
+        sqlx::query("UPDATE accounts SET name = 'renamed'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let name = CachedAccount { id: 1 }.find_name(&pool).await.unwrap();
+        assert_eq!(name.as_deref(), Some("nui"));
+        assert_eq!(
+            CachedAccount { id: 1 }.find_name(&pool.clone()).await.unwrap(),
+            name
+        );
+        // results of other pools are not shared
+        let other = sqlite().await;
+        sqlx::query("UPDATE accounts SET name = 'other'")
+            .execute(&other)
+            .await
+            .unwrap();
+        let name = CachedAccount { id: 1 }.find_name(&other).await.unwrap();
+        assert_eq!(name.as_deref(), Some("other"));
+        // capacity is 1, result of the first pool is evicted
+        let name = CachedAccount { id: 1 }.find_name(&pool).await.unwrap();
+        assert_eq!(name.as_deref(), Some("renamed"));
+        assert_eq!(CachedAccount { id: 2 }.find_name(&pool).await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_upsert() {
+        let pool = sqlite().await;
+        let account = NewAccount { id: 2, name: "a" };
+        assert_eq!(account.insert(&pool).await.unwrap().name, "a");
+        assert!(account.insert(&pool).await.is_err());
+        let account = NewAccount { id: 2, name: "b" };
+        let expect = Account {
+            id: 2,
+            name: "b".to_owned(),
+        };
+        assert_eq!(account.upsert(&pool).await.unwrap(), expect);
+        let account = NewAccount { id: 3, name: "c" };
+        assert_eq!(account.upsert(&pool).await.unwrap().id, 3);
+    }
+
+    #[tokio::test]
+    async fn test_named() {
+        let pool = sqlite().await;
+        let find = FindNamed { id: 1, name: "NUI" };
+        assert_eq!(find.count(&pool).await.unwrap(), 1);
+        let find = FindNamed { id: 0, name: "NUI" };
+        assert_eq!(find.count(&pool).await.unwrap(), 1);
+        let find = FindNamed { id: 2, name: "NUI" };
+        assert_eq!(find.count(&pool).await.unwrap(), 0);
+        let error = find.missing(&pool).await.unwrap_err();
+        assert!(matches!(error, sqlx::Error::InvalidArgument(_)));
+    }
+
+    #[allow(dead_code)]
+    async fn postgres_query_compiles(pool: &sqlx::PgPool) -> sqlx::Result<Vec<String>> {
+        FindNamed { id: 1, name: "a" }.find_postgres(pool).await?;
+        ListNames.list_postgres(pool).await
+    }
+
+    // there is no MySQL server in tests, only make sure generated methods type check
+    #[allow(dead_code)]
+    async fn mysql_query_compiles(pool: &sqlx::MySqlPool) -> sqlx::Result<()> {
+        let find = FindAccount { id: 1 };
+        find.get_mysql(pool).await?;
+        find.delete_mysql(pool).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+//! Memory usage of current process, independent of global allocator.
+//!
+//! ```ignore
+//! caco3_web::generate_read_jemalloc_raw_data!(fn read_jemalloc_raw_data);
+//!
+//! let allocator: Box<dyn AllocatorInfo> = if cfg!(target_env = "musl") {
+//!     Box::new(ProcStatus)
+//! } else {
+//!     Box::new(Jemalloc(read_jemalloc_raw_data))
+//! };
+//! let memory = allocator.memory_stats();
+//! ```
+use byte_unit::Byte;
+use serde::{Serialize, Serializer};
+
+use crate::jemalloc::info::{serialize_byte, JemallocRawData};
+
+/// Memory statistics, fields are `None` if not provided by the allocator.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MemoryStats {
+    pub allocator: &'static str,
+    /// Bytes allocated by the application.
+    #[serde(serialize_with = "serialize_option_byte")]
+    pub allocated: Option<Byte>,
+    /// Bytes of physical memory used by the process.
+    #[serde(serialize_with = "serialize_option_byte")]
+    pub resident: Option<Byte>,
+    /// Peak of `resident`.
+    #[serde(serialize_with = "serialize_option_byte")]
+    pub peak_resident: Option<Byte>,
+}
+
+fn serialize_option_byte<S>(this: &Option<Byte>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    match this {
+        Some(byte) => serialize_byte(byte, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Source of memory statistics.
+pub trait AllocatorInfo: Send + Sync {
+    /// Returns `None` if statistics can't be read.
+    fn memory_stats(&self) -> Option<MemoryStats>;
+}
+
+/// Statistics from jemalloc, read by a function generated with
+/// [`generate_read_jemalloc_raw_data`](crate::generate_read_jemalloc_raw_data).
+#[derive(Debug, Clone, Copy)]
+pub struct Jemalloc<F>(pub F);
+
+impl<F> AllocatorInfo for Jemalloc<F>
+where
+    F: Fn() -> Option<JemallocRawData> + Send + Sync,
+{
+    fn memory_stats(&self) -> Option<MemoryStats> {
+        let raw = (self.0)()?;
+        Some(MemoryStats {
+            allocator: "jemalloc",
+            allocated: Some(Byte::from_u64(raw.allocated_bytes.try_into().ok()?)),
+            resident: Some(Byte::from_u64(raw.resident_bytes.try_into().ok()?)),
+            peak_resident: None,
+        })
+    }
+}
+
+/// Statistics from `/proc/self/status`, works with any allocator on Linux.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcStatus;
+
+impl ProcStatus {
+    fn parse(status: &str) -> MemoryStats {
+        // lines look like `VmRSS:	  1420 kB`
+        let field = |name: &str| {
+            let line = status.lines().find(|l| l.starts_with(name))?;
+            let mut parts = line[name.len()..].split_whitespace();
+            let value: u64 = parts.next()?.parse().ok()?;
+            let bytes = match parts.next() {
+                Some("kB") => value.checked_mul(1024)?,
+                None => value,
+                Some(_) => return None,
+            };
+            Some(Byte::from_u64(bytes))
+        };
+        MemoryStats {
+            allocator: "system",
+            allocated: None,
+            resident: field("VmRSS:"),
+            peak_resident: field("VmHWM:"),
+        }
+    }
+}
+
+impl AllocatorInfo for ProcStatus {
+    fn memory_stats(&self) -> Option<MemoryStats> {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+        Some(Self::parse(&status))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_proc_status() {
+        let status = "Name:\tcat\nVmPeak:\t    2640 kB\nVmHWM:\t    1420 kB\nVmRSS:\t    1024 kB\n";
+        let stats = ProcStatus::parse(status);
+        assert_eq!(stats.resident, Some(Byte::from_u64(1024 * 1024)));
+        assert_eq!(stats.peak_resident, Some(Byte::from_u64(1420 * 1024)));
+        assert_eq!(stats.allocated, None);
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["resident"], "1.00 MiB");
+        assert!(json["allocated"].is_null());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_proc_status() {
+        let stats = ProcStatus.memory_stats().unwrap();
+        assert!(stats.resident.is_some());
+    }
+
+    #[test]
+    fn test_jemalloc() {
+        let info = Jemalloc(|| {
+            Some(JemallocRawData {
+                active_bytes: 0,
+                allocated_bytes: 1024,
+                mapped_bytes: 0,
+                metadata_bytes: 0,
+                resident_bytes: 2048,
+                retained_bytes: 0,
+                background_thread: None,
+                number_of_arenas: 1,
+            })
+        });
+        let stats = info.memory_stats().unwrap();
+        assert_eq!(stats.allocator, "jemalloc");
+        assert_eq!(stats.allocated, Some(Byte::from_u64(1024)));
+        assert!(Jemalloc(|| None).memory_stats().is_none());
+    }
+}
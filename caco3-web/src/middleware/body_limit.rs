@@ -0,0 +1,250 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::extract::DefaultBodyLimit;
+use axum::http::{header, Request, Response, StatusCode};
+use axum::response::IntoResponse;
+use axum::BoxError;
+use futures_core::ready;
+use http_body::{Frame, SizeHint};
+use http_body_util::{LengthLimitError, Limited};
+use pin_project::pin_project;
+use tower::{Layer, Service};
+use tracing::trace;
+
+use crate::json::ApiJson;
+
+/// [`Layer`] that limits request body size and responds `413 Payload Too Large` in [`ApiJson`] format.
+///
+/// Apply this layer to individual routes to have per-route limits.
+/// It replaces axum's [`DefaultBodyLimit`] of wrapped routes.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimitLayer {
+    limit: usize,
+}
+
+impl BodyLimitLayer {
+    pub fn new(limit: usize) -> Self {
+        Self { limit }
+    }
+}
+
+impl<S> Layer<S> for BodyLimitLayer {
+    type Service = BodyLimitService<<DefaultBodyLimit as Layer<S>>::Service>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodyLimitService {
+            // we enforce the limit ourselves
+            inner: DefaultBodyLimit::disable().layer(inner),
+            limit: self.limit,
+        }
+    }
+}
+
+/// Middleware that limits request body size.
+#[derive(Debug, Clone, Copy)]
+pub struct BodyLimitService<S> {
+    inner: S,
+    limit: usize,
+}
+
+fn payload_too_large(limit: usize) -> Response<Body> {
+    let json = ApiJson::unit_error_builder()
+        .error(format!("Payload too large, maximum size is {limit} bytes"))
+        .build();
+    (StatusCode::PAYLOAD_TOO_LARGE, json).into_response()
+}
+
+impl<S> Service<Request<Body>> for BodyLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BodyLimitFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        let limit = self.limit;
+        let content_length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        if content_length.is_some_and(|n| n > limit as u64) {
+            trace!("BodyLimitService: content-length = {content_length:?}, limit = {limit}");
+            return BodyLimitFuture {
+                limit,
+                exceeded: None,
+                state: FutureState::Rejected,
+            };
+        }
+        let exceeded = Arc::new(AtomicBool::new(false));
+        let req = req.map(|body| {
+            Body::new(FlaggedLimited {
+                inner: Limited::new(body, limit),
+                exceeded: exceeded.clone(),
+            })
+        });
+        BodyLimitFuture {
+            limit,
+            exceeded: Some(exceeded),
+            state: FutureState::Polling(self.inner.call(req)),
+        }
+    }
+}
+
+#[pin_project]
+pub struct BodyLimitFuture<F> {
+    limit: usize,
+    exceeded: Option<Arc<AtomicBool>>,
+    #[pin]
+    state: FutureState<F>,
+}
+
+#[pin_project(project = FutureStateProj)]
+enum FutureState<F> {
+    Polling(#[pin] F),
+    Rejected,
+}
+
+impl<F, E> Future for BodyLimitFuture<F>
+where
+    F: Future<Output = Result<Response<Body>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        match this.state.project() {
+            FutureStateProj::Polling(fut) => {
+                let response = ready!(fut.poll(cx))?;
+                let exceeded = this
+                    .exceeded
+                    .as_ref()
+                    .is_some_and(|v| v.load(Ordering::Relaxed));
+                if exceeded {
+                    // replace whatever response the inner service made of the body error
+                    return Poll::Ready(Ok(payload_too_large(*this.limit)));
+                }
+                Poll::Ready(Ok(response))
+            }
+            FutureStateProj::Rejected => Poll::Ready(Ok(payload_too_large(*this.limit))),
+        }
+    }
+}
+
+/// [`Limited`] body that remembers whether the limit is exceeded.
+#[pin_project]
+struct FlaggedLimited {
+    #[pin]
+    inner: Limited<Body>,
+    exceeded: Arc<AtomicBool>,
+}
+
+impl HttpBody for FlaggedLimited {
+    type Data = Bytes;
+    type Error = BoxError;
+
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.project();
+        let frame = ready!(this.inner.poll_frame(cx));
+        if let Some(Err(err)) = &frame {
+            if err.is::<LengthLimitError>() {
+                this.exceeded.store(true, Ordering::Relaxed);
+            }
+        }
+        Poll::Ready(frame)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::post;
+    use axum::Router;
+    use futures_core::Stream;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/small", post(|body: Bytes| async move { body.len().to_string() }))
+            .route_layer(BodyLimitLayer::new(4))
+    }
+
+    async fn send(body: Body) -> (StatusCode, Bytes) {
+        let req = Request::post("/small").body(body).unwrap();
+        let res = app().oneshot(req).await.unwrap();
+        let status = res.status();
+        (status, axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap())
+    }
+
+    struct Chunks(Vec<&'static [u8]>);
+
+    impl Stream for Chunks {
+        type Item = Result<Bytes, std::io::Error>;
+
+        fn poll_next(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+            let chunk = (!self.0.is_empty()).then(|| Ok(Bytes::from_static(self.0.remove(0))));
+            Poll::Ready(chunk)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_within_limit() {
+        assert_eq!(send(Body::from("abcd")).await, (StatusCode::OK, "4".into()));
+    }
+
+    #[tokio::test]
+    async fn test_content_length_exceeded() {
+        let called = Arc::new(AtomicBool::new(false));
+        let app = Router::new()
+            .route(
+                "/small",
+                post({
+                    let called = called.clone();
+                    move || async move { called.store(true, Ordering::SeqCst) }
+                }),
+            )
+            .route_layer(BodyLimitLayer::new(4));
+        let req = Request::post("/small")
+            .header(header::CONTENT_LENGTH, 5)
+            .body(Body::from("abcde"))
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["message"], "Payload too large, maximum size is 4 bytes");
+        assert!(!called.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_streaming_body_exceeded() {
+        let body = Body::from_stream(Chunks(vec![b"abc", b"def"]));
+        let (status, body) = send(body).await;
+        assert_eq!(status, StatusCode::PAYLOAD_TOO_LARGE);
+        let json: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["code"], "-1");
+    }
+}
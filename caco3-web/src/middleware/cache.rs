@@ -0,0 +1,348 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::header::{CACHE_CONTROL, SET_COOKIE};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode, Uri};
+use axum::response::IntoResponse;
+use caco3::cache::TtlCache;
+use futures_core::future::BoxFuture;
+use tower::{Layer, Service};
+use tracing::{trace, warn};
+
+use crate::json::ApiJson;
+
+/// Response header indicating whether response is served from cache.
+pub const X_CACHE: HeaderName = HeaderName::from_static("x-cache");
+
+const HIT: HeaderValue = HeaderValue::from_static("HIT");
+const MISS: HeaderValue = HeaderValue::from_static("MISS");
+
+const DEFAULT_CAPACITY: NonZeroUsize = match NonZeroUsize::new(1024) {
+    Some(val) => val,
+    None => unreachable!(),
+};
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// Extract cache key from request.
+///
+/// Returning `None` bypasses the cache.
+pub trait KeyExtractor {
+    fn extract(&self, uri: &Uri, headers: &HeaderMap) -> Option<String>;
+}
+
+impl<F> KeyExtractor for F
+where
+    F: Fn(&Uri, &HeaderMap) -> Option<String>,
+{
+    fn extract(&self, uri: &Uri, headers: &HeaderMap) -> Option<String> {
+        self(uri, headers)
+    }
+}
+
+/// Default key extractor, use request path and query as a cache key.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PathAndQuery;
+
+impl KeyExtractor for PathAndQuery {
+    fn extract(&self, uri: &Uri, _headers: &HeaderMap) -> Option<String> {
+        let key = match uri.path_and_query() {
+            Some(pq) => pq.as_str().to_owned(),
+            None => uri.path().to_owned(),
+        };
+        Some(key)
+    }
+}
+
+/// Response extension to override TTL of the layer for a particular response.
+#[derive(Debug, Clone, Copy)]
+pub struct CacheTtl(pub Duration);
+
+#[derive(Clone)]
+struct CachedResponse {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+impl CachedResponse {
+    fn to_response(&self) -> Response<Body> {
+        let mut response = Response::new(Body::from(self.body.clone()));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers.clone();
+        response.headers_mut().insert(X_CACHE, HIT);
+        response
+    }
+}
+
+type Storage = TtlCache<String, CachedResponse>;
+
+/// Responses setting cookies or marked `Cache-Control: private` or `no-store` belong to one client.
+fn is_shareable(headers: &HeaderMap) -> bool {
+    if headers.contains_key(SET_COOKIE) {
+        return false;
+    }
+    let directives = headers
+        .get_all(CACHE_CONTROL)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','));
+    for directive in directives {
+        let name = directive.split('=').next().unwrap_or_default().trim();
+        if name.eq_ignore_ascii_case("private") || name.eq_ignore_ascii_case("no-store") {
+            return false;
+        }
+    }
+    true
+}
+
+fn mark_miss(mut response: Response<Body>) -> Response<Body> {
+    response.headers_mut().insert(X_CACHE, MISS);
+    response
+}
+
+/// [`Layer`] that caches successful `GET` responses in memory.
+///
+/// Apply this layer to expensive read-only routes, each layer instance has its own TTL and storage.
+/// Responses with `Set-Cookie`, `Cache-Control: private` or `Cache-Control: no-store` are not cached.
+/// Every response is marked with `X-Cache: HIT` or `X-Cache: MISS`.
+#[derive(Clone)]
+pub struct ResponseCacheLayer<K = PathAndQuery> {
+    ttl: Duration,
+    key_extractor: Arc<K>,
+    max_body_bytes: usize,
+    storage: Arc<Mutex<Storage>>,
+}
+
+impl ResponseCacheLayer {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            key_extractor: Arc::new(PathAndQuery),
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            storage: Arc::new(Mutex::new(Storage::new(DEFAULT_CAPACITY.get(), ttl))),
+        }
+    }
+}
+
+impl<K> ResponseCacheLayer<K> {
+    /// Set maximum number of cached responses.
+    pub fn capacity(mut self, capacity: NonZeroUsize) -> Self {
+        self.storage = Arc::new(Mutex::new(Storage::new(capacity.get(), self.ttl)));
+        self
+    }
+
+    /// Responses bigger than this size are not cached.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Replace key extractor.
+    pub fn key_extractor<K2: KeyExtractor>(self, key_extractor: K2) -> ResponseCacheLayer<K2> {
+        ResponseCacheLayer {
+            ttl: self.ttl,
+            key_extractor: Arc::new(key_extractor),
+            max_body_bytes: self.max_body_bytes,
+            storage: self.storage,
+        }
+    }
+}
+
+impl<S, K> Layer<S> for ResponseCacheLayer<K> {
+    type Service = ResponseCacheService<S, K>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ResponseCacheService {
+            inner,
+            ttl: self.ttl,
+            key_extractor: self.key_extractor.clone(),
+            max_body_bytes: self.max_body_bytes,
+            storage: self.storage.clone(),
+        }
+    }
+}
+
+/// Middleware that caches successful `GET` responses in memory.
+#[derive(Clone)]
+pub struct ResponseCacheService<S, K> {
+    inner: S,
+    ttl: Duration,
+    key_extractor: Arc<K>,
+    max_body_bytes: usize,
+    storage: Arc<Mutex<Storage>>,
+}
+
+fn lock(storage: &Mutex<Storage>) -> MutexGuard<'_, Storage> {
+    // Poisoned state is not a problem for us.
+    storage.lock().unwrap_or_else(|x| x.into_inner())
+}
+
+impl<ReqBody, S, K> Service<Request<ReqBody>> for ResponseCacheService<S, K>
+where
+    S: Service<Request<ReqBody>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    K: KeyExtractor,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let key = if req.method() == Method::GET {
+            self.key_extractor.extract(req.uri(), req.headers())
+        } else {
+            None
+        };
+        let Some(key) = key else {
+            let future = self.inner.call(req);
+            return Box::pin(async move { future.await.map(mark_miss) });
+        };
+        if let Some(cached) = lock(&self.storage).get(&key) {
+            trace!("ResponseCacheService: hit key = {key:?}");
+            let response = cached.to_response();
+            return Box::pin(async move { Ok(response) });
+        }
+
+        // take the service that was ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let ttl = self.ttl;
+        let max_body_bytes = self.max_body_bytes;
+        let storage = self.storage.clone();
+        Box::pin(async move {
+            let response = inner.call(req).await?;
+            let cacheable = response.status().is_success()
+                && is_shareable(response.headers())
+                && response
+                    .body()
+                    .size_hint()
+                    .upper()
+                    .is_some_and(|n| n <= max_body_bytes as u64);
+            if !cacheable {
+                return Ok(mark_miss(response));
+            }
+            let (parts, body) = response.into_parts();
+            let body = match axum::body::to_bytes(body, max_body_bytes).await {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    warn!("ResponseCacheService: failed to buffer response body: {err}");
+                    let response = (
+                        StatusCode::INTERNAL_SERVER_ERROR,
+                        ApiJson::default_error(),
+                    );
+                    return Ok(mark_miss(response.into_response()));
+                }
+            };
+            let ttl = parts.extensions.get::<CacheTtl>().map_or(ttl, |v| v.0);
+            let cached = CachedResponse {
+                status: parts.status,
+                headers: parts.headers.clone(),
+                body: body.clone(),
+            };
+            lock(&storage).insert_with_ttl(key, cached, ttl);
+            Ok(mark_miss(Response::from_parts(parts, Body::from(body))))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::routing::{get, post};
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(counter: Arc<AtomicUsize>, layer: ResponseCacheLayer) -> Router {
+        Router::new()
+            .route(
+                "/report",
+                get(move || async move { counter.fetch_add(1, Ordering::SeqCst).to_string() }),
+            )
+            .layer(layer)
+    }
+
+    async fn get_report(app: &Router, uri: &str) -> (Option<HeaderValue>, Bytes) {
+        let req = Request::get(uri).body(Body::empty()).unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        let x_cache = res.headers().get(X_CACHE).cloned();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (x_cache, body)
+    }
+
+    #[tokio::test]
+    async fn test_cache_hit_and_miss() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let app = app(counter, ResponseCacheLayer::new(Duration::from_secs(60)));
+
+        assert_eq!(get_report(&app, "/report").await, (Some(MISS), "0".into()));
+        assert_eq!(get_report(&app, "/report").await, (Some(HIT), "0".into()));
+        // different query is a different key
+        assert_eq!(get_report(&app, "/report?a=1").await, (Some(MISS), "1".into()));
+    }
+
+    #[tokio::test]
+    async fn test_cache_expired() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let app = app(counter, ResponseCacheLayer::new(Duration::ZERO));
+
+        assert_eq!(get_report(&app, "/report").await, (Some(MISS), "0".into()));
+        assert_eq!(get_report(&app, "/report").await, (Some(MISS), "1".into()));
+    }
+
+    #[tokio::test]
+    async fn test_cache_capacity() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let layer = ResponseCacheLayer::new(Duration::from_secs(60))
+            .capacity(NonZeroUsize::new(2).unwrap());
+        let app = app(counter, layer);
+
+        assert_eq!(get_report(&app, "/report?a").await, (Some(MISS), "0".into()));
+        assert_eq!(get_report(&app, "/report?b").await, (Some(MISS), "1".into()));
+        assert_eq!(get_report(&app, "/report?a").await, (Some(HIT), "0".into()));
+        assert_eq!(get_report(&app, "/report?c").await, (Some(MISS), "2".into()));
+        assert_eq!(get_report(&app, "/report?a").await, (Some(HIT), "0".into()));
+        // least recently used is evicted
+        assert_eq!(get_report(&app, "/report?b").await, (Some(MISS), "3".into()));
+    }
+
+    #[tokio::test]
+    async fn test_private_response() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let response = move |headers: [(HeaderName, &'static str); 1]| {
+            let counter = counter.clone();
+            move || async move { (headers, counter.fetch_add(1, Ordering::SeqCst).to_string()) }
+        };
+        let app = Router::new()
+            .route("/cookie", get(response([(SET_COOKIE, "session=1")])))
+            .route("/private", get(response([(CACHE_CONTROL, "max-age=60, Private")])))
+            .route("/no-store", get(response([(CACHE_CONTROL, "no-store")])))
+            .route("/public", get(response([(CACHE_CONTROL, "public, max-age=60")])))
+            .route("/submit", post(|| async { "ok" }))
+            .layer(ResponseCacheLayer::new(Duration::from_secs(60)));
+
+        assert_eq!(get_report(&app, "/cookie").await, (Some(MISS), "0".into()));
+        assert_eq!(get_report(&app, "/cookie").await, (Some(MISS), "1".into()));
+        assert_eq!(get_report(&app, "/private").await, (Some(MISS), "2".into()));
+        assert_eq!(get_report(&app, "/private").await, (Some(MISS), "3".into()));
+        assert_eq!(get_report(&app, "/no-store").await, (Some(MISS), "4".into()));
+        assert_eq!(get_report(&app, "/no-store").await, (Some(MISS), "5".into()));
+        assert_eq!(get_report(&app, "/public").await, (Some(MISS), "6".into()));
+        assert_eq!(get_report(&app, "/public").await, (Some(HIT), "6".into()));
+        // bypassed and failed responses are marked too
+        assert_eq!(get_report(&app, "/missing").await, (Some(MISS), "".into()));
+        let req = Request::post("/submit").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.headers().get(X_CACHE), Some(&MISS));
+    }
+}
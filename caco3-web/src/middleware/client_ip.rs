@@ -0,0 +1,369 @@
+//! Client ip extraction honoring proxy headers.
+//!
+//! Proxy headers are only honored when the connected peer is a trusted proxy.
+//! The header chain is walked from right to left, the first address which is not a trusted proxy
+//! is the client address.
+//!
+//! Headers are looked up in following order, only the first present header is used.
+//! 1. `Forwarded` ([RFC 7239](https://www.rfc-editor.org/rfc/rfc7239))
+//! 2. `X-Forwarded-For`
+//! 3. `X-Real-IP`
+//!
+//! The connected peer is read from [`ConnectInfo<SocketAddr>`], the server must be started with
+//! `into_make_service_with_connect_info::<SocketAddr>()`.
+use std::fmt;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::extract::{ConnectInfo, FromRequestParts};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, Request, StatusCode};
+use serde::{Deserialize, Deserializer, Serialize};
+use thiserror::Error;
+use tower::{Layer, Service};
+use tracing::{trace, warn};
+
+use crate::json::ApiJson;
+
+const FORWARDED: &str = "forwarded";
+const X_FORWARDED_FOR: &str = "x-forwarded-for";
+const X_REAL_IP: &str = "x-real-ip";
+
+/// Client ip address, inserted into request extensions by [`ClientIpLayer`].
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub struct ClientIp(pub IpAddr);
+
+impl fmt::Display for ClientIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.0, f)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ClientIp {
+    type Rejection = (StatusCode, ApiJson<()>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<ClientIp>() {
+            Some(ip) => Ok(*ip),
+            None => {
+                warn!("ClientIp extension not found, is ClientIpLayer installed?");
+                Err((StatusCode::INTERNAL_SERVER_ERROR, ApiJson::default_error()))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+#[error("invalid ip network: {0}")]
+pub struct ParseIpNetError(String);
+
+/// An ip network in CIDR notation, e.g. `10.0.0.0/8`.
+///
+/// Single address without prefix length is also accepted.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        fn masked(bits: u128, width: u8, prefix_len: u8) -> u128 {
+            if prefix_len == 0 {
+                0
+            } else {
+                bits >> (width - prefix_len)
+            }
+        }
+        match (self.addr, normalize(ip)) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let net = u32::from(net).into();
+                let ip = u32::from(ip).into();
+                masked(net, 32, self.prefix_len) == masked(ip, 32, self.prefix_len)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let net = u128::from(net);
+                let ip = u128::from(ip);
+                masked(net, 128, self.prefix_len) == masked(ip, 128, self.prefix_len)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Convert ipv4-mapped ipv6 address to ipv4.
+fn normalize(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        v4 => v4,
+    }
+}
+
+impl FromStr for IpNet {
+    type Err = ParseIpNetError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseIpNetError(s.to_owned());
+        let (addr, prefix_len) = match s.split_once('/') {
+            Some((addr, len)) => (addr, Some(len)),
+            None => (s, None),
+        };
+        let addr = normalize(addr.trim().parse::<IpAddr>().map_err(|_| err())?);
+        let max_len = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix_len = match prefix_len {
+            Some(len) => len.trim().parse::<u8>().map_err(|_| err())?,
+            None => max_len,
+        };
+        if prefix_len > max_len {
+            return Err(err());
+        }
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl fmt::Display for IpNet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl<'de> Deserialize<'de> for IpNet {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for IpNet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Client ip configuration.
+///
+/// ```toml
+/// [client_ip]
+/// trusted_proxies = ["127.0.0.1", "10.0.0.0/8"]
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct ClientIpConfig {
+    #[serde(default)]
+    pub trusted_proxies: Vec<IpNet>,
+}
+
+impl ClientIpConfig {
+    fn is_trusted(&self, ip: IpAddr) -> bool {
+        self.trusted_proxies.iter().any(|net| net.contains(ip))
+    }
+
+    /// Resolve client ip from connected peer and request headers.
+    pub fn resolve(&self, peer: IpAddr, headers: &HeaderMap) -> IpAddr {
+        let mut client = normalize(peer);
+        if !self.is_trusted(client) {
+            return client;
+        }
+        let hops = forwarded_hops(headers);
+        for hop in hops.iter().rev() {
+            let Some(hop) = hop else {
+                // We can't go any further, the last trusted proxy is the best we know.
+                break;
+            };
+            client = normalize(*hop);
+            if !self.is_trusted(client) {
+                break;
+            }
+        }
+        client
+    }
+}
+
+/// Get forwarded hops from the first present proxy header, `None` is unparseable entry.
+fn forwarded_hops(headers: &HeaderMap) -> Vec<Option<IpAddr>> {
+    fn values<'a>(headers: &'a HeaderMap, name: &str) -> impl Iterator<Item = &'a str> {
+        headers
+            .get_all(name)
+            .into_iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+    }
+
+    if headers.contains_key(FORWARDED) {
+        values(headers, FORWARDED).map(parse_forwarded_element).collect()
+    } else if headers.contains_key(X_FORWARDED_FOR) {
+        values(headers, X_FORWARDED_FOR).map(parse_node).collect()
+    } else {
+        values(headers, X_REAL_IP).take(1).map(parse_node).collect()
+    }
+}
+
+/// Parse `for` parameter of a `Forwarded` header element, e.g. `for=192.0.2.60;proto=http`.
+fn parse_forwarded_element(element: &str) -> Option<IpAddr> {
+    element.split(';').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        key.trim()
+            .eq_ignore_ascii_case("for")
+            .then(|| parse_node(value.trim().trim_matches('"')))?
+    })
+}
+
+/// Parse ip address with optional port, e.g. `192.0.2.60`, `192.0.2.60:80`, `[2001:db8::1]:80`.
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let node = node.trim();
+    if let Ok(ip) = node.parse::<IpAddr>() {
+        return Some(ip);
+    }
+    if let Ok(addr) = node.parse::<SocketAddr>() {
+        return Some(addr.ip());
+    }
+    // bracketed ipv6 without port
+    node.strip_prefix('[')
+        .and_then(|s| s.strip_suffix(']'))
+        .and_then(|s| s.parse().ok())
+}
+
+/// [`Layer`] that inserts [`ClientIp`] into request extensions.
+#[derive(Debug, Clone)]
+pub struct ClientIpLayer {
+    config: Arc<ClientIpConfig>,
+}
+
+impl ClientIpLayer {
+    pub fn new(config: ClientIpConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+        }
+    }
+}
+
+impl<S> Layer<S> for ClientIpLayer {
+    type Service = ClientIpService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ClientIpService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that inserts [`ClientIp`] into request extensions.
+#[derive(Debug, Clone)]
+pub struct ClientIpService<S> {
+    inner: S,
+    config: Arc<ClientIpConfig>,
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for ClientIpService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        match peer {
+            Some(peer) => {
+                let ip = self.config.resolve(peer, req.headers());
+                trace!("ClientIpService: peer = {peer}, client_ip = {ip}");
+                req.extensions_mut().insert(ClientIp(ip));
+            }
+            None => {
+                trace!("ClientIpService: ConnectInfo<SocketAddr> not found");
+            }
+        }
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    fn config() -> ClientIpConfig {
+        ClientIpConfig {
+            trusted_proxies: vec!["10.0.0.0/8".parse().unwrap(), "::1".parse().unwrap()],
+        }
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn test_ip_net() {
+        let net: IpNet = "10.0.0.0/8".parse().unwrap();
+        assert!(net.contains(ip("10.1.2.3")));
+        assert!(net.contains(ip("::ffff:10.1.2.3")));
+        assert!(!net.contains(ip("11.0.0.1")));
+        let net: IpNet = "2001:db8::/32".parse().unwrap();
+        assert!(net.contains(ip("2001:db8:cafe::17")));
+        assert!(!net.contains(ip("2001:db9::1")));
+        let net: IpNet = "0.0.0.0/0".parse().unwrap();
+        assert!(net.contains(ip("1.2.3.4")));
+        assert!("10.0.0.0/33".parse::<IpNet>().is_err());
+        assert!("foo".parse::<IpNet>().is_err());
+    }
+
+    #[test]
+    fn test_untrusted_peer() {
+        let headers = headers(&[(X_FORWARDED_FOR, "1.1.1.1")]);
+        assert_eq!(config().resolve(ip("8.8.8.8"), &headers), ip("8.8.8.8"));
+    }
+
+    #[test]
+    fn test_x_forwarded_for() {
+        let headers = headers(&[(X_FORWARDED_FOR, "6.6.6.6, 1.1.1.1, 10.0.0.2")]);
+        assert_eq!(config().resolve(ip("10.0.0.1"), &headers), ip("1.1.1.1"));
+        // all hops are trusted
+        let headers = self::headers(&[(X_FORWARDED_FOR, "10.0.0.3")]);
+        assert_eq!(config().resolve(ip("10.0.0.1"), &headers), ip("10.0.0.3"));
+    }
+
+    #[test]
+    fn test_forwarded() {
+        let headers = headers(&[(
+            FORWARDED,
+            r#"for=6.6.6.6, for="[2001:db8:cafe::17]:4711";proto=https, for=10.0.0.2:80"#,
+        )]);
+        assert_eq!(config().resolve(ip("::1"), &headers), ip("2001:db8:cafe::17"));
+        // obfuscated identifier stops the walk
+        let headers = self::headers(&[(FORWARDED, "for=1.1.1.1, for=_hidden, for=10.0.0.2")]);
+        assert_eq!(config().resolve(ip("10.0.0.1"), &headers), ip("10.0.0.2"));
+    }
+
+    #[test]
+    fn test_x_real_ip() {
+        let headers = headers(&[(X_REAL_IP, "1.1.1.1")]);
+        assert_eq!(config().resolve(ip("10.0.0.1"), &headers), ip("1.1.1.1"));
+    }
+
+    #[test]
+    fn test_deserialize_config() {
+        let config: ClientIpConfig =
+            serde_json::from_str(r#"{"trusted_proxies": ["127.0.0.1", "10.0.0.0/8"]}"#).unwrap();
+        assert_eq!(config.trusted_proxies.len(), 2);
+        assert!(serde_json::from_str::<ClientIpConfig>(r#"{"trusted_proxies": ["x"]}"#).is_err());
+    }
+}
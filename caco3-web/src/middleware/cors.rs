@@ -0,0 +1,236 @@
+//! CORS configuration.
+//!
+//! Example configuration
+//! ```toml
+//! [cors]
+//! allowed_origins = ["https://example.com", "https://*.example.com"]
+//! allowed_methods = ["GET", "POST"]
+//! allowed_headers = ["content-type", "authorization"]
+//! allow_credentials = true
+//! max_age_secs = 3600
+//! ```
+//!
+//! `*` is accepted in origins, methods and headers lists to allow anything.
+//! In origin patterns, `*` matches any sequence of characters except `/`.
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer, ExposeHeaders};
+
+const WILDCARD: &str = "*";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum CorsConfigError {
+    #[error("invalid origin: {0}")]
+    InvalidOrigin(String),
+    #[error("invalid method: {0}")]
+    InvalidMethod(String),
+    #[error("invalid header: {0}")]
+    InvalidHeader(String),
+}
+
+/// CORS configuration.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct CorsConfig {
+    #[serde(default)]
+    pub allowed_origins: Vec<String>,
+    #[serde(default)]
+    pub allowed_methods: Vec<String>,
+    #[serde(default)]
+    pub allowed_headers: Vec<String>,
+    #[serde(default)]
+    pub exposed_headers: Vec<String>,
+    #[serde(default)]
+    pub allow_credentials: bool,
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+fn has_wildcard(values: &[String]) -> bool {
+    values.iter().any(|v| v == WILDCARD)
+}
+
+fn parse_headers(values: &[String]) -> Result<Vec<HeaderName>, CorsConfigError> {
+    values
+        .iter()
+        .map(|v| v.parse().map_err(|_| CorsConfigError::InvalidHeader(v.clone())))
+        .collect()
+}
+
+/// Match `origin` against `pattern`, `*` matches any sequence of characters except `/`.
+fn match_origin(pattern: &str, origin: &str) -> bool {
+    match pattern.split_once('*') {
+        None => pattern == origin,
+        Some((prefix, rest)) => {
+            let Some(origin) = origin.strip_prefix(prefix) else {
+                return false;
+            };
+            // try every possible length of wildcard match
+            origin
+                .char_indices()
+                .map(|(i, _)| i)
+                .chain([origin.len()])
+                .take_while(|&i| !origin[..i].contains('/'))
+                .any(|i| match_origin(rest, &origin[i..]))
+        }
+    }
+}
+
+impl CorsConfig {
+    fn allow_origin(&self) -> Result<AllowOrigin, CorsConfigError> {
+        if has_wildcard(&self.allowed_origins) {
+            // `Access-Control-Allow-Origin: *` can't be used with credentials
+            return Ok(if self.allow_credentials {
+                AllowOrigin::mirror_request()
+            } else {
+                AllowOrigin::any()
+            });
+        }
+        let (patterns, exact): (Vec<_>, Vec<_>) =
+            self.allowed_origins.iter().partition(|v| v.contains('*'));
+        let exact = exact
+            .into_iter()
+            .map(|v| {
+                HeaderValue::from_str(v).map_err(|_| CorsConfigError::InvalidOrigin(v.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if patterns.is_empty() {
+            return Ok(AllowOrigin::list(exact));
+        }
+        let patterns: Vec<String> = patterns.into_iter().cloned().collect();
+        Ok(AllowOrigin::predicate(move |origin, _| {
+            if exact.contains(origin) {
+                return true;
+            }
+            origin
+                .to_str()
+                .is_ok_and(|origin| patterns.iter().any(|p| match_origin(p, origin)))
+        }))
+    }
+
+    fn allow_methods(&self) -> Result<AllowMethods, CorsConfigError> {
+        if has_wildcard(&self.allowed_methods) {
+            return Ok(if self.allow_credentials {
+                AllowMethods::mirror_request()
+            } else {
+                AllowMethods::any()
+            });
+        }
+        let methods = self
+            .allowed_methods
+            .iter()
+            .map(|v| {
+                Method::from_bytes(v.to_ascii_uppercase().as_bytes())
+                    .map_err(|_| CorsConfigError::InvalidMethod(v.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(AllowMethods::list(methods))
+    }
+
+    fn allow_headers(&self) -> Result<AllowHeaders, CorsConfigError> {
+        if has_wildcard(&self.allowed_headers) {
+            return Ok(if self.allow_credentials {
+                AllowHeaders::mirror_request()
+            } else {
+                AllowHeaders::any()
+            });
+        }
+        Ok(AllowHeaders::list(parse_headers(&self.allowed_headers)?))
+    }
+
+    fn expose_headers(&self) -> Result<ExposeHeaders, CorsConfigError> {
+        if has_wildcard(&self.exposed_headers) && !self.allow_credentials {
+            return Ok(ExposeHeaders::any());
+        }
+        let headers = self
+            .exposed_headers
+            .iter()
+            .filter(|v| *v != WILDCARD)
+            .cloned()
+            .collect::<Vec<_>>();
+        Ok(ExposeHeaders::list(parse_headers(&headers)?))
+    }
+
+    /// Build [`CorsLayer`] from this configuration.
+    pub fn into_layer(self) -> Result<CorsLayer, CorsConfigError> {
+        let mut layer = CorsLayer::new()
+            .allow_origin(self.allow_origin()?)
+            .allow_methods(self.allow_methods()?)
+            .allow_headers(self.allow_headers()?)
+            .expose_headers(self.expose_headers()?)
+            .allow_credentials(self.allow_credentials);
+        if let Some(secs) = self.max_age_secs {
+            layer = layer.max_age(Duration::from_secs(secs));
+        }
+        Ok(layer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::http::{header, Request, StatusCode};
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_match_origin() {
+        assert!(match_origin("https://example.com", "https://example.com"));
+        assert!(match_origin("https://*.example.com", "https://api.example.com"));
+        assert!(match_origin("https://*.example.com", "https://a.b.example.com"));
+        assert!(!match_origin("https://*.example.com", "https://example.com"));
+        assert!(!match_origin("https://*.example.com", "https://evil.com/.example.com"));
+        assert!(match_origin("http://localhost:*", "http://localhost:3000"));
+    }
+
+    async fn preflight(config: CorsConfig, origin: &str) -> Option<HeaderValue> {
+        let app = Router::new()
+            .route("/", get(|| async { "ok" }))
+            .layer(config.into_layer().unwrap());
+        let req = Request::options("/")
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "GET")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        res.headers()
+            .get(header::ACCESS_CONTROL_ALLOW_ORIGIN)
+            .cloned()
+    }
+
+    #[tokio::test]
+    async fn test_into_layer() {
+        let config: CorsConfig = serde_json::from_value(serde_json::json!({
+            "allowed_origins": ["https://example.com", "https://*.example.org"],
+            "allowed_methods": ["get", "POST"],
+            "allowed_headers": ["*"],
+            "allow_credentials": true,
+            "max_age_secs": 60,
+        }))
+        .unwrap();
+        let origin = "https://api.example.org";
+        assert_eq!(preflight(config.clone(), origin).await.unwrap(), origin);
+        let origin = "https://example.com";
+        assert_eq!(preflight(config.clone(), origin).await.unwrap(), origin);
+        assert!(preflight(config, "https://example.net").await.is_none());
+    }
+
+    #[test]
+    fn test_invalid_config() {
+        let config = CorsConfig {
+            allowed_methods: vec!["GET POST".to_owned()],
+            ..Default::default()
+        };
+        assert!(matches!(
+            config.into_layer(),
+            Err(CorsConfigError::InvalidMethod(_))
+        ));
+    }
+}
@@ -0,0 +1,245 @@
+use std::task::{Context, Poll};
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{header, HeaderMap, HeaderValue, Method, Request, Response, StatusCode};
+use axum::response::IntoResponse;
+use futures_core::future::BoxFuture;
+use tower::{Layer, Service};
+use tracing::{trace, warn};
+
+use crate::json::ApiJson;
+
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+
+/// 64-bit FNV-1a, stable across processes and releases unlike `DefaultHasher`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(PRIME)
+    })
+}
+
+fn strong_etag(body: &[u8]) -> HeaderValue {
+    let etag = format!("\"{:016x}\"", fnv1a(body));
+    HeaderValue::try_from(etag).expect("hex digits are valid header value")
+}
+
+/// Whether `If-None-Match` header matches `etag`, using weak comparison as required by RFC 9110.
+fn if_none_match(headers: &HeaderMap, etag: &HeaderValue) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+    let Ok(etag) = etag.to_str().map(opaque) else {
+        return false;
+    };
+    headers
+        .get_all(header::IF_NONE_MATCH)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .any(|tag| tag.trim() == "*" || opaque(tag) == etag)
+}
+
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|mime| {
+            let mime = mime.trim();
+            mime.eq_ignore_ascii_case("application/json")
+                || mime
+                    .get(mime.len().saturating_sub(5)..)
+                    .is_some_and(|suffix| suffix.eq_ignore_ascii_case("+json"))
+        })
+}
+
+fn not_modified(mut headers: HeaderMap, etag: HeaderValue) -> Response<Body> {
+    // representation headers are meaningless without a body
+    headers.remove(header::CONTENT_TYPE);
+    headers.remove(header::CONTENT_LENGTH);
+    headers.insert(header::ETAG, etag);
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = StatusCode::NOT_MODIFIED;
+    *response.headers_mut() = headers;
+    response
+}
+
+/// [`Layer`] that sets strong `ETag` on successful JSON responses and answers
+/// `304 Not Modified` to requests with matching `If-None-Match`.
+///
+/// The response body is buffered and hashed, so only apply this layer to routes with
+/// small and frequently polled responses, e.g. with [`Router::route_layer`](axum::Router::route_layer).
+/// Responses that already have `ETag` are left as is, except for the `304` handling.
+#[derive(Debug, Clone, Copy)]
+pub struct EtagLayer {
+    max_body_bytes: usize,
+}
+
+impl Default for EtagLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EtagLayer {
+    pub fn new() -> Self {
+        Self {
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+        }
+    }
+
+    /// Responses bigger than this size are passed through without `ETag`.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        self.max_body_bytes = max_body_bytes;
+        self
+    }
+}
+
+impl<S> Layer<S> for EtagLayer {
+    type Service = EtagService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        EtagService {
+            inner,
+            max_body_bytes: self.max_body_bytes,
+        }
+    }
+}
+
+/// Middleware that sets `ETag` on JSON responses.
+#[derive(Debug, Clone, Copy)]
+pub struct EtagService<S> {
+    inner: S,
+    max_body_bytes: usize,
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for EtagService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        if !matches!(*req.method(), Method::GET | Method::HEAD) {
+            return Box::pin(self.inner.call(req));
+        }
+        let request_headers = req.headers().clone();
+
+        // take the service that was ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let max_body_bytes = self.max_body_bytes;
+        Box::pin(async move {
+            let response = inner.call(req).await?;
+            if !response.status().is_success() {
+                return Ok(response);
+            }
+            if let Some(etag) = response.headers().get(header::ETAG).cloned() {
+                if if_none_match(&request_headers, &etag) {
+                    let (parts, _) = response.into_parts();
+                    return Ok(not_modified(parts.headers, etag));
+                }
+                return Ok(response);
+            }
+            let eligible = is_json(response.headers())
+                && response
+                    .body()
+                    .size_hint()
+                    .upper()
+                    .is_some_and(|n| n <= max_body_bytes as u64);
+            if !eligible {
+                return Ok(response);
+            }
+            let (mut parts, body) = response.into_parts();
+            let body: Bytes = match axum::body::to_bytes(body, max_body_bytes).await {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    warn!("EtagService: failed to buffer response body: {err}");
+                    let response = (StatusCode::INTERNAL_SERVER_ERROR, ApiJson::default_error());
+                    return Ok(response.into_response());
+                }
+            };
+            let etag = strong_etag(&body);
+            if if_none_match(&request_headers, &etag) {
+                trace!("EtagService: not modified, etag = {etag:?}");
+                return Ok(not_modified(parts.headers, etag));
+            }
+            parts.headers.insert(header::ETAG, etag);
+            Ok(Response::from_parts(parts, Body::from(body)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use axum::{Json, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app() -> Router {
+        Router::new()
+            .route("/json", get(|| async { Json([1, 2, 3]) }))
+            .route_layer(EtagLayer::new())
+            .route("/text", get(|| async { "plain" }))
+    }
+
+    async fn send(uri: &str, if_none_match: Option<&HeaderValue>) -> Response<Body> {
+        let mut req = Request::get(uri);
+        if let Some(value) = if_none_match {
+            req = req.header(header::IF_NONE_MATCH, value);
+        }
+        app()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_etag_and_not_modified() {
+        let res = send("/json", None).await;
+        assert_eq!(res.status(), StatusCode::OK);
+        let etag = res.headers().get(header::ETAG).unwrap().clone();
+        assert_eq!(etag, strong_etag(b"[1,2,3]"));
+
+        let res = send("/json", Some(&etag)).await;
+        assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(res.headers().get(header::ETAG), Some(&etag));
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.is_empty());
+
+        let stale = HeaderValue::from_static("\"0000000000000000\"");
+        assert_eq!(send("/json", Some(&stale)).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_route_without_layer() {
+        let res = send("/text", None).await;
+        assert!(res.headers().get(header::ETAG).is_none());
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let etag = HeaderValue::from_static("\"abc\"");
+        let mut headers = HeaderMap::new();
+        assert!(!if_none_match(&headers, &etag));
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static("\"x\", W/\"abc\""),
+        );
+        assert!(if_none_match(&headers, &etag));
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+        assert!(if_none_match(&headers, &etag));
+    }
+}
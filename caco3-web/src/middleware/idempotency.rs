@@ -0,0 +1,474 @@
+//! Idempotency-key support for unsafe methods.
+//!
+//! When a request carries `Idempotency-Key` header, its response is stored for a TTL and
+//! replayed on retries with the same key. Replayed responses have `Idempotent-Replayed: true` header.
+//! A retry that arrives while the first request is still in progress is rejected with
+//! `409 Conflict`.
+//!
+//! Server error responses (5xx) are not stored, so a client can retry them. Body of a response
+//! bigger than [`max_body_bytes`](IdempotencyLayer::max_body_bytes) is not stored, a retry is
+//! answered with `422 Unprocessable Entity` rather than processing the request again.
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use axum::body::{Body, Bytes, HttpBody};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Method, Request, Response, StatusCode};
+use axum::response::IntoResponse;
+use futures_core::future::BoxFuture;
+use tower::{Layer, Service};
+use tracing::{trace, warn};
+
+use crate::json::ApiJson;
+
+pub const IDEMPOTENCY_KEY: HeaderName = HeaderName::from_static("idempotency-key");
+pub const IDEMPOTENT_REPLAYED: HeaderName = HeaderName::from_static("idempotent-replayed");
+
+const DEFAULT_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_IN_PROGRESS_TTL: Duration = Duration::from_secs(60);
+const DEFAULT_MAX_BODY_BYTES: usize = 1024 * 1024;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// A response stored by [`IdempotencyStore`].
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// `None` if body is too big or its size is unknown.
+    pub body: Option<Bytes>,
+}
+
+impl StoredResponse {
+    fn to_response(&self) -> Response<Body> {
+        let mut response = match &self.body {
+            Some(body) => {
+                let mut response = Response::new(Body::from(body.clone()));
+                *response.status_mut() = self.status;
+                *response.headers_mut() = self.headers.clone();
+                response
+            }
+            None => not_stored(),
+        };
+        response
+            .headers_mut()
+            .insert(IDEMPOTENT_REPLAYED, HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// Result of [`IdempotencyStore::begin`].
+#[derive(Debug, Clone)]
+pub enum Begin {
+    /// Key is reserved for the caller.
+    Started,
+    /// Another request with the same key is in progress.
+    InProgress,
+    /// A request with the same key is already completed.
+    Completed(StoredResponse),
+}
+
+/// Storage of idempotent responses.
+pub trait IdempotencyStore: Send + Sync + 'static {
+    /// Reserve `key` for `ttl` if it is not known.
+    fn begin(&self, key: &str, ttl: Duration) -> impl Future<Output = Begin> + Send;
+
+    /// Store response of reserved `key` for `ttl`.
+    fn complete(
+        &self,
+        key: &str,
+        response: StoredResponse,
+        ttl: Duration,
+    ) -> impl Future<Output = ()> + Send;
+
+    /// Release reserved `key` without storing response.
+    fn abort(&self, key: &str) -> impl Future<Output = ()> + Send;
+}
+
+enum Slot {
+    InProgress,
+    Completed(StoredResponse),
+}
+
+struct Entries {
+    slots: HashMap<String, (Slot, Instant)>,
+    next_sweep: Instant,
+}
+
+impl Entries {
+    /// Insert a slot, expired slots are removed at most once per [`SWEEP_INTERVAL`].
+    fn insert(&mut self, key: &str, slot: Slot, now: Instant, ttl: Duration) {
+        if now >= self.next_sweep {
+            self.slots.retain(|_, (_, expires_at)| *expires_at > now);
+            self.next_sweep = now + SWEEP_INTERVAL;
+        }
+        self.slots.insert(key.to_owned(), (slot, now + ttl));
+    }
+}
+
+/// In-memory [`IdempotencyStore`].
+pub struct MemoryStore {
+    entries: Mutex<Entries>,
+}
+
+impl Default for MemoryStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self {
+            entries: Mutex::new(Entries {
+                slots: HashMap::new(),
+                next_sweep: Instant::now() + SWEEP_INTERVAL,
+            }),
+        }
+    }
+
+    fn entries(&self) -> MutexGuard<'_, Entries> {
+        // Poisoned state is not a problem for us.
+        self.entries.lock().unwrap_or_else(|x| x.into_inner())
+    }
+}
+
+impl IdempotencyStore for MemoryStore {
+    async fn begin(&self, key: &str, ttl: Duration) -> Begin {
+        let now = Instant::now();
+        let mut entries = self.entries();
+        match entries.slots.get(key) {
+            Some((Slot::InProgress, expires_at)) if *expires_at > now => Begin::InProgress,
+            Some((Slot::Completed(response), expires_at)) if *expires_at > now => {
+                Begin::Completed(response.clone())
+            }
+            _ => {
+                entries.insert(key, Slot::InProgress, now, ttl);
+                Begin::Started
+            }
+        }
+    }
+
+    async fn complete(&self, key: &str, response: StoredResponse, ttl: Duration) {
+        let now = Instant::now();
+        self.entries()
+            .insert(key, Slot::Completed(response), now, ttl);
+    }
+
+    async fn abort(&self, key: &str) {
+        self.entries().slots.remove(key);
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    ttl: Duration,
+    in_progress_ttl: Duration,
+    max_body_bytes: usize,
+    methods: Vec<Method>,
+}
+
+/// [`Layer`] that replays stored responses of requests with the same `Idempotency-Key`.
+pub struct IdempotencyLayer<T> {
+    store: Arc<T>,
+    config: Arc<Config>,
+}
+
+impl<T> Clone for IdempotencyLayer<T> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+impl IdempotencyLayer<MemoryStore> {
+    /// Create layer backed by [`MemoryStore`].
+    pub fn in_memory() -> Self {
+        Self::new(MemoryStore::new())
+    }
+}
+
+impl<T: IdempotencyStore> IdempotencyLayer<T> {
+    pub fn new(store: T) -> Self {
+        Self {
+            store: Arc::new(store),
+            config: Arc::new(Config {
+                ttl: DEFAULT_TTL,
+                in_progress_ttl: DEFAULT_IN_PROGRESS_TTL,
+                max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+                methods: vec![Method::POST, Method::PATCH],
+            }),
+        }
+    }
+
+    /// How long a completed response is kept.
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        Arc::make_mut(&mut self.config).ttl = ttl;
+        self
+    }
+
+    /// How long a key is reserved while the request is processing.
+    ///
+    /// The reservation outlives a cancelled request until this TTL is elapsed.
+    pub fn in_progress_ttl(mut self, ttl: Duration) -> Self {
+        Arc::make_mut(&mut self.config).in_progress_ttl = ttl;
+        self
+    }
+
+    /// Body of a response bigger than this size is not stored, retries are rejected instead.
+    pub fn max_body_bytes(mut self, max_body_bytes: usize) -> Self {
+        Arc::make_mut(&mut self.config).max_body_bytes = max_body_bytes;
+        self
+    }
+
+    /// Methods which are subject to idempotency check, default to `POST` and `PATCH`.
+    pub fn methods(mut self, methods: impl IntoIterator<Item = Method>) -> Self {
+        Arc::make_mut(&mut self.config).methods = methods.into_iter().collect();
+        self
+    }
+}
+
+impl<S, T> Layer<S> for IdempotencyLayer<T> {
+    type Service = IdempotencyService<S, T>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        IdempotencyService {
+            inner,
+            store: self.store.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that replays stored responses of requests with the same `Idempotency-Key`.
+pub struct IdempotencyService<S, T> {
+    inner: S,
+    store: Arc<T>,
+    config: Arc<Config>,
+}
+
+impl<S: Clone, T> Clone for IdempotencyService<S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+            store: self.store.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+fn not_stored() -> Response<Body> {
+    let json = ApiJson::unit_error_builder()
+        .error("Response of a request with the same idempotency key is not available")
+        .build();
+    (StatusCode::UNPROCESSABLE_ENTITY, json).into_response()
+}
+
+fn conflict() -> Response<Body> {
+    let json = ApiJson::unit_error_builder()
+        .error("A request with the same idempotency key is in progress")
+        .build();
+    (StatusCode::CONFLICT, json).into_response()
+}
+
+impl<ReqBody, S, T> Service<Request<ReqBody>> for IdempotencyService<S, T>
+where
+    S: Service<Request<ReqBody>, Response = Response<Body>> + Clone + Send + 'static,
+    S::Future: Send,
+    S::Error: Send,
+    T: IdempotencyStore,
+    ReqBody: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let key = if self.config.methods.contains(req.method()) {
+            req.headers()
+                .get(IDEMPOTENCY_KEY)
+                .and_then(|v| v.to_str().ok())
+                .map(|v| format!("{} {} {}", req.method(), req.uri().path(), v))
+        } else {
+            None
+        };
+        let Some(key) = key else {
+            return Box::pin(self.inner.call(req));
+        };
+
+        // take the service that was ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let store = self.store.clone();
+        let config = self.config.clone();
+        Box::pin(async move {
+            match store.begin(&key, config.in_progress_ttl).await {
+                Begin::Started => {}
+                Begin::InProgress => {
+                    trace!("IdempotencyService: in progress key = {key:?}");
+                    return Ok(conflict());
+                }
+                Begin::Completed(stored) => {
+                    trace!("IdempotencyService: replay key = {key:?}");
+                    return Ok(stored.to_response());
+                }
+            }
+            let response = match inner.call(req).await {
+                Ok(response) => response,
+                Err(err) => {
+                    store.abort(&key).await;
+                    return Err(err);
+                }
+            };
+            if response.status().is_server_error() {
+                store.abort(&key).await;
+                return Ok(response);
+            }
+            let body_storable = response
+                .body()
+                .size_hint()
+                .upper()
+                .is_some_and(|n| n <= config.max_body_bytes as u64);
+            // the request is processed, so the key is completed even if body is not stored
+            let mut stored = StoredResponse {
+                status: response.status(),
+                headers: response.headers().clone(),
+                body: None,
+            };
+            if !body_storable {
+                store.complete(&key, stored, config.ttl).await;
+                return Ok(response);
+            }
+            let (parts, body) = response.into_parts();
+            let body = match axum::body::to_bytes(body, config.max_body_bytes).await {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    warn!("IdempotencyService: failed to buffer response body: {err}");
+                    store.complete(&key, stored, config.ttl).await;
+                    let response = (StatusCode::INTERNAL_SERVER_ERROR, ApiJson::default_error());
+                    return Ok(response.into_response());
+                }
+            };
+            stored.body = Some(body.clone());
+            store.complete(&key, stored, config.ttl).await;
+            Ok(Response::from_parts(parts, Body::from(body)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::routing::post;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn send(app: &Router, key: Option<&str>) -> (StatusCode, bool, Bytes) {
+        let mut req = Request::post("/payments");
+        if let Some(key) = key {
+            req = req.header(IDEMPOTENCY_KEY, key);
+        }
+        let res = app
+            .clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let replayed = res.headers().contains_key(IDEMPOTENT_REPLAYED);
+        let status = res.status();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        (status, replayed, body)
+    }
+
+    #[tokio::test]
+    async fn test_replay() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/payments",
+                post(move || async move { counter.fetch_add(1, Ordering::SeqCst).to_string() }),
+            )
+            .layer(IdempotencyLayer::in_memory());
+
+        assert_eq!(send(&app, Some("a")).await, (StatusCode::OK, false, "0".into()));
+        assert_eq!(send(&app, Some("a")).await, (StatusCode::OK, true, "0".into()));
+        assert_eq!(send(&app, Some("b")).await, (StatusCode::OK, false, "1".into()));
+        // no key, no idempotency check
+        assert_eq!(send(&app, None).await, (StatusCode::OK, false, "2".into()));
+        assert_eq!(send(&app, None).await, (StatusCode::OK, false, "3".into()));
+    }
+
+    #[tokio::test]
+    async fn test_server_error_is_not_stored() {
+        let app = Router::new()
+            .route(
+                "/payments",
+                post(|| async { StatusCode::SERVICE_UNAVAILABLE }),
+            )
+            .layer(IdempotencyLayer::in_memory());
+        let (status, replayed, _) = send(&app, Some("a")).await;
+        assert_eq!((status, replayed), (StatusCode::SERVICE_UNAVAILABLE, false));
+        let (status, replayed, _) = send(&app, Some("a")).await;
+        assert_eq!((status, replayed), (StatusCode::SERVICE_UNAVAILABLE, false));
+    }
+
+    #[tokio::test]
+    async fn test_body_is_not_stored() {
+        let counter = Arc::new(AtomicUsize::new(0));
+        let app = Router::new()
+            .route(
+                "/payments",
+                post(move || async move {
+                    let n = counter.fetch_add(1, Ordering::SeqCst);
+                    format!("{n} too big to store")
+                }),
+            )
+            .layer(IdempotencyLayer::in_memory().max_body_bytes(4));
+
+        let expect = (StatusCode::OK, false, "0 too big to store".into());
+        assert_eq!(send(&app, Some("a")).await, expect);
+        let (status, replayed, _) = send(&app, Some("a")).await;
+        assert_eq!((status, replayed), (StatusCode::UNPROCESSABLE_ENTITY, true));
+        let expect = (StatusCode::OK, false, "1 too big to store".into());
+        assert_eq!(send(&app, Some("b")).await, expect);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_expiry() {
+        let store = MemoryStore::new();
+        let response = StoredResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: None,
+        };
+        store.complete("a", response, Duration::ZERO).await;
+        assert!(matches!(
+            store.begin("a", Duration::ZERO).await,
+            Begin::Started
+        ));
+        assert!(matches!(
+            store.begin("a", Duration::ZERO).await,
+            Begin::Started
+        ));
+        assert_eq!(store.entries().slots.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_memory_store_in_progress() {
+        let store = MemoryStore::new();
+        let ttl = Duration::from_secs(60);
+        assert!(matches!(store.begin("a", ttl).await, Begin::Started));
+        assert!(matches!(store.begin("a", ttl).await, Begin::InProgress));
+        store.abort("a").await;
+        assert!(matches!(store.begin("a", ttl).await, Begin::Started));
+    }
+}
@@ -0,0 +1,228 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::extract::FromRequestParts;
+use axum::http::request::Parts;
+use axum::http::{header, Request, StatusCode};
+use tower::{Layer, Service};
+use tracing::{trace, warn};
+
+use crate::json::ApiJson;
+
+/// Negotiated locale, inserted into request extensions by [`LocaleLayer`].
+///
+/// The value is one of supported locales given to [`LocaleLayer::new`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct Locale(pub Cow<'static, str>);
+
+impl Locale {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Primary language subtag, e.g. `th` of `th-TH`.
+    pub fn language(&self) -> &str {
+        primary_language(&self.0)
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for Locale {
+    type Rejection = (StatusCode, ApiJson<()>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Locale>() {
+            Some(locale) => Ok(locale.clone()),
+            None => {
+                warn!("Locale extension not found, is LocaleLayer installed?");
+                Err((StatusCode::INTERNAL_SERVER_ERROR, ApiJson::default_error()))
+            }
+        }
+    }
+}
+
+fn primary_language(tag: &str) -> &str {
+    tag.split(['-', '_']).next().unwrap_or(tag)
+}
+
+/// Parse `Accept-Language` header value into language ranges ordered by quality, highest first.
+///
+/// Ranges with zero or invalid quality are dropped.
+pub fn parse_accept_language(value: &str) -> Vec<(&str, f32)> {
+    let mut ranges: Vec<(&str, f32)> = value
+        .split(',')
+        .filter_map(|item| {
+            let mut params = item.split(';');
+            let range = params.next()?.trim();
+            if range.is_empty() {
+                return None;
+            }
+            let mut quality = 1.0;
+            for param in params {
+                if let Some((key, value)) = param.split_once('=') {
+                    if key.trim().eq_ignore_ascii_case("q") {
+                        quality = value.trim().parse::<f32>().ok()?;
+                    }
+                }
+            }
+            (quality > 0.0 && quality <= 1.0).then_some((range, quality))
+        })
+        .collect();
+    // stable sort keeps header order for equal quality
+    ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+    ranges
+}
+
+#[derive(Debug)]
+struct Supported {
+    locales: Vec<Cow<'static, str>>,
+}
+
+impl Supported {
+    fn negotiate(&self, accept_language: Option<&str>) -> &Cow<'static, str> {
+        let default = &self.locales[0];
+        let Some(accept_language) = accept_language else {
+            return default;
+        };
+        for (range, _) in parse_accept_language(accept_language) {
+            if range == "*" {
+                return default;
+            }
+            // exact match
+            if let Some(locale) = self.locales.iter().find(|l| l.eq_ignore_ascii_case(range)) {
+                return locale;
+            }
+            // same primary language, e.g. `th-TH` and `th`
+            let language = primary_language(range);
+            if let Some(locale) = self
+                .locales
+                .iter()
+                .find(|l| primary_language(l).eq_ignore_ascii_case(language))
+            {
+                return locale;
+            }
+        }
+        default
+    }
+}
+
+/// [`Layer`] that negotiates [`Locale`] from `Accept-Language` header.
+#[derive(Debug, Clone)]
+pub struct LocaleLayer {
+    supported: Arc<Supported>,
+}
+
+impl LocaleLayer {
+    /// Create layer with supported locales, the first one is the default locale.
+    ///
+    /// panic if `locales` is empty.
+    pub fn new<I, L>(locales: I) -> Self
+    where
+        I: IntoIterator<Item = L>,
+        L: Into<Cow<'static, str>>,
+    {
+        let locales: Vec<_> = locales.into_iter().map(Into::into).collect();
+        assert!(!locales.is_empty(), "at least one supported locale is required");
+        Self {
+            supported: Arc::new(Supported { locales }),
+        }
+    }
+}
+
+impl<S> Layer<S> for LocaleLayer {
+    type Service = LocaleService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LocaleService {
+            inner,
+            supported: self.supported.clone(),
+        }
+    }
+}
+
+/// Middleware that negotiates [`Locale`] from `Accept-Language` header.
+#[derive(Debug, Clone)]
+pub struct LocaleService<S> {
+    inner: S,
+    supported: Arc<Supported>,
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for LocaleService<S>
+where
+    S: Service<Request<ReqBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = S::Future;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        let accept_language = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok());
+        let locale = Locale(self.supported.negotiate(accept_language).clone());
+        trace!("LocaleService: accept_language = {accept_language:?}, locale = {locale}");
+        req.extensions_mut().insert(locale);
+        self.inner.call(req)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_accept_language() {
+        let actual = parse_accept_language("th-TH, en;q=0.8, *;q=0.1, fr;q=0, de;q=x");
+        assert_eq!(actual, vec![("th-TH", 1.0), ("en", 0.8), ("*", 0.1)]);
+        assert!(parse_accept_language("").is_empty());
+    }
+
+    #[test]
+    fn test_negotiate() {
+        let supported = Supported {
+            locales: vec!["en".into(), "th".into(), "zh-Hant".into()],
+        };
+        assert_eq!(supported.negotiate(None), "en");
+        assert_eq!(supported.negotiate(Some("th-TH,en;q=0.5")), "th");
+        assert_eq!(supported.negotiate(Some("fr, zh-hant;q=0.9")), "zh-Hant");
+        assert_eq!(supported.negotiate(Some("en;q=0.1, th;q=0.9")), "th");
+        assert_eq!(supported.negotiate(Some("fr")), "en");
+    }
+
+    #[tokio::test]
+    async fn test_locale_layer() {
+        let app = Router::new()
+            .route("/", get(|locale: Locale| async move { locale.to_string() }))
+            .layer(LocaleLayer::new(["en", "th"]));
+        let req = Request::get("/")
+            .header(header::ACCEPT_LANGUAGE, "th-TH;q=0.9, fr")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body, "th");
+    }
+
+    #[test]
+    fn test_locale_language() {
+        assert_eq!(Locale("th-TH".into()).language(), "th");
+        assert_eq!(Locale("en".into()).language(), "en");
+    }
+}
@@ -0,0 +1,276 @@
+use std::borrow::Cow;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use axum::body::Body;
+use axum::extract::State;
+use axum::http::{Request, Response, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use pin_project::pin_project;
+use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+use tower::{Layer, Service};
+use tracing::{info, trace};
+
+use crate::json::ApiJson;
+
+const DEFAULT_MAINTENANCE_CODE: &str = "MAINTENANCE";
+const DEFAULT_MAINTENANCE_MESSAGE: &str = "Service is under maintenance";
+
+/// A shared switch that turns maintenance mode on and off.
+///
+/// Cloning the switch is cheap, every clone observes the same state.
+#[derive(Debug, Clone)]
+pub struct MaintenanceSwitch {
+    tx: watch::Sender<bool>,
+}
+
+impl MaintenanceSwitch {
+    pub fn new(enabled: bool) -> Self {
+        let (tx, _rx) = watch::channel(enabled);
+        Self { tx }
+    }
+
+    /// Returns `true` if maintenance mode is enabled.
+    pub fn is_enabled(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Set maintenance mode, returns previous state.
+    pub fn set(&self, enabled: bool) -> bool {
+        let previous = self.tx.send_replace(enabled);
+        if previous != enabled {
+            info!("Maintenance mode changed: {previous} -> {enabled}");
+        }
+        previous
+    }
+
+    pub fn enable(&self) -> bool {
+        self.set(true)
+    }
+
+    pub fn disable(&self) -> bool {
+        self.set(false)
+    }
+
+    /// Subscribe to maintenance mode changes.
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.tx.subscribe()
+    }
+}
+
+impl Default for MaintenanceSwitch {
+    fn default() -> Self {
+        Self::new(false)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, Eq, PartialEq)]
+pub struct MaintenanceStatus {
+    pub enabled: bool,
+}
+
+async fn get_status(State(switch): State<MaintenanceSwitch>) -> ApiJson<MaintenanceStatus> {
+    ApiJson::ok(MaintenanceStatus {
+        enabled: switch.is_enabled(),
+    })
+}
+
+async fn set_status(
+    State(switch): State<MaintenanceSwitch>,
+    Json(status): Json<MaintenanceStatus>,
+) -> ApiJson<MaintenanceStatus> {
+    switch.set(status.enabled);
+    ApiJson::ok(status)
+}
+
+/// Admin router for reading (`GET`) and flipping (`PUT`) maintenance mode.
+///
+/// The `PUT` body is `{"enabled": true}`.
+/// Protecting this router is the responsibility of caller.
+pub fn admin_router<S>(switch: MaintenanceSwitch) -> Router<S> {
+    Router::new()
+        .route("/", get(get_status).put(set_status))
+        .with_state(switch)
+}
+
+#[derive(Debug, Clone)]
+struct Config {
+    code: Cow<'static, str>,
+    message: Cow<'static, str>,
+    allowed_paths: Vec<Cow<'static, str>>,
+}
+
+impl Config {
+    fn is_allowed(&self, path: &str) -> bool {
+        self.allowed_paths.iter().any(|p| p == path)
+    }
+}
+
+/// [`Layer`] that rejects requests with `503 Service Unavailable` while maintenance mode is enabled.
+///
+/// Health check paths registered by [`MaintenanceLayer::allow_path`] are always let through.
+#[derive(Debug, Clone)]
+pub struct MaintenanceLayer {
+    switch: MaintenanceSwitch,
+    config: Arc<Config>,
+}
+
+impl MaintenanceLayer {
+    pub fn new(switch: MaintenanceSwitch) -> Self {
+        Self {
+            switch,
+            config: Arc::new(Config {
+                code: DEFAULT_MAINTENANCE_CODE.into(),
+                message: DEFAULT_MAINTENANCE_MESSAGE.into(),
+                allowed_paths: vec![],
+            }),
+        }
+    }
+
+    /// Set `code` of the error response.
+    pub fn code(mut self, code: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).code = code.into();
+        self
+    }
+
+    /// Set `message` of the error response.
+    pub fn message(mut self, message: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config).message = message.into();
+        self
+    }
+
+    /// Let request of given path through even if maintenance mode is enabled.
+    pub fn allow_path(mut self, path: impl Into<Cow<'static, str>>) -> Self {
+        Arc::make_mut(&mut self.config)
+            .allowed_paths
+            .push(path.into());
+        self
+    }
+}
+
+impl<S> Layer<S> for MaintenanceLayer {
+    type Service = MaintenanceService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        MaintenanceService {
+            inner,
+            switch: self.switch.clone(),
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Middleware that rejects requests while maintenance mode is enabled.
+#[derive(Debug, Clone)]
+pub struct MaintenanceService<S> {
+    inner: S,
+    switch: MaintenanceSwitch,
+    config: Arc<Config>,
+}
+
+impl<ReqBody, S> Service<Request<ReqBody>> for MaintenanceService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<Body>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = MaintenanceFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let path = req.uri().path();
+        if self.switch.is_enabled() && !self.config.is_allowed(path) {
+            trace!("MaintenanceService: reject path = {path:?}");
+            let json = ApiJson::unit_error_builder()
+                .code(self.config.code.clone())
+                .error(self.config.message.clone())
+                .build();
+            let response = (StatusCode::SERVICE_UNAVAILABLE, json).into_response();
+            return MaintenanceFuture::Unavailable(Some(response));
+        }
+        MaintenanceFuture::Polling(self.inner.call(req))
+    }
+}
+
+#[pin_project(project = MaintenanceFutureProj)]
+pub enum MaintenanceFuture<F> {
+    Polling(#[pin] F),
+    Unavailable(Option<Response<Body>>),
+}
+
+impl<F, E> Future for MaintenanceFuture<F>
+where
+    F: Future<Output = Result<Response<Body>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project() {
+            MaintenanceFutureProj::Polling(fut) => fut.poll(cx),
+            MaintenanceFutureProj::Unavailable(response) => {
+                let response = response
+                    .take()
+                    .expect("MaintenanceFuture polled after completion");
+                Poll::Ready(Ok(response))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::routing::get;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(switch: MaintenanceSwitch) -> Router {
+        Router::new()
+            .route("/", get(|| async { "ok" }))
+            .route("/healthz", get(|| async { "ok" }))
+            .layer(MaintenanceLayer::new(switch).allow_path("/healthz"))
+    }
+
+    async fn status_of(app: Router, path: &str) -> StatusCode {
+        let req = Request::get(path).body(Body::empty()).unwrap();
+        app.oneshot(req).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_mode() {
+        let switch = MaintenanceSwitch::default();
+        let app = app(switch.clone());
+        assert_eq!(status_of(app.clone(), "/").await, StatusCode::OK);
+
+        assert!(!switch.enable());
+        assert_eq!(
+            status_of(app.clone(), "/").await,
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        assert_eq!(status_of(app.clone(), "/healthz").await, StatusCode::OK);
+
+        assert!(switch.disable());
+        assert_eq!(status_of(app, "/").await, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_admin_router() {
+        let switch = MaintenanceSwitch::default();
+        let admin = admin_router(switch.clone());
+        let req = Request::put("/")
+            .header("content-type", "application/json")
+            .body(Body::from(r#"{"enabled":true}"#))
+            .unwrap();
+        let res = admin.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(switch.is_enabled());
+    }
+}
@@ -0,0 +1,220 @@
+//! Http metrics recorded through the [`metrics`] facade.
+//!
+//! Following metrics are recorded
+//! * `http_requests_total` counter, labeled by `method`, `path` and `status`.
+//! * `http_request_duration_seconds` histogram, labeled by `method`, `path` and `status`.
+//! * `http_requests_in_flight` gauge, labeled by `method` and `path`.
+//!
+//! `path` is the matched route, requests that don't match any route are labeled as `unmatched`
+//! to keep label cardinality bounded. `status` is a status class e.g. `2xx`.
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use axum::extract::{MatchedPath, State};
+use axum::http::{Method, Request, Response, StatusCode};
+use futures_core::ready;
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use pin_project::pin_project;
+use tower::{Layer, Service};
+
+pub const HTTP_REQUESTS_TOTAL: &str = "http_requests_total";
+pub const HTTP_REQUEST_DURATION_SECONDS: &str = "http_request_duration_seconds";
+pub const HTTP_REQUESTS_IN_FLIGHT: &str = "http_requests_in_flight";
+
+const UNMATCHED_PATH: &str = "unmatched";
+
+/// Default latency buckets, in seconds.
+pub const DEFAULT_LATENCY_BUCKETS: &[f64] = &[
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Install global Prometheus recorder with [`DEFAULT_LATENCY_BUCKETS`] for request duration.
+///
+/// Returned handle is used by [`render`] handler.
+pub fn install_prometheus_recorder() -> Result<PrometheusHandle, BuildError> {
+    PrometheusBuilder::new()
+        .set_buckets_for_metric(
+            Matcher::Full(HTTP_REQUEST_DURATION_SECONDS.to_owned()),
+            DEFAULT_LATENCY_BUCKETS,
+        )?
+        .install_recorder()
+}
+
+/// Axum handler rendering metrics in Prometheus exposition format.
+///
+/// ```ignore
+/// let handle = install_prometheus_recorder()?;
+/// let app = Router::new()
+///     .route("/metrics", get(render))
+///     .with_state(handle);
+/// ```
+pub async fn render(State(handle): State<PrometheusHandle>) -> String {
+    handle.render()
+}
+
+fn status_class(status: StatusCode) -> &'static str {
+    match status.as_u16() {
+        100..=199 => "1xx",
+        200..=299 => "2xx",
+        300..=399 => "3xx",
+        400..=499 => "4xx",
+        _ => "5xx",
+    }
+}
+
+/// [`Layer`] that records http metrics.
+#[derive(Debug, Clone, Default)]
+pub struct HttpMetricsLayer {
+    _priv: (),
+}
+
+impl HttpMetricsLayer {
+    pub fn new() -> Self {
+        Default::default()
+    }
+}
+
+impl<S> Layer<S> for HttpMetricsLayer {
+    type Service = HttpMetricsService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        HttpMetricsService { inner }
+    }
+}
+
+/// Middleware that records http metrics.
+#[derive(Debug, Clone)]
+pub struct HttpMetricsService<S> {
+    inner: S,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for HttpMetricsService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = HttpMetricsFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let path = match req.extensions().get::<MatchedPath>() {
+            Some(matched_path) => matched_path.as_str().to_owned(),
+            None => UNMATCHED_PATH.to_owned(),
+        };
+        let in_flight = InFlight::new(req.method().clone(), path);
+        HttpMetricsFuture {
+            in_flight,
+            start: Instant::now(),
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+/// Decrement in-flight gauge on drop, also when future is cancelled.
+struct InFlight {
+    method: Method,
+    path: String,
+}
+
+impl InFlight {
+    fn new(method: Method, path: String) -> Self {
+        gauge!(
+            HTTP_REQUESTS_IN_FLIGHT,
+            "method" => method.to_string(),
+            "path" => path.clone(),
+        )
+        .increment(1.0);
+        Self { method, path }
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        gauge!(
+            HTTP_REQUESTS_IN_FLIGHT,
+            "method" => self.method.to_string(),
+            "path" => self.path.clone(),
+        )
+        .decrement(1.0);
+    }
+}
+
+#[pin_project]
+pub struct HttpMetricsFuture<F> {
+    in_flight: InFlight,
+    start: Instant,
+    #[pin]
+    inner: F,
+}
+
+impl<F, ResBody, E> Future for HttpMetricsFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = ready!(this.inner.poll(cx));
+        let status = match &output {
+            Ok(response) => status_class(response.status()),
+            // error is usually converted to 500 by the server
+            Err(_) => status_class(StatusCode::INTERNAL_SERVER_ERROR),
+        };
+        let labels = [
+            ("method", this.in_flight.method.to_string()),
+            ("path", this.in_flight.path.clone()),
+            ("status", status.to_owned()),
+        ];
+        counter!(HTTP_REQUESTS_TOTAL, &labels).increment(1);
+        histogram!(HTTP_REQUEST_DURATION_SECONDS, &labels).record(this.start.elapsed());
+        Poll::Ready(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use metrics_exporter_prometheus::PrometheusRecorder;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_status_class() {
+        assert_eq!(status_class(StatusCode::CONTINUE), "1xx");
+        assert_eq!(status_class(StatusCode::OK), "2xx");
+        assert_eq!(status_class(StatusCode::FOUND), "3xx");
+        assert_eq!(status_class(StatusCode::NOT_FOUND), "4xx");
+        assert_eq!(status_class(StatusCode::BAD_GATEWAY), "5xx");
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn test_record_metrics() {
+        let recorder: PrometheusRecorder = PrometheusBuilder::new().build_recorder();
+        let handle = recorder.handle();
+        let _guard = metrics::set_default_local_recorder(&recorder);
+
+        let app = Router::new()
+            .route("/users/{id}", get(|| async { "ok" }))
+            .layer(HttpMetricsLayer::new());
+        let req = Request::get("/users/1").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+
+        let rendered = handle.render();
+        assert!(rendered.contains(
+            r#"http_requests_total{method="GET",path="/users/{id}",status="2xx"} 1"#
+        ));
+        assert!(rendered.contains(r#"http_requests_in_flight{method="GET",path="/users/{id}"} 0"#));
+    }
+}
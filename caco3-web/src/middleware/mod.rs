@@ -1,3 +1,15 @@
 //! A collection of middleware.
 
+pub mod body_limit;
+pub mod cache;
+pub mod client_ip;
+#[cfg(feature = "cors")]
+pub mod cors;
+pub mod etag;
+pub mod idempotency;
+pub mod locale;
+pub mod maintenance;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod request_trace;
+pub mod slow_request;
@@ -1,22 +1,167 @@
 use std::future::Future;
 use std::pin::Pin;
+use std::sync::Arc;
 use std::task::{Context, Poll};
+use std::time::Instant;
 
 use axum::extract::MatchedPath;
-use axum::http::{Method, Request, Response, Uri};
+use axum::http::{HeaderMap, Method, Request, Response, Uri};
 use futures_core::ready;
 use pin_project::pin_project;
 use tower::{Layer, Service};
-use tracing::trace;
+use tracing::{info, trace};
+
+use crate::_macro_support::AutoUnitDuration;
+
+/// Header and query parameter activating trace of a single request, see [`ConfigurableTracer`].
+pub const DEBUG_TRACE: &str = "x-debug-trace";
 
 pub trait RequestTrace {
     fn is_traced(&self, path: &str, matched: bool) -> bool;
 
+    /// Trace this request regardless of its path, e.g. when it carries a debug header.
+    fn is_forced(&self, headers: &HeaderMap, uri: &Uri) -> bool {
+        let _ = (headers, uri);
+        false
+    }
+
     fn enabled(&self) -> bool {
         true
     }
 }
 
+/// [`RequestTrace`] tracing configured paths, and any request carrying a secret in
+/// `x-debug-trace` header or query parameter.
+///
+/// Value of the query parameter is redacted from [`RequestTraceData::uri`], as it may be logged.
+/// Without a secret, requests can't activate tracing.
+///
+/// ```
+/// use caco3_web::middleware::request_trace::{ConfigurableTracer, RequestTraceLayer};
+///
+/// let tracer = ConfigurableTracer::new()
+///     .trace_path("/api/orders/{id}")
+///     .debug_secret("s3cr3t");
+/// let layer = RequestTraceLayer::new(move || tracer.clone());
+/// ```
+#[derive(Debug, Clone)]
+pub struct ConfigurableTracer {
+    enabled: bool,
+    trace_unmatched: bool,
+    paths: Arc<[String]>,
+    secret: Option<Arc<str>>,
+}
+
+impl ConfigurableTracer {
+    pub fn new() -> Self {
+        Self {
+            enabled: true,
+            trace_unmatched: false,
+            paths: Arc::new([]),
+            secret: None,
+        }
+    }
+
+    /// Skip collecting [`RequestTraceData`] entirely when `false`.
+    pub fn enable(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Trace requests to a route, given as matched path such as `/users/{id}`.
+    pub fn trace_path(mut self, path: impl Into<String>) -> Self {
+        let mut paths = self.paths.to_vec();
+        paths.push(path.into());
+        self.paths = paths.into();
+        self
+    }
+
+    /// Trace requests not matching any route.
+    pub fn trace_unmatched(mut self, trace: bool) -> Self {
+        self.trace_unmatched = trace;
+        self
+    }
+
+    /// Secret to be given in `x-debug-trace` header or query parameter to trace a request.
+    pub fn debug_secret(mut self, secret: impl Into<String>) -> Self {
+        self.secret = Some(secret.into().into());
+        self
+    }
+}
+
+impl Default for ConfigurableTracer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestTrace for ConfigurableTracer {
+    fn is_traced(&self, path: &str, matched: bool) -> bool {
+        if matched {
+            self.paths.iter().any(|traced| traced == path)
+        } else {
+            self.trace_unmatched
+        }
+    }
+
+    fn is_forced(&self, headers: &HeaderMap, uri: &Uri) -> bool {
+        let Some(secret) = self.secret.as_deref() else {
+            return false;
+        };
+        let from_header = headers
+            .get_all(DEBUG_TRACE)
+            .iter()
+            .any(|value| constant_time_eq(value.as_bytes(), secret.as_bytes()));
+        let from_query = || {
+            uri.query()
+                .into_iter()
+                .flat_map(|query| query.split('&'))
+                .filter_map(|pair| pair.split_once('='))
+                .any(|(key, value)| {
+                    key == DEBUG_TRACE && constant_time_eq(value.as_bytes(), secret.as_bytes())
+                })
+        };
+        from_header || from_query()
+    }
+
+    fn enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// Compare without short-circuiting on the first mismatch, so timing doesn't reveal the secret.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Replace value of `x-debug-trace` query parameter, so the secret doesn't end up in logs.
+fn redact_debug_trace(uri: &Uri) -> Uri {
+    let Some(query) = uri.query() else {
+        return uri.clone();
+    };
+    let is_debug_trace = |pair: &str| {
+        pair.split_once('=')
+            .is_some_and(|(key, _)| key == DEBUG_TRACE)
+    };
+    if !query.split('&').any(is_debug_trace) {
+        return uri.clone();
+    }
+    let query = query
+        .split('&')
+        .map(|pair| {
+            if is_debug_trace(pair) {
+                "x-debug-trace=REDACTED"
+            } else {
+                pair
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = format!("{}?{query}", uri.path()).parse().ok();
+    Uri::from_parts(parts).unwrap_or_else(|_| Uri::from_static("/"))
+}
+
 /// A struct contain Http request info.
 #[derive(Debug, Clone)]
 pub struct RequestTraceData {
@@ -24,7 +169,7 @@ pub struct RequestTraceData {
     pub trace: bool,
     /// Request method.
     pub method: Method,
-    /// Request uri.
+    /// Request uri, with value of `x-debug-trace` query parameter redacted.
     pub uri: Uri,
 }
 
@@ -90,11 +235,12 @@ impl<ReqBody, ResBody, S, F, T> Service<Request<ReqBody>> for RequestTraceServic
                 matched = false;
                 path = req.uri().path();
             };
-            let trace = tracer.is_traced(path, matched);
+            let trace =
+                tracer.is_traced(path, matched) || tracer.is_forced(req.headers(), req.uri());
             request_trace = Some(RequestTraceData {
                 trace,
                 method: req.method().clone(),
-                uri: req.uri().clone(),
+                uri: redact_debug_trace(req.uri()),
             });
             trace!(
                 "RequestTraceService: path = {path:?}, \
@@ -147,3 +293,173 @@ impl<Request, ResBody, S> Future for RequestTraceFuture<Request, S>
         }
     }
 }
+
+/// [`Layer`] that logs requests marked to be traced by [`RequestTraceLayer`].
+///
+/// Install it outside [`RequestTraceLayer`], so [`RequestTraceData`] is in the response
+/// extensions by the time the inner service completes.
+///
+/// ```
+/// use axum::Router;
+/// use caco3_web::middleware::request_trace::{ConfigurableTracer, RequestTraceLayer, TraceLogLayer};
+///
+/// let tracer = ConfigurableTracer::new().debug_secret("s3cr3t");
+/// let app: Router = Router::new()
+///     .layer(RequestTraceLayer::new(move || tracer.clone()))
+///     .layer(TraceLogLayer::new());
+/// ```
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TraceLogLayer;
+
+impl TraceLogLayer {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl<S> Layer<S> for TraceLogLayer {
+    type Service = TraceLogService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        TraceLogService { inner }
+    }
+}
+
+/// Middleware that logs requests marked to be traced by [`RequestTraceService`].
+#[derive(Debug, Clone, Copy)]
+pub struct TraceLogService<S> {
+    inner: S,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for TraceLogService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = TraceLogFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        TraceLogFuture {
+            start: Instant::now(),
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+#[pin_project]
+pub struct TraceLogFuture<F> {
+    start: Instant,
+    #[pin]
+    inner: F,
+}
+
+impl<F, ResBody, E> Future for TraceLogFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.project();
+        let output = ready!(this.inner.poll(cx));
+        if let Ok(response) = &output {
+            if let Some(data) = response.extensions().get::<RequestTraceData>() {
+                if data.trace {
+                    info!(
+                        method = %data.method,
+                        uri = %data.uri,
+                        status = response.status().as_u16(),
+                        latency = %AutoUnitDuration::from(this.start.elapsed()),
+                        "Traced request completed",
+                    );
+                }
+            }
+        }
+        Poll::Ready(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn app(tracer: ConfigurableTracer) -> Router {
+        Router::new()
+            .route("/traced", get(|| async { "ok" }))
+            .route("/other", get(|| async { "ok" }))
+            .route_layer(RequestTraceLayer::new(move || tracer.clone()))
+            .layer(TraceLogLayer::new())
+    }
+
+    async fn is_traced(app: Router, req: Request<Body>) -> Option<bool> {
+        let res = app.oneshot(req).await.unwrap();
+        res.extensions()
+            .get::<RequestTraceData>()
+            .map(|data| data.trace)
+    }
+
+    #[tokio::test]
+    async fn test_trace_by_path() {
+        let app = app(ConfigurableTracer::new().trace_path("/traced"));
+        let req = Request::get("/traced").body(Body::empty()).unwrap();
+        assert_eq!(is_traced(app.clone(), req).await, Some(true));
+        let req = Request::get("/other").body(Body::empty()).unwrap();
+        assert_eq!(is_traced(app, req).await, Some(false));
+
+        let app = self::app(ConfigurableTracer::new().enable(false));
+        let req = Request::get("/traced").body(Body::empty()).unwrap();
+        assert_eq!(is_traced(app, req).await, None);
+    }
+
+    #[tokio::test]
+    async fn test_trace_by_secret() {
+        let app = app(ConfigurableTracer::new().debug_secret("s3cr3t"));
+        let req = Request::get("/other")
+            .header(DEBUG_TRACE, "s3cr3t")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(is_traced(app.clone(), req).await, Some(true));
+        let req = Request::get("/other?a=1&x-debug-trace=s3cr3t")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(is_traced(app.clone(), req).await, Some(true));
+        let req = Request::get("/other")
+            .header(DEBUG_TRACE, "guess")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(is_traced(app, req).await, Some(false));
+
+        // without a secret, the header is ignored
+        let app = self::app(ConfigurableTracer::new());
+        let req = Request::get("/other")
+            .header(DEBUG_TRACE, "")
+            .body(Body::empty())
+            .unwrap();
+        assert_eq!(is_traced(app, req).await, Some(false));
+    }
+
+    #[tokio::test]
+    async fn test_redact_secret() {
+        let app = app(ConfigurableTracer::new().debug_secret("s3cr3t"));
+        let req = Request::get("/other?a=1&x-debug-trace=s3cr3t&b=2")
+            .body(Body::empty())
+            .unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        let data = res.extensions().get::<RequestTraceData>().unwrap();
+        assert!(data.trace);
+        assert_eq!(data.uri, "/other?a=1&x-debug-trace=REDACTED&b=2");
+
+        let uri = Uri::from_static("http://example.com/other?a=1");
+        assert_eq!(redact_debug_trace(&uri), uri);
+    }
+}
@@ -0,0 +1,201 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use axum::http::{Method, Request, Response, Uri};
+use pin_project::pin_project;
+use tokio::time::{sleep, Instant, Sleep};
+use tower::{Layer, Service};
+use tracing::warn;
+
+use crate::_macro_support::AutoUnitDuration;
+use crate::middleware::request_trace::RequestTraceData;
+
+/// [`Layer`] that warns about requests slower than a threshold.
+///
+/// A warning is emitted as soon as the threshold is elapsed, and again when the slow request
+/// completes, regardless of its result.
+///
+/// If [`RequestTraceLayer`](crate::middleware::request_trace::RequestTraceLayer) is installed
+/// inside this layer, the completion warning includes its [`RequestTraceData`].
+#[derive(Debug, Clone, Copy)]
+pub struct SlowRequestLayer {
+    threshold: Duration,
+}
+
+impl SlowRequestLayer {
+    pub fn new(threshold: Duration) -> Self {
+        Self { threshold }
+    }
+}
+
+impl<S> Layer<S> for SlowRequestLayer {
+    type Service = SlowRequestService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowRequestService {
+            inner,
+            threshold: self.threshold,
+        }
+    }
+}
+
+/// Middleware that warns about requests slower than a threshold.
+#[derive(Debug, Clone, Copy)]
+pub struct SlowRequestService<S> {
+    inner: S,
+    threshold: Duration,
+}
+
+impl<ReqBody, ResBody, S> Service<Request<ReqBody>> for SlowRequestService<S>
+where
+    S: Service<Request<ReqBody>, Response = Response<ResBody>>,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = SlowRequestFuture<S::Future>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: Request<ReqBody>) -> Self::Future {
+        let method = req.method().clone();
+        let uri = req.uri().clone();
+        SlowRequestFuture {
+            method,
+            uri,
+            start: Instant::now(),
+            threshold: self.threshold,
+            watchdog: Some(sleep(self.threshold)),
+            inner: self.inner.call(req),
+        }
+    }
+}
+
+#[pin_project]
+pub struct SlowRequestFuture<F> {
+    method: Method,
+    uri: Uri,
+    start: Instant,
+    threshold: Duration,
+    #[pin]
+    watchdog: Option<Sleep>,
+    #[pin]
+    inner: F,
+}
+
+impl<F, ResBody, E> Future for SlowRequestFuture<F>
+where
+    F: Future<Output = Result<Response<ResBody>, E>>,
+{
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut this = self.project();
+        if let Poll::Ready(output) = this.inner.poll(cx) {
+            let elapsed = this.start.elapsed();
+            if elapsed >= *this.threshold {
+                let request_trace = match &output {
+                    Ok(response) => response.extensions().get::<RequestTraceData>().cloned(),
+                    Err(_) => None,
+                };
+                warn!(
+                    method = %this.method,
+                    uri = %this.uri,
+                    ?request_trace,
+                    succeeded = output.is_ok(),
+                    "Slow request completed in {}",
+                    AutoUnitDuration::from(elapsed),
+                );
+            }
+            return Poll::Ready(output);
+        }
+        if let Some(watchdog) = this.watchdog.as_mut().as_pin_mut() {
+            if watchdog.poll(cx).is_ready() {
+                warn!(
+                    method = %this.method,
+                    uri = %this.uri,
+                    "Slow request still running after {}",
+                    AutoUnitDuration::from(this.start.elapsed()),
+                );
+                this.watchdog.set(None);
+            }
+        }
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+    use std::sync::{Arc, Mutex};
+
+    use axum::body::Body;
+    use axum::routing::get;
+    use axum::Router;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[derive(Clone, Default)]
+    struct Logs(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Logs {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Warnings logged while requesting `path`.
+    async fn warnings(path: &str) -> Vec<String> {
+        let logs = Logs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let logs = logs.clone();
+                move || logs.clone()
+            })
+            .without_time()
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+        let app = Router::new()
+            .route(
+                "/slow",
+                get(|| async {
+                    sleep(Duration::from_secs(2)).await;
+                    "ok"
+                }),
+            )
+            .route("/fast", get(|| async { "ok" }))
+            .layer(SlowRequestLayer::new(Duration::from_secs(1)));
+        let req = Request::get(path).body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert!(res.status().is_success());
+        let logs = logs.0.lock().unwrap();
+        String::from_utf8_lossy(&logs)
+            .lines()
+            .filter(|line| line.contains("WARN"))
+            .map(str::to_owned)
+            .collect()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_slow_request() {
+        let warnings = warnings("/slow").await;
+        assert_eq!(warnings.len(), 2, "{warnings:?}");
+        assert!(warnings[0].contains("Slow request still running after 1 s"));
+        assert!(warnings[1].contains("Slow request completed in 2 s"));
+        assert!(warnings[1].contains("uri=/slow"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_fast_request() {
+        assert_eq!(warnings("/fast").await, Vec::<String>::new());
+    }
+}
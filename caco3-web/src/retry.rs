@@ -0,0 +1,214 @@
+//! Retry fallible async operations with exponential backoff.
+//!
+//! ```ignore
+//! let policy = RetryPolicy::new(5).retry_if(|e: &reqwest::Error| e.is_timeout() || e.is_connect());
+//!
+//! // re-evaluate an expression
+//! let response = retry!(policy, client.get(url).send().await)?;
+//! // or call a closure
+//! let response = policy.run(|| client.get(url).send()).await?;
+//! ```
+use std::future::Future;
+use std::time::Duration;
+
+pub use caco3::time::{Backoff, Jitter};
+use tracing::debug;
+
+/// Decide if an error should be retried.
+pub trait RetryIf<E> {
+    fn retry_if(&self, error: &E) -> bool;
+}
+
+/// Retry every error.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AlwaysRetry;
+
+impl<E> RetryIf<E> for AlwaysRetry {
+    fn retry_if(&self, _error: &E) -> bool {
+        true
+    }
+}
+
+impl<E, F> RetryIf<E> for F
+where
+    F: Fn(&E) -> bool,
+{
+    fn retry_if(&self, error: &E) -> bool {
+        self(error)
+    }
+}
+
+/// Retry policy with exponential backoff.
+///
+/// Backoff doubles after each attempt up to `max_backoff`. [`Jitter::Equal`] is used by default,
+/// a random backoff between half and full of it, so clients failed at the same time don't retry
+/// together.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy<R = AlwaysRetry> {
+    /// Maximum number of attempts including the first one.
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    pub jitter: Jitter,
+    retry_if: R,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32) -> Self {
+        Self {
+            max_attempts,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            jitter: Jitter::Equal,
+            retry_if: AlwaysRetry,
+        }
+    }
+}
+
+impl<R> RetryPolicy<R> {
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Retry only errors matched by `retry_if`.
+    pub fn retry_if<F>(self, retry_if: F) -> RetryPolicy<F> {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            initial_backoff: self.initial_backoff,
+            max_backoff: self.max_backoff,
+            jitter: self.jitter,
+            retry_if,
+        }
+    }
+
+    /// Backoffs between attempts of one run, starting from the first failed attempt.
+    pub fn backoff(&self) -> Backoff {
+        Backoff::new(self.initial_backoff)
+            .max_delay(self.max_backoff)
+            .jitter(self.jitter)
+    }
+
+    /// Backoff before next attempt from `backoff` of this run,
+    /// `None` if `error` of `attempt` should not be retried.
+    pub fn next_backoff<E>(
+        &self,
+        attempt: u32,
+        error: &E,
+        backoff: &mut Backoff,
+    ) -> Option<Duration>
+    where
+        R: RetryIf<E>,
+    {
+        if attempt < self.max_attempts && self.retry_if.retry_if(error) {
+            backoff.next()
+        } else {
+            None
+        }
+    }
+
+    /// Call `f` until its future succeeds or policy stops retrying.
+    pub async fn run<F, Fut, T, E>(&self, mut f: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        R: RetryIf<E>,
+    {
+        let mut backoff = self.backoff();
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            match f().await {
+                Ok(value) => return Ok(value),
+                Err(error) => match self.next_backoff(attempt, &error, &mut backoff) {
+                    Some(backoff) => sleep_before_retry(attempt, backoff).await,
+                    None => return Err(error),
+                },
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+pub async fn sleep_before_retry(attempt: u32, backoff: Duration) {
+    debug!("Attempt {attempt} failed, retry in {backoff:?}");
+    tokio::time::sleep(backoff).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    #[test]
+    fn test_backoff() {
+        let policy = RetryPolicy::new(10).jitter(Jitter::None);
+        let exact: Vec<_> = policy.backoff().take(10).collect();
+        assert_eq!(exact[0], Duration::from_millis(100));
+        assert_eq!(exact[2], Duration::from_millis(400));
+        assert_eq!(policy.backoff().nth(100), Some(Duration::from_secs(10)));
+
+        let mut backoff = RetryPolicy::new(10).backoff();
+        for max in exact {
+            let backoff = backoff.next().unwrap();
+            assert!(backoff >= max / 2 && backoff <= max, "{backoff:?}");
+        }
+
+        let mut backoff = policy.backoff();
+        assert!(policy.next_backoff(9, &(), &mut backoff).is_some());
+        assert!(policy.next_backoff(10, &(), &mut backoff).is_none());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_retry() {
+        let policy = RetryPolicy::new(3);
+        let attempts = Cell::new(0);
+        let result: Result<(), u32> = crate::retry!(policy, {
+            attempts.set(attempts.get() + 1);
+            Err(attempts.get())
+        });
+        assert_eq!(result, Err(3));
+
+        let policy = policy.retry_if(|e: &u32| *e < 2);
+        attempts.set(0);
+        let result = policy
+            .run(|| async {
+                attempts.set(attempts.get() + 1);
+                Err::<(), _>(attempts.get())
+            })
+            .await;
+        assert_eq!(result, Err(2));
+
+        attempts.set(0);
+        let result = crate::retry!(
+            policy,
+            async {
+                attempts.set(attempts.get() + 1);
+                if attempts.get() < 2 {
+                    Err(0)
+                } else {
+                    Ok("ok")
+                }
+            }
+            .await
+        );
+        assert_eq!(result, Ok("ok"));
+    }
+}
@@ -0,0 +1,167 @@
+//! Builders of queries with variable number of binds, which query macros can't express.
+//!
+//! Placeholders are generated by [`QueryBuilder`], `$1, $2, ...` for PostgreSQL and `?` for SQLite and MySQL.
+//! Values are always bound, but table and column names are not escaped, never build them from untrusted input.
+use sqlx::query_builder::Separated;
+use sqlx::{Database, Encode, QueryBuilder, Type};
+
+/// Build `<base_sql> IN (<values>)`, more SQL can be pushed to the returned builder.
+///
+/// Empty `values` produce `IN (NULL)`, which matches nothing.
+///
+/// ```ignore
+/// let mut query = in_clause::<Postgres, _>("SELECT * FROM accounts WHERE id", ids);
+/// query.push(" AND active");
+/// let accounts: Vec<Account> = query.build_query_as().fetch_all(&pool).await?;
+/// ```
+pub fn in_clause<'args, DB, I>(base_sql: &str, values: I) -> QueryBuilder<'args, DB>
+where
+    DB: Database,
+    DB::Arguments<'args>: Default,
+    I: IntoIterator,
+    I::Item: 'args + Encode<'args, DB> + Type<DB>,
+{
+    let mut builder = QueryBuilder::new(base_sql);
+    builder.push(" IN (");
+    let mut separated = builder.separated(", ");
+    let mut is_empty = true;
+    for value in values {
+        separated.push_bind(value);
+        is_empty = false;
+    }
+    if is_empty {
+        builder.push("NULL");
+    }
+    builder.push(")");
+    builder
+}
+
+/// A row of [`bulk_insert`], implemented for tuples of up to 12 values.
+pub trait BindRow<'args, DB: Database> {
+    /// Number of values in a row.
+    const LEN: usize;
+
+    fn bind_row(self, row: &mut Separated<'_, 'args, DB, &'static str>);
+}
+
+macro_rules! impl_bind_row {
+    ($len:literal; $($value:ident),+) => {
+        impl<'args, DB, $($value),+> BindRow<'args, DB> for ($($value,)+)
+        where
+            DB: Database,
+            $($value: 'args + Encode<'args, DB> + Type<DB>,)+
+        {
+            const LEN: usize = $len;
+
+            #[allow(non_snake_case)]
+            fn bind_row(self, row: &mut Separated<'_, 'args, DB, &'static str>) {
+                let ($($value,)+) = self;
+                $(row.push_bind($value);)+
+            }
+        }
+    };
+}
+
+impl_bind_row!(1; A);
+impl_bind_row!(2; A, B);
+impl_bind_row!(3; A, B, C);
+impl_bind_row!(4; A, B, C, D);
+impl_bind_row!(5; A, B, C, D, E);
+impl_bind_row!(6; A, B, C, D, E, F);
+impl_bind_row!(7; A, B, C, D, E, F, G);
+impl_bind_row!(8; A, B, C, D, E, F, G, H);
+impl_bind_row!(9; A, B, C, D, E, F, G, H, I);
+impl_bind_row!(10; A, B, C, D, E, F, G, H, I, J);
+impl_bind_row!(11; A, B, C, D, E, F, G, H, I, J, K);
+impl_bind_row!(12; A, B, C, D, E, F, G, H, I, J, K, L);
+
+/// Build `INSERT INTO <table> (<columns>) VALUES (...), (...)`, more SQL can be pushed to the returned builder.
+///
+/// Databases limit number of binds in a query, e.g. 65535 for PostgreSQL,
+/// split large `rows` into chunks.
+///
+/// ```ignore
+/// let rows = accounts.iter().map(|a| (&a.name, a.active));
+/// bulk_insert::<Postgres, _>("accounts", &["name", "active"], rows)
+///     .build()
+///     .execute(&pool)
+///     .await?;
+/// ```
+///
+/// panic if `rows` is empty or length of a row doesn't match length of `columns`.
+pub fn bulk_insert<'args, DB, I>(table: &str, columns: &[&str], rows: I) -> QueryBuilder<'args, DB>
+where
+    DB: Database,
+    DB::Arguments<'args>: Default,
+    I: IntoIterator,
+    I::Item: BindRow<'args, DB>,
+{
+    assert_eq!(
+        <I::Item as BindRow<'args, DB>>::LEN,
+        columns.len(),
+        "row length must match number of columns"
+    );
+    let mut rows = rows.into_iter().peekable();
+    assert!(
+        rows.peek().is_some(),
+        "bulk insert requires at least one row"
+    );
+    let mut builder = QueryBuilder::new("INSERT INTO ");
+    builder.push(table);
+    builder.push(" (");
+    builder.push(columns.join(", "));
+    builder.push(") ");
+    builder.push_values(rows, |mut row, values| values.bind_row(&mut row));
+    builder
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Postgres, Sqlite, SqlitePool};
+
+    use super::*;
+
+    #[test]
+    fn test_placeholders() {
+        let query = in_clause::<Postgres, _>("SELECT * FROM t WHERE id", [1, 2, 3]);
+        assert_eq!(query.sql(), "SELECT * FROM t WHERE id IN ($1, $2, $3)");
+        let query = in_clause::<Sqlite, _>("SELECT * FROM t WHERE id", [1, 2]);
+        assert_eq!(query.sql(), "SELECT * FROM t WHERE id IN (?, ?)");
+        let query = in_clause::<Sqlite, _>("SELECT * FROM t WHERE id", Vec::<i64>::new());
+        assert_eq!(query.sql(), "SELECT * FROM t WHERE id IN (NULL)");
+
+        let query = bulk_insert::<Postgres, _>("t", &["a", "b"], [(1, "x"), (2, "y")]);
+        assert_eq!(
+            query.sql(),
+            "INSERT INTO t (a, b) VALUES ($1, $2), ($3, $4)"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "row length must match number of columns")]
+    fn test_bulk_insert_mismatch() {
+        bulk_insert::<Sqlite, _>("t", &["a"], [(1, 2)]);
+    }
+
+    #[tokio::test]
+    async fn test_sqlite() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.unwrap();
+        sqlx::query("CREATE TABLE t (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let names = ["a", "b", "c"];
+        let rows = names.iter().enumerate().map(|(i, name)| (i as i64, *name));
+        let result = bulk_insert("t", &["id", "name"], rows)
+            .build()
+            .execute(&pool)
+            .await
+            .unwrap();
+        assert_eq!(result.rows_affected(), 3);
+
+        let mut query = in_clause("SELECT name FROM t WHERE id", [0_i64, 2]);
+        query.push(" ORDER BY id");
+        let found: Vec<String> = query.build_query_scalar().fetch_all(&pool).await.unwrap();
+        assert_eq!(found, ["a", "c"]);
+    }
+}
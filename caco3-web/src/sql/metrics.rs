@@ -0,0 +1,107 @@
+//! Metrics of queries run by methods generated with [`postgres_query!`](crate::postgres_query)
+//! and other query macros.
+//!
+//! Every generated method reports its query to a global hook, which is not set by default.
+//! With `metrics` feature, [`install`] sets a hook recording through the [`metrics`] facade
+//! * `sql_queries_total` counter, labeled by `tag` and `status`, `ok` or `error`.
+//! * `sql_query_duration_seconds` histogram, labeled by `tag`.
+//!
+//! `tag` is module path and name of generated method, e.g. `app::dal::account::find`.
+//!
+//! ```ignore
+//! let handle = PrometheusBuilder::new()
+//!     .set_buckets_for_metric(Matcher::Full(SQL_QUERY_DURATION_SECONDS.to_owned()), DEFAULT_LATENCY_BUCKETS)?
+//!     .install_recorder()?;
+//! caco3_web::sql::metrics::install();
+//! ```
+use std::sync::OnceLock;
+use std::time::Duration;
+
+pub const SQL_QUERIES_TOTAL: &str = "sql_queries_total";
+pub const SQL_QUERY_DURATION_SECONDS: &str = "sql_query_duration_seconds";
+
+/// A query run by a generated method.
+#[derive(Debug, Clone, Copy)]
+pub struct QueryEvent<'a> {
+    pub tag: &'a str,
+    pub elapsed: Duration,
+    pub success: bool,
+}
+
+pub type QueryHook = fn(&QueryEvent<'_>);
+
+static HOOK: OnceLock<QueryHook> = OnceLock::new();
+
+/// Set global hook, returns `false` if a hook was already set.
+pub fn set_hook(hook: QueryHook) -> bool {
+    HOOK.set(hook).is_ok()
+}
+
+pub(crate) fn hook() -> Option<QueryHook> {
+    HOOK.get().copied()
+}
+
+/// Record `event` through the [`metrics`] facade.
+#[cfg(feature = "metrics")]
+pub fn record(event: &QueryEvent<'_>) {
+    let tag = event.tag.to_owned();
+    let status = if event.success { "ok" } else { "error" };
+    metrics::counter!(SQL_QUERIES_TOTAL, "tag" => tag.clone(), "status" => status).increment(1);
+    metrics::histogram!(SQL_QUERY_DURATION_SECONDS, "tag" => tag).record(event.elapsed);
+}
+
+/// Set [`record`] as global hook, returns `false` if a hook was already set.
+#[cfg(feature = "metrics")]
+pub fn install() -> bool {
+    set_hook(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+
+    static EVENTS: Mutex<Vec<(String, bool)>> = Mutex::new(Vec::new());
+
+    fn push_event(event: &QueryEvent<'_>) {
+        let mut events = EVENTS.lock().unwrap();
+        events.push((event.tag.to_owned(), event.success));
+    }
+
+    struct Answer;
+
+    impl Answer {
+        crate::sqlite_query! {
+            get_scalar("SELECT 42") -> i64,
+            async fn get ()
+        }
+
+        crate::sqlite_query! {
+            get_scalar("SELECT * FROM missing") -> i64,
+            async fn get_missing ()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hook() {
+        assert!(set_hook(push_event));
+        assert!(!set_hook(push_event));
+        let pool = sqlx::SqlitePool::connect("sqlite::memory:").await.unwrap();
+        assert_eq!(Answer.get(&pool).await.unwrap(), 42);
+        assert!(Answer.get_missing(&pool).await.is_err());
+        // queries of other tests may run concurrently
+        let events: Vec<_> = EVENTS
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(tag, _)| tag.starts_with(module_path!()))
+            .cloned()
+            .collect();
+        let tag = concat!(module_path!(), "::get");
+        assert_eq!(
+            events,
+            [(tag.to_owned(), true), (format!("{tag}_missing"), false)]
+        );
+    }
+}
@@ -0,0 +1,293 @@
+//! Embedded SQL migrations.
+//!
+//! A migration file is named `<version>_<description>.sql`, e.g. `0001_create_accounts.sql`.
+//! Applied migrations are recorded in a table, `_caco3_migrations` by default,
+//! with checksum of their SQL, an applied migration must not be modified.
+//!
+//! ```ignore
+//! let migrator = embed_migrations!("migrations" => [
+//!     "0001_create_accounts.sql",
+//!     "0002_add_account_email.sql",
+//! ])?;
+//! migrator.apply(&pool).await?;
+//! ```
+//!
+//! On PostgreSQL, migrations are applied while holding an advisory lock,
+//! so instances of a service starting at the same time don't apply the same migration.
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+use std::{fs, io};
+
+use sha2::{Digest, Sha256};
+use sqlx::{ColumnIndex, Connection, Database, Decode, Executor, IntoArguments, Pool, Type};
+use thiserror::Error;
+use tracing::info;
+
+const DEFAULT_TABLE: &str = "_caco3_migrations";
+/// Advisory lock key of PostgreSQL, an arbitrary number.
+const ADVISORY_LOCK_KEY: i64 = 0x6361_636f_335f_6d67;
+
+#[derive(Debug, Error)]
+pub enum MigrateError {
+    #[error("migrate: invalid migration file name {0:?}, expect <version>_<description>.sql")]
+    InvalidFileName(String),
+    #[error("migrate: duplicate migration version {0}")]
+    DuplicateVersion(i64),
+    #[error("migrate: migration {0} was applied but is missing")]
+    VersionMissing(i64),
+    #[error("migrate: migration {0} was modified after it was applied")]
+    ChecksumMismatch(i64),
+    #[error("migrate: read migrations: {0}")]
+    Io(#[from] io::Error),
+    #[error("migrate: {0}")]
+    Sqlx(#[from] sqlx::Error),
+}
+
+/// A migration, usually created from a file.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub version: i64,
+    pub description: Cow<'static, str>,
+    pub sql: Cow<'static, str>,
+}
+
+impl Migration {
+    pub fn new(
+        version: i64,
+        description: impl Into<Cow<'static, str>>,
+        sql: impl Into<Cow<'static, str>>,
+    ) -> Self {
+        Self {
+            version,
+            description: description.into(),
+            sql: sql.into(),
+        }
+    }
+
+    /// Create migration from file name `<version>_<description>.sql`.
+    pub fn from_file(
+        file_name: &str,
+        sql: impl Into<Cow<'static, str>>,
+    ) -> Result<Self, MigrateError> {
+        let invalid = || MigrateError::InvalidFileName(file_name.to_owned());
+        let stem = file_name.strip_suffix(".sql").ok_or_else(invalid)?;
+        let (version, description) = stem.split_once('_').ok_or_else(invalid)?;
+        let version = version.parse().map_err(|_| invalid())?;
+        Ok(Self::new(version, description.replace('_', " "), sql))
+    }
+
+    /// Hex encoded SHA-256 of SQL.
+    pub fn checksum(&self) -> String {
+        let digest = Sha256::digest(self.sql.as_bytes());
+        digest.iter().fold(String::with_capacity(64), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+    }
+}
+
+/// Registry of migrations ordered by version.
+#[derive(Debug, Clone)]
+pub struct Migrator {
+    migrations: Vec<Migration>,
+    table: Cow<'static, str>,
+}
+
+impl Migrator {
+    pub fn new(migrations: impl IntoIterator<Item = Migration>) -> Result<Self, MigrateError> {
+        let mut migrations: Vec<Migration> = migrations.into_iter().collect();
+        migrations.sort_by_key(|m| m.version);
+        if let Some(w) = migrations.windows(2).find(|w| w[0].version == w[1].version) {
+            return Err(MigrateError::DuplicateVersion(w[0].version));
+        }
+        Ok(Self {
+            migrations,
+            table: Cow::Borrowed(DEFAULT_TABLE),
+        })
+    }
+
+    /// Create migrator from pairs of file name and SQL, see [`embed_migrations!`](crate::embed_migrations).
+    pub fn from_files<I>(files: I) -> Result<Self, MigrateError>
+    where
+        I: IntoIterator<Item = (&'static str, &'static str)>,
+    {
+        let migrations = files
+            .into_iter()
+            .map(|(file_name, sql)| Migration::from_file(file_name, sql))
+            .collect::<Result<Vec<_>, _>>()?;
+        Self::new(migrations)
+    }
+
+    /// Read `*.sql` files of `dir` at runtime.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self, MigrateError> {
+        let mut migrations = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "sql") {
+                let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+                let sql = fs::read_to_string(&path)?;
+                migrations.push(Migration::from_file(&file_name, sql)?);
+            }
+        }
+        Self::new(migrations)
+    }
+
+    /// Change table recording applied migrations, the name is not escaped.
+    pub fn table(mut self, table: impl Into<Cow<'static, str>>) -> Self {
+        self.table = table.into();
+        self
+    }
+
+    pub fn migrations(&self) -> &[Migration] {
+        &self.migrations
+    }
+
+    /// Apply pending migrations, each in its own transaction, returns versions of applied migrations.
+    pub async fn apply<DB>(&self, pool: &Pool<DB>) -> Result<Vec<i64>, MigrateError>
+    where
+        DB: Database,
+        for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+        i64: Type<DB> + for<'r> Decode<'r, DB>,
+        String: Type<DB> + for<'r> Decode<'r, DB>,
+        usize: ColumnIndex<DB::Row>,
+        for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    {
+        let mut conn = pool.acquire().await?;
+        let postgres = DB::NAME == "PostgreSQL";
+        if postgres {
+            let sql = format!("SELECT pg_advisory_lock({ADVISORY_LOCK_KEY})");
+            sqlx::raw_sql(&sql).execute(&mut *conn).await?;
+        }
+        let result = self.apply_locked::<DB>(&mut conn).await;
+        if postgres {
+            let sql = format!("SELECT pg_advisory_unlock({ADVISORY_LOCK_KEY})");
+            sqlx::raw_sql(&sql).execute(&mut *conn).await?;
+        }
+        result
+    }
+
+    async fn apply_locked<DB>(&self, conn: &mut DB::Connection) -> Result<Vec<i64>, MigrateError>
+    where
+        DB: Database,
+        for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+        i64: Type<DB> + for<'r> Decode<'r, DB>,
+        String: Type<DB> + for<'r> Decode<'r, DB>,
+        usize: ColumnIndex<DB::Row>,
+        for<'q> DB::Arguments<'q>: IntoArguments<'q, DB>,
+    {
+        let table = &self.table;
+        let sql = format!(
+            "CREATE TABLE IF NOT EXISTS {table} (
+                version BIGINT PRIMARY KEY,
+                description TEXT NOT NULL,
+                checksum TEXT NOT NULL,
+                applied_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+            )"
+        );
+        sqlx::raw_sql(&sql).execute(&mut *conn).await?;
+        let sql = format!("SELECT version, checksum FROM {table}");
+        let applied: HashMap<i64, String> = sqlx::query_as::<DB, (i64, String)>(&sql)
+            .fetch_all(&mut *conn)
+            .await?
+            .into_iter()
+            .collect();
+        for (&version, checksum) in &applied {
+            let migration = self
+                .migrations
+                .iter()
+                .find(|m| m.version == version)
+                .ok_or(MigrateError::VersionMissing(version))?;
+            if migration.checksum() != *checksum {
+                return Err(MigrateError::ChecksumMismatch(version));
+            }
+        }
+
+        let mut versions = Vec::new();
+        for migration in self
+            .migrations
+            .iter()
+            .filter(|m| !applied.contains_key(&m.version))
+        {
+            let mut tx = conn.begin().await?;
+            sqlx::raw_sql(&migration.sql).execute(&mut *tx).await?;
+            let sql = format!(
+                "INSERT INTO {table} (version, description, checksum) VALUES ({}, '{}', '{}')",
+                migration.version,
+                migration.description.replace('\'', "''"),
+                migration.checksum(),
+            );
+            sqlx::raw_sql(&sql).execute(&mut *tx).await?;
+            tx.commit().await?;
+            info!(
+                "Applied migration {} {}",
+                migration.version, migration.description
+            );
+            versions.push(migration.version);
+        }
+        Ok(versions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::SqlitePool;
+
+    use super::*;
+
+    fn migrator() -> Migrator {
+        Migrator::from_files([
+            (
+                "0002_add_email.sql",
+                "ALTER TABLE accounts ADD COLUMN email TEXT",
+            ),
+            (
+                "0001_create_accounts.sql",
+                "CREATE TABLE accounts (id INTEGER PRIMARY KEY); CREATE INDEX accounts_id ON accounts (id);",
+            ),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_file() {
+        let migration = Migration::from_file("0010_create_it's_table.sql", "").unwrap();
+        assert_eq!(migration.version, 10);
+        assert_eq!(migration.description, "create it's table");
+        assert!(Migration::from_file("create.sql", "").is_err());
+        assert!(Migration::from_file("0001_create.txt", "").is_err());
+        let versions: Vec<_> = migrator().migrations().iter().map(|m| m.version).collect();
+        assert_eq!(versions, [1, 2]);
+        let duplicate = Migrator::new([Migration::new(1, "a", ""), Migration::new(1, "b", "")]);
+        assert!(matches!(duplicate, Err(MigrateError::DuplicateVersion(1))));
+    }
+
+    #[tokio::test]
+    async fn test_apply() {
+        // a single connection, otherwise each connection has its own in-memory database
+        let pool: SqlitePool = sqlx::pool::PoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let migrator = migrator();
+        assert_eq!(migrator.apply(&pool).await.unwrap(), [1, 2]);
+        assert!(migrator.apply(&pool).await.unwrap().is_empty());
+        sqlx::query("INSERT INTO accounts (id, email) VALUES (1, 'a@b.c')")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let modified = Migrator::new([
+            Migration::new(1, "create accounts", "SELECT 1"),
+            migrator.migrations()[1].clone(),
+        ])
+        .unwrap();
+        let result = modified.apply(&pool).await;
+        assert!(matches!(result, Err(MigrateError::ChecksumMismatch(1))));
+        let missing = Migrator::new([migrator.migrations()[0].clone()]).unwrap();
+        let result = missing.apply(&pool).await;
+        assert!(matches!(result, Err(MigrateError::VersionMissing(2))));
+    }
+}
@@ -0,0 +1,195 @@
+#[cfg(feature = "sqlx")]
+pub mod builder;
+#[cfg(feature = "sqlx")]
+pub mod migrate;
+pub mod metrics;
+pub mod named;
+pub mod page;
+#[cfg(feature = "sqlx")]
+mod pool;
+#[cfg(feature = "sqlx")]
+mod route;
+#[cfg(feature = "sqlx")]
+mod tx;
+
+#[cfg(feature = "sqlx")]
+pub use pool::*;
+#[cfg(feature = "sqlx")]
+pub use route::*;
+#[cfg(feature = "sqlx")]
+pub use tx::*;
+
+use std::borrow::Cow;
+
+mod private {
+    pub trait Sealed {}
+
+    impl<T> Sealed for T where T: AsRef<str> {}
+}
+
+pub trait SqlTrimBoxed: private::Sealed {
+    /// Remove comments and blank lines, other lines are kept as is.
+    fn sql_trim_boxed(&self) -> Box<str>;
+
+    /// Remove comments and collapse consecutive whitespaces into a single space,
+    /// the result is a single line.
+    fn sql_compact_boxed(&self) -> Box<str>;
+}
+
+/// Remove block comments, nested comments are supported.
+///
+/// If `compact`, also remove line comments and collapse consecutive whitespaces.
+/// String literals and quoted identifiers are kept as is.
+fn strip_sql(query: &str, compact: bool) -> String {
+    let mut output = String::with_capacity(query.len());
+    let mut chars = query.chars().peekable();
+    let mut space = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut depth = 1;
+                let mut prev = '\0';
+                while let Some(c) = chars.next_if(|_| depth > 0) {
+                    match (prev, c) {
+                        ('/', '*') => (depth, prev) = (depth + 1, '\0'),
+                        ('*', '/') => (depth, prev) = (depth - 1, '\0'),
+                        _ => prev = c,
+                    }
+                }
+                if compact {
+                    space = true;
+                } else {
+                    output.push(' ');
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                if compact {
+                    while chars.next_if(|&c| c != '\n').is_some() {}
+                    space = true;
+                } else {
+                    output.push(c);
+                    while let Some(c) = chars.next_if(|&c| c != '\n') {
+                        output.push(c);
+                    }
+                }
+            }
+            c if compact && c.is_whitespace() => space = true,
+            c => {
+                if space && !output.is_empty() {
+                    output.push(' ');
+                }
+                space = false;
+                output.push(c);
+                if c == '\'' || c == '"' {
+                    // a doubled quote is parsed as end and start of quoted text
+                    for quoted in chars.by_ref() {
+                        output.push(quoted);
+                        if quoted == c {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+    output
+}
+
+fn keep_sql_line(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    let is_comment_line = || trimmed.starts_with("--");
+    let is_blank_line = || trimmed.is_empty();
+    let skip = is_comment_line() || is_blank_line();
+    let keep = !skip;
+    keep.then_some(line)
+}
+
+fn sql_trim_boxed(query: &str) -> Box<str> {
+    let query = if query.contains("/*") {
+        Cow::Owned(strip_sql(query, false))
+    } else {
+        Cow::Borrowed(query)
+    };
+    query
+        .lines()
+        .flat_map(keep_sql_line)
+        .collect::<Vec<_>>()
+        .join("\n")
+        .into_boxed_str()
+}
+
+impl<T: AsRef<str>> SqlTrimBoxed for T {
+    fn sql_trim_boxed(&self) -> Box<str> {
+        sql_trim_boxed(self.as_ref())
+    }
+
+    fn sql_compact_boxed(&self) -> Box<str> {
+        strip_sql(self.as_ref(), true).into_boxed_str()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use indoc::indoc;
+
+    use super::*;
+
+    #[test]
+    fn sql_trim_boxed() {
+        let query = indoc! {r#"
+            -- This is comment
+            SELECT * FROM users;
+        "#};
+        let actual = query.sql_trim_boxed();
+        let expect = "SELECT * FROM users;";
+        assert_eq!(actual.as_ref().trim(), expect);
+
+        let query = indoc! {r#"
+            -- PREPARE data_request__update_fsm_state(bigint, fsm_state, timestamptz) AS
+            UPDATE data_request
+            SET fsm_state     = $2,
+                -- Also comment here
+                modified_date = $3
+            WHERE data_request_id = $1;
+            -- Comment after sql
+        "#};
+        let actual = query.sql_trim_boxed();
+        let expect = indoc! {r#"
+            UPDATE data_request
+            SET fsm_state     = $2,
+                modified_date = $3
+            WHERE data_request_id = $1;
+        "#};
+        assert_eq!(actual.as_ref().trim(), expect.trim());
+    }
+    #[test]
+    fn sql_trim_block_comments() {
+        let query = indoc! {r#"
+            /*
+             * Find accounts.
+             */
+            SELECT id, /* name, */ email
+            FROM accounts /* outer /* nested */ comment */ AS a
+            WHERE note = '/* not a comment */' -- line /* comment
+        "#};
+        let expect = indoc! {r#"
+            SELECT id,   email
+            FROM accounts   AS a
+            WHERE note = '/* not a comment */' -- line /* comment"#};
+        assert_eq!(&*query.sql_trim_boxed(), expect);
+    }
+
+    #[test]
+    fn sql_compact_boxed() {
+        let query = indoc! {r#"
+            -- Find accounts.
+            SELECT id, /* name, */ email
+            FROM   accounts
+            WHERE  note = 'two  spaces -- /*' -- trailing comment
+              AND  "quoted  name" = 'it''s';
+        "#};
+        let expect = r#"SELECT id, email FROM accounts WHERE note = 'two  spaces -- /*' AND "quoted  name" = 'it''s';"#;
+        assert_eq!(&*query.sql_compact_boxed(), expect);
+    }
+}
@@ -0,0 +1,124 @@
+//! Named parameters, `:name` placeholders are rewritten to positional placeholders of database.
+//!
+//! PostgreSQL placeholders are numbered, a parameter used more than once is bound once,
+//! other databases use `?` and a parameter is bound for each placeholder.
+//!
+//! ```
+//! use caco3_web::sql::named::NamedSql;
+//!
+//! let sql = "SELECT * FROM accounts WHERE owner_id = :owner OR created_by = :owner AND name = :name";
+//! let named = NamedSql::parse(sql, "PostgreSQL");
+//! assert_eq!(named.sql(), "SELECT * FROM accounts WHERE owner_id = $1 OR created_by = $1 AND name = $2");
+//! assert_eq!(named.params(), ["owner", "name"]);
+//!
+//! let named = NamedSql::parse(sql, "SQLite");
+//! assert_eq!(named.sql(), "SELECT * FROM accounts WHERE owner_id = ? OR created_by = ? AND name = ?");
+//! assert_eq!(named.params(), ["owner", "owner", "name"]);
+//! ```
+//!
+//! Query macros accept `named` SQL, see [`postgres_query!`](crate::postgres_query).
+//! String literals, quoted identifiers, comments and PostgreSQL casts e.g. `id::text` are kept as is.
+use std::fmt::Write as _;
+
+/// SQL with positional placeholders, and names of parameters in bind order.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct NamedSql {
+    sql: String,
+    params: Vec<String>,
+}
+
+impl NamedSql {
+    /// Rewrite `:name` placeholders for `database`, which is `sqlx::Database::NAME`.
+    pub fn parse(sql: &str, database: &str) -> Self {
+        let numbered = database == "PostgreSQL";
+        let mut output = String::with_capacity(sql.len());
+        let mut params: Vec<String> = Vec::new();
+        let mut chars = sql.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\'' | '"' => {
+                    let end = sql[i + 1..].find(c).map_or(sql.len(), |n| i + n + 2);
+                    output.push_str(&sql[i..end]);
+                    while chars.next_if(|&(j, _)| j < end).is_some() {}
+                }
+                '-' if sql[i..].starts_with("--") => {
+                    let end = sql[i..].find('\n').map_or(sql.len(), |n| i + n);
+                    output.push_str(&sql[i..end]);
+                    while chars.next_if(|&(j, _)| j < end).is_some() {}
+                }
+                '/' if sql[i..].starts_with("/*") => {
+                    let end = sql[i + 2..].find("*/").map_or(sql.len(), |n| i + n + 4);
+                    output.push_str(&sql[i..end]);
+                    while chars.next_if(|&(j, _)| j < end).is_some() {}
+                }
+                ':' if sql[i..].starts_with("::") => {
+                    output.push_str("::");
+                    chars.next();
+                }
+                ':' if chars
+                    .peek()
+                    .is_some_and(|&(_, c)| c.is_ascii_alphabetic() || c == '_') =>
+                {
+                    let start = i + 1;
+                    let mut end = start;
+                    while let Some((j, c)) =
+                        chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+                    {
+                        end = j + c.len_utf8();
+                    }
+                    let name = &sql[start..end];
+                    if numbered {
+                        let index = match params.iter().position(|p| p == name) {
+                            Some(index) => index,
+                            None => {
+                                params.push(name.to_owned());
+                                params.len() - 1
+                            }
+                        };
+                        let _ = write!(output, "${}", index + 1);
+                    } else {
+                        params.push(name.to_owned());
+                        output.push('?');
+                    }
+                }
+                c => output.push(c),
+            }
+        }
+        Self {
+            sql: output,
+            params,
+        }
+    }
+
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Names of parameters, in bind order.
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let sql = "SELECT id::text, ':skip', \":skip\" -- :skip\nFROM t /* :skip */ WHERE a = :a_1 AND b = :b AND c = :a_1";
+        let named = NamedSql::parse(sql, "PostgreSQL");
+        assert_eq!(
+            named.sql(),
+            "SELECT id::text, ':skip', \":skip\" -- :skip\nFROM t /* :skip */ WHERE a = $1 AND b = $2 AND c = $1"
+        );
+        assert_eq!(named.params(), ["a_1", "b"]);
+        let named = NamedSql::parse(sql, "MySQL");
+        assert!(named.sql().ends_with("a = ? AND b = ? AND c = ?"));
+        assert_eq!(named.params(), ["a_1", "b", "a_1"]);
+
+        let named = NamedSql::parse("SELECT ':', 'it''s :x', :1, :é", "SQLite");
+        assert_eq!(named.sql(), "SELECT ':', 'it''s :x', :1, :é");
+        assert!(named.params().is_empty());
+    }
+}
@@ -0,0 +1,337 @@
+//! Pagination of list queries, see `fetch_page` of [`postgres_query!`](crate::postgres_query).
+//!
+//! A list endpoint takes [`PageRequest`] from query string and returns [`Page`] with [`ApiJson::page`].
+//!
+//! ```ignore
+//! async fn list(Query(request): Query<PageRequest>, State(pool): State<PgPool>) -> Response {
+//!     match ListAccounts.list(&pool, &request).await {
+//!         Ok(page) => ApiJson::page(page.map(AccountDto::from)).into_response(),
+//!         Err(e) => ...,
+//!     }
+//! }
+//! ```
+//!
+//! [`ApiJson::page`]: crate::json::ApiJson::page
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+pub const DEFAULT_PAGE_LIMIT: u32 = 20;
+pub const MAX_PAGE_LIMIT: u32 = 1000;
+
+/// Position of next page, formatted as an opaque string.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Cursor {
+    /// Number of rows to skip, used by offset pagination.
+    Offset(u64),
+    /// Key of the last row of previous page, used by keyset pagination.
+    After(i64),
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[error("invalid cursor {0:?}")]
+pub struct InvalidCursor(pub String);
+
+impl Display for Cursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Offset(offset) => write!(f, "o{offset}"),
+            Self::After(key) => write!(f, "k{key}"),
+        }
+    }
+}
+
+impl FromStr for Cursor {
+    type Err = InvalidCursor;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidCursor(s.to_owned());
+        match s.split_at_checked(1) {
+            Some(("o", offset)) => offset.parse().map(Self::Offset).map_err(|_| invalid()),
+            Some(("k", key)) => key.parse().map(Self::After).map_err(|_| invalid()),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Serialize for Cursor {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Cursor {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Requested page, usually deserialized from query string e.g. `?limit=50&cursor=k1234`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+pub struct PageRequest {
+    #[serde(default = "default_limit")]
+    pub limit: u32,
+    #[serde(default)]
+    pub cursor: Option<Cursor>,
+}
+
+fn default_limit() -> u32 {
+    DEFAULT_PAGE_LIMIT
+}
+
+impl Default for PageRequest {
+    fn default() -> Self {
+        Self::new(DEFAULT_PAGE_LIMIT)
+    }
+}
+
+impl PageRequest {
+    pub fn new(limit: u32) -> Self {
+        Self {
+            limit,
+            cursor: None,
+        }
+    }
+
+    pub fn cursor(mut self, cursor: Cursor) -> Self {
+        self.cursor = Some(cursor);
+        self
+    }
+
+    /// Limit clamped to `1..=MAX_PAGE_LIMIT`.
+    pub fn effective_limit(&self) -> u32 {
+        self.limit.clamp(1, MAX_PAGE_LIMIT)
+    }
+
+    fn offset(&self) -> Result<u64, InvalidCursor> {
+        match self.cursor {
+            None => Ok(0),
+            Some(Cursor::Offset(offset)) => Ok(offset),
+            Some(cursor) => Err(InvalidCursor(cursor.to_string())),
+        }
+    }
+
+    #[doc(hidden)]
+    pub fn after_key(&self) -> Result<Option<i64>, InvalidCursor> {
+        match self.cursor {
+            None => Ok(None),
+            Some(Cursor::After(key)) => Ok(Some(key)),
+            Some(cursor) => Err(InvalidCursor(cursor.to_string())),
+        }
+    }
+
+    /// Append `LIMIT` and `OFFSET` to `sql`, one more row is fetched to know if next page exists.
+    ///
+    /// `sql` must be checked by [`check_page_sql`].
+    #[doc(hidden)]
+    pub fn offset_sql(&self, sql: &str) -> Result<String, InvalidCursor> {
+        let limit = self.effective_limit() + 1;
+        let offset = self.offset()?;
+        Ok(format!("{sql} LIMIT {limit} OFFSET {offset}"))
+    }
+
+    /// Wrap `sql` in a subquery for keyset pagination, so it can have any clause except `LIMIT`.
+    ///
+    /// `sql` must be checked by [`check_page_sql`].
+    #[doc(hidden)]
+    pub fn keyset_select(&self, sql: &str) -> String {
+        format!("SELECT * FROM ({sql}) AS page")
+    }
+
+    /// Append filter of rows after cursor to SQL of [`keyset_select`](Self::keyset_select),
+    /// a placeholder must be appended next.
+    #[doc(hidden)]
+    pub fn push_keyset_filter(&self, sql: &mut String, key: &str, descending: bool) {
+        let op = if descending { "<" } else { ">" };
+        sql.push_str(&format!(" WHERE {key} {op} "));
+    }
+
+    /// Append `ORDER BY` and `LIMIT` of keyset pagination to SQL of [`keyset_select`](Self::keyset_select).
+    #[doc(hidden)]
+    pub fn push_keyset_suffix(&self, sql: &mut String, key: &str, descending: bool) {
+        let limit = self.effective_limit() + 1;
+        let order = if descending { " DESC" } else { "" };
+        sql.push_str(&format!(" ORDER BY {key}{order} LIMIT {limit}"));
+    }
+
+    #[doc(hidden)]
+    pub fn offset_page<T>(&self, rows: Vec<T>) -> Page<T> {
+        let limit = u64::from(self.effective_limit());
+        // offset is validated before query
+        let offset = self.offset().unwrap_or_default();
+        Page::from_rows(rows, self.effective_limit(), |_| {
+            Cursor::Offset(offset + limit)
+        })
+    }
+
+    #[doc(hidden)]
+    pub fn keyset_page<T>(&self, rows: Vec<T>, key: impl Fn(&T) -> i64) -> Page<T> {
+        Page::from_rows(rows, self.effective_limit(), |last| {
+            Cursor::After(key(last))
+        })
+    }
+}
+
+/// Remove trailing semicolon of `sql`, fails if it has `LIMIT` or `OFFSET` outside of subqueries.
+#[doc(hidden)]
+pub fn check_page_sql(sql: &str) -> Result<Box<str>, String> {
+    let sql = sql.trim_end().trim_end_matches(';').trim_end();
+    match top_level_keyword(sql, &["LIMIT", "OFFSET"]) {
+        Some(keyword) => Err(format!(
+            "SQL of fetch_page must not have {keyword}, it is added by pagination"
+        )),
+        None => Ok(sql.into()),
+    }
+}
+
+/// First of `keywords` in `sql`, outside of parentheses, quotes and comments.
+fn top_level_keyword<'k>(sql: &str, keywords: &[&'k str]) -> Option<&'k str> {
+    let bytes = sql.as_bytes();
+    let len = bytes.len();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < len {
+        let c = bytes[i];
+        match c {
+            b'\'' | b'"' | b'`' => {
+                i += 1;
+                while i < len && bytes[i] != c {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < len && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i + 1 < len && !(bytes[i] == b'*' && bytes[i + 1] == b'/') {
+                    i += 1;
+                }
+                i += 2;
+            }
+            b'(' => {
+                depth += 1;
+                i += 1;
+            }
+            b')' => {
+                depth = depth.saturating_sub(1);
+                i += 1;
+            }
+            c if c.is_ascii_alphanumeric() || c == b'_' => {
+                let start = i;
+                while i < len && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                let word = &sql[start..i];
+                if depth == 0 {
+                    if let Some(keyword) = keywords.iter().find(|k| k.eq_ignore_ascii_case(word)) {
+                        return Some(keyword);
+                    }
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// A page of items and cursor of next page, `None` if this is the last page.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<Cursor>,
+}
+
+impl<T> Page<T> {
+    fn from_rows(mut rows: Vec<T>, limit: u32, next: impl FnOnce(&T) -> Cursor) -> Self {
+        let limit = limit as usize;
+        let next_cursor = if rows.len() > limit {
+            rows.truncate(limit);
+            rows.last().map(next)
+        } else {
+            None
+        };
+        Self {
+            items: rows,
+            next_cursor,
+        }
+    }
+
+    /// Convert items, e.g. from database entities to response objects.
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor() {
+        assert_eq!("o20".parse(), Ok(Cursor::Offset(20)));
+        assert_eq!("k-5".parse(), Ok(Cursor::After(-5)));
+        assert!("x1".parse::<Cursor>().is_err());
+        assert!("".parse::<Cursor>().is_err());
+        assert_eq!(Cursor::After(12).to_string(), "k12");
+
+        let request: PageRequest = serde_json::from_str(r#"{"cursor": "o40"}"#).unwrap();
+        assert_eq!(request, PageRequest::new(20).cursor(Cursor::Offset(40)));
+    }
+
+    #[test]
+    fn test_page() {
+        let request = PageRequest::new(2).cursor(Cursor::Offset(4));
+        assert_eq!(
+            request.offset_sql("SELECT 1").unwrap(),
+            "SELECT 1 LIMIT 3 OFFSET 4"
+        );
+        let page = request.offset_page(vec![1, 2, 3]);
+        assert_eq!(page.items, [1, 2]);
+        assert_eq!(page.next_cursor, Some(Cursor::Offset(6)));
+        assert_eq!(request.offset_page(vec![1]).next_cursor, None);
+
+        let mut sql = request.keyset_select("SELECT * FROM t ORDER BY id");
+        request.push_keyset_filter(&mut sql, "id", true);
+        sql.push_str("$1");
+        request.push_keyset_suffix(&mut sql, "id", true);
+        assert_eq!(
+            sql,
+            "SELECT * FROM (SELECT * FROM t ORDER BY id) AS page WHERE id < $1 ORDER BY id DESC LIMIT 3"
+        );
+
+        let page = PageRequest::new(2).keyset_page(vec![10, 11, 12], |k| *k);
+        assert_eq!(page.next_cursor, Some(Cursor::After(11)));
+        assert!(PageRequest::new(2).after_key().unwrap().is_none());
+        assert!(request.after_key().is_err());
+    }
+    #[test]
+    fn test_check_page_sql() {
+        assert_eq!(
+            check_page_sql("SELECT * FROM t ORDER BY id;\n").as_deref(),
+            Ok("SELECT * FROM t ORDER BY id")
+        );
+        assert!(check_page_sql("SELECT * FROM t ORDER BY id limit 5").is_err());
+        assert!(check_page_sql("SELECT * FROM t OFFSET 5").is_err());
+        assert!(check_page_sql(
+            "SELECT * FROM t WHERE id IN (SELECT id FROM u LIMIT 5) AND note = 'limit' -- limit"
+        )
+        .is_ok());
+        assert!(check_page_sql("SELECT limits, \"offset\" FROM t /* LIMIT */").is_ok());
+    }
+}
@@ -0,0 +1,283 @@
+use futures_core::future::BoxFuture;
+use futures_core::stream::BoxStream;
+use sqlx::{Database, Describe, Either, Error, Execute, Executor, Pool};
+
+/// Executor of `#[primary]` query methods, implemented for pools, connections and [`ReadWritePools`].
+pub trait RouteExecutor<'c, DB: Database> {
+    type Executor: Executor<'c, Database = DB>;
+
+    /// Executor of queries which must run on primary.
+    fn primary(self) -> Self::Executor;
+}
+
+impl<'c, DB: Database> RouteExecutor<'c, DB> for &'c Pool<DB>
+where
+    for<'a> &'a mut DB::Connection: Executor<'a, Database = DB>,
+{
+    type Executor = Self;
+
+    fn primary(self) -> Self {
+        self
+    }
+}
+
+impl<'c, DB: Database> RouteExecutor<'c, DB> for &'c mut DB::Connection
+where
+    &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    type Executor = Self;
+
+    fn primary(self) -> Self {
+        self
+    }
+}
+
+/// A primary pool and a replica pool.
+///
+/// `&ReadWritePools` is an executor, so it can be passed where a pool is expected.
+/// `SELECT` queries run on replica, other queries e.g. `INSERT ... RETURNING` and every
+/// `execute` query run on primary. Add `#[primary]` to a query method to run a read which
+/// must see a preceding write on primary.
+///
+/// ```ignore
+/// let pools = ReadWritePools::new(
+///     build_pg_pool(&config.primary).await?,
+///     build_pg_pool(&config.replica).await?,
+/// );
+/// let account = FindAccount { id }.find(&pools).await?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReadWritePools<DB: Database> {
+    primary: Pool<DB>,
+    replica: Pool<DB>,
+}
+
+impl<DB: Database> ReadWritePools<DB> {
+    pub fn new(primary: Pool<DB>, replica: Pool<DB>) -> Self {
+        Self { primary, replica }
+    }
+
+    /// Use one pool for both kinds of queries, e.g. in development.
+    pub fn primary_only(primary: Pool<DB>) -> Self {
+        Self {
+            replica: primary.clone(),
+            primary,
+        }
+    }
+
+    pub fn primary(&self) -> &Pool<DB> {
+        &self.primary
+    }
+
+    pub fn replica(&self) -> &Pool<DB> {
+        &self.replica
+    }
+}
+
+impl<'c, DB: Database> RouteExecutor<'c, DB> for &'c ReadWritePools<DB>
+where
+    for<'a> &'a mut DB::Connection: Executor<'a, Database = DB>,
+{
+    type Executor = &'c Pool<DB>;
+
+    fn primary(self) -> &'c Pool<DB> {
+        &self.primary
+    }
+}
+
+impl<'p, DB: Database> Executor<'p> for &'_ ReadWritePools<DB>
+where
+    for<'c> &'c mut DB::Connection: Executor<'c, Database = DB>,
+{
+    type Database = DB;
+
+    fn execute<'e, 'q: 'e, E>(self, query: E) -> BoxFuture<'e, Result<DB::QueryResult, Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, DB>,
+    {
+        self.primary.execute(query)
+    }
+
+    fn execute_many<'e, 'q: 'e, E>(self, query: E) -> BoxStream<'e, Result<DB::QueryResult, Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, DB>,
+    {
+        self.primary.execute_many(query)
+    }
+
+    fn fetch_many<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxStream<'e, Result<Either<DB::QueryResult, DB::Row>, Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, DB>,
+    {
+        if is_read_only(query.sql()) {
+            self.replica.fetch_many(query)
+        } else {
+            self.primary.fetch_many(query)
+        }
+    }
+
+    fn fetch_optional<'e, 'q: 'e, E>(
+        self,
+        query: E,
+    ) -> BoxFuture<'e, Result<Option<DB::Row>, Error>>
+    where
+        'p: 'e,
+        E: 'q + Execute<'q, DB>,
+    {
+        if is_read_only(query.sql()) {
+            self.replica.fetch_optional(query)
+        } else {
+            self.primary.fetch_optional(query)
+        }
+    }
+
+    fn prepare_with<'e, 'q: 'e>(
+        self,
+        sql: &'q str,
+        parameters: &'e [DB::TypeInfo],
+    ) -> BoxFuture<'e, Result<DB::Statement<'q>, Error>>
+    where
+        'p: 'e,
+    {
+        self.primary.prepare_with(sql, parameters)
+    }
+
+    #[doc(hidden)]
+    fn describe<'e, 'q: 'e>(self, sql: &'q str) -> BoxFuture<'e, Result<Describe<DB>, Error>>
+    where
+        'p: 'e,
+    {
+        self.primary.describe(sql)
+    }
+}
+
+/// Whether `sql` is a `SELECT` statement, leading comments are skipped.
+fn is_read_only(sql: &str) -> bool {
+    let mut sql = sql.trim_start();
+    loop {
+        if let Some(rest) = sql.strip_prefix("--") {
+            sql = rest
+                .split_once('\n')
+                .map_or("", |(_, rest)| rest)
+                .trim_start();
+        } else if let Some(rest) = sql.strip_prefix("/*") {
+            sql = rest
+                .split_once("*/")
+                .map_or("", |(_, rest)| rest)
+                .trim_start();
+        } else {
+            break;
+        }
+    }
+    let keyword_end = sql
+        .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+        .unwrap_or(sql.len());
+    sql[..keyword_end].eq_ignore_ascii_case("select")
+}
+
+#[cfg(test)]
+mod tests {
+    use sqlx::{Sqlite, SqlitePool};
+
+    use super::*;
+
+    async fn pool(name: &str) -> SqlitePool {
+        // a single connection, otherwise each connection has its own in-memory database
+        let pool = sqlx::pool::PoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE accounts (id INTEGER PRIMARY KEY, name TEXT NOT NULL)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO accounts (id, name) VALUES (1, ?)")
+            .bind(name)
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    struct Account {
+        id: i64,
+        name: &'static str,
+    }
+
+    impl Account {
+        crate::sqlite_query! {
+            get_scalar("SELECT name FROM accounts WHERE id = ?") -> String,
+            async fn name {
+                id,
+            }
+        }
+
+        crate::sqlite_query! {
+            get_scalar("SELECT name FROM accounts WHERE id = ?") -> String,
+            #[primary]
+            async fn primary_name {
+                id,
+            }
+        }
+
+        crate::sqlite_query! {
+            execute_rows("UPDATE accounts SET name = ? WHERE id = ?"),
+            async fn rename {
+                name,
+                id,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_route() {
+        let pools = ReadWritePools::<Sqlite>::new(pool("primary").await, pool("replica").await);
+        let account = Account {
+            id: 1,
+            name: "renamed",
+        };
+        assert_eq!(account.name(&pools).await.unwrap(), "replica");
+        assert_eq!(account.primary_name(&pools).await.unwrap(), "primary");
+        assert_eq!(account.rename(&pools).await.unwrap(), 1);
+        assert_eq!(account.primary_name(&pools).await.unwrap(), "renamed");
+        assert_eq!(account.name(&pools).await.unwrap(), "replica");
+        // plain executors run every query
+        assert_eq!(account.name(pools.primary()).await.unwrap(), "renamed");
+        {
+            let mut conn = pools.replica().acquire().await.unwrap();
+            assert_eq!(account.primary_name(&mut *conn).await.unwrap(), "replica");
+        }
+
+        let name: String =
+            sqlx::query_scalar("UPDATE accounts SET name = 'returned' WHERE id = 1 RETURNING name")
+                .fetch_one(&pools)
+                .await
+                .unwrap();
+        assert_eq!(name, "returned");
+        assert_eq!(account.primary_name(&pools).await.unwrap(), "returned");
+        assert_eq!(account.name(&pools).await.unwrap(), "replica");
+    }
+
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("SELECT 1"));
+        assert!(is_read_only(
+            "  -- find\n/* accounts */ select * from accounts"
+        ));
+        assert!(!is_read_only(
+            "INSERT INTO accounts (id) VALUES (1) RETURNING id"
+        ));
+        assert!(!is_read_only(
+            "WITH deleted AS (DELETE FROM accounts RETURNING id) SELECT * FROM deleted"
+        ));
+        assert!(!is_read_only("selection"));
+        assert!(!is_read_only(""));
+    }
+}
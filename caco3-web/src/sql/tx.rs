@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use futures_core::future::BoxFuture;
+use sqlx::{Database, Pool, Transaction};
+use tracing::{debug, warn};
+
+/// Error returned from transaction body of [`TxExt::with_tx`].
+pub trait TxError: From<sqlx::Error> {
+    /// Returns `true` if transaction should be retried.
+    fn is_retryable(&self) -> bool;
+}
+
+impl TxError for sqlx::Error {
+    fn is_retryable(&self) -> bool {
+        is_serialization_failure(self)
+    }
+}
+
+/// Returns `true` if `error` is a serialization failure or a deadlock,
+/// a transaction failed with these errors may succeed if retried.
+pub fn is_serialization_failure(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(e) => matches!(e.code().as_deref(), Some("40001" | "40P01")),
+        _ => false,
+    }
+}
+
+/// Retry policy of transaction with exponential backoff.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct TxRetry {
+    pub max_retries: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for TxRetry {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl TxRetry {
+    /// Don't retry.
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        let factor = 1u32.checked_shl(retry).unwrap_or(u32::MAX);
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// Run a closure in a transaction.
+///
+/// Transaction is committed if the closure returns `Ok`, otherwise it is rolled back.
+/// Closure may be called again if it fails with retryable error, see [`TxError`].
+///
+/// ```ignore
+/// let id = pool
+///     .with_tx(|tx| {
+///         Box::pin(async move {
+///             let id = account.insert(&mut **tx).await?;
+///             Audit::created(id).insert(&mut **tx).await?;
+///             Ok::<_, sqlx::Error>(id)
+///         })
+///     })
+///     .await?;
+/// ```
+///
+/// See [`transactional!`](crate::transactional) for shorter syntax.
+pub trait TxExt<DB: Database> {
+    /// Run `f` in a transaction with default retry policy.
+    fn with_tx<'a, F, T, E>(&'a self, f: F) -> BoxFuture<'a, Result<T, E>>
+    where
+        F: for<'t> FnMut(&'t mut Transaction<'static, DB>) -> BoxFuture<'t, Result<T, E>>
+            + Send
+            + 'a,
+        T: Send + 'a,
+        E: TxError + Send + 'a,
+    {
+        self.with_tx_retry(TxRetry::default(), f)
+    }
+
+    /// Run `f` in a transaction with given retry policy.
+    fn with_tx_retry<'a, F, T, E>(&'a self, retry: TxRetry, f: F) -> BoxFuture<'a, Result<T, E>>
+    where
+        F: for<'t> FnMut(&'t mut Transaction<'static, DB>) -> BoxFuture<'t, Result<T, E>>
+            + Send
+            + 'a,
+        T: Send + 'a,
+        E: TxError + Send + 'a;
+}
+
+impl<DB: Database> TxExt<DB> for Pool<DB> {
+    fn with_tx_retry<'a, F, T, E>(&'a self, retry: TxRetry, mut f: F) -> BoxFuture<'a, Result<T, E>>
+    where
+        F: for<'t> FnMut(&'t mut Transaction<'static, DB>) -> BoxFuture<'t, Result<T, E>>
+            + Send
+            + 'a,
+        T: Send + 'a,
+        E: TxError + Send + 'a,
+    {
+        Box::pin(async move {
+            let mut retries = 0;
+            loop {
+                let mut tx = self.begin().await?;
+                let result = match f(&mut tx).await {
+                    Ok(value) => tx.commit().await.map(|_| value).map_err(E::from),
+                    Err(e) => {
+                        if let Err(rollback_error) = tx.rollback().await {
+                            warn!("Failed to rollback transaction: {rollback_error}");
+                        }
+                        Err(e)
+                    }
+                };
+                match result {
+                    Err(e) if retries < retry.max_retries && e.is_retryable() => {
+                        let backoff = retry.backoff(retries);
+                        retries += 1;
+                        debug!("Retry transaction in {backoff:?}, retries: {retries}");
+                        tokio::time::sleep(backoff).await;
+                    }
+                    result => return result,
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use sqlx::SqlitePool;
+
+    use super::*;
+
+    #[derive(Debug)]
+    enum AppError {
+        Sqlx(sqlx::Error),
+        Conflict,
+    }
+
+    impl From<sqlx::Error> for AppError {
+        fn from(e: sqlx::Error) -> Self {
+            Self::Sqlx(e)
+        }
+    }
+
+    impl TxError for AppError {
+        fn is_retryable(&self) -> bool {
+            match self {
+                Self::Sqlx(e) => is_serialization_failure(e),
+                Self::Conflict => true,
+            }
+        }
+    }
+
+    async fn pool() -> SqlitePool {
+        // a single connection, otherwise each connection has its own in-memory database
+        let pool = sqlx::pool::PoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE items (id INTEGER PRIMARY KEY)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        pool
+    }
+
+    async fn count(pool: &SqlitePool) -> i64 {
+        sqlx::query_scalar("SELECT count(*) FROM items")
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_commit_and_rollback() {
+        let pool = pool().await;
+        let id = pool
+            .with_tx(|tx| {
+                Box::pin(async move {
+                    sqlx::query("INSERT INTO items (id) VALUES (1)")
+                        .execute(&mut **tx)
+                        .await?;
+                    Ok::<_, sqlx::Error>(1)
+                })
+            })
+            .await
+            .unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(count(&pool).await, 1);
+
+        let result = pool
+            .with_tx(|tx| {
+                Box::pin(async move {
+                    sqlx::query("INSERT INTO items (id) VALUES (2)")
+                        .execute(&mut **tx)
+                        .await?;
+                    sqlx::query("INSERT INTO items (id) VALUES (1)")
+                        .execute(&mut **tx)
+                        .await?;
+                    Ok::<_, sqlx::Error>(())
+                })
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(count(&pool).await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_retry() {
+        let pool = pool().await;
+        let attempts = AtomicU32::new(0);
+        pool.with_tx(|tx| {
+            let attempt = attempts.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move {
+                sqlx::query("INSERT INTO items (id) VALUES (1)")
+                    .execute(&mut **tx)
+                    .await?;
+                if attempt < 2 {
+                    return Err(AppError::Conflict);
+                }
+                Ok(())
+            })
+        })
+        .await
+        .unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(count(&pool).await, 1);
+
+        let attempts = AtomicU32::new(0);
+        let result = pool
+            .with_tx_retry(TxRetry::none(), |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async { Err::<(), _>(AppError::Conflict) })
+            })
+            .await;
+        assert!(matches!(result, Err(AppError::Conflict)));
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_transactional() {
+        let pool = pool().await;
+        let ids = [1, 2];
+        crate::transactional!(pool, |tx| {
+            for id in ids {
+                sqlx::query("INSERT INTO items (id) VALUES (?)")
+                    .bind(id)
+                    .execute(&mut **tx)
+                    .await?;
+            }
+            Ok::<_, sqlx::Error>(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(count(&pool).await, 2);
+
+        let result = crate::transactional!(&pool, retry = TxRetry::none(), |tx| {
+            sqlx::query("DELETE FROM items").execute(&mut **tx).await?;
+            Err::<(), _>(AppError::Conflict)
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(count(&pool).await, 2);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let retry = TxRetry::default();
+        assert_eq!(retry.backoff(0), Duration::from_millis(10));
+        assert_eq!(retry.backoff(2), Duration::from_millis(40));
+        assert_eq!(retry.backoff(100), Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,316 @@
+//! In-memory caches.
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+/// Cache with expiring entries and a bounded number of entries, evicting the least recently used
+/// entry when full.
+///
+/// Methods take `&mut self`, wrap in a `Mutex` to share between threads.
+/// Expired entries are dropped when accessed, when space is needed, or by [`sweep`](TtlCache::sweep).
+///
+/// ```
+/// use std::time::Duration;
+/// use caco3::cache::TtlCache;
+///
+/// let mut cache = TtlCache::new(2, Duration::from_secs(60));
+/// cache.insert("a", 1);
+/// cache.insert("b", 2);
+/// assert_eq!(cache.get("a"), Some(&1));
+/// // "b" is the least recently used
+/// cache.insert("c", 3);
+/// assert_eq!(cache.get("b"), None);
+/// assert_eq!(*cache.get_or_insert_with("b", || 4), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct TtlCache<K, V> {
+    entries: HashMap<K, Entry<V>>,
+    /// Keys by last use, least recent first.
+    recency: BTreeMap<u64, K>,
+    /// Keys by expiration, earliest first.
+    expiry: BTreeMap<(Instant, u64), K>,
+    capacity: usize,
+    default_ttl: Duration,
+    counter: u64,
+}
+
+#[derive(Debug, Clone)]
+struct Entry<V> {
+    value: V,
+    expires_at: Instant,
+    /// Key in `expiry`, fixed at insertion.
+    id: u64,
+    /// Key in `recency`, renewed on each use.
+    last_used: u64,
+}
+
+impl<V> Entry<V> {
+    fn is_expired(&self, now: Instant) -> bool {
+        self.expires_at <= now
+    }
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Create a cache holding at most `capacity` entries, each living for `default_ttl` unless
+    /// inserted with [`insert_with_ttl`](TtlCache::insert_with_ttl).
+    ///
+    /// # Panics
+    /// Panics if `capacity` is zero.
+    pub fn new(capacity: usize, default_ttl: Duration) -> Self {
+        assert!(capacity > 0, "capacity must be greater than zero");
+        Self {
+            entries: HashMap::new(),
+            recency: BTreeMap::new(),
+            expiry: BTreeMap::new(),
+            capacity,
+            default_ttl,
+            counter: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of entries, including expired ones not dropped yet.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Get an unexpired value and mark it as recently used.
+    pub fn get<Q>(&mut self, key: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.get_at(key, Instant::now())
+    }
+
+    /// Returns `true` if `key` has an unexpired value, without marking it as used.
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = Instant::now();
+        self.entries
+            .get(key)
+            .is_some_and(|entry| !entry.is_expired(now))
+    }
+
+    /// Insert with the default TTL, returning the previous unexpired value.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        self.insert_with_ttl(key, value, self.default_ttl)
+    }
+
+    /// Insert with the given TTL, returning the previous unexpired value.
+    pub fn insert_with_ttl(&mut self, key: K, value: V, ttl: Duration) -> Option<V> {
+        self.insert_at(key, value, ttl, Instant::now())
+    }
+
+    /// Get an unexpired value, or insert one from `f` with the default TTL.
+    pub fn get_or_insert_with<F>(&mut self, key: K, f: F) -> &V
+    where
+        F: FnOnce() -> V,
+    {
+        self.get_or_insert_with_at(key, f, Instant::now())
+    }
+
+    pub fn remove<Q>(&mut self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let now = Instant::now();
+        self.remove_entry(key)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value)
+    }
+
+    /// Drop expired entries, returning number of entries dropped.
+    pub fn sweep(&mut self) -> usize {
+        self.sweep_at(Instant::now())
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+        self.expiry.clear();
+    }
+
+    fn get_at<Q>(&mut self, key: &Q, now: Instant) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if !self.touch(key, now) {
+            return None;
+        }
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+
+    fn get_or_insert_with_at<F>(&mut self, key: K, f: F, now: Instant) -> &V
+    where
+        F: FnOnce() -> V,
+    {
+        if !self.touch(&key, now) {
+            self.insert_at(key.clone(), f(), self.default_ttl, now);
+        }
+        &self.entries[&key].value
+    }
+
+    fn insert_at(&mut self, key: K, value: V, ttl: Duration, now: Instant) -> Option<V> {
+        let previous = self
+            .remove_entry(&key)
+            .filter(|entry| !entry.is_expired(now))
+            .map(|entry| entry.value);
+        if self.entries.len() >= self.capacity {
+            self.sweep_at(now);
+        }
+        while self.entries.len() >= self.capacity {
+            let (_, lru) = self.recency.pop_first().expect("recency has every key");
+            self.remove_entry(&lru);
+        }
+        // far future TTLs never expire
+        let expires_at = now.checked_add(ttl).unwrap_or_else(|| far_future(now));
+        let id = self.next_counter();
+        self.recency.insert(id, key.clone());
+        self.expiry.insert((expires_at, id), key.clone());
+        let entry = Entry {
+            value,
+            expires_at,
+            id,
+            last_used: id,
+        };
+        self.entries.insert(key, entry);
+        previous
+    }
+
+    fn sweep_at(&mut self, now: Instant) -> usize {
+        let mut dropped = 0;
+        while let Some(entry) = self.expiry.first_entry() {
+            if entry.key().0 > now {
+                break;
+            }
+            let key = entry.remove();
+            self.remove_entry(&key);
+            dropped += 1;
+        }
+        dropped
+    }
+
+    /// Mark an unexpired entry as recently used, dropping it if expired.
+    fn touch<Q>(&mut self, key: &Q, now: Instant) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let counter = self.counter + 1;
+        let Some(entry) = self.entries.get_mut(key) else {
+            return false;
+        };
+        if entry.is_expired(now) {
+            self.remove_entry(key);
+            return false;
+        }
+        let last_used = std::mem::replace(&mut entry.last_used, counter);
+        self.counter = counter;
+        let key = self
+            .recency
+            .remove(&last_used)
+            .expect("recency has every key");
+        self.recency.insert(counter, key);
+        true
+    }
+
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<Entry<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let entry = self.entries.remove(key)?;
+        self.recency.remove(&entry.last_used);
+        self.expiry.remove(&(entry.expires_at, entry.id));
+        Some(entry)
+    }
+
+    fn next_counter(&mut self) -> u64 {
+        self.counter += 1;
+        self.counter
+    }
+}
+
+fn far_future(now: Instant) -> Instant {
+    // roughly 30 years, big enough for any cache and small enough for every platform
+    now + Duration::from_secs(30 * 365 * 24 * 60 * 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn test_ttl() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(10, 10 * SECOND);
+        cache.insert_at("a", 1, 10 * SECOND, now);
+        cache.insert_at("b", 2, 20 * SECOND, now);
+        cache.insert_at("c", 3, Duration::MAX, now);
+        assert_eq!(cache.get_at("a", now + 5 * SECOND), Some(&1));
+        assert_eq!(cache.get_at("a", now + 10 * SECOND), None);
+        assert_eq!(cache.len(), 2);
+
+        assert_eq!(cache.sweep_at(now + 30 * SECOND), 1);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get_at("c", now + 30 * SECOND), Some(&3));
+        assert_eq!(cache.insert_at("c", 4, SECOND, now), Some(3));
+        assert_eq!(cache.insert_at("c", 5, SECOND, now + 2 * SECOND), None);
+    }
+
+    #[test]
+    fn test_lru_eviction() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(3, 10 * SECOND);
+        cache.insert_at(1, "a", 10 * SECOND, now);
+        cache.insert_at(2, "b", 10 * SECOND, now);
+        cache.insert_at(3, "c", 10 * SECOND, now);
+        cache.get_at(&1, now);
+        cache.insert_at(4, "d", 10 * SECOND, now);
+        assert_eq!(cache.get_at(&2, now), None);
+        assert_eq!(cache.len(), 3);
+
+        // expired entries go first, even if recently used
+        cache.insert_at(5, "e", SECOND, now);
+        cache.get_at(&5, now);
+        cache.insert_at(6, "f", 10 * SECOND, now + 2 * SECOND);
+        assert_eq!(cache.get_at(&1, now + 2 * SECOND), Some(&"a"));
+        assert_eq!(cache.get_at(&4, now + 2 * SECOND), Some(&"d"));
+        assert_eq!(cache.get_at(&6, now + 2 * SECOND), Some(&"f"));
+        assert_eq!(cache.recency.len(), 3);
+        assert_eq!(cache.expiry.len(), 3);
+    }
+
+    #[test]
+    fn test_get_or_insert_with() {
+        let now = Instant::now();
+        let mut cache = TtlCache::new(1, 10 * SECOND);
+        assert_eq!(*cache.get_or_insert_with_at("a", || 1, now), 1);
+        assert_eq!(*cache.get_or_insert_with_at("a", || 2, now), 1);
+        assert_eq!(
+            *cache.get_or_insert_with_at("a", || 3, now + 10 * SECOND),
+            3
+        );
+        assert_eq!(*cache.get_or_insert_with_at("b", || 4, now), 4);
+        assert!(!cache.contains_key("a"));
+    }
+}
@@ -1,5 +1,5 @@
 pub use bool_from_choice::bool_from_choice;
-pub use meta::MetaConfig;
+pub use meta::{MetaConfig, MetaConfigGetter};
 
 mod bool_from_choice;
 mod meta;
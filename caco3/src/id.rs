@@ -0,0 +1,179 @@
+//! Sortable unique identifiers.
+//!
+//! Both [`Ulid`] and [`UuidV7`] start with a millisecond timestamp followed by a counter, so ids
+//! from the same [`IdGenerator`] sort in creation order, even within the same millisecond.
+//! They are not secrets, don't use them as session tokens.
+//!
+//! ```
+//! use caco3::id::{IdGenerator, Ulid, UuidV7};
+//!
+//! let a = Ulid::generate();
+//! let b = Ulid::generate();
+//! assert!(a < b);
+//! assert_eq!(a.to_string().parse::<Ulid>(), Ok(a));
+//!
+//! // same seed and clock, same ids
+//! let ids = IdGenerator::seeded(42, 1_700_000_000_000);
+//! let replay = IdGenerator::seeded(42, 1_700_000_000_000);
+//! assert_eq!(ids.uuid_v7(), replay.uuid_v7());
+//! ```
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{de, Deserializer, Serializer};
+use thiserror::Error;
+
+pub use ulid::Ulid;
+pub use uuid_v7::UuidV7;
+
+mod ulid;
+mod uuid_v7;
+
+static DEFAULT_GENERATOR: IdGenerator = IdGenerator::new();
+
+/// Lock-free generator of monotonic ids.
+///
+/// Ids of each kind are strictly increasing, the timestamp is moved ahead when the counter of
+/// a millisecond is exhausted.
+#[derive(Debug)]
+pub struct IdGenerator {
+    /// Timestamp and counter of the last ULID.
+    ulid: AtomicU64,
+    /// Timestamp and counter of the last UUIDv7.
+    uuid_v7: AtomicU64,
+    random: Random,
+    clock: Clock,
+}
+
+#[derive(Debug)]
+enum Random {
+    Os,
+    /// State of splitmix64.
+    Seeded(AtomicU64),
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Clock {
+    System,
+    Fixed(u64),
+}
+
+impl IdGenerator {
+    pub const fn new() -> Self {
+        Self {
+            ulid: AtomicU64::new(0),
+            uuid_v7: AtomicU64::new(0),
+            random: Random::Os,
+            clock: Clock::System,
+        }
+    }
+
+    /// Deterministic generator for tests, the clock is frozen at `unix_ms` and random bits come
+    /// from a PRNG seeded with `seed`.
+    pub const fn seeded(seed: u64, unix_ms: u64) -> Self {
+        Self {
+            ulid: AtomicU64::new(0),
+            uuid_v7: AtomicU64::new(0),
+            random: Random::Seeded(AtomicU64::new(seed)),
+            clock: Clock::Fixed(unix_ms),
+        }
+    }
+
+    pub fn ulid(&self) -> Ulid {
+        let state = next_state(&self.ulid, self.now_ms(), Ulid::COUNTER_BITS);
+        Ulid::from_parts(state, self.random_u64())
+    }
+
+    pub fn uuid_v7(&self) -> UuidV7 {
+        let state = next_state(&self.uuid_v7, self.now_ms(), UuidV7::COUNTER_BITS);
+        UuidV7::from_parts(state, self.random_u64())
+    }
+
+    fn now_ms(&self) -> u64 {
+        match self.clock {
+            Clock::System => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis() as u64),
+            Clock::Fixed(unix_ms) => unix_ms,
+        }
+    }
+
+    fn random_u64(&self) -> u64 {
+        match &self.random {
+            Random::Os => getrandom::u64().expect("failed to get random bytes from OS"),
+            Random::Seeded(state) => {
+                let mut z = state
+                    .fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed)
+                    .wrapping_add(0x9e37_79b9_7f4a_7c15);
+                z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+                z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+                z ^ (z >> 31)
+            }
+        }
+    }
+}
+
+impl Default for IdGenerator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Advance `state`, a timestamp followed by `counter_bits` of counter, to `now_ms` or past the
+/// last state, whichever is greater.
+fn next_state(state: &AtomicU64, now_ms: u64, counter_bits: u32) -> u64 {
+    let floor = now_ms << counter_bits;
+    let mut last = state.load(Ordering::Relaxed);
+    loop {
+        let next = floor.max(last + 1);
+        match state.compare_exchange_weak(last, next, Ordering::Relaxed, Ordering::Relaxed) {
+            Ok(_) => return next,
+            Err(actual) => last = actual,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ParseIdError {
+    #[error("invalid length {found}, expected {expected}")]
+    InvalidLength { expected: usize, found: usize },
+    #[error("invalid character {0:?}")]
+    InvalidCharacter(char),
+    #[error("value is out of range")]
+    Overflow,
+    #[error("not a version 7 UUID")]
+    NotVersion7,
+}
+
+fn serialize_display<T: fmt::Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+fn deserialize_from_str<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+where
+    T: FromStr<Err = ParseIdError>,
+    D: Deserializer<'de>,
+{
+    struct Visitor<T>(PhantomData<T>);
+
+    impl<T: FromStr<Err = ParseIdError>> de::Visitor<'_> for Visitor<T> {
+        type Value = T;
+
+        fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("an id string")
+        }
+
+        fn visit_str<E: de::Error>(self, v: &str) -> Result<T, E> {
+            v.parse().map_err(E::custom)
+        }
+    }
+
+    deserializer.deserialize_str(Visitor(PhantomData))
+}
@@ -0,0 +1,138 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{deserialize_from_str, serialize_display, ParseIdError, DEFAULT_GENERATOR};
+
+/// Crockford's base32 alphabet.
+const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+const LEN: usize = 26;
+
+/// [ULID](https://github.com/ulid/spec), 48 bits of milliseconds since Unix epoch, followed by
+/// 16 bits of counter and 64 random bits.
+///
+/// Formatted as 26 characters of Crockford's base32, parsing is case-insensitive.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Ulid(u128);
+
+impl Ulid {
+    pub(super) const COUNTER_BITS: u32 = 16;
+
+    /// Generate from the default generator.
+    pub fn generate() -> Self {
+        DEFAULT_GENERATOR.ulid()
+    }
+
+    pub(super) fn from_parts(state: u64, random: u64) -> Self {
+        Self((u128::from(state) << 64) | u128::from(random))
+    }
+
+    pub const fn from_u128(value: u128) -> Self {
+        Self(value)
+    }
+
+    pub const fn as_u128(&self) -> u128 {
+        self.0
+    }
+
+    /// Milliseconds since Unix epoch.
+    pub const fn timestamp_ms(&self) -> u64 {
+        (self.0 >> 80) as u64
+    }
+}
+
+impl Display for Ulid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buf = [0; LEN];
+        for (i, c) in buf.iter_mut().enumerate() {
+            let shift = 5 * (LEN - 1 - i);
+            *c = ALPHABET[(self.0 >> shift) as usize & 0x1f];
+        }
+        f.write_str(std::str::from_utf8(&buf).expect("alphabet is ASCII"))
+    }
+}
+
+impl FromStr for Ulid {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.len() != LEN {
+            return Err(ParseIdError::InvalidLength {
+                expected: LEN,
+                found: s.len(),
+            });
+        }
+        let mut value: u128 = 0;
+        for (i, c) in s.chars().enumerate() {
+            let digit = ALPHABET
+                .iter()
+                .position(|&a| char::from(a).eq_ignore_ascii_case(&c))
+                .ok_or(ParseIdError::InvalidCharacter(c))?;
+            // 26 characters hold 130 bits, the first one may only use 3 bits
+            if i == 0 && digit > 7 {
+                return Err(ParseIdError::Overflow);
+            }
+            value = (value << 5) | digit as u128;
+        }
+        Ok(Self(value))
+    }
+}
+
+impl Serialize for Ulid {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_display(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Ulid {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_tokens, Token};
+
+    use super::super::IdGenerator;
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse() {
+        let ulid = Ulid::from_u128(0x0156_3e3a_b5d3_d676_4c61_efb9_9302_bd5b);
+        assert_eq!(ulid.to_string(), "01ARZ3NDEKTSV4RRFFQ69G5FAV");
+        assert_eq!("01arz3ndektsv4rrffq69g5fav".parse(), Ok(ulid));
+        assert_eq!(
+            Ulid::from_u128(u128::MAX).to_string(),
+            "7ZZZZZZZZZZZZZZZZZZZZZZZZZ"
+        );
+        assert_eq!(
+            "8ZZZZZZZZZZZZZZZZZZZZZZZZZ".parse::<Ulid>(),
+            Err(ParseIdError::Overflow)
+        );
+        assert_eq!(
+            "01ARZ3NDEKTSV4RRFFQ69G5FAU".parse::<Ulid>(),
+            Err(ParseIdError::InvalidCharacter('U'))
+        );
+        assert_eq!(
+            "006".parse::<Ulid>(),
+            Err(ParseIdError::InvalidLength {
+                expected: 26,
+                found: 3
+            })
+        );
+        assert_tokens(&ulid, &[Token::Str("01ARZ3NDEKTSV4RRFFQ69G5FAV")]);
+    }
+
+    #[test]
+    fn test_monotonic() {
+        let generator = IdGenerator::seeded(1, 1_700_000_000_000);
+        let ids: Vec<_> = (0..1000).map(|_| generator.ulid()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(ids[0].timestamp_ms(), 1_700_000_000_000);
+
+        let replay = IdGenerator::seeded(1, 1_700_000_000_000);
+        assert_eq!(replay.ulid(), ids[0]);
+    }
+}
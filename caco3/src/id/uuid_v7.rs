@@ -0,0 +1,154 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{deserialize_from_str, serialize_display, ParseIdError, DEFAULT_GENERATOR};
+
+const VERSION_MASK: u128 = 0xf << 76;
+const VERSION_7: u128 = 0x7 << 76;
+const VARIANT_MASK: u128 = 0b11 << 62;
+const VARIANT_RFC: u128 = 0b10 << 62;
+const HYPHENS: [usize; 4] = [8, 13, 18, 23];
+
+/// [UUID version 7](https://www.rfc-editor.org/rfc/rfc9562#name-uuid-version-7), 48 bits of
+/// milliseconds since Unix epoch, followed by a 12 bits counter and 62 random bits.
+///
+/// Formatted as lowercase hyphenated hex, parsing also accepts uppercase and the simple form
+/// without hyphens.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct UuidV7(u128);
+
+impl UuidV7 {
+    pub(super) const COUNTER_BITS: u32 = 12;
+
+    /// Generate from the default generator.
+    pub fn generate() -> Self {
+        DEFAULT_GENERATOR.uuid_v7()
+    }
+
+    pub(super) fn from_parts(state: u64, random: u64) -> Self {
+        let timestamp = u128::from(state >> Self::COUNTER_BITS);
+        let counter = u128::from(state & 0xfff);
+        let random = u128::from(random) & !VARIANT_MASK;
+        Self((timestamp << 80) | VERSION_7 | (counter << 64) | VARIANT_RFC | random)
+    }
+
+    /// Fail unless `value` has version 7 and RFC 9562 variant bits.
+    pub const fn from_u128(value: u128) -> Result<Self, ParseIdError> {
+        if value & VERSION_MASK != VERSION_7 || value & VARIANT_MASK != VARIANT_RFC {
+            return Err(ParseIdError::NotVersion7);
+        }
+        Ok(Self(value))
+    }
+
+    pub const fn as_u128(&self) -> u128 {
+        self.0
+    }
+
+    pub const fn to_bytes(&self) -> [u8; 16] {
+        self.0.to_be_bytes()
+    }
+
+    /// Milliseconds since Unix epoch.
+    pub const fn timestamp_ms(&self) -> u64 {
+        (self.0 >> 80) as u64
+    }
+}
+
+impl Display for UuidV7 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let v = self.0;
+        write!(
+            f,
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            v >> 96,
+            (v >> 80) & 0xffff,
+            (v >> 64) & 0xffff,
+            (v >> 48) & 0xffff,
+            v & 0xffff_ffff_ffff,
+        )
+    }
+}
+
+impl FromStr for UuidV7 {
+    type Err = ParseIdError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let hyphenated = match s.len() {
+            36 => true,
+            32 => false,
+            found => {
+                return Err(ParseIdError::InvalidLength {
+                    expected: 36,
+                    found,
+                })
+            }
+        };
+        let mut value: u128 = 0;
+        for (i, c) in s.chars().enumerate() {
+            if hyphenated && HYPHENS.contains(&i) {
+                if c != '-' {
+                    return Err(ParseIdError::InvalidCharacter(c));
+                }
+                continue;
+            }
+            let digit = c.to_digit(16).ok_or(ParseIdError::InvalidCharacter(c))?;
+            value = (value << 4) | u128::from(digit);
+        }
+        Self::from_u128(value)
+    }
+}
+
+impl Serialize for UuidV7 {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_display(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for UuidV7 {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize_from_str(deserializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_test::{assert_tokens, Token};
+
+    use super::super::IdGenerator;
+    use super::*;
+
+    #[test]
+    fn test_format_and_parse() {
+        let generator = IdGenerator::seeded(7, 0x0189_1234_5678);
+        let id = generator.uuid_v7();
+        let s = id.to_string();
+        assert!(s.starts_with("01891234-5678-7000-"), "{s}");
+        assert_eq!(s.parse(), Ok(id));
+        assert_eq!(s.replace('-', "").to_uppercase().parse(), Ok(id));
+
+        let parsed = uuid::Uuid::parse_str(&s).unwrap();
+        assert_eq!(parsed.get_version_num(), 7);
+        assert_eq!(parsed.get_variant(), uuid::Variant::RFC4122);
+        assert_eq!(parsed.as_u128(), id.as_u128());
+
+        let v4 = uuid::Uuid::new_v4().to_string();
+        assert_eq!(v4.parse::<UuidV7>(), Err(ParseIdError::NotVersion7));
+        assert_eq!(
+            "01891234+5678-7000-8000-000000000000".parse::<UuidV7>(),
+            Err(ParseIdError::InvalidCharacter('+'))
+        );
+        assert_tokens(&id, &[Token::Str(Box::leak(s.into_boxed_str()))]);
+    }
+
+    #[test]
+    fn test_monotonic() {
+        let generator = IdGenerator::seeded(1, 1_700_000_000_000);
+        // more than the 4096 counter values of a millisecond
+        let ids: Vec<_> = (0..10_000).map(|_| generator.uuid_v7()).collect();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(ids[0].timestamp_ms(), 1_700_000_000_000);
+        assert_eq!(ids[9_999].timestamp_ms(), 1_700_000_000_002);
+    }
+}
@@ -1,8 +1,11 @@
 #![deny(rust_2018_idioms)]
 #![allow(clippy::result_unit_err)]
 
+pub mod cache;
 pub mod cargo;
 pub mod config;
+pub mod id;
+pub mod redacted;
 pub mod time;
 
 pub mod token;
\ No newline at end of file
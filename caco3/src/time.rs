@@ -2,9 +2,11 @@ use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use time::UtcOffset;
 
+pub use backoff::{Backoff, Jitter};
 #[cfg(feature = "local-offset")]
 pub use local_time::{local_now, local_utc_offset};
 
+mod backoff;
 pub mod human_duration;
 #[cfg(feature = "local-offset")]
 mod local_time;
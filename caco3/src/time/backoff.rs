@@ -0,0 +1,234 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Randomization applied to each delay of [`Backoff`].
+///
+/// See <https://aws.amazon.com/blogs/architecture/exponential-backoff-and-jitter/>.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub enum Jitter {
+    /// Exact exponential delays.
+    #[default]
+    None,
+    /// Random between zero and the exponential delay.
+    Full,
+    /// Half of the exponential delay plus random up to the other half.
+    Equal,
+    /// Random between the initial delay and three times the previous delay, doesn't use the multiplier.
+    Decorrelated,
+}
+
+/// Iterator over delays between retries with exponential growth.
+///
+/// The iterator is infinite unless [`max_elapsed`](Backoff::max_elapsed) is set,
+/// use [`Iterator::take`] to limit number of retries.
+///
+/// ```
+/// use std::time::Duration;
+/// use caco3::time::{Backoff, Jitter};
+///
+/// let delays: Vec<_> = Backoff::new(Duration::from_millis(100))
+///     .max_delay(Duration::from_millis(500))
+///     .take(4)
+///     .collect();
+/// assert_eq!(delays, [100, 200, 400, 500].map(Duration::from_millis));
+///
+/// let mut backoff = Backoff::new(Duration::from_millis(100))
+///     .jitter(Jitter::Full)
+///     .max_elapsed(Duration::from_secs(10));
+/// while let Some(delay) = backoff.next() {
+///     // try something, then sleep for `delay` if it failed
+/// #   break;
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max_delay: Duration,
+    multiplier: f64,
+    jitter: Jitter,
+    max_elapsed: Option<Duration>,
+    /// Exponential delay of the next retry, before jitter.
+    current: Duration,
+    /// Previous delay after jitter.
+    previous: Duration,
+    start: Option<Instant>,
+    exhausted: bool,
+    /// State of splitmix64.
+    rng: u64,
+}
+
+impl Backoff {
+    /// Start at `initial` and double each time, without upper limit or jitter.
+    pub fn new(initial: Duration) -> Self {
+        let seed = getrandom::u64().unwrap_or_else(|_| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_nanos() as u64)
+        });
+        Self {
+            initial,
+            max_delay: Duration::MAX,
+            multiplier: 2.0,
+            jitter: Jitter::None,
+            max_elapsed: None,
+            current: initial,
+            previous: initial,
+            start: None,
+            exhausted: false,
+            rng: seed,
+        }
+    }
+
+    /// Upper limit of each delay.
+    pub fn max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self.current = self.initial.min(max_delay);
+        self
+    }
+
+    /// Growth factor of each delay.
+    ///
+    /// # Panics
+    /// Panics if `multiplier` is less than 1 or not finite.
+    pub fn multiplier(mut self, multiplier: f64) -> Self {
+        assert!(
+            multiplier.is_finite() && multiplier >= 1.0,
+            "multiplier must be finite and at least 1"
+        );
+        self.multiplier = multiplier;
+        self
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// Stop once waiting for the next delay would exceed `max_elapsed` since the first delay was
+    /// taken, time spent between calls to `next` is included.
+    pub fn max_elapsed(mut self, max_elapsed: Duration) -> Self {
+        self.max_elapsed = Some(max_elapsed);
+        self
+    }
+
+    /// Seed the random number generator of jitter, for reproducible delays.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.rng = seed;
+        self
+    }
+
+    /// Start over from the initial delay, e.g. after a success.
+    pub fn reset(&mut self) {
+        self.current = self.initial.min(self.max_delay);
+        self.previous = self.initial;
+        self.start = None;
+        self.exhausted = false;
+    }
+
+    fn random_between(&mut self, low: Duration, high: Duration) -> Duration {
+        self.rng = self.rng.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        // 53 bits fit in f64 mantissa, unit is in [0, 1)
+        let unit = (z >> 11) as f64 / (1u64 << 53) as f64;
+        low + high.saturating_sub(low).mul_f64(unit)
+    }
+}
+
+impl Iterator for Backoff {
+    type Item = Duration;
+
+    fn next(&mut self) -> Option<Duration> {
+        if self.exhausted {
+            return None;
+        }
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let base = self.current;
+        let delay = match self.jitter {
+            Jitter::None => base,
+            Jitter::Full => self.random_between(Duration::ZERO, base),
+            Jitter::Equal => base / 2 + self.random_between(Duration::ZERO, base - base / 2),
+            Jitter::Decorrelated => {
+                let low = self.initial.min(self.max_delay);
+                let high = self.previous.saturating_mul(3).min(self.max_delay);
+                self.random_between(low, high.max(low))
+            }
+        };
+        if let Some(max_elapsed) = self.max_elapsed {
+            if start.elapsed().saturating_add(delay) > max_elapsed {
+                self.exhausted = true;
+                return None;
+            }
+        }
+        self.previous = delay;
+        self.current = Duration::try_from_secs_f64(base.as_secs_f64() * self.multiplier)
+            .unwrap_or(Duration::MAX)
+            .min(self.max_delay);
+        Some(delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn test_exponential() {
+        let delays: Vec<_> = Backoff::new(10 * MS)
+            .multiplier(3.0)
+            .max_delay(100 * MS)
+            .take(5)
+            .collect();
+        assert_eq!(delays, [10 * MS, 30 * MS, 90 * MS, 100 * MS, 100 * MS]);
+
+        let mut backoff = Backoff::new(Duration::from_secs(u64::MAX / 4));
+        assert_eq!(backoff.nth(2), Some(Duration::MAX));
+    }
+
+    #[test]
+    fn test_jitter() {
+        let backoff = Backoff::new(10 * MS).max_delay(100 * MS).seed(1);
+        let exact: Vec<_> = backoff.clone().take(20).collect();
+
+        let full: Vec<_> = backoff.clone().jitter(Jitter::Full).take(20).collect();
+        assert!(full.iter().zip(&exact).all(|(d, e)| d <= e));
+        assert_ne!(full, exact);
+
+        let equal: Vec<_> = backoff.clone().jitter(Jitter::Equal).take(20).collect();
+        assert!(equal
+            .iter()
+            .zip(&exact)
+            .all(|(d, e)| *d >= *e / 2 && d <= e));
+
+        let decorrelated: Vec<_> = backoff
+            .clone()
+            .jitter(Jitter::Decorrelated)
+            .take(20)
+            .collect();
+        assert!(decorrelated
+            .iter()
+            .all(|d| (10 * MS..=100 * MS).contains(d)));
+        assert!(decorrelated
+            .windows(2)
+            .all(|w| w[1] <= (w[0] * 3).min(100 * MS)));
+
+        let replay: Vec<_> = backoff.jitter(Jitter::Decorrelated).take(20).collect();
+        assert_eq!(replay, decorrelated);
+    }
+
+    #[test]
+    fn test_max_elapsed() {
+        let mut backoff =
+            Backoff::new(Duration::from_secs(1)).max_elapsed(Duration::from_millis(3500));
+        assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+        assert_eq!(backoff.next(), Some(Duration::from_secs(2)));
+        assert_eq!(backoff.next(), None);
+        assert_eq!(backoff.next(), None);
+
+        backoff.reset();
+        assert_eq!(backoff.next(), Some(Duration::from_secs(1)));
+    }
+}
@@ -1,6 +1,9 @@
 #![allow(clippy::unnecessary_lazy_evaluations)]
 
 use std::fmt::{self, Display, Write};
+use std::str::FromStr;
+
+use thiserror::Error;
 
 const MINUTE_SECONDS: u64 = 60;
 const HOUR_SECONDS: u64 = 60 * MINUTE_SECONDS;
@@ -30,6 +33,10 @@ impl HumanDuration {
         self.0 % MINUTE_SECONDS
     }
 
+    pub const fn as_secs(self) -> u64 {
+        self.0
+    }
+
     pub fn format(self, num_components: u8) -> String {
         let capacity = (num_components.saturating_mul(4)).min(16).into();
         let mut buf = String::with_capacity(capacity);
@@ -67,6 +74,10 @@ pub struct DurationComponent {
 impl DurationComponent {
     pub const ALL_COMPONENTS: u8 = 4;
 
+    pub const fn value(&self) -> u64 {
+        self.value
+    }
+
     fn days(value: u64) -> Self {
         Self {
             value,
@@ -197,6 +208,66 @@ impl Display for HumanDurationDisplay {
     }
 }
 
+#[derive(Debug, Clone, Eq, PartialEq, Error)]
+#[non_exhaustive]
+pub enum ParseHumanDurationError {
+    #[error("empty duration")]
+    Empty,
+    #[error("invalid duration component {0:?}, expected a number followed by d, h, m or s")]
+    InvalidComponent(String),
+    #[error("duration is too large")]
+    Overflow,
+}
+
+/// Parse components of days, hours, minutes and seconds, e.g. `30s`, `1h 30m` or `1d12h`.
+///
+/// A number without unit is seconds.
+impl FromStr for HumanDuration {
+    type Err = ParseHumanDurationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            return Err(ParseHumanDurationError::Empty);
+        }
+        if let Ok(secs) = s.parse() {
+            return Ok(HumanDuration(secs));
+        }
+        let mut total: u64 = 0;
+        let mut rest = s;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len());
+            if digits == 0 {
+                let component = rest.split_whitespace().next().unwrap_or(rest);
+                return Err(ParseHumanDurationError::InvalidComponent(
+                    component.to_owned(),
+                ));
+            }
+            let unit_len = rest[digits..]
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(rest.len() - digits);
+            let (component, remaining) = rest.split_at(digits + unit_len);
+            let invalid = || ParseHumanDurationError::InvalidComponent(component.to_owned());
+            let value: u64 = component[..digits].parse().map_err(|_| invalid())?;
+            let unit_seconds = match &component[digits..] {
+                "d" => DAY_SECONDS,
+                "h" => HOUR_SECONDS,
+                "m" => MINUTE_SECONDS,
+                "s" => 1,
+                _ => return Err(invalid()),
+            };
+            total = value
+                .checked_mul(unit_seconds)
+                .and_then(|secs| total.checked_add(secs))
+                .ok_or(ParseHumanDurationError::Overflow)?;
+            rest = remaining.trim_start();
+        }
+        Ok(HumanDuration(total))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use time::ext::NumericalStdDuration;
@@ -229,4 +300,35 @@ mod tests {
         assert_eq!(components[2].to_string(), "7m");
         assert_eq!(components[3].to_string(), "3s");
     }
+
+    #[test]
+    fn test_parse() {
+        let parse = |s: &str| s.parse::<HumanDuration>().map(HumanDuration::as_secs);
+        assert_eq!(parse("30"), Ok(30));
+        assert_eq!(parse("30s"), Ok(30));
+        assert_eq!(parse("5m"), Ok(5 * MINUTE_SECONDS));
+        assert_eq!(parse(" 1h 30m "), Ok(HOUR_SECONDS + 30 * MINUTE_SECONDS));
+        assert_eq!(parse("1d12h"), Ok(DAY_SECONDS + 12 * HOUR_SECONDS));
+        assert_eq!(
+            parse("1d 5h 7m 3s").map(|s| HumanDuration(s).to_string()),
+            Ok("1d 5h 7m 3s".to_owned())
+        );
+        assert_eq!(parse(""), Err(ParseHumanDurationError::Empty));
+        assert_eq!(
+            parse("5 m"),
+            Err(ParseHumanDurationError::InvalidComponent("5".to_owned()))
+        );
+        assert_eq!(
+            parse("1h 5x"),
+            Err(ParseHumanDurationError::InvalidComponent("5x".to_owned()))
+        );
+        assert_eq!(
+            parse("-5s"),
+            Err(ParseHumanDurationError::InvalidComponent("-5s".to_owned()))
+        );
+        assert_eq!(
+            parse("99999999999999999d"),
+            Err(ParseHumanDurationError::Overflow)
+        );
+    }
 }